@@ -0,0 +1,5 @@
+fn main() {
+    // the Node.js addon links against symbols the node binary provides at load time
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
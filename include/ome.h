@@ -0,0 +1,122 @@
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#ifndef OME_H
+#define OME_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum OmeStatus {
+  OmeStatus_Ok = 0,
+  OmeStatus_NullPointer = 1,
+  OmeStatus_ZeroQuantity = 2,
+  OmeStatus_MissingPrice = 3,
+  /**
+   * The order was entered, but the matcher gave it an id that isn't a number
+   */
+  OmeStatus_UnrepresentableId = 4,
+} OmeStatus;
+
+typedef enum OmeSide {
+  OmeSide_Buy = 0,
+  OmeSide_Sell = 1,
+} OmeSide;
+
+typedef enum OmeOrderType {
+  OmeOrderType_Limit = 0,
+  OmeOrderType_Market = 1,
+} OmeOrderType;
+
+/**
+ * Opaque engine handle
+ */
+typedef struct OmeEngine OmeEngine;
+
+typedef struct OmeTrade {
+  uint64_t id;
+  uint64_t buy_order_id;
+  uint64_t sell_order_id;
+  uint64_t price;
+  uint64_t quantity;
+  /**
+   * Side of the incoming order that took liquidity
+   */
+  enum OmeSide aggressor;
+} OmeTrade;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an engine; `policy` is one of the server's `OME_MATCHING_POLICY` names (price-time
+ * when NULL). Returns NULL for an unknown policy. Free it with `ome_engine_free`.
+ *
+ * # Safety
+ * `policy` must be NULL or a valid NUL-terminated string.
+ */
+struct OmeEngine *ome_engine_new(const char *policy, uint64_t min_quantity);
+
+/**
+ * Destroys an engine created by `ome_engine_new`; NULL is ignored.
+ *
+ * # Safety
+ * `engine` must come from `ome_engine_new` and not be used afterwards.
+ */
+void ome_engine_free(struct OmeEngine *engine);
+
+/**
+ * Submits an order (`price` is ignored for market orders) and writes its id to `order_id`
+ * (may be NULL). Resulting trades are queued for `ome_poll_trades`.
+ *
+ * # Safety
+ * `engine` must be a live engine and `order_id` NULL or valid for writes.
+ */
+enum OmeStatus ome_submit(struct OmeEngine *engine,
+                          enum OmeSide side,
+                          enum OmeOrderType order_type,
+                          uint64_t price,
+                          uint64_t quantity,
+                          uint64_t *order_id);
+
+/**
+ * Cancels a resting order, returning whether it was found.
+ *
+ * # Safety
+ * `engine` must be a live engine.
+ */
+bool ome_cancel(struct OmeEngine *engine, uint64_t order_id);
+
+/**
+ * Moves up to `capacity` queued trades, oldest first, into `out` and returns how many. Trades
+ * an `OmeTrade` can't hold are dropped.
+ *
+ * # Safety
+ * `engine` must be a live engine and `out` valid for `capacity` writes.
+ */
+size_t ome_poll_trades(struct OmeEngine *engine, struct OmeTrade *out, size_t capacity);
+
+/**
+ * Best bid price, 0 when there are no bids.
+ *
+ * # Safety
+ * `engine` must be a live engine.
+ */
+uint64_t ome_best_bid(const struct OmeEngine *engine);
+
+/**
+ * Best ask price, 0 when there are no asks.
+ *
+ * # Safety
+ * `engine` must be a live engine.
+ */
+uint64_t ome_best_ask(const struct OmeEngine *engine);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* OME_H */
@@ -0,0 +1,308 @@
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, Query, State, rejection::QueryRejection},
+    middleware,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::{self, Auth, Principal},
+    error::{ApiError, ErrorCode},
+    markprice::MarkPrice,
+    matchingengine::MatchingEngine,
+    order::{Order, OrderStatus, ParticipantId, Price, Quantity, Trade, TradeId},
+    settlement::{BatchStatus, SettlementInstruction},
+    twap::{TwapOrder, Twaps},
+    validation::FieldError,
+    wallet::AccountBalances,
+};
+
+/// Path alias for the caller's own account
+const OWN_ACCOUNT: &str = "me";
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+
+#[derive(Debug, Deserialize)]
+struct TradesQuery {
+    limit: Option<usize>,
+    /// Only trades with a smaller id, pass the previous page's `next_before`
+    before: Option<TradeId>,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountOrdersResponse {
+    account: ParticipantId,
+    orders: Vec<AccountOrder>,
+    /// TWAP parents, working and recently finished
+    algos: Vec<TwapOrder>,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountOrder {
+    #[serde(flatten)]
+    order: Order,
+    status: OrderStatus,
+    remaining_quantity: Quantity,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountBalancesResponse {
+    account: ParticipantId,
+    balances: AccountBalances,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountTradesResponse {
+    account: ParticipantId,
+    trades: Vec<Trade>,
+    /// Cursor for the next (older) page, `None` on the last page
+    next_before: Option<TradeId>,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountPositionsResponse {
+    account: ParticipantId,
+    net_position: i64,
+    average_entry_price: Option<Price>,
+    /// Quantity resting or queued on each side
+    open_buy: Quantity,
+    open_sell: Quantity,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountPnlResponse {
+    account: ParticipantId,
+    net_position: i64,
+    average_entry_price: Option<Price>,
+    /// Cents
+    realized_pnl: i128,
+    /// `None` until the book has a mark price
+    unrealized_pnl: Option<i128>,
+    mark_price: Option<MarkPrice>,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountFeesResponse {
+    account: ParticipantId,
+    /// Cents, over all trades that weren't busted
+    total: u64,
+    /// Rolling 30-day traded notional (cents) as of the last tier refresh
+    volume_30d: u64,
+    maker_bps: u64,
+    taker_bps: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Obligation {
+    batch: u64,
+    #[serde(flatten)]
+    instruction: SettlementInstruction,
+}
+
+#[derive(Debug, Serialize)]
+struct AccountSettlementResponse {
+    account: ParticipantId,
+    pending: Vec<Obligation>,
+    settled: Vec<Obligation>,
+}
+
+/// Per-account queries; an account is the participant id orders are attributed to
+pub fn routes(auth: Auth) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/accounts/{id}/orders", get(get_account_orders))
+        .route("/accounts/{id}/trades", get(get_account_trades))
+        .route("/accounts/{id}/balances", get(get_account_balances))
+        .route("/accounts/{id}/positions", get(get_account_positions))
+        .route("/accounts/{id}/pnl", get(get_account_pnl))
+        .route("/accounts/{id}/fees", get(get_account_fees))
+        .route("/accounts/{id}/settlement", get(get_account_settlement))
+        .route_layer(middleware::from_fn_with_state(
+            auth,
+            auth::require_read_only,
+        ))
+}
+
+/// Resolves `me` and checks the caller may see the requested account
+fn resolve_account(principal: &Principal, id: String) -> Result<ParticipantId, ApiError> {
+    let account = if id == OWN_ACCOUNT {
+        principal.subject.clone()
+    } else {
+        id
+    };
+    if !principal.can_access_account(&account) {
+        return Err(ApiError::new(
+            ErrorCode::Forbidden,
+            format!("{} may not access account {account}", principal.subject),
+        ));
+    }
+    Ok(account)
+}
+
+async fn get_account_orders(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Extension(twaps): Extension<Twaps>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountOrdersResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    let orders = engine
+        .owner_orders(&account)
+        .await
+        .into_iter()
+        .map(|(order, status)| AccountOrder {
+            remaining_quantity: order.quantity,
+            order,
+            status,
+        })
+        .collect();
+    let algos = twaps.of_owner(&account).await;
+    Ok(Json(AccountOrdersResponse {
+        account,
+        orders,
+        algos,
+    }))
+}
+
+async fn get_account_trades(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+    query: Result<Query<TradesQuery>, QueryRejection>,
+) -> Result<Json<AccountTradesResponse>, ApiError> {
+    let Query(query) = query?;
+    let account = resolve_account(&principal, id)?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "limit",
+            format!("limit must be between 1 and {MAX_PAGE_SIZE}"),
+        )]));
+    }
+    let trades = engine.owner_trades(&account, query.before, limit).await;
+    let next_before = (trades.len() == limit)
+        .then(|| trades.last().map(|t| t.id))
+        .flatten();
+    Ok(Json(AccountTradesResponse {
+        account,
+        trades,
+        next_before,
+    }))
+}
+
+async fn get_account_balances(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountBalancesResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    Ok(Json(AccountBalancesResponse {
+        balances: engine.balances(&account).await,
+        account,
+    }))
+}
+
+async fn get_account_positions(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountPositionsResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    let exposure = engine.owner_exposure(&account).await;
+    let position = engine.position(&account).await;
+    Ok(Json(AccountPositionsResponse {
+        account,
+        net_position: position.net,
+        average_entry_price: position.average_entry_price(),
+        open_buy: exposure.open_buy,
+        open_sell: exposure.open_sell,
+    }))
+}
+
+async fn get_account_pnl(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountPnlResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    let position = engine.position(&account).await;
+    let mark_price = engine.mark_price().await;
+    Ok(Json(AccountPnlResponse {
+        account,
+        net_position: position.net,
+        average_entry_price: position.average_entry_price(),
+        realized_pnl: position.realized_pnl,
+        unrealized_pnl: mark_price.map(|mark| position.unrealized_pnl(mark.price)),
+        mark_price,
+    }))
+}
+
+async fn get_account_fees(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountFeesResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    let (total, volume_30d) = engine.fees_paid(&account).await;
+    let (maker_bps, taker_bps) = engine.fee_rates(&account).await;
+    Ok(Json(AccountFeesResponse {
+        account,
+        total,
+        volume_30d,
+        maker_bps,
+        taker_bps,
+    }))
+}
+
+async fn get_account_settlement(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<String>,
+) -> Result<Json<AccountSettlementResponse>, ApiError> {
+    let account = resolve_account(&principal, id)?;
+    let (mut pending, mut settled) = (Vec::new(), Vec::new());
+    for (batch, status, instruction) in engine.settlement_obligations(&account).await {
+        let obligation = Obligation { batch, instruction };
+        match status {
+            BatchStatus::Settled => settled.push(obligation),
+            _ => pending.push(obligation),
+        }
+    }
+    Ok(Json(AccountSettlementResponse {
+        account,
+        pending,
+        settled,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::auth::Role;
+
+    fn principal(subject: &str, role: Role) -> Principal {
+        Principal {
+            subject: subject.to_string(),
+            roles: vec![role],
+        }
+    }
+
+    #[test]
+    fn test_resolve_account() {
+        let alice = principal("alice", Role::Trader);
+        assert_eq!(
+            resolve_account(&alice, String::from("me")).unwrap(),
+            "alice"
+        );
+        assert_eq!(
+            resolve_account(&alice, String::from("alice")).unwrap(),
+            "alice"
+        );
+        let err = resolve_account(&alice, String::from("bob")).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Forbidden);
+
+        let admin = principal("ops", Role::Admin);
+        assert_eq!(resolve_account(&admin, String::from("bob")).unwrap(), "bob");
+    }
+}
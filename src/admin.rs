@@ -0,0 +1,823 @@
+use axum::{
+    Extension, Json, Router,
+    body::Bytes,
+    extract::{
+        DefaultBodyLimit, Path, Query, State,
+        rejection::{JsonRejection, QueryRejection},
+    },
+    middleware,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    audit::{self, AuditEntry, GENESIS_HASH},
+    auth::{self, Auth},
+    consensus::Consensus,
+    error::{ApiError, ErrorCode},
+    matchingengine::{MatchingEngine, TradeAdjustmentError, TradingState},
+    mmprotection::QuoteProtection,
+    order::{OrderId, ParticipantId, Price, Quantity, Trade, TradeId},
+    otr::{OtrConfig, OtrStanding},
+    ratelimit::RateLimiter,
+    retention::RetentionStats,
+    risk::{BandLimits, RiskLimits},
+    runtimeconfig::RuntimeConfig,
+    settlement::{SettlementBatch, SettlementError},
+    state::EngineState,
+    validation::FieldError,
+    wallet::{AccountBalances, Asset},
+};
+
+const MAX_AUDIT_PAGE: usize = 10_000;
+pub const MAX_STATE_BYTES: usize = 64 * 1024 * 1024; // state imports carry whole books, past the default body limit
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    #[serde(default = "first_seq")]
+    from: u64,
+    limit: Option<usize>,
+}
+
+fn first_seq() -> u64 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyAuditRequest {
+    entries: Vec<AuditEntry>,
+    /// Hash of the entry before the first one, defaults to the genesis hash
+    prev_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RetentionResponse {
+    /// Trades in memory
+    pooled_trades: usize,
+    /// Trades in the spill file
+    spilled_trades: usize,
+    #[serde(flatten)]
+    stats: RetentionStats,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditExportResponse {
+    entries: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct AuditVerifyResponse {
+    valid: bool,
+    entries: u64,
+    /// Hash of the last entry checked
+    head_hash: String,
+    /// Sequence number of the first entry that breaks the chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_invalid: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HaltRequest {
+    /// Whether resting orders may still be canceled while halted
+    #[serde(default)]
+    allow_cancels: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ClearBookRequest {
+    #[serde(default)]
+    wipe_trades: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ClearBookResponse {
+    canceled: Vec<OrderId>,
+    trades_wiped: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct BustTradeRequest {
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockTradeRequest {
+    buyer: ParticipantId,
+    seller: ParticipantId,
+    /// Negotiated price in cents
+    price: Price,
+    quantity: Quantity,
+}
+
+#[derive(Debug, Deserialize)]
+struct CorrectTradeRequest {
+    reason: String,
+    /// Corrected price in cents
+    price: Option<Price>,
+    quantity: Option<Quantity>,
+}
+
+#[derive(Debug, Serialize)]
+struct TradeAdjustmentResponse {
+    trade: Trade,
+}
+
+#[derive(Debug, Serialize)]
+struct OtrResponse {
+    config: OtrConfig,
+    offenders: Vec<OtrStanding>,
+}
+
+#[derive(Debug, Serialize)]
+struct KillSwitchResponse {
+    participant: ParticipantId,
+    canceled: Vec<OrderId>,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockedParticipantsResponse {
+    blocked: Vec<ParticipantId>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ReferencePriceRequest {
+    /// Static reference in cents; omit to fall back to the mark price
+    price: Option<Price>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReferencePriceResponse {
+    reference_price: Option<Price>,
+    price_bands: Option<BandLimits>,
+}
+
+#[derive(Debug, Serialize)]
+struct RiskLimitsResponse {
+    participant: ParticipantId,
+    limits: RiskLimits,
+    net_position: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct QuoteProtectionResponse {
+    participant: ParticipantId,
+    /// `null` when the participant's quotes aren't protected
+    protection: Option<QuoteProtection>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransferRequest {
+    asset: Asset,
+    /// Quantity units for `base`, cents for `quote`
+    amount: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct BalancesResponse {
+    account: ParticipantId,
+    balances: AccountBalances,
+}
+
+#[derive(Debug, Serialize)]
+struct SettlementBatchesResponse {
+    batches: Vec<SettlementBatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct SettlementBatchResponse {
+    /// `None` when nothing traded since the previous batch was closed
+    batch: Option<SettlementBatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct StateImportResponse {
+    resting_orders: usize,
+    queued_orders: usize,
+    trades: usize,
+    /// Sequence number the next engine event continues from
+    next_seq: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct TradingStateResponse {
+    trading: TradingState,
+}
+
+/// Admin-only routes, all nested under `/admin`; `/admin/config` adjusts `rate_limiter` too. In
+/// consensus mode the commands that change the engine go through `consensus`.
+pub fn routes(
+    auth: Auth,
+    rate_limiter: RateLimiter,
+    consensus: Option<Consensus>,
+) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/admin/trading", get(get_trading_state))
+        .route("/admin/halt", post(halt_trading))
+        .route("/admin/resume", post(resume_trading))
+        .route("/admin/failover", post(failover))
+        .route("/admin/clear-book", post(clear_book))
+        .route(
+            "/admin/state",
+            get(export_state)
+                .post(import_state)
+                .layer(DefaultBodyLimit::max(MAX_STATE_BYTES)),
+        )
+        .route("/admin/reference-price", post(set_reference_price))
+        .route("/admin/block-trades", post(report_block_trade))
+        .route("/admin/trades/{id}/bust", post(bust_trade))
+        .route("/admin/trades/{id}/correct", post(correct_trade))
+        .route("/admin/participants/blocked", get(get_blocked_participants))
+        .route("/admin/participants/otr", get(get_otr_offenders))
+        .route("/admin/participants/{id}/kill", post(kill_participant))
+        .route("/admin/participants/{id}/enable", post(enable_participant))
+        .route("/admin/accounts/{id}/deposit", post(deposit))
+        .route("/admin/accounts/{id}/withdraw", post(withdraw))
+        .route("/admin/retention", get(get_retention))
+        .route("/admin/config", get(get_config).put(update_config))
+        .route("/admin/audit", get(export_audit_log))
+        .route(
+            "/admin/audit/verify",
+            get(verify_audit_log).post(verify_audit_export),
+        )
+        .route("/admin/settlement/batches", get(get_settlement_batches))
+        .route("/admin/settlement/close", post(close_settlement_batch))
+        .route("/admin/settlement/batches/{id}/settle", post(settle_batch))
+        .route(
+            "/admin/participants/{id}/limits",
+            get(get_risk_limits).put(set_risk_limits),
+        )
+        .route(
+            "/admin/participants/{id}/quote-protection",
+            get(get_quote_protection).put(set_quote_protection),
+        )
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_admin))
+        .layer(Extension(rate_limiter))
+        .layer(Extension(consensus))
+}
+
+/// Admin commands take optional JSON options, an empty body means "use the defaults"
+fn parse_optional_body<T: DeserializeOwned + Default>(body: &[u8]) -> Result<T, ApiError> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(body).map_err(|e| ApiError::new(ErrorCode::MalformedJson, e.to_string()))
+}
+
+/// Commands that write engine events go to the primary; on a standby they would fork its
+/// audit chain from the primary's
+async fn require_primary(engine: &MatchingEngine) -> Result<(), ApiError> {
+    if engine.is_standby().await {
+        return Err(ApiError::new(
+            ErrorCode::Standby,
+            "engine is a standby, send the command to the primary or fail over first",
+        ));
+    }
+    Ok(())
+}
+
+async fn get_retention(State(engine): State<MatchingEngine>) -> Json<RetentionResponse> {
+    let (pooled_trades, spilled_trades) = engine.hot_trades().await;
+    Json(RetentionResponse {
+        pooled_trades,
+        spilled_trades,
+        stats: engine.retention_stats().await,
+    })
+}
+
+async fn get_trading_state(State(engine): State<MatchingEngine>) -> Json<TradingStateResponse> {
+    Json(TradingStateResponse {
+        trading: engine.trading_state().await,
+    })
+}
+
+async fn halt_trading(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    body: Bytes,
+) -> Result<Json<TradingStateResponse>, ApiError> {
+    let req: HaltRequest = parse_optional_body(&body)?;
+    let trading = match &consensus {
+        Some(consensus) => consensus.halt(req.allow_cancels).await?,
+        None => engine.halt(req.allow_cancels).await,
+    };
+    Ok(Json(TradingStateResponse { trading }))
+}
+
+async fn resume_trading(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+) -> Result<Json<TradingStateResponse>, ApiError> {
+    let resumed = match &consensus {
+        Some(consensus) => consensus.resume().await?,
+        None => engine.resume().await,
+    };
+    if !resumed {
+        return Err(ApiError::new(
+            ErrorCode::InvalidTransition,
+            "trading is not halted",
+        ));
+    }
+    Ok(Json(TradingStateResponse {
+        trading: engine.trading_state().await,
+    }))
+}
+
+async fn failover(
+    State(engine): State<MatchingEngine>,
+) -> Result<Json<TradingStateResponse>, ApiError> {
+    if !engine.promote().await {
+        return Err(ApiError::new(
+            ErrorCode::InvalidTransition,
+            "engine is not a standby",
+        ));
+    }
+    Ok(Json(TradingStateResponse {
+        trading: engine.trading_state().await,
+    }))
+}
+
+async fn clear_book(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    body: Bytes,
+) -> Result<Json<ClearBookResponse>, ApiError> {
+    require_primary(&engine).await?;
+    let req: ClearBookRequest = parse_optional_body(&body)?;
+    let canceled = match &consensus {
+        Some(consensus) => consensus.clear_book(req.wipe_trades).await?,
+        None => engine.clear_book(req.wipe_trades).await,
+    };
+    Ok(Json(ClearBookResponse {
+        canceled: canceled.into_iter().map(|o| o.id).collect(),
+        trades_wiped: req.wipe_trades,
+    }))
+}
+
+async fn export_state(State(engine): State<MatchingEngine>) -> Json<EngineState> {
+    Json(engine.export_state().await)
+}
+
+async fn import_state(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    payload: Result<Json<EngineState>, JsonRejection>,
+) -> Result<Json<StateImportResponse>, ApiError> {
+    require_primary(&engine).await?;
+    let Json(mut state) = payload?;
+    let own_spill = engine.spill_path().await;
+    state
+        .load_spilled_trades(own_spill.as_deref())
+        .await
+        .map_err(|e| ApiError::validation(vec![e]))?;
+    state.validate().map_err(ApiError::validation)?;
+    let response = StateImportResponse {
+        resting_orders: state.bids.len() + state.asks.len(),
+        queued_orders: state.queued_orders.len(),
+        trades: state.trades.len(),
+        next_seq: state.last_seq + 1,
+    };
+    match &consensus {
+        Some(consensus) => consensus.import_state(state).await?,
+        None => engine.import_state(state).await,
+    }
+    Ok(Json(response))
+}
+
+async fn set_reference_price(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    body: Bytes,
+) -> Result<Json<ReferencePriceResponse>, ApiError> {
+    let req: ReferencePriceRequest = parse_optional_body(&body)?;
+    if req.price == Some(0) {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "price",
+            "price must be greater than 0",
+        )]));
+    }
+    match &consensus {
+        Some(consensus) => consensus.set_reference_price(req.price).await?,
+        None => engine.set_reference_price(req.price).await,
+    }
+    Ok(Json(ReferencePriceResponse {
+        reference_price: engine.reference_price().await,
+        price_bands: engine.band_limits().await,
+    }))
+}
+
+async fn current_config(engine: &MatchingEngine, rate_limiter: &RateLimiter) -> RuntimeConfig {
+    RuntimeConfig {
+        rate_limit: Some(rate_limiter.config().await),
+        ..engine.runtime_config().await
+    }
+}
+
+async fn get_config(
+    State(engine): State<MatchingEngine>,
+    Extension(rate_limiter): Extension<RateLimiter>,
+) -> Json<RuntimeConfig> {
+    Json(current_config(&engine, &rate_limiter).await)
+}
+
+/// Changes the sections of the limits the request carries, without a restart; the change is
+/// audited. Answers the limits now in force.
+async fn update_config(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(rate_limiter): Extension<RateLimiter>,
+    payload: Result<Json<RuntimeConfig>, JsonRejection>,
+) -> Result<Json<RuntimeConfig>, ApiError> {
+    require_primary(&engine).await?;
+    let Json(update) = payload?;
+    update.validate().map_err(ApiError::validation)?;
+    let rate_limit = update.rate_limit;
+    match &consensus {
+        Some(consensus) => consensus.update_config(update).await?,
+        None => engine.update_config(update).await,
+    }
+    // the node's own limiter, changed once the rest of the update has gone through
+    if let Some(config) = rate_limit {
+        rate_limiter.set_config(config).await;
+    }
+    Ok(Json(current_config(&engine, &rate_limiter).await))
+}
+
+fn trade_adjustment_error(trade_id: TradeId, err: TradeAdjustmentError) -> ApiError {
+    match err {
+        TradeAdjustmentError::NotFound => ApiError::new(
+            ErrorCode::TradeNotFound,
+            format!("no trade with id {trade_id} in the trade history"),
+        ),
+        TradeAdjustmentError::AlreadyBusted => ApiError::new(
+            ErrorCode::InvalidTransition,
+            format!("trade {trade_id} is already busted"),
+        ),
+    }
+}
+
+/// Books a block trade the two parties negotiated off the exchange
+async fn report_block_trade(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    payload: Result<Json<BlockTradeRequest>, JsonRejection>,
+) -> Result<Json<TradeAdjustmentResponse>, ApiError> {
+    let Json(req) = payload?;
+    let mut errors = Vec::new();
+    if req.price == 0 {
+        errors.push(FieldError::new("price", "price must be greater than 0"));
+    }
+    if req.quantity == 0 {
+        errors.push(FieldError::new(
+            "quantity",
+            "quantity must be greater than 0",
+        ));
+    }
+    if req.buyer == req.seller {
+        errors.push(FieldError::new(
+            "seller",
+            "buyer and seller must be different participants",
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let trade = match &consensus {
+        Some(consensus) => {
+            consensus
+                .report_block_trade(req.buyer, req.seller, req.price, req.quantity)
+                .await?
+        }
+        None => {
+            engine
+                .report_block_trade(req.buyer, req.seller, req.price, req.quantity)
+                .await?
+        }
+    };
+    Ok(Json(TradeAdjustmentResponse { trade }))
+}
+
+async fn bust_trade(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(trade_id): Path<TradeId>,
+    payload: Result<Json<BustTradeRequest>, JsonRejection>,
+) -> Result<Json<TradeAdjustmentResponse>, ApiError> {
+    require_primary(&engine).await?;
+    let Json(req) = payload?;
+    let result = match &consensus {
+        Some(consensus) => consensus.bust_trade(trade_id, req.reason).await?,
+        None => engine.bust_trade(trade_id, req.reason).await,
+    };
+    let trade = result.map_err(|e| trade_adjustment_error(trade_id, e))?;
+    Ok(Json(TradeAdjustmentResponse { trade }))
+}
+
+async fn correct_trade(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(trade_id): Path<TradeId>,
+    payload: Result<Json<CorrectTradeRequest>, JsonRejection>,
+) -> Result<Json<TradeAdjustmentResponse>, ApiError> {
+    require_primary(&engine).await?;
+    let Json(req) = payload?;
+    let mut errors = Vec::new();
+    if req.price == Some(0) {
+        errors.push(FieldError::new("price", "price must be greater than 0"));
+    }
+    if req.quantity == Some(0) {
+        errors.push(FieldError::new(
+            "quantity",
+            "quantity must be greater than 0",
+        ));
+    }
+    if req.price.is_none() && req.quantity.is_none() {
+        errors.push(FieldError::new(
+            "price",
+            "a price or quantity correction is required",
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let result = match &consensus {
+        Some(consensus) => {
+            consensus
+                .correct_trade(trade_id, req.price, req.quantity, req.reason)
+                .await?
+        }
+        None => {
+            engine
+                .correct_trade(trade_id, req.price, req.quantity, req.reason)
+                .await
+        }
+    };
+    let trade = result.map_err(|e| trade_adjustment_error(trade_id, e))?;
+    Ok(Json(TradeAdjustmentResponse { trade }))
+}
+
+async fn get_blocked_participants(
+    State(engine): State<MatchingEngine>,
+) -> Json<BlockedParticipantsResponse> {
+    Json(BlockedParticipantsResponse {
+        blocked: engine.blocked_participants().await,
+    })
+}
+
+async fn kill_participant(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(participant): Path<ParticipantId>,
+) -> Result<Json<KillSwitchResponse>, ApiError> {
+    require_primary(&engine).await?;
+    let canceled = match &consensus {
+        Some(consensus) => consensus.kill_participant(participant.clone()).await?,
+        None => engine.kill_participant(participant.clone()).await?,
+    };
+    Ok(Json(KillSwitchResponse {
+        participant,
+        canceled: canceled.into_iter().map(|o| o.id).collect(),
+    }))
+}
+
+async fn enable_participant(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(participant): Path<ParticipantId>,
+) -> Result<Json<BlockedParticipantsResponse>, ApiError> {
+    let enabled = match &consensus {
+        Some(consensus) => consensus.enable_participant(participant.clone()).await?,
+        None => engine.enable_participant(&participant).await?,
+    };
+    if !enabled {
+        return Err(ApiError::new(
+            ErrorCode::InvalidTransition,
+            format!("participant {participant} is not blocked"),
+        ));
+    }
+    Ok(Json(BlockedParticipantsResponse {
+        blocked: engine.blocked_participants().await,
+    }))
+}
+
+async fn get_risk_limits(
+    State(engine): State<MatchingEngine>,
+    Path(participant): Path<ParticipantId>,
+) -> Json<RiskLimitsResponse> {
+    let limits = engine.risk_limits(&participant).await;
+    let net_position = engine.net_position(&participant).await;
+    Json(RiskLimitsResponse {
+        participant,
+        limits,
+        net_position,
+    })
+}
+
+async fn set_risk_limits(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(participant): Path<ParticipantId>,
+    payload: Result<Json<RiskLimits>, JsonRejection>,
+) -> Result<Json<RiskLimitsResponse>, ApiError> {
+    let Json(limits) = payload?;
+    match &consensus {
+        Some(consensus) => {
+            consensus
+                .set_risk_limits(participant.clone(), limits)
+                .await?
+        }
+        None => engine.set_risk_limits(participant.clone(), limits).await,
+    }
+    let net_position = engine.net_position(&participant).await;
+    Ok(Json(RiskLimitsResponse {
+        participant,
+        limits,
+        net_position,
+    }))
+}
+
+async fn get_quote_protection(
+    State(engine): State<MatchingEngine>,
+    Path(participant): Path<ParticipantId>,
+) -> Json<QuoteProtectionResponse> {
+    let protection = engine.quote_protection(&participant).await;
+    Json(QuoteProtectionResponse {
+        participant,
+        protection,
+    })
+}
+
+/// Participants over the order-to-trade ratio, worst first
+async fn get_otr_offenders(State(engine): State<MatchingEngine>) -> Json<OtrResponse> {
+    let (config, offenders) = engine.otr_offenders().await;
+    Json(OtrResponse { config, offenders })
+}
+
+async fn set_quote_protection(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(participant): Path<ParticipantId>,
+    payload: Result<Json<QuoteProtection>, JsonRejection>,
+) -> Result<Json<QuoteProtectionResponse>, ApiError> {
+    let Json(protection) = payload?;
+    let mut errors = Vec::new();
+    if protection.max_fills == Some(0) {
+        errors.push(FieldError::new("max_fills", "must be > 0"));
+    }
+    if protection.max_volume == Some(0) {
+        errors.push(FieldError::new("max_volume", "must be > 0"));
+    }
+    if protection.interval_ms == 0 {
+        errors.push(FieldError::new("interval_ms", "must be > 0"));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    match &consensus {
+        Some(consensus) => {
+            consensus
+                .set_quote_protection(participant.clone(), protection)
+                .await?
+        }
+        None => {
+            engine
+                .set_quote_protection(participant.clone(), protection)
+                .await
+        }
+    }
+    Ok(Json(QuoteProtectionResponse {
+        participant: participant.clone(),
+        protection: engine.quote_protection(&participant).await,
+    }))
+}
+
+async fn deposit(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(account): Path<ParticipantId>,
+    payload: Result<Json<TransferRequest>, JsonRejection>,
+) -> Result<Json<BalancesResponse>, ApiError> {
+    let Json(req) = payload?;
+    let balances = match &consensus {
+        Some(consensus) => {
+            consensus
+                .deposit(account.clone(), req.asset, req.amount)
+                .await?
+        }
+        None => engine.deposit(account.clone(), req.asset, req.amount).await,
+    };
+    Ok(Json(BalancesResponse { account, balances }))
+}
+
+async fn withdraw(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(account): Path<ParticipantId>,
+    payload: Result<Json<TransferRequest>, JsonRejection>,
+) -> Result<Json<BalancesResponse>, ApiError> {
+    let Json(req) = payload?;
+    let balances = match &consensus {
+        Some(consensus) => {
+            consensus
+                .withdraw(account.clone(), req.asset, req.amount)
+                .await?
+        }
+        None => engine.withdraw(&account, req.asset, req.amount).await,
+    };
+    let balances = balances.ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::InsufficientBalance,
+            format!("{account} has less than {} available", req.amount),
+        )
+    })?;
+    Ok(Json(BalancesResponse { account, balances }))
+}
+
+async fn get_settlement_batches(
+    State(engine): State<MatchingEngine>,
+) -> Json<SettlementBatchesResponse> {
+    Json(SettlementBatchesResponse {
+        batches: engine.settlement_batches(None).await,
+    })
+}
+
+async fn close_settlement_batch(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+) -> Result<Json<SettlementBatchResponse>, ApiError> {
+    let batch = match &consensus {
+        Some(consensus) => consensus.close_settlement_batch().await?,
+        None => engine.close_settlement_batch().await,
+    };
+    Ok(Json(SettlementBatchResponse { batch }))
+}
+
+async fn settle_batch(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Path(batch_id): Path<u64>,
+) -> Result<Json<SettlementBatchResponse>, ApiError> {
+    let result = match &consensus {
+        Some(consensus) => consensus.settle_batch(batch_id).await?,
+        None => engine.settle_batch(batch_id).await,
+    };
+    let batch = result.map_err(|e| match e {
+        SettlementError::NotFound => ApiError::new(
+            ErrorCode::BatchNotFound,
+            format!("no closed settlement batch with id {batch_id}"),
+        ),
+        SettlementError::NotPending => ApiError::new(
+            ErrorCode::InvalidTransition,
+            format!("settlement batch {batch_id} is already settled"),
+        ),
+    })?;
+    Ok(Json(SettlementBatchResponse { batch: Some(batch) }))
+}
+
+async fn export_audit_log(
+    State(engine): State<MatchingEngine>,
+    query: Result<Query<AuditQuery>, QueryRejection>,
+) -> Result<Json<AuditExportResponse>, ApiError> {
+    let Query(query) = query?;
+    let limit = query.limit.unwrap_or(MAX_AUDIT_PAGE);
+    if limit == 0 || limit > MAX_AUDIT_PAGE {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "limit",
+            format!("limit must be between 1 and {MAX_AUDIT_PAGE}"),
+        )]));
+    }
+    Ok(Json(AuditExportResponse {
+        entries: engine.audit_entries(query.from, limit).await,
+    }))
+}
+
+async fn verify_audit_log(State(engine): State<MatchingEngine>) -> Json<AuditVerifyResponse> {
+    let (entries, head_hash, result) = engine.verify_audit_log().await;
+    Json(AuditVerifyResponse {
+        valid: result.is_ok(),
+        entries,
+        head_hash,
+        first_invalid: result.err(),
+    })
+}
+
+/// Verifies a previously exported (possibly partial) chain, e.g. an archived copy
+async fn verify_audit_export(
+    payload: Result<Json<VerifyAuditRequest>, JsonRejection>,
+) -> Result<Json<AuditVerifyResponse>, ApiError> {
+    let Json(req) = payload?;
+    let prev_hash = req.prev_hash.as_deref().unwrap_or(GENESIS_HASH);
+    let result = audit::verify(&req.entries, prev_hash);
+    Ok(Json(AuditVerifyResponse {
+        valid: result.is_ok(),
+        entries: req.entries.len() as u64,
+        head_hash: req
+            .entries
+            .last()
+            .map_or(prev_hash, |e| e.hash.as_str())
+            .to_string(),
+        first_invalid: result.err(),
+    }))
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    errors::Result,
+    file::{
+        properties::WriterProperties,
+        writer::{SerializedFileWriter, SerializedRowGroupWriter},
+    },
+    schema::parser::parse_message_type,
+};
+
+use crate::{
+    matchingengine::MatchingEngine,
+    order::Timestamp,
+    settlement,
+    tradeexport::{self, Execution},
+};
+
+pub const ARCHIVE_DIR_ENV: &str = "OME_ARCHIVE_DIR"; // unset disables archiving
+pub const ARCHIVE_INTERVAL_SECS_ENV: &str = "OME_ARCHIVE_INTERVAL_SECS";
+
+/// Symbol of the default exchange's partitions; tenants use their name
+pub const ROOT_SYMBOL: &str = "root";
+
+const DEFAULT_ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+pub const DAY_MS: Timestamp = 24 * 60 * 60 * 1000;
+
+const TRADE_SCHEMA: &str = "
+    message trade {
+        required int64 trade_id;
+        required int64 seq;
+        required int64 time (TIMESTAMP(MILLIS, true));
+        required int64 price;
+        required int64 quantity;
+        optional binary aggressor (UTF8);
+        required binary buy_order_id (UTF8);
+        required binary sell_order_id (UTF8);
+        optional binary buyer (UTF8);
+        optional binary seller (UTF8);
+        required binary status (UTF8);
+    }
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveConfig {
+    pub dir: PathBuf,
+    /// How often to look for completed days
+    pub interval: Duration,
+}
+
+impl ArchiveConfig {
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var(ARCHIVE_DIR_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())?;
+        let interval = std::env::var(ARCHIVE_INTERVAL_SECS_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_ARCHIVE_INTERVAL);
+        Some(ArchiveConfig {
+            dir: dir.into(),
+            interval,
+        })
+    }
+}
+
+/// Start (unix ms) of the UTC day `time` falls on
+pub fn day_of(time: Timestamp) -> Timestamp {
+    time - time % DAY_MS
+}
+
+/// `<dir>/date=YYYY-MM-DD/symbol=<symbol>/trades.parquet`
+pub fn partition_path(dir: &Path, day: Timestamp, symbol: &str) -> PathBuf {
+    let date = chrono::DateTime::from_timestamp_millis(day as i64)
+        .expect("day within chrono's range")
+        .format("%Y-%m-%d");
+    dir.join(format!("date={date}"))
+        .join(format!("symbol={symbol}"))
+        .join("trades.parquet")
+}
+
+/// Groups executions by the UTC day they happened on
+fn by_day(executions: Vec<Execution>) -> BTreeMap<Timestamp, Vec<Execution>> {
+    let mut days: BTreeMap<Timestamp, Vec<Execution>> = BTreeMap::new();
+    for execution in executions {
+        days.entry(day_of(execution.time))
+            .or_default()
+            .push(execution);
+    }
+    days
+}
+
+fn write_int64(row_group: &mut SerializedRowGroupWriter<'_, File>, values: Vec<i64>) -> Result<()> {
+    let mut column = row_group.next_column()?.expect("column in schema");
+    column
+        .typed::<Int64Type>()
+        .write_batch(&values, None, None)?;
+    column.close()
+}
+
+fn write_strings(
+    row_group: &mut SerializedRowGroupWriter<'_, File>,
+    values: Vec<Option<String>>,
+) -> Result<()> {
+    let mut column = row_group.next_column()?.expect("column in schema");
+    let writer = column.typed::<ByteArrayType>();
+    let optional = writer.get_descriptor().max_def_level() > 0;
+    let def_levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+    let values: Vec<ByteArray> = values
+        .iter()
+        .flatten()
+        .map(|v| ByteArray::from(v.as_str()))
+        .collect();
+    writer.write_batch(&values, optional.then_some(&def_levels[..]), None)?;
+    column.close()
+}
+
+/// Writes `executions` as one row group to `path`, through a temporary file so readers never see
+/// a partial archive
+pub fn write_parquet(path: &Path, executions: &[Execution]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("parquet.partial");
+    let schema = Arc::new(parse_message_type(TRADE_SCHEMA)?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(File::create(&partial)?, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+
+    let int64 = |f: fn(&Execution) -> u64| executions.iter().map(|e| f(e) as i64).collect();
+    let strings = |f: fn(&Execution) -> Option<String>| executions.iter().map(f).collect();
+    write_int64(&mut row_group, int64(|e| e.trade.id))?;
+    write_int64(&mut row_group, int64(|e| e.seq))?;
+    write_int64(&mut row_group, int64(|e| e.time))?;
+    write_int64(&mut row_group, int64(|e| e.trade.price))?;
+    write_int64(&mut row_group, int64(|e| e.trade.quantity))?;
+    write_strings(
+        &mut row_group,
+        strings(|e| {
+            e.trade
+                .aggressor
+                .map(|s| tradeexport::side_name(s).to_string())
+        }),
+    )?;
+    write_strings(
+        &mut row_group,
+        strings(|e| Some(e.trade.buy_order_id.clone())),
+    )?;
+    write_strings(
+        &mut row_group,
+        strings(|e| Some(e.trade.sell_order_id.clone())),
+    )?;
+    write_strings(&mut row_group, strings(|e| e.trade.buyer.clone()))?;
+    write_strings(&mut row_group, strings(|e| e.trade.seller.clone()))?;
+    write_strings(
+        &mut row_group,
+        strings(|e| Some(tradeexport::status_name(&e.trade.status).to_string())),
+    )?;
+
+    row_group.close()?;
+    writer.close()?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Writes one archive per UTC day of `executions` that has none yet; returns the number of files
+/// written
+pub fn archive_days(dir: &Path, symbol: &str, executions: Vec<Execution>) -> Result<usize> {
+    let mut written = 0;
+    for (day, executions) in by_day(executions) {
+        let path = partition_path(dir, day, symbol);
+        if path.exists() {
+            continue;
+        }
+        write_parquet(&path, &executions)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+/// Every `config.interval`, rolls the trades of days that ended since the last run into Parquet
+/// files, one per day and symbol. The live API keeps serving recent trades from memory; busts
+/// and corrections after a day was archived don't rewrite its file.
+pub async fn run_archiver(engine: MatchingEngine, symbol: String, config: ArchiveConfig) {
+    let mut tick = tokio::time::interval(config.interval);
+    let mut archived_until = 0; // days before this were archived by this process
+    loop {
+        tick.tick().await;
+        let today = day_of(settlement::now_ms());
+        if today <= archived_until {
+            continue;
+        }
+        let executions = engine.executions(archived_until, today).await;
+        let (dir, symbol_name) = (config.dir.clone(), symbol.clone());
+        let result =
+            tokio::task::spawn_blocking(move || archive_days(&dir, &symbol_name, executions))
+                .await
+                .expect("archive task panicked");
+        match result {
+            Ok(written) => {
+                if written > 0 {
+                    println!("{symbol}: archived {written} day(s) of trades");
+                }
+                archived_until = today;
+            }
+            Err(e) => println!("{symbol}: trade archiving failed, retrying next run: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{Side, Trade};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn execution(id: u64, time: Timestamp) -> Execution {
+        let mut trade = Trade::new(id, format!("b{id}"), format!("s{id}"), 100 + id, 5)
+            .with_aggressor(Side::Buy);
+        trade.buyer = Some("alice".to_string());
+        Execution {
+            seq: id,
+            time,
+            trade,
+        }
+    }
+
+    #[test]
+    fn test_partition_path() {
+        assert_eq!(
+            partition_path(Path::new("/archive"), 1_700_006_400_000, "acme"),
+            Path::new("/archive/date=2023-11-15/symbol=acme/trades.parquet")
+        );
+    }
+
+    #[test]
+    fn test_archive_days() {
+        let dir = std::env::temp_dir().join(format!("ome-archive-{}", std::process::id()));
+        let day = 1_700_006_400_000;
+        let executions = vec![
+            execution(1, day + 10),
+            execution(2, day + 20),
+            execution(3, day + DAY_MS),
+        ];
+        assert_eq!(archive_days(&dir, "root", executions.clone()).unwrap(), 2);
+        // existing partitions are left alone
+        assert_eq!(archive_days(&dir, "root", executions).unwrap(), 0);
+
+        let file = File::open(partition_path(&dir, day, "root")).unwrap();
+        let reader = SerializedFileReader::new(file).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        let row = reader.get_row_iter(None).unwrap().next().unwrap().unwrap();
+        assert_eq!(
+            row.to_string(),
+            "{trade_id: 1, seq: 1, time: 2023-11-15 00:00:00.010 +00:00, price: 101, \
+             quantity: 5, aggressor: \"buy\", buy_order_id: \"b1\", sell_order_id: \"s1\", \
+             buyer: \"alice\", seller: null, status: \"active\"}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
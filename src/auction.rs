@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::{
+    order::{Price, Quantity, Side},
+    orderbook::OrderBook,
+};
+
+/// Indicative (or final) outcome of uncrossing the book at a single price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Equilibrium {
+    pub price: Price,
+    pub volume: Quantity,
+    /// Buy quantity minus sell quantity left unexecuted at `price`
+    pub imbalance: i128,
+}
+
+/// Finds the price that maximises executable volume. Ties are broken by the smallest
+/// imbalance, then towards the side with surplus (higher price when buyers remain), then the lower price.
+pub fn equilibrium(book: &OrderBook) -> Option<Equilibrium> {
+    let candidates: BTreeSet<Price> = book
+        .levels(Side::Buy)
+        .chain(book.levels(Side::Sell))
+        .map(|(price, _)| price)
+        .collect();
+
+    let mut best: Option<Equilibrium> = None;
+    for price in candidates {
+        let demand: Quantity = book
+            .levels(Side::Buy)
+            .take_while(|(p, _)| *p >= price)
+            .flat_map(|(_, level)| level.iter())
+            .map(|o| o.quantity)
+            .sum();
+        let supply: Quantity = book
+            .levels(Side::Sell)
+            .take_while(|(p, _)| *p <= price)
+            .flat_map(|(_, level)| level.iter())
+            .map(|o| o.quantity)
+            .sum();
+        let volume = demand.min(supply);
+        if volume == 0 {
+            continue;
+        }
+
+        let candidate = Equilibrium {
+            price,
+            volume,
+            imbalance: demand as i128 - supply as i128,
+        };
+        best = match best {
+            None => Some(candidate),
+            Some(current) => Some(better(current, candidate)),
+        };
+    }
+    best
+}
+
+// candidates arrive in ascending price order
+fn better(current: Equilibrium, candidate: Equilibrium) -> Equilibrium {
+    if candidate.volume != current.volume {
+        return if candidate.volume > current.volume {
+            candidate
+        } else {
+            current
+        };
+    }
+    if candidate.imbalance.abs() != current.imbalance.abs() {
+        return if candidate.imbalance.abs() < current.imbalance.abs() {
+            candidate
+        } else {
+            current
+        };
+    }
+    if candidate.imbalance > 0 {
+        candidate // buy pressure pushes the price up
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{Order, OrderType, Side};
+
+    fn book(orders: &[(Side, Price, Quantity)]) -> OrderBook {
+        let mut ob = OrderBook::new();
+        for (i, (side, price, quantity)) in orders.iter().enumerate() {
+            ob.add_order(Order::new(
+                i.to_string(),
+                *side,
+                OrderType::Limit,
+                *quantity,
+                *price,
+                i as u64,
+            ));
+        }
+        ob
+    }
+
+    #[test]
+    fn test_no_cross() {
+        let ob = book(&[(Side::Buy, 99, 10), (Side::Sell, 101, 10)]);
+        assert_eq!(equilibrium(&ob), None);
+    }
+
+    #[test]
+    fn test_max_volume_price() {
+        let ob = book(&[
+            (Side::Buy, 102, 10),
+            (Side::Buy, 101, 20),
+            (Side::Buy, 100, 30),
+            (Side::Sell, 99, 15),
+            (Side::Sell, 100, 20),
+            (Side::Sell, 101, 40),
+        ]);
+        // at 100: demand 60, supply 35 -> 35; at 101: demand 30, supply 75 -> 30
+        assert_eq!(
+            equilibrium(&ob),
+            Some(Equilibrium {
+                price: 100,
+                volume: 35,
+                imbalance: 25,
+            })
+        );
+    }
+
+    #[test]
+    fn test_imbalance_tiebreak() {
+        let ob = book(&[(Side::Buy, 105, 10), (Side::Sell, 100, 10)]);
+        // every price in [100, 105] executes 10 with no imbalance -> lowest price
+        assert_eq!(equilibrium(&ob).unwrap().price, 100);
+
+        let ob = book(&[(Side::Buy, 105, 15), (Side::Sell, 100, 10)]);
+        // buyers left over at every candidate -> highest price
+        assert_eq!(equilibrium(&ob).unwrap().price, 105);
+    }
+}
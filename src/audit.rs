@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    circuitbreaker::BreakerTrip,
+    order::{
+        Order, OrderId, ParticipantId, Price, Quantity, RejectReason, Timestamp, Trade, TradeId,
+    },
+    replication::ReplicationError,
+    rfq::{Rfq, RfqId, RfqResponse},
+    runtimeconfig::RuntimeConfig,
+};
+
+/// `prev_hash` of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    OrderAccepted {
+        order: Order,
+    },
+    /// Held back until `activate_at` (unix ms), then accepted or rejected like a new order
+    OrderScheduled {
+        order: Order,
+        activate_at: Timestamp,
+    },
+    OrderRejected {
+        order_id: OrderId,
+        owner: Option<ParticipantId>,
+        reason: RejectReason,
+    },
+    OrderCanceled {
+        order: Order,
+    },
+    /// A DAY order's remaining quantity, pulled when the session closed
+    OrderExpired {
+        order: Order,
+    },
+    /// The market reached the trigger of an order accepted with one; the order, its trigger
+    /// cleared, is then accepted or rejected like a new order
+    OrderTriggered {
+        order: Order,
+    },
+    /// A trailing stop followed the market; its trigger is now at `price`
+    TriggerMoved {
+        order_id: OrderId,
+        owner: Option<ParticipantId>,
+        price: Price,
+    },
+    /// A participant's quote now stands as these orders, its previous ones pulled
+    QuoteUpdated {
+        owner: Option<ParticipantId>,
+        orders: Vec<OrderId>,
+    },
+    /// Fills of a participant's quote reached their market maker protection within its
+    /// interval; the `canceled` rest of the quote was pulled
+    QuoteProtectionTripped {
+        owner: Option<ParticipantId>,
+        fills: u32,
+        volume: Quantity,
+        canceled: Vec<OrderId>,
+    },
+    /// A taker asked the selected makers for a price
+    RfqCreated {
+        rfq: Rfq,
+    },
+    /// A maker answered an RFQ, replacing their previous response
+    RfqQuoted {
+        rfq_id: RfqId,
+        taker: Option<ParticipantId>,
+        response: RfqResponse,
+    },
+    /// The RFQ's window closed; `trade_id` is the execution of the best usable response,
+    /// `None` when there was none
+    RfqClosed {
+        rfq_id: RfqId,
+        taker: Option<ParticipantId>,
+        trade_id: Option<TradeId>,
+    },
+    TradeExecuted {
+        trade: Trade,
+    },
+    /// Carries the trade after the bust or correction, its status has the reason
+    TradeBusted {
+        trade: Trade,
+    },
+    TradeCorrected {
+        trade: Trade,
+    },
+    /// Trade prices moved too far within the breaker's window; the book is halted or in a
+    /// volatility auction until `trip.ends_at()`
+    CircuitBreakerTripped {
+        trip: BreakerTrip,
+    },
+    /// The cooldown of `trip` ended and the book is back, the auction uncrossed
+    CircuitBreakerEnded {
+        trip: BreakerTrip,
+    },
+    /// An operator changed the limits through `/admin/config`; carries only the sections changed
+    ConfigChanged {
+        config: RuntimeConfig,
+    },
+    /// The kill switch blocked `owner`; their pulled orders come before as cancels
+    ParticipantBlocked {
+        owner: Option<ParticipantId>,
+    },
+    ParticipantUnblocked {
+        owner: Option<ParticipantId>,
+    },
+    /// An operator halted trading, or changed whether cancels are allowed while halted
+    TradingHalted {
+        allow_cancels: bool,
+    },
+    /// Trading resumed after a halt, by an operator or at the end of the breaker's cooldown
+    TradingResumed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Unix timestamp (ms)
+    pub timestamp: Timestamp,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+    pub prev_hash: String,
+    /// Hex SHA-256 over `prev_hash`, `seq`, `timestamp` and the event
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn digest(prev_hash: &str, seq: u64, timestamp: Timestamp, event: &AuditEvent) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.as_bytes());
+        hasher.update(seq.to_be_bytes());
+        hasher.update(timestamp.to_be_bytes());
+        hasher.update(serde_json::to_vec(event).expect("audit events serialize"));
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Append-only, hash-chained record of every order event; editing or dropping an entry
+/// breaks the chain from that entry on. Its `seq` is the engine's global event sequence.
+#[derive(Debug)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    /// Sequence number and hash of the entry the held ones follow; `0` and the genesis hash
+    /// unless the log was resumed from an imported state
+    base_seq: u64,
+    base_hash: String,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog {
+            entries: Vec::new(),
+            base_seq: 0,
+            base_hash: GENESIS_HASH.to_string(),
+        }
+    }
+}
+
+impl AuditLog {
+    /// Sequence number the next appended event gets
+    pub fn next_seq(&self) -> u64 {
+        self.last_seq() + 1
+    }
+
+    /// Sequence number of the latest event, `0` before the first
+    pub fn last_seq(&self) -> u64 {
+        self.base_seq + self.entries.len() as u64
+    }
+
+    /// Drops the held entries and continues the chain after event `seq` with hash `hash`, so
+    /// an engine restored from an exported state keeps numbering where the original left off.
+    pub fn resume_after(&mut self, seq: u64, hash: String) {
+        self.entries.clear();
+        self.base_seq = seq;
+        self.base_hash = hash;
+    }
+
+    /// Appends `event` as the next entry and returns it
+    pub fn append(&mut self, event: AuditEvent, timestamp: Timestamp) -> &AuditEntry {
+        let prev_hash = self.head().to_string();
+        let seq = self.next_seq();
+        let hash = AuditEntry::digest(&prev_hash, seq, timestamp, &event);
+        let entry = AuditEntry {
+            seq,
+            timestamp,
+            event,
+            prev_hash,
+            hash,
+        };
+        self.entries.push(entry);
+        self.entries.last().expect("just appended")
+    }
+
+    /// Appends an entry of another engine's log, e.g. a primary this one mirrors, keeping its
+    /// sequence number, timestamp and hash. Returns `false` for an entry that is already held.
+    pub fn append_replicated(&mut self, entry: AuditEntry) -> Result<bool, ReplicationError> {
+        let expected = self.next_seq();
+        if entry.seq < expected {
+            return Ok(false);
+        }
+        if entry.seq > expected {
+            return Err(ReplicationError::OutOfSequence {
+                expected,
+                got: entry.seq,
+            });
+        }
+        let hash = AuditEntry::digest(&entry.prev_hash, entry.seq, entry.timestamp, &entry.event);
+        if entry.prev_hash != self.head() || entry.hash != hash {
+            return Err(ReplicationError::HashMismatch { seq: entry.seq });
+        }
+        self.entries.push(entry);
+        Ok(true)
+    }
+
+    /// Up to `limit` entries starting at sequence number `from`
+    pub fn export(&self, from: u64, limit: usize) -> Vec<AuditEntry> {
+        let start = (from.max(self.base_seq + 1) - self.base_seq - 1) as usize;
+        self.entries
+            .iter()
+            .skip(start)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Entries timestamped at or after `timestamp`
+    pub fn since(&self, timestamp: Timestamp) -> &[AuditEntry] {
+        let start = self.entries.partition_point(|e| e.timestamp < timestamp);
+        &self.entries[start..]
+    }
+
+    /// Sequence number of the first entry at or after `timestamp`; `None` when no kept entry is
+    /// older, so where that point falls isn't known
+    pub fn seq_at(&self, timestamp: Timestamp) -> Option<u64> {
+        let start = self.entries.partition_point(|e| e.timestamp < timestamp);
+        (start > 0).then(|| self.base_seq + start as u64 + 1)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    pub fn head(&self) -> &str {
+        self.entries
+            .last()
+            .map_or(self.base_hash.as_str(), |e| e.hash.as_str())
+    }
+
+    pub fn verify(&self) -> Result<(), u64> {
+        verify(&self.entries, &self.base_hash)
+    }
+}
+
+/// Checks that `entries` form an unbroken chain starting after `prev_hash`, returns the
+/// sequence number of the first entry that doesn't
+pub fn verify(entries: &[AuditEntry], prev_hash: &str) -> Result<(), u64> {
+    let mut prev_hash = prev_hash;
+    let first_seq = entries.first().map_or(1, |e| e.seq);
+    for (expected_seq, entry) in (first_seq..).zip(entries) {
+        let hash = AuditEntry::digest(prev_hash, entry.seq, entry.timestamp, &entry.event);
+        if entry.seq != expected_seq || entry.prev_hash != prev_hash || entry.hash != hash {
+            return Err(entry.seq);
+        }
+        prev_hash = &entry.hash;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    fn order(id: &str) -> Order {
+        Order::new(id.to_string(), Side::Buy, OrderType::Limit, 10, 100, 1)
+    }
+
+    fn log() -> AuditLog {
+        let mut log = AuditLog::default();
+        log.append(AuditEvent::OrderAccepted { order: order("1") }, 10);
+        log.append(AuditEvent::OrderCanceled { order: order("1") }, 20);
+        log.append(
+            AuditEvent::OrderRejected {
+                order_id: String::from("2"),
+                owner: None,
+                reason: RejectReason::Halted,
+            },
+            30,
+        );
+        log
+    }
+
+    #[test]
+    fn test_chain() {
+        let log = log();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.verify(), Ok(()));
+        let entries = log.export(1, 10);
+        assert_eq!(entries[0].prev_hash, GENESIS_HASH);
+        assert_eq!(entries[1].prev_hash, entries[0].hash);
+        assert_eq!(log.head(), entries[2].hash);
+
+        // a partial export verifies against the hash of the entry before it
+        let tail = log.export(2, 10);
+        assert_eq!(verify(&tail, &entries[0].hash), Ok(()));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut log = log();
+        log.entries[1].timestamp = 21;
+        assert_eq!(log.verify(), Err(2));
+
+        let mut log = self::log();
+        log.entries.remove(1);
+        assert_eq!(log.verify(), Err(3));
+    }
+
+    #[test]
+    fn test_resume_after() {
+        let original = log();
+        let mut log = AuditLog::default();
+        log.resume_after(original.last_seq(), original.head().to_string());
+        assert_eq!(log.next_seq(), 4);
+        log.append(AuditEvent::OrderAccepted { order: order("3") }, 40);
+
+        let entries = log.export(1, 10);
+        assert_eq!(entries[0].seq, 4);
+        assert_eq!(entries[0].prev_hash, original.head());
+        assert_eq!(log.verify(), Ok(()));
+    }
+}
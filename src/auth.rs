@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, header::AUTHORIZATION},
+    middleware::Next,
+    response::Response,
+};
+use jsonwebtoken::{DecodingKey, Validation, decode};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ApiError, ErrorCode};
+
+pub const JWT_SECRET_ENV: &str = "OME_JWT_SECRET";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    ReadOnly,
+    Trader,
+    Admin,
+    /// Only the drop-copy feed, for risk/compliance consumers
+    DropCopy,
+}
+
+impl Role {
+    /// Admins may do everything, traders may also read; read-only and drop-copy are just that
+    pub fn grants(&self, required: Role) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Trader => matches!(required, Role::Trader | Role::ReadOnly),
+            Role::ReadOnly => required == Role::ReadOnly,
+            Role::DropCopy => required == Role::DropCopy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub roles: Vec<Role>,
+    pub exp: u64,
+    /// Tenant the token is scoped to; tokens without one are only valid on the default exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// Subject of every request while authentication is disabled
+pub const ANONYMOUS: &str = "anonymous";
+
+/// The authenticated caller, inserted into the request extensions by the auth middleware
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub roles: Vec<Role>,
+}
+
+impl Principal {
+    /// Whether the caller went unauthenticated, authentication being disabled
+    pub fn is_anonymous(&self) -> bool {
+        self.subject == ANONYMOUS
+    }
+
+    pub fn has_role(&self, required: Role) -> bool {
+        self.roles.iter().any(|r| r.grants(required))
+    }
+
+    /// Participants see their own account, admins see every account
+    pub fn can_access_account(&self, account: &str) -> bool {
+        self.subject == account || self.has_role(Role::Admin)
+    }
+}
+
+#[derive(Clone)]
+pub struct Auth {
+    key: Option<Arc<DecodingKey>>, // `None` disables authentication altogether
+    validation: Validation,
+    tenant: Option<String>, // only tokens scoped to this tenant are accepted
+}
+
+impl Auth {
+    pub fn new(secret: &[u8]) -> Self {
+        Auth {
+            key: Some(Arc::new(DecodingKey::from_secret(secret))),
+            validation: Validation::default(), // HS256 with `exp` required
+            tenant: None,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Auth {
+            key: None,
+            validation: Validation::default(),
+            tenant: None,
+        }
+    }
+
+    /// Same keys, but only accepting tokens issued for `tenant`
+    pub fn for_tenant(&self, tenant: &str) -> Self {
+        Auth {
+            tenant: Some(tenant.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// Enables HS256 JWT authentication when `OME_JWT_SECRET` is set
+    pub fn from_env() -> Self {
+        match std::env::var(JWT_SECRET_ENV) {
+            Ok(secret) if !secret.is_empty() => Auth::new(secret.as_bytes()),
+            _ => Auth::disabled(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Principal, ApiError> {
+        let key = match &self.key {
+            Some(key) => key,
+            None => {
+                return Ok(Principal {
+                    subject: String::from(ANONYMOUS),
+                    roles: vec![Role::Admin],
+                });
+            }
+        };
+
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::new(ErrorCode::Unauthorized, "missing bearer token"))?;
+
+        let data = decode::<Claims>(token, key, &self.validation)
+            .map_err(|e| ApiError::new(ErrorCode::Unauthorized, format!("invalid token: {e}")))?;
+        if data.claims.tenant != self.tenant {
+            return Err(ApiError::new(
+                ErrorCode::Forbidden,
+                "token was issued for another tenant",
+            ));
+        }
+
+        Ok(Principal {
+            subject: data.claims.sub,
+            roles: data.claims.roles,
+        })
+    }
+
+    pub fn authorize(&self, headers: &HeaderMap, required: Role) -> Result<Principal, ApiError> {
+        let principal = self.authenticate(headers)?;
+        if principal.has_role(required) {
+            Ok(principal)
+        } else {
+            Err(ApiError::new(
+                ErrorCode::Forbidden,
+                format!("{} lacks the {:?} role", principal.subject, required),
+            ))
+        }
+    }
+}
+
+async fn require_role(
+    auth: &Auth,
+    required: Role,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let principal = auth.authorize(req.headers(), required)?;
+    req.extensions_mut().insert(principal);
+    Ok(next.run(req).await)
+}
+
+pub async fn require_read_only(
+    State(auth): State<Auth>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, Role::ReadOnly, req, next).await
+}
+
+pub async fn require_trader(
+    State(auth): State<Auth>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, Role::Trader, req, next).await
+}
+
+pub async fn require_drop_copy(
+    State(auth): State<Auth>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, Role::DropCopy, req, next).await
+}
+
+pub async fn require_admin(
+    State(auth): State<Auth>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    require_role(&auth, Role::Admin, req, next).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn bearer(roles: Vec<Role>, exp: u64) -> HeaderMap {
+        tenant_bearer(roles, exp, None)
+    }
+
+    fn tenant_bearer(roles: Vec<Role>, exp: u64, tenant: Option<&str>) -> HeaderMap {
+        let claims = Claims {
+            sub: String::from("alice"),
+            roles,
+            exp,
+            tenant: tenant.map(String::from),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {token}").parse().unwrap());
+        headers
+    }
+
+    fn far_future() -> u64 {
+        (chrono::Utc::now().timestamp() + 3600) as u64
+    }
+
+    #[test]
+    fn test_role_grants() {
+        assert!(Role::Admin.grants(Role::Trader));
+        assert!(Role::Trader.grants(Role::ReadOnly));
+        assert!(!Role::Trader.grants(Role::Admin));
+        assert!(!Role::ReadOnly.grants(Role::Trader));
+        assert!(Role::Admin.grants(Role::DropCopy));
+        assert!(!Role::Trader.grants(Role::DropCopy));
+        assert!(!Role::DropCopy.grants(Role::ReadOnly));
+    }
+
+    #[test]
+    fn test_authorize() {
+        let auth = Auth::new(SECRET);
+
+        let principal = auth
+            .authorize(&bearer(vec![Role::Trader], far_future()), Role::Trader)
+            .unwrap();
+        assert_eq!(principal.subject, "alice");
+
+        let err = auth
+            .authorize(&bearer(vec![Role::ReadOnly], far_future()), Role::Trader)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Forbidden);
+
+        let err = auth
+            .authorize(&HeaderMap::new(), Role::ReadOnly)
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+
+        let err = auth
+            .authorize(&bearer(vec![Role::Admin], 1), Role::Admin) // expired
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+    }
+
+    #[test]
+    fn test_tenant_scoped_tokens() {
+        let root = Auth::new(SECRET);
+        let acme = root.for_tenant("acme");
+        let acme_token = tenant_bearer(vec![Role::Trader], far_future(), Some("acme"));
+        let root_token = bearer(vec![Role::Trader], far_future());
+
+        assert!(acme.authorize(&acme_token, Role::Trader).is_ok());
+        let err = acme.authorize(&root_token, Role::Trader).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Forbidden);
+        assert!(root.authorize(&acme_token, Role::Trader).is_err());
+        assert!(
+            root.for_tenant("other")
+                .authorize(&acme_token, Role::Trader)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_disabled_auth_allows_everything() {
+        let auth = Auth::disabled();
+        assert!(!auth.is_enabled());
+        assert!(auth.authorize(&HeaderMap::new(), Role::Admin).is_ok());
+    }
+}
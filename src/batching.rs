@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+    time::Instant,
+};
+
+use crate::{
+    matchingengine::MatchingEngine,
+    order::{Order, RejectReason, Trade},
+};
+
+pub const BATCH_MAX_ORDERS_ENV: &str = "OME_BATCH_MAX_ORDERS";
+pub const BATCH_MAX_DELAY_US_ENV: &str = "OME_BATCH_MAX_DELAY_US";
+pub const SUBMISSION_QUEUE_SIZE_ENV: &str = "OME_SUBMISSION_QUEUE_SIZE";
+pub const DEFAULT_SUBMISSION_QUEUE_SIZE: usize = 10_000;
+
+/// How many orders may wait for the engine, how many of them it matches under one book lock,
+/// and how long the first of a batch may wait for it to fill up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    pub queue_size: usize,
+    pub max_orders: usize,
+    pub max_delay: Duration,
+}
+
+impl Default for BatchConfig {
+    /// One order at a time, as soon as it's queued
+    fn default() -> Self {
+        BatchConfig {
+            queue_size: DEFAULT_SUBMISSION_QUEUE_SIZE,
+            max_orders: 1,
+            max_delay: Duration::ZERO,
+        }
+    }
+}
+
+impl BatchConfig {
+    pub fn from_env() -> Self {
+        let default = BatchConfig::default();
+        let var = |name| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        BatchConfig {
+            queue_size: var(SUBMISSION_QUEUE_SIZE_ENV)
+                .filter(|size| *size > 0)
+                .map_or(default.queue_size, |size| size as usize),
+            max_orders: var(BATCH_MAX_ORDERS_ENV)
+                .filter(|max| *max > 0)
+                .map_or(default.max_orders, |max| max as usize),
+            max_delay: var(BATCH_MAX_DELAY_US_ENV).map_or(default.max_delay, Duration::from_micros),
+        }
+    }
+}
+
+type Submission = (Order, oneshot::Sender<Result<Vec<Trade>, RejectReason>>);
+
+/// Orders waiting for the batcher, in arrival order
+#[derive(Clone)]
+pub struct SubmissionQueue {
+    sender: mpsc::Sender<Submission>,
+}
+
+impl SubmissionQueue {
+    /// Queues `order` and waits for the result of its batch. A full queue refuses the order
+    /// right away rather than have it wait for the book.
+    pub async fn submit(&self, order: Order) -> Result<Vec<Trade>, RejectReason> {
+        let (reply, result) = oneshot::channel();
+        match self.sender.try_send((order, reply)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Err(RejectReason::Overloaded),
+            Err(TrySendError::Closed(_)) => panic!("the batcher runs as long as its queue"),
+        }
+        result.await.expect("the batcher answers every order")
+    }
+
+    /// Orders waiting, and how many fit
+    pub fn depth(&self) -> (usize, usize) {
+        let size = self.sender.max_capacity();
+        (size - self.sender.capacity(), size)
+    }
+}
+
+/// Starts the batcher of `engine` and returns the queue feeding it
+pub fn start(engine: MatchingEngine, config: BatchConfig) -> SubmissionQueue {
+    let (sender, receiver) = mpsc::channel(config.queue_size);
+    tokio::spawn(run_batcher(engine, receiver, config));
+    SubmissionQueue { sender }
+}
+
+/// Takes the next batch off the queue: whatever is queued up to `max_orders`, waiting up to
+/// `max_delay` after the first order for more
+async fn next_batch(
+    receiver: &mut mpsc::Receiver<Submission>,
+    config: BatchConfig,
+) -> Option<Vec<Submission>> {
+    let first = receiver.recv().await?;
+    let deadline = Instant::now() + config.max_delay;
+    let mut batch = vec![first];
+    while batch.len() < config.max_orders {
+        match tokio::time::timeout_at(deadline, receiver.recv()).await {
+            Ok(Some(submission)) => batch.push(submission),
+            _ => break,
+        }
+    }
+    Some(batch)
+}
+
+async fn run_batcher(
+    mut engine: MatchingEngine,
+    mut receiver: mpsc::Receiver<Submission>,
+    config: BatchConfig,
+) {
+    while let Some(batch) = next_batch(&mut receiver, config).await {
+        let (orders, replies): (Vec<Order>, Vec<_>) = batch.into_iter().unzip();
+        let results = engine.submit_batch(orders).await;
+        for (reply, result) in replies.into_iter().zip(results) {
+            let _ = reply.send(result); // the client went away
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    #[tokio::test]
+    async fn test_batch_bounds() {
+        let (sender, mut receiver) = mpsc::channel(5);
+        for id in 0..5 {
+            let order = Order::new(id.to_string(), Side::Buy, OrderType::Limit, 1, 100, id);
+            sender.try_send((order, oneshot::channel().0)).unwrap();
+        }
+        let config = BatchConfig {
+            max_orders: 3,
+            max_delay: Duration::from_millis(10),
+            ..BatchConfig::default()
+        };
+        assert_eq!(next_batch(&mut receiver, config).await.unwrap().len(), 3);
+        // two left, the delay runs out waiting for a third
+        assert_eq!(next_batch(&mut receiver, config).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batched_orders_match() {
+        let mut engine = MatchingEngine::new();
+        let queue = start(
+            engine.clone(),
+            BatchConfig {
+                max_orders: 10,
+                max_delay: Duration::from_millis(5),
+                ..BatchConfig::default()
+            },
+        );
+        engine.set_submission_queue(queue).await;
+        let order = |id: &str, side| Order::new(id.to_string(), side, OrderType::Limit, 5, 100, 1);
+        let mut buyer = engine.clone();
+        let (sell, buy) = tokio::join!(
+            engine.submit_queued(order("s1", Side::Sell)),
+            buyer.submit_queued(order("b1", Side::Buy))
+        );
+        let trades: Vec<Trade> = sell.unwrap().into_iter().chain(buy.unwrap()).collect();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(engine.trade_history().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_refuses() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let queue = SubmissionQueue { sender };
+        let order = |id: &str| Order::new(id.to_string(), Side::Buy, OrderType::Limit, 1, 100, 1);
+        assert_eq!(queue.depth(), (0, 1));
+        queue
+            .sender
+            .try_send((order("1"), oneshot::channel().0))
+            .unwrap();
+        assert_eq!(queue.depth(), (1, 1));
+        assert_eq!(
+            queue.submit(order("2")).await,
+            Err(RejectReason::Overloaded)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! `ome-cli`: script against a running server without hand-written curl calls.
+//!
+//! Every command prints the server's JSON response (`watch` prints one line per print) and exits
+//! non-zero when the server answers with an error status.
+
+use std::{
+    io::{self, Write},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use hmac::{Hmac, Mac};
+use serde_json::{Value, json};
+use sha2::Sha256;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:61666";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "ome-cli",
+    about = "Command line client for the order matching engine"
+)]
+struct Cli {
+    /// Base URL of the server
+    #[arg(long, env = "OME_URL", default_value = DEFAULT_URL)]
+    url: String,
+    /// JWT sent as a bearer token
+    #[arg(long, env = "OME_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// API key for HMAC request signing, used together with --api-secret
+    #[arg(long, env = "OME_API_KEY", requires = "api_secret")]
+    api_key: Option<String>,
+    #[arg(
+        long,
+        env = "OME_API_SECRET",
+        hide_env_values = true,
+        requires = "api_key"
+    )]
+    api_secret: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Submit an order; limit if --price is given, market otherwise
+    Submit {
+        #[arg(value_enum)]
+        side: CliSide,
+        quantity: u64,
+        /// Limit price in cents
+        #[arg(long)]
+        price: Option<u64>,
+        /// Expire what is left when the trading session closes
+        #[arg(long)]
+        day: bool,
+        /// Hold the order back until this time, unix ms
+        #[arg(long)]
+        activate_at: Option<u64>,
+    },
+    /// Cancel a working order
+    Cancel { id: String },
+    /// Print the resting orders
+    Book,
+    /// Print the trade history, of one account with --account
+    Trades {
+        #[arg(long)]
+        account: Option<String>,
+    },
+    /// Follow the time and sales tape
+    Watch {
+        /// Replay prints from this event sequence number on
+        #[arg(long)]
+        from: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CliSide {
+    Buy,
+    Sell,
+}
+
+/// `POST /orders` body
+fn order_body(
+    side: CliSide,
+    quantity: u64,
+    price: Option<u64>,
+    day: bool,
+    activate_at: Option<u64>,
+) -> Value {
+    let side = match side {
+        CliSide::Buy => "Buy",
+        CliSide::Sell => "Sell",
+    };
+    let order_type = if price.is_some() { "Limit" } else { "Market" };
+    let mut body =
+        json!({ "side": side, "order_type": order_type, "price": price, "quantity": quantity });
+    if day {
+        body["time_in_force"] = json!("Day");
+    }
+    if let Some(activate_at) = activate_at {
+        body["activate_at"] = json!(activate_at);
+    }
+    body
+}
+
+/// Hex HMAC-SHA256 over timestamp, nonce, method, path and body, as the server verifies it
+fn signature(
+    secret: &str,
+    timestamp: u64,
+    nonce: &str,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(nonce.as_bytes());
+    mac.update(method.as_bytes());
+    mac.update(path.as_bytes());
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+struct Client {
+    url: String,
+    token: Option<String>,
+    signing: Option<(String, String)>,
+    agent: ureq::Agent,
+}
+
+impl Client {
+    /// Sends one request and returns the status and the parsed body
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<(u16, Value), String> {
+        let url = format!("{}{path}", self.url);
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let mut headers = Vec::new();
+        if let Some(token) = &self.token {
+            headers.push(("authorization", format!("Bearer {token}")));
+        }
+        if let Some((key, secret)) = &self.signing {
+            let timestamp = chrono::Utc::now().timestamp_millis() as u64;
+            let nonce = uuid::Uuid::new_v4().to_string();
+            let signature = signature(secret, timestamp, &nonce, method, path, &body);
+            headers.push(("x-ome-api-key", key.clone()));
+            headers.push(("x-ome-timestamp", timestamp.to_string()));
+            headers.push(("x-ome-nonce", nonce));
+            headers.push(("x-ome-signature", signature));
+        }
+
+        let response = match method {
+            "POST" => {
+                let mut request = self
+                    .agent
+                    .post(&url)
+                    .header("content-type", "application/json");
+                for (name, value) in &headers {
+                    request = request.header(*name, value);
+                }
+                request.send(&body)
+            }
+            _ => {
+                let mut request = if method == "DELETE" {
+                    self.agent.delete(&url)
+                } else {
+                    self.agent.get(&url)
+                };
+                for (name, value) in &headers {
+                    request = request.header(*name, value);
+                }
+                request.call()
+            }
+        };
+        let mut response = response.map_err(|e| format!("{url}: {e}"))?;
+        let status = response.status().as_u16();
+        let text = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| e.to_string())?;
+        let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+        Ok((status, body))
+    }
+
+    /// Prints the tape as JSON lines until the server closes the socket
+    fn watch(&self, from: Option<u64>) -> Result<(), String> {
+        let mut url = format!("{}/tape", self.url.replacen("http", "ws", 1));
+        if let Some(from) = from {
+            url.push_str(&format!("?from={from}"));
+        }
+        let (mut socket, _) = tungstenite::connect(&url).map_err(|e| format!("{url}: {e}"))?;
+        loop {
+            match socket.read() {
+                Ok(msg) if msg.is_text() => {
+                    // a closed stdout (`| head`) ends the watch
+                    if writeln!(io::stdout(), "{}", msg.to_text().unwrap_or_default()).is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(msg) if msg.is_close() => return Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let client = Client {
+        url: cli.url.trim_end_matches('/').to_string(),
+        token: cli.token,
+        signing: cli.api_key.zip(cli.api_secret),
+        agent,
+    };
+
+    let result = match cli.command {
+        Command::Watch { from } => client.watch(from).map(|_| None),
+        Command::Submit {
+            side,
+            quantity,
+            price,
+            day,
+            activate_at,
+        } => client
+            .request(
+                "POST",
+                "/orders",
+                Some(order_body(side, quantity, price, day, activate_at)),
+            )
+            .map(Some),
+        Command::Cancel { id } => client
+            .request("DELETE", &format!("/orders/{id}/cancel"), None)
+            .map(Some),
+        Command::Book => client.request("GET", "/orderbook", None).map(Some),
+        Command::Trades { account } => {
+            let path = match account {
+                Some(account) => format!("/accounts/{account}/trades"),
+                None => "/trades".to_string(),
+            };
+            client.request("GET", &path, None).map(Some)
+        }
+    };
+
+    match result {
+        Ok(None) => ExitCode::SUCCESS,
+        Ok(Some((status, body))) => {
+            let _ = writeln!(
+                io::stdout(),
+                "{}",
+                serde_json::to_string_pretty(&body).expect("JSON values serialize")
+            );
+            if (200..300).contains(&status) {
+                ExitCode::SUCCESS
+            } else {
+                eprintln!("server answered {status}");
+                ExitCode::FAILURE
+            }
+        }
+        Err(e) => {
+            eprintln!("ome-cli: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli =
+            Cli::try_parse_from(["ome-cli", "submit", "buy", "10", "--price", "1500"]).unwrap();
+        let Command::Submit {
+            side,
+            quantity,
+            price,
+            day,
+            activate_at,
+        } = cli.command
+        else {
+            panic!("expected submit");
+        };
+        assert_eq!(
+            order_body(side, quantity, price, day, activate_at),
+            json!({ "side": "Buy", "order_type": "Limit", "price": 1500, "quantity": 10 })
+        );
+        assert_eq!(
+            order_body(CliSide::Sell, 5, None, false, None)["order_type"],
+            json!("Market")
+        );
+        assert_eq!(
+            order_body(CliSide::Sell, 5, None, false, Some(1_700_000_000_000))["activate_at"],
+            json!(1_700_000_000_000u64)
+        );
+
+        // a key without its secret is a usage error
+        assert!(Cli::try_parse_from(["ome-cli", "--api-key", "k", "book"]).is_err());
+    }
+}
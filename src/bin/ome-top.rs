@@ -0,0 +1,201 @@
+//! `ome-top`: live depth ladder and trade tape of a running server, in the terminal.
+//!
+//! `ome-top [URL]` (default `$OME_URL`, else `http://127.0.0.1:61666`); `q` or Esc quits.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Paragraph},
+};
+use serde::Deserialize;
+
+const DEFAULT_URL: &str = "http://127.0.0.1:61666";
+const REFRESH: Duration = Duration::from_millis(500); // how often the book is polled
+const TAPE_LENGTH: usize = 200; // prints kept for the tape pane
+
+/// One row of the ladder: total quantity and order count at a price
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Level {
+    price: u64,
+    quantity: u64,
+    orders: usize,
+}
+
+/// `/orderbook`, best price first on each side
+#[derive(Debug, Default, Deserialize)]
+struct OrderBookView {
+    bids: Vec<Level>,
+    asks: Vec<Level>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TapeEntry {
+    time: u64,
+    price: u64,
+    size: u64,
+    aggressor: Option<String>,
+}
+
+struct App {
+    url: String,
+    book: OrderBookView,
+    error: Option<String>,
+    tape: Arc<Mutex<VecDeque<TapeEntry>>>,
+}
+
+impl App {
+    fn refresh(&mut self) {
+        let book = ureq::get(format!("{}/orderbook", self.url))
+            .call()
+            .map_err(|e| e.to_string())
+            .and_then(|mut r| r.body_mut().read_to_string().map_err(|e| e.to_string()))
+            .and_then(|body| serde_json::from_str(&body).map_err(|e| e.to_string()));
+        match book {
+            Ok(book) => {
+                self.book = book;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [top, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [book_area, tape_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(top);
+
+        // asks above bids, best prices meeting in the middle
+        let rows = (book_area.height.saturating_sub(2) / 2) as usize;
+        let (asks, bids) = (&self.book.asks, &self.book.bids);
+        let row = |level: &Level, color| {
+            Line::styled(
+                format!(
+                    "{:>12} {:>12} {:>6}",
+                    level.price, level.quantity, level.orders
+                ),
+                Style::default().fg(color),
+            )
+        };
+        let mut lines = vec![Line::from(format!(
+            "{:>12} {:>12} {:>6}",
+            "price", "quantity", "orders"
+        ))];
+        lines.extend(
+            asks.iter()
+                .take(rows.saturating_sub(1))
+                .rev()
+                .map(|l| row(l, Color::Red)),
+        );
+        lines.extend(bids.iter().take(rows).map(|l| row(l, Color::Green)));
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Book ")),
+            book_area,
+        );
+
+        let tape = self.tape.lock().expect("tape lock poisoned");
+        let prints: Vec<Line> = tape
+            .iter()
+            .rev()
+            .take(tape_area.height as usize)
+            .map(|t| {
+                let color = match t.aggressor.as_deref() {
+                    Some("Buy") => Color::Green,
+                    Some("Sell") => Color::Red,
+                    _ => Color::Yellow,
+                };
+                let time = chrono::DateTime::from_timestamp_millis(t.time as i64)
+                    .map(|d| d.format("%H:%M:%S%.3f").to_string())
+                    .unwrap_or_default();
+                Line::styled(
+                    format!("{time} {:>12} {:>10}", t.price, t.size),
+                    Style::default().fg(color),
+                )
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(prints).block(Block::bordered().title(" Tape ")),
+            tape_area,
+        );
+
+        let status_line = match &self.error {
+            Some(e) => Line::styled(
+                format!("{}: {e}", self.url),
+                Style::default().fg(Color::Red),
+            ),
+            None => Line::from(format!("{}  (q to quit)", self.url)),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+}
+
+/// Follows `/tape` in the background, reconnecting after errors
+fn spawn_tape(url: &str, tape: Arc<Mutex<VecDeque<TapeEntry>>>) {
+    let ws_url = format!("{}/tape", url.replacen("http", "ws", 1));
+    thread::spawn(move || {
+        loop {
+            if let Ok((mut socket, _)) = tungstenite::connect(&ws_url) {
+                while let Ok(msg) = socket.read() {
+                    let Ok(entry) = serde_json::from_str::<TapeEntry>(msg.to_text().unwrap_or(""))
+                    else {
+                        continue;
+                    };
+                    let mut tape = tape.lock().expect("tape lock poisoned");
+                    if tape.len() >= TAPE_LENGTH {
+                        tape.pop_front();
+                    }
+                    tape.push_back(entry);
+                }
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
+
+fn main() -> std::io::Result<()> {
+    let url = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("OME_URL").ok())
+        .unwrap_or_else(|| DEFAULT_URL.to_string());
+    let url = url.trim_end_matches('/').to_string();
+
+    let tape = Arc::new(Mutex::new(VecDeque::new()));
+    spawn_tape(&url, Arc::clone(&tape));
+    let mut app = App {
+        url,
+        book: OrderBookView::default(),
+        error: None,
+        tape,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = (|| {
+        let mut last_refresh: Option<Instant> = None;
+        loop {
+            if last_refresh.is_none_or(|t| t.elapsed() >= REFRESH) {
+                app.refresh();
+                last_refresh = Some(Instant::now());
+            }
+            terminal.draw(|frame| app.draw(frame))?;
+            if event::poll(Duration::from_millis(100))?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            {
+                return Ok(());
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
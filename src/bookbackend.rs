@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use crate::order::{Order, OrderId, OrderKey, Price, Side};
+
+/// Storage for the resting orders of an [`OrderBook`](crate::orderbook::OrderBook): both sides
+/// grouped into price levels, each level in time priority. The book keeps the order index and
+/// decides where in a level an order goes; implementations only have to store levels.
+pub trait OrderBookBackend<Id: OrderKey = OrderId>: Default + Clone + Send + Sync {
+    /// Queues `order` at its price level, `position` is its index within the level
+    fn add(&mut self, order: Order<Id>, position: impl FnOnce(&[Order<Id>]) -> usize);
+
+    /// Front order of the best level of `side`
+    fn peek(&self, side: Side) -> Option<&Order<Id>>;
+
+    fn pop(&mut self, side: Side) -> Option<Order<Id>>;
+
+    /// Removes order `id` from the level at `price`
+    fn cancel(&mut self, side: Side, price: Price, id: &Id) -> Option<Order<Id>>;
+
+    fn get(&self, side: Side, price: Price, id: &Id) -> Option<&Order<Id>>;
+
+    /// Removes and returns a whole level
+    fn take_level(&mut self, side: Side, price: Price) -> Vec<Order<Id>>;
+
+    /// Puts `orders` in front of the level at `price`
+    fn restore_level(&mut self, side: Side, price: Price, orders: Vec<Order<Id>>);
+
+    /// Non-empty levels of `side`, best price first
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (Price, &[Order<Id>])> + '_>;
+
+    /// Number of non-empty levels of `side`; backends that know it without walking the levels
+    /// should say so
+    fn level_count(&self, side: Side) -> usize {
+        self.levels(side).count()
+    }
+
+    fn clear(&mut self);
+}
+
+/// The default backend: one `BTreeMap` of levels per side, emptied levels are dropped
+#[derive(Debug, Clone)]
+pub struct BTreeBackend<Id = OrderId> {
+    bids: BTreeMap<Price, Vec<Order<Id>>>,
+    asks: BTreeMap<Price, Vec<Order<Id>>>,
+}
+
+impl<Id> Default for BTreeBackend<Id> {
+    fn default() -> Self {
+        BTreeBackend {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+}
+
+impl<Id: OrderKey> BTreeBackend<Id> {
+    fn side(&self, side: Side) -> &BTreeMap<Price, Vec<Order<Id>>> {
+        match side {
+            Side::Buy => &self.bids,
+            Side::Sell => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price, Vec<Order<Id>>> {
+        match side {
+            Side::Buy => &mut self.bids,
+            Side::Sell => &mut self.asks,
+        }
+    }
+
+    fn best_price(&self, side: Side) -> Option<Price> {
+        match side {
+            Side::Buy => self.bids.last_key_value(),
+            Side::Sell => self.asks.first_key_value(),
+        }
+        .map(|(price, _)| *price)
+    }
+}
+
+impl<Id: OrderKey> OrderBookBackend<Id> for BTreeBackend<Id> {
+    fn add(&mut self, order: Order<Id>, position: impl FnOnce(&[Order<Id>]) -> usize) {
+        let level = self.side_mut(order.side).entry(order.price).or_default();
+        let pos = position(level);
+        level.insert(pos, order);
+    }
+
+    fn peek(&self, side: Side) -> Option<&Order<Id>> {
+        let price = self.best_price(side)?;
+        self.side(side).get(&price)?.first()
+    }
+
+    fn pop(&mut self, side: Side) -> Option<Order<Id>> {
+        let price = self.best_price(side)?;
+        let levels = self.side_mut(side);
+        let level = levels.get_mut(&price)?;
+        let front = level.remove(0);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        Some(front)
+    }
+
+    fn cancel(&mut self, side: Side, price: Price, id: &Id) -> Option<Order<Id>> {
+        let levels = self.side_mut(side);
+        let level = levels.get_mut(&price)?;
+        let index = level.iter().position(|o| &o.id == id)?;
+        let order = level.remove(index);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+        Some(order)
+    }
+
+    fn get(&self, side: Side, price: Price, id: &Id) -> Option<&Order<Id>> {
+        self.side(side).get(&price)?.iter().find(|o| &o.id == id)
+    }
+
+    fn take_level(&mut self, side: Side, price: Price) -> Vec<Order<Id>> {
+        self.side_mut(side).remove(&price).unwrap_or_default()
+    }
+
+    fn restore_level(&mut self, side: Side, price: Price, orders: Vec<Order<Id>>) {
+        if orders.is_empty() {
+            return;
+        }
+        let level = self.side_mut(side).entry(price).or_default();
+        level.splice(0..0, orders);
+    }
+
+    fn levels(&self, side: Side) -> Box<dyn Iterator<Item = (Price, &[Order<Id>])> + '_> {
+        match side {
+            Side::Buy => Box::new(self.bids.iter().rev().map(|(p, l)| (*p, l.as_slice()))),
+            Side::Sell => Box::new(self.asks.iter().map(|(p, l)| (*p, l.as_slice()))),
+        }
+    }
+
+    fn level_count(&self, side: Side) -> usize {
+        self.side(side).len()
+    }
+
+    fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::OrderType;
+
+    fn order(id: &str, side: Side, price: Price) -> Order {
+        Order::new(id.to_string(), side, OrderType::Limit, 1, price, 1)
+    }
+
+    #[test]
+    fn test_btree_backend() {
+        let mut backend = BTreeBackend::default();
+        backend.add(order("b1", Side::Buy, 100), |level| level.len());
+        backend.add(order("b2", Side::Buy, 101), |level| level.len());
+        backend.add(order("a1", Side::Sell, 105), |level| level.len());
+        backend.add(order("a0", Side::Sell, 105), |_| 0);
+
+        assert_eq!(backend.peek(Side::Buy).unwrap().id, "b2");
+        assert_eq!(backend.peek(Side::Sell).unwrap().id, "a0");
+        let bid_prices: Vec<Price> = backend.levels(Side::Buy).map(|(p, _)| p).collect();
+        assert_eq!(bid_prices, vec![101, 100]);
+        assert_eq!(backend.level_count(Side::Sell), 1);
+
+        assert_eq!(backend.pop(Side::Buy).unwrap().id, "b2");
+        assert!(
+            backend
+                .cancel(Side::Buy, 100, &String::from("b1"))
+                .is_some()
+        );
+        assert!(backend.peek(Side::Buy).is_none());
+        assert_eq!(backend.levels(Side::Buy).count(), 0); // emptied levels are dropped
+
+        assert!(backend.get(Side::Sell, 105, &String::from("a1")).is_some());
+        assert_eq!(backend.take_level(Side::Sell, 105).len(), 2);
+        assert!(backend.peek(Side::Sell).is_none());
+    }
+}
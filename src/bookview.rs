@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    order::{Order, Price, Quantity, Side},
+    orderbook::{OrderBook, SideStats},
+};
+
+/// Total quantity and order count resting at a price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub price: Price,
+    pub quantity: Quantity,
+    pub orders: usize,
+}
+
+impl PriceLevel {
+    fn of(price: Price, orders: &[Order]) -> Self {
+        PriceLevel {
+            price,
+            quantity: orders.iter().map(|o| o.quantity).sum(),
+            orders: orders.len(),
+        }
+    }
+}
+
+/// Levels of each side, best price first, and the totals of each side as of a change to the
+/// book. Readers load the latest one the engine published instead of waiting for the book lock.
+#[derive(Debug, Default)]
+pub struct BookSnapshot {
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub bid_stats: SideStats,
+    pub ask_stats: SideStats,
+}
+
+impl BookSnapshot {
+    pub fn of(order_book: &OrderBook) -> Self {
+        let levels = |side| {
+            order_book
+                .levels(side)
+                .map(|(price, orders)| PriceLevel::of(price, orders))
+                .collect()
+        };
+        BookSnapshot {
+            bids: levels(Side::Buy),
+            asks: levels(Side::Sell),
+            bid_stats: order_book.side_stats(Side::Buy),
+            ask_stats: order_book.side_stats(Side::Sell),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::OrderType;
+
+    #[test]
+    fn test_levels_best_first() {
+        let mut book = OrderBook::new();
+        let order = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+        };
+        book.add_order(order("b1", Side::Buy, 5, 100));
+        book.add_order(order("b2", Side::Buy, 1, 101));
+        book.add_order(order("b3", Side::Buy, 2, 100));
+        book.add_order(order("a1", Side::Sell, 4, 103));
+
+        let snapshot = BookSnapshot::of(&book);
+        assert_eq!(
+            snapshot.bids,
+            vec![
+                PriceLevel {
+                    price: 101,
+                    quantity: 1,
+                    orders: 1
+                },
+                PriceLevel {
+                    price: 100,
+                    quantity: 7,
+                    orders: 2
+                },
+            ]
+        );
+        assert_eq!(snapshot.asks[0].price, 103);
+    }
+}
@@ -0,0 +1,413 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    extract::{Query, State, rejection::QueryRejection},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast::error::RecvError};
+
+use crate::{
+    audit::AuditEvent,
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::{Price, Quantity, Timestamp},
+    validation::FieldError,
+};
+
+pub const CANDLE_DIR_ENV: &str = "OME_CANDLE_DIR"; // unset keeps candles in memory only
+
+const MINUTE: Timestamp = 60_000;
+const HOUR: Timestamp = 60 * MINUTE;
+const DAY: Timestamp = 24 * HOUR;
+/// Widths candles are kept at, finest first; other intervals are merged from these
+const TIERS: [(Timestamp, &str); 3] = [(MINUTE, "1m"), (HOUR, "1h"), (DAY, "1d")];
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MAX_CANDLES: usize = 10_000;
+
+/// Price and volume of the regular trades within one interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Candle {
+    /// Unix ms the interval starts at, a multiple of its width
+    pub start: Timestamp,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Quantity,
+    pub trades: u64,
+}
+
+impl Candle {
+    fn new(start: Timestamp, price: Price, quantity: Quantity) -> Self {
+        Candle {
+            start,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: quantity,
+            trades: 1,
+        }
+    }
+
+    /// Takes in `later`, which follows this candle
+    fn merge(&mut self, later: &Candle) {
+        self.high = self.high.max(later.high);
+        self.low = self.low.min(later.low);
+        self.close = later.close;
+        self.volume += later.volume;
+        self.trades += later.trades;
+    }
+}
+
+/// Candles of one width, with the file they are kept in
+#[derive(Debug)]
+struct Tier {
+    width: Timestamp,
+    candles: BTreeMap<Timestamp, Candle>,
+    /// Changed since they were last written
+    pending: BTreeSet<Timestamp>,
+    file: Option<File>,
+}
+
+impl Tier {
+    /// Loads the candles kept at `path`; a candle written more than once counts as last written
+    fn open(width: Timestamp, path: Option<PathBuf>) -> io::Result<Self> {
+        let mut candles = BTreeMap::new();
+        let file = match path {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if path.exists() {
+                    for line in BufReader::new(File::open(&path)?).lines() {
+                        // a line cut short by a crash is skipped
+                        if let Ok(candle) = serde_json::from_str::<Candle>(&line?) {
+                            candles.insert(candle.start, candle);
+                        }
+                    }
+                }
+                Some(OpenOptions::new().create(true).append(true).open(&path)?)
+            }
+            None => None,
+        };
+        Ok(Tier {
+            width,
+            candles,
+            pending: BTreeSet::new(),
+            file,
+        })
+    }
+
+    fn add(&mut self, time: Timestamp, price: Price, quantity: Quantity) {
+        let start = time - time % self.width;
+        let trade = Candle::new(start, price, quantity);
+        self.candles
+            .entry(start)
+            .and_modify(|candle| candle.merge(&trade))
+            .or_insert(trade);
+        self.pending.insert(start);
+    }
+
+    /// Writes the changed candles that ended by `now`; the open one is written once it ends
+    fn flush(&mut self, now: Timestamp) -> io::Result<()> {
+        let Some(file) = &mut self.file else {
+            self.pending.clear();
+            return Ok(());
+        };
+        let ended: Vec<Timestamp> = self
+            .pending
+            .iter()
+            .copied()
+            .take_while(|start| start + self.width <= now)
+            .collect();
+        if ended.is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        for start in &ended {
+            serde_json::to_writer(&mut lines, &self.candles[start]).expect("candles serialize");
+            lines.push(b'\n');
+        }
+        file.write_all(&lines)?;
+        for start in ended {
+            self.pending.remove(&start);
+        }
+        Ok(())
+    }
+}
+
+/// One exchange's candles at every tier, the ended ones kept on disk when there is a
+/// directory for them, so history outlives the process. Busts and corrections don't change
+/// candles already built.
+#[derive(Debug)]
+pub struct CandleStore {
+    tiers: Vec<Tier>,
+}
+
+impl CandleStore {
+    /// Loads the candles of `symbol` kept in `dir` (`<dir>/<symbol>.candles-1m.jsonl` and so
+    /// on), or starts in memory only without one
+    pub fn open(dir: Option<&Path>, symbol: &str) -> io::Result<Self> {
+        let tiers = TIERS
+            .iter()
+            .map(|(width, name)| {
+                let path = dir.map(|dir| dir.join(format!("{symbol}.candles-{name}.jsonl")));
+                Tier::open(*width, path)
+            })
+            .collect::<io::Result<Vec<Tier>>>()?;
+        let mut store = CandleStore { tiers };
+        store.rebuild_open();
+        Ok(store)
+    }
+
+    /// Candles are written once they end, so those open when the process stopped are rebuilt
+    /// from the finer tier, which lost at most its last minute
+    fn rebuild_open(&mut self) {
+        for i in 1..self.tiers.len() {
+            let (finer, coarser) = self.tiers.split_at_mut(i);
+            let (finer, coarser) = (&finer[i - 1], &mut coarser[0]);
+            let written_until = coarser
+                .candles
+                .last_key_value()
+                .map_or(0, |(start, _)| start + coarser.width);
+            for candle in finer.candles.range(written_until..).map(|(_, c)| c) {
+                let start = candle.start - candle.start % coarser.width;
+                coarser
+                    .candles
+                    .entry(start)
+                    .and_modify(|c| c.merge(candle))
+                    .or_insert(Candle { start, ..*candle });
+                coarser.pending.insert(start);
+            }
+        }
+    }
+
+    pub fn add_trade(&mut self, time: Timestamp, price: Price, quantity: Quantity) {
+        for tier in &mut self.tiers {
+            tier.add(time, price, quantity);
+        }
+    }
+
+    pub fn flush(&mut self, now: Timestamp) -> io::Result<()> {
+        self.tiers.iter_mut().try_for_each(|tier| tier.flush(now))
+    }
+
+    /// Candles `interval` ms wide starting from `from` up to `to`, oldest first and at most
+    /// the latest `limit`; intervals without trades have none. `interval` is a multiple of a
+    /// minute and candles align with the unix epoch.
+    pub fn query(
+        &self,
+        interval: Timestamp,
+        from: Timestamp,
+        to: Timestamp,
+        limit: usize,
+    ) -> Vec<Candle> {
+        let tier = self
+            .tiers
+            .iter()
+            .rev()
+            .find(|tier| interval.is_multiple_of(tier.width))
+            .expect("intervals are whole minutes");
+        let from = from - from % interval;
+        let mut candles: Vec<Candle> = Vec::new();
+        for candle in tier.candles.range(from..to).map(|(_, c)| c) {
+            let start = candle.start - candle.start % interval;
+            match candles.last_mut() {
+                Some(last) if last.start == start => last.merge(candle),
+                _ => candles.push(Candle { start, ..*candle }),
+            }
+        }
+        let skip = candles.len().saturating_sub(limit);
+        candles.split_off(skip)
+    }
+}
+
+/// The candle store of one exchange, shared by the task building it and `/candles`
+#[derive(Debug, Clone)]
+pub struct Candles {
+    store: Arc<RwLock<CandleStore>>,
+}
+
+impl Candles {
+    /// Candles of `symbol` kept in `$OME_CANDLE_DIR`; kept in memory when that's unset or
+    /// can't be read
+    pub fn from_env(symbol: &str) -> Self {
+        let dir = std::env::var(CANDLE_DIR_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        let store = CandleStore::open(dir.as_deref(), symbol).unwrap_or_else(|e| {
+            println!("{symbol}: candles kept in memory only: {e}");
+            CandleStore::open(None, symbol).expect("memory needs no files")
+        });
+        Candles {
+            store: Arc::new(RwLock::new(store)),
+        }
+    }
+}
+
+/// Adds every regular trade of `engine` to `candles` as long as the server runs, and writes
+/// out candles as they end
+pub async fn run_candles(engine: MatchingEngine, candles: Candles) {
+    let mut events = engine.subscribe_events();
+    let mut next_seq = engine.last_seq().await + 1;
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        let entries = tokio::select! {
+            received = events.recv() => match received {
+                Ok(entry) => vec![entry],
+                Err(RecvError::Lagged(_)) => engine.audit_entries(next_seq, usize::MAX).await,
+                Err(RecvError::Closed) => return,
+            },
+            _ = flush.tick() => {
+                if let Err(e) = candles.store.write().await.flush(engine.clock().now_ms()) {
+                    println!("candles not written, retrying: {e}");
+                }
+                continue;
+            }
+        };
+        let mut store = candles.store.write().await;
+        for entry in entries {
+            if entry.seq < next_seq {
+                continue; // seen before catching up
+            }
+            next_seq = entry.seq + 1;
+            if let AuditEvent::TradeExecuted { trade } = &entry.event
+                && trade.trade_type.is_regular()
+            {
+                store.add_trade(entry.timestamp, trade.price, trade.quantity);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CandleQuery {
+    /// Width of a candle, a multiple of 60; default one minute
+    interval_secs: Option<u64>,
+    /// Unix ms, inclusive
+    from: Option<Timestamp>,
+    /// Unix ms, exclusive
+    to: Option<Timestamp>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct CandlesResponse {
+    interval_secs: u64,
+    candles: Vec<Candle>,
+}
+
+/// `/candles`, from the candles built by [`run_candles`]
+pub fn routes(candles: Candles) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/candles", get(get_candles))
+        .with_state(candles)
+}
+
+async fn get_candles(
+    State(candles): State<Candles>,
+    query: Result<Query<CandleQuery>, QueryRejection>,
+) -> Result<Json<CandlesResponse>, ApiError> {
+    let Query(query) = query?;
+    let interval_secs = query.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS);
+    let mut errors = Vec::new();
+    if interval_secs == 0 || !interval_secs.is_multiple_of(60) {
+        errors.push(FieldError::new("interval_secs", "must be a multiple of 60"));
+    }
+    if query.limit == Some(0) {
+        errors.push(FieldError::new("limit", "must be > 0"));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+    let limit = query.limit.unwrap_or(MAX_CANDLES).min(MAX_CANDLES);
+    let candles = candles.store.read().await.query(
+        interval_secs.saturating_mul(1_000),
+        query.from.unwrap_or(0),
+        query.to.unwrap_or(Timestamp::MAX),
+        limit,
+    );
+    Ok(Json(CandlesResponse {
+        interval_secs,
+        candles,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candles_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("ome-candles-{}", std::process::id()));
+        let day = 1_700_006_400_000;
+        let mut store = CandleStore::open(Some(&dir), "acme").unwrap();
+        store.add_trade(day + 10, 100, 1);
+        store.add_trade(day + 20, 104, 2);
+        store.add_trade(day + MINUTE, 98, 1);
+        store.add_trade(day + 5 * MINUTE + 1, 101, 3);
+        store.add_trade(day + HOUR, 110, 1);
+        store.flush(day + 6 * MINUTE).unwrap(); // the first hour hasn't ended yet
+        store.add_trade(day + 5 * MINUTE + 2, 99, 1); // late, written again
+        store.flush(day + DAY).unwrap();
+
+        let store = CandleStore::open(Some(&dir), "acme").unwrap();
+        let five = store.query(5 * MINUTE, 0, Timestamp::MAX, 10);
+        assert_eq!(
+            five,
+            vec![
+                Candle {
+                    start: day,
+                    open: 100,
+                    high: 104,
+                    low: 98,
+                    close: 98,
+                    volume: 4,
+                    trades: 3
+                },
+                Candle {
+                    start: day + 5 * MINUTE,
+                    open: 101,
+                    high: 101,
+                    low: 99,
+                    close: 99,
+                    volume: 4,
+                    trades: 2
+                },
+                Candle::new(day + HOUR, 110, 1),
+            ]
+        );
+        // two hours from the hourly tier; only the latest hour
+        let hours = store.query(2 * HOUR, day, day + DAY, 10);
+        assert_eq!(hours.len(), 1);
+        assert_eq!(
+            (hours[0].start, hours[0].trades, hours[0].close),
+            (day, 6, 110)
+        );
+        assert_eq!(store.query(HOUR, day, day + DAY, 1)[0].start, day + HOUR);
+        let days = store.query(DAY, 0, Timestamp::MAX, 10);
+        assert_eq!((days[0].open, days[0].high, days[0].low), (100, 110, 98));
+
+        // stopped before the day ended, its candle is rebuilt from the hours and minutes
+        let mut store = CandleStore::open(Some(&dir), "acme").unwrap();
+        store.add_trade(day + DAY + 10, 120, 1);
+        store.add_trade(day + DAY + MINUTE, 121, 1);
+        store.flush(day + DAY + 2 * MINUTE).unwrap();
+        let store = CandleStore::open(Some(&dir), "acme").unwrap();
+        let days = store.query(DAY, day + DAY, Timestamp::MAX, 10);
+        assert_eq!((days[0].open, days[0].close, days[0].trades), (120, 121, 2));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
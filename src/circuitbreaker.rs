@@ -0,0 +1,222 @@
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    matchingengine::MatchingEngine,
+    order::{Price, Timestamp},
+};
+
+pub const BREAKER_MAX_MOVE_PCT_ENV: &str = "OME_BREAKER_MAX_MOVE_PCT"; // unset or `0` disables the breaker
+pub const BREAKER_WINDOW_SECS_ENV: &str = "OME_BREAKER_WINDOW_SECS";
+pub const BREAKER_COOLDOWN_SECS_ENV: &str = "OME_BREAKER_COOLDOWN_SECS";
+pub const BREAKER_ACTION_ENV: &str = "OME_BREAKER_ACTION"; // `halt` | `auction`
+
+const COOLDOWN_TICK: Duration = Duration::from_millis(100);
+
+/// What the engine does with the book when the breaker trips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreakerAction {
+    /// Halt trading; cancels stay allowed
+    Halt,
+    /// Switch to a volatility auction that is uncrossed when the cooldown ends
+    Auction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Maximum move of the last price against any price inside the window, in percent
+    pub max_move_pct: f64,
+    pub window: Duration,
+    pub cooldown: Duration,
+    pub action: BreakerAction,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            max_move_pct: 0.0,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(300),
+            action: BreakerAction::Halt,
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    pub fn from_env() -> Self {
+        let default = CircuitBreakerConfig::default();
+        let secs = |env: &str| {
+            std::env::var(env)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_secs)
+        };
+        CircuitBreakerConfig {
+            max_move_pct: std::env::var(BREAKER_MAX_MOVE_PCT_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_move_pct),
+            window: secs(BREAKER_WINDOW_SECS_ENV).unwrap_or(default.window),
+            cooldown: secs(BREAKER_COOLDOWN_SECS_ENV).unwrap_or(default.cooldown),
+            action: match std::env::var(BREAKER_ACTION_ENV).as_deref() {
+                Ok("auction") => BreakerAction::Auction,
+                _ => default.action,
+            },
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_move_pct > 0.0
+    }
+}
+
+/// Emitted when the breaker trips; kept around so clients can see why the book stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakerTrip {
+    pub price: Price,
+    /// Price inside the window the move was measured against
+    pub reference: Price,
+    pub action: BreakerAction,
+    /// Unix timestamp (ms)
+    pub tripped_at: Timestamp,
+    pub cooldown_ms: u64,
+}
+
+impl BreakerTrip {
+    /// Unix ms the book comes back at
+    pub fn ends_at(&self) -> Timestamp {
+        self.tripped_at.saturating_add(self.cooldown_ms)
+    }
+}
+
+/// Watches trade prices over a sliding window of the engine's clock
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    prices: VecDeque<(Timestamp, Price)>, // unix ms of the trade, price
+    trip: Option<BreakerTrip>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker {
+            config,
+            prices: VecDeque::new(),
+            trip: None,
+        }
+    }
+
+    /// The active trip, `None` while the book trades normally
+    pub fn trip(&self) -> Option<BreakerTrip> {
+        self.trip
+    }
+
+    /// Clears the active trip, returns it if there was one
+    pub fn reset(&mut self) -> Option<BreakerTrip> {
+        self.prices.clear();
+        self.trip.take()
+    }
+
+    /// Takes over `trip` as the active one, e.g. a primary's on its standby
+    pub fn restore(&mut self, trip: BreakerTrip) {
+        self.prices.clear();
+        self.trip = Some(trip);
+    }
+
+    /// Records a trade price at `now` (unix ms) and returns the trip if it moved too far from
+    /// the window
+    pub fn observe(&mut self, price: Price, now: Timestamp) -> Option<BreakerTrip> {
+        if !self.config.is_enabled() || self.trip.is_some() {
+            return None;
+        }
+
+        let window = self.config.window.as_millis() as Timestamp;
+        while let Some((at, _)) = self.prices.front()
+            && now.saturating_sub(*at) > window
+        {
+            self.prices.pop_front();
+        }
+
+        let reference = self
+            .prices
+            .iter()
+            .map(|(_, p)| *p)
+            .max_by_key(|p| p.abs_diff(price));
+        self.prices.push_back((now, price));
+
+        let reference = reference?;
+        let moved_pct = price.abs_diff(reference) as f64 * 100.0 / reference.max(1) as f64;
+        if moved_pct <= self.config.max_move_pct {
+            return None;
+        }
+
+        self.trip = Some(BreakerTrip {
+            price,
+            reference,
+            action: self.config.action,
+            tripped_at: now,
+            cooldown_ms: self.config.cooldown.as_millis() as u64,
+        });
+        self.trip
+    }
+}
+
+/// Brings the book back once the active trip's cooldown is over on the engine's clock
+pub async fn run_cooldown(engine: MatchingEngine) {
+    let mut tick = tokio::time::interval(COOLDOWN_TICK);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        engine.expire_circuit_breaker().await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn breaker(max_move_pct: f64) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            max_move_pct,
+            window: Duration::from_secs(10),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_trips_on_large_move() {
+        let mut cb = breaker(5.0);
+        let t0 = 1_700_000_000_000;
+        assert_eq!(cb.observe(100, t0), None);
+        assert_eq!(cb.observe(104, t0), None);
+        let trip = cb.observe(94, t0 + 5).unwrap(); // 9.6% below 104
+        assert_eq!((trip.price, trip.reference), (94, 104));
+        assert_eq!(cb.trip(), Some(trip));
+        assert_eq!(trip.tripped_at, t0 + 5);
+        assert_eq!(trip.ends_at(), t0 + 5 + 300_000);
+
+        // already tripped, nothing new until reset
+        assert_eq!(cb.observe(50, t0), None);
+        assert_eq!(cb.reset(), Some(trip));
+        assert_eq!(cb.trip(), None);
+    }
+
+    #[test]
+    fn test_window_expiry() {
+        let mut cb = breaker(5.0);
+        let t0 = 1_700_000_000_000;
+        assert_eq!(cb.observe(100, t0), None);
+        // the 100 print has left the window
+        assert_eq!(cb.observe(120, t0 + 11_000), None);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut cb = breaker(0.0);
+        let t0 = 1_700_000_000_000;
+        assert_eq!(cb.observe(100, t0), None);
+        assert_eq!(cb.observe(1, t0), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::order::Timestamp;
+
+/// Where an engine takes the time from to stamp orders, trades and events: the system clock in
+/// production, a [`VirtualClock`] in simulations and tests
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Unix time in nanoseconds, the resolution of order timestamps
+    fn now_ns(&self) -> Timestamp;
+
+    /// Unix time in milliseconds, the resolution of event and trade-report times
+    fn now_ms(&self) -> Timestamp {
+        self.now_ns() / 1_000_000
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ns(&self) -> Timestamp {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos() as Timestamp)
+    }
+}
+
+/// Advances one nanosecond every time it's read, starting at 1: a stand-in for time where only
+/// the order of events matters, e.g. [`crate::matching::Matcher`]'s default
+#[derive(Debug, Default)]
+pub struct TickClock {
+    ticks: AtomicU64,
+}
+
+impl Clock for TickClock {
+    fn now_ns(&self) -> Timestamp {
+        self.ticks.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+/// A clock that only moves when told to. Clones share the time, so a simulation can keep one
+/// and hand another to the engine.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    now_ns: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// Stopped at `now_ns` (unix ns)
+    pub fn starting_at(now_ns: Timestamp) -> Self {
+        VirtualClock {
+            now_ns: Arc::new(AtomicU64::new(now_ns)),
+        }
+    }
+
+    pub fn set(&self, now_ns: Timestamp) {
+        self.now_ns.store(now_ns, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now_ns
+            .fetch_add(by.as_nanos() as Timestamp, Ordering::SeqCst);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_ns(&self) -> Timestamp {
+        self.now_ns.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_virtual_clock() {
+        let clock = VirtualClock::starting_at(1_700_000_000_000_000_000);
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(shared.now_ns(), 1_700_000_001_500_000_000);
+        assert_eq!(shared.now_ms(), 1_700_000_001_500);
+        clock.set(5);
+        assert_eq!(shared.now_ns(), 5);
+
+        let ticks = TickClock::default();
+        assert_eq!((ticks.now_ns(), ticks.now_ns()), (1, 2));
+        assert!(SystemClock.now_ms() > 1_700_000_000_000);
+    }
+}
@@ -0,0 +1,1405 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Cursor, Write},
+    ops::RangeBounds,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{DefaultBodyLimit, rejection::JsonRejection},
+    middleware,
+    routing::{get, post},
+};
+use openraft::{
+    AnyError, BasicNode, Config, Entry, EntryPayload, LogId, RaftLogReader, RaftMetrics,
+    RaftSnapshotBuilder, Snapshot, SnapshotMeta, StorageError, StorageIOError, StoredMembership,
+    Vote,
+    error::{
+        ClientWriteError, InstallSnapshotError, NetworkError, RPCError, RaftError, RemoteError,
+        Unreachable,
+    },
+    network::{RPCOption, RaftNetwork, RaftNetworkFactory},
+    raft::{
+        AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest,
+        InstallSnapshotResponse, VoteRequest, VoteResponse,
+    },
+    storage::{Adaptor, LogState, RaftStorage},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::Mutex;
+
+use crate::{
+    admin,
+    auth::{self, Auth},
+    clock::{Clock, SystemClock},
+    error::{ApiError, ErrorCode},
+    matchingengine::{MatchingEngine, TradeAdjustmentError, TradingState},
+    mmprotection::QuoteProtection,
+    order::{
+        Order, OrderId, ParticipantId, Price, Quantity, RejectReason, Timestamp, Trade, TradeId,
+    },
+    quotes::{Quote, QuoteAck},
+    rfq::{Rfq, RfqError, RfqId, RfqResponse},
+    risk::RiskLimits,
+    runtimeconfig::RuntimeConfig,
+    schedule::MarketPhase,
+    settlement::{SettlementBatch, SettlementError},
+    state::EngineState,
+    wallet::{AccountBalances, Asset},
+};
+
+pub const RAFT_NODE_ID_ENV: &str = "OME_RAFT_NODE_ID"; // unset disables consensus mode
+pub const RAFT_PEERS_ENV: &str = "OME_RAFT_PEERS"; // `1=http://a:61666,2=http://b:61666,...`, this node included
+pub const RAFT_TOKEN_ENV: &str = "OME_RAFT_TOKEN"; // admin token the nodes present to each other
+pub const RAFT_DIR_ENV: &str = "OME_RAFT_DIR"; // where the node keeps its vote, log and snapshot
+
+const DEFAULT_RAFT_DIR: &str = "raft";
+const VOTE_FILE: &str = "vote.json";
+const LOG_FILE: &str = "log.jsonl";
+const SNAPSHOT_FILE: &str = "snapshot";
+
+const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+openraft::declare_raft_types!(
+    pub TypeConfig:
+        D = Stamped,
+        R = Option<CommandResult>,
+);
+
+type NodeId = u64;
+type Raft = openraft::Raft<TypeConfig>;
+
+tokio::task_local! {
+    /// Time of the entry being applied, as the leader stamped it
+    static APPLYING_AT: Timestamp;
+}
+
+/// The engine's clock in consensus mode. While an entry is applied it reads the time the leader
+/// stamped on the entry, so that every member times the trades, events, phase changes and
+/// protection windows the entry causes alike; otherwise it's the system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogClock;
+
+impl Clock for LogClock {
+    fn now_ns(&self) -> Timestamp {
+        APPLYING_AT
+            .try_with(|at| *at)
+            .unwrap_or_else(|_| SystemClock.now_ns())
+    }
+}
+
+/// A log entry: the command, and the leader's time (unix ns) when it took the command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stamped {
+    pub at: Timestamp,
+    pub command: Command,
+}
+
+/// An order entry or admin command, applied to the engine once a majority of the cluster
+/// logged it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Command {
+    /// `order` carries the id and timestamp the leader gave it, so every node books it the same
+    Submit {
+        order: Order,
+    },
+    /// Cancels on behalf of `owner`, or of an admin when `None`
+    Cancel {
+        order_id: OrderId,
+        owner: Option<ParticipantId>,
+    },
+    /// Holds `order` until `activate_at` (unix ms)
+    Schedule {
+        order: Order,
+        activate_at: Timestamp,
+    },
+    /// Releases a scheduled order into matching, committed by the leader once it's due
+    Activate {
+        order_id: OrderId,
+    },
+    /// Replaces the owner's previous quote, the sides carrying ids and timestamps like `Submit`
+    Quote {
+        quote: Quote,
+    },
+    /// Opens an RFQ, its `expires_at` set by the leader
+    RequestQuote {
+        rfq: Rfq,
+    },
+    RespondRfq {
+        rfq_id: RfqId,
+        response: RfqResponse,
+    },
+    /// Trades an RFQ's best response, committed by the leader once its window closed
+    CloseRfq {
+        rfq_id: RfqId,
+    },
+    /// Kill switch: cancels the owner's orders and blocks it
+    Kill {
+        owner: ParticipantId,
+    },
+    Enable {
+        owner: ParticipantId,
+    },
+    Halt {
+        allow_cancels: bool,
+    },
+    Resume,
+    ClearBook {
+        wipe_trades: bool,
+    },
+    BustTrade {
+        trade_id: TradeId,
+        reason: String,
+    },
+    CorrectTrade {
+        trade_id: TradeId,
+        price: Option<Price>,
+        quantity: Option<Quantity>,
+        reason: String,
+    },
+    BlockTrade {
+        buyer: ParticipantId,
+        seller: ParticipantId,
+        price: Price,
+        quantity: Quantity,
+    },
+    /// The engine's sections of a runtime config change; rate limits stay with each node
+    Configure {
+        config: RuntimeConfig,
+    },
+    SetReferencePrice {
+        price: Option<Price>,
+    },
+    SetRiskLimits {
+        owner: ParticipantId,
+        limits: RiskLimits,
+    },
+    SetQuoteProtection {
+        owner: ParticipantId,
+        protection: QuoteProtection,
+    },
+    Deposit {
+        owner: ParticipantId,
+        asset: Asset,
+        amount: u64,
+    },
+    Withdraw {
+        owner: ParticipantId,
+        asset: Asset,
+        amount: u64,
+    },
+    CloseSettlement,
+    Settle {
+        batch_id: u64,
+    },
+    /// Replaces the whole engine state, its spilled trades already read in by the node that
+    /// took the import
+    ImportState {
+        state: Box<EngineState>,
+    },
+    /// Moves the market into `phase`, committed by the leader as its schedule comes due
+    SetMarketPhase {
+        phase: MarketPhase,
+    },
+    /// Recomputes the fee tiers from the trailing volume, committed by the leader periodically
+    RefreshFeeTiers,
+}
+
+/// What applying a [`Command`] to the book returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CommandResult {
+    Submitted(Result<Vec<Trade>, RejectReason>),
+    Canceled(Result<bool, RejectReason>),
+    Scheduled(Result<(), RejectReason>),
+    Quoted(Result<QuoteAck, RejectReason>),
+    Rfq(Result<(), RfqError>),
+    RfqClosed(Option<Trade>),
+    Killed(Result<Vec<Order>, RejectReason>),
+    Enabled(Result<bool, RejectReason>),
+    Halted(TradingState),
+    Resumed(bool),
+    Cleared(Vec<Order>),
+    Adjusted(Result<Trade, TradeAdjustmentError>),
+    BlockTraded(Result<Trade, RejectReason>),
+    /// `None` for a withdrawal the account couldn't cover
+    Balances(Option<AccountBalances>),
+    SettlementClosed(Option<SettlementBatch>),
+    Settled(Result<SettlementBatch, SettlementError>),
+    /// The trades of the auction uncrossed or the queued orders released by the phase change
+    PhaseChanged(Vec<Trade>),
+    /// The command has nothing to report beyond being applied
+    Applied,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaftConfig {
+    pub node_id: NodeId,
+    /// Base URL of every member, this node included
+    pub peers: BTreeMap<NodeId, String>,
+    pub token: Option<String>,
+    /// Directory of the node's vote, log and latest snapshot, one subdirectory per exchange
+    pub dir: PathBuf,
+}
+
+/// Parses `1=http://a:61666,2=http://b:61666` into node ids and base URLs
+pub fn parse_peers(value: &str) -> Result<BTreeMap<NodeId, String>, String> {
+    let mut peers = BTreeMap::new();
+    for peer in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (id, url) = peer
+            .split_once('=')
+            .ok_or_else(|| format!("{peer}: expected <node id>=<url>"))?;
+        let id = id
+            .trim()
+            .parse()
+            .map_err(|_| format!("{peer}: node id must be a number"))?;
+        let url = url.trim().trim_end_matches('/');
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("{peer}: url must start with http:// or https://"));
+        }
+        if peers.insert(id, url.to_string()).is_some() {
+            return Err(format!("node {id} is listed more than once"));
+        }
+    }
+    Ok(peers)
+}
+
+impl RaftConfig {
+    /// Panics on a malformed cluster definition: a node that quietly fell back to running alone
+    /// would acknowledge orders the rest of the cluster never sees
+    pub fn from_env() -> Option<Self> {
+        let node_id = std::env::var(RAFT_NODE_ID_ENV)
+            .ok()
+            .filter(|id| !id.is_empty())?;
+        let node_id = node_id
+            .parse()
+            .unwrap_or_else(|_| panic!("{RAFT_NODE_ID_ENV}: {node_id} is not a number"));
+        let peers = parse_peers(&std::env::var(RAFT_PEERS_ENV).unwrap_or_default())
+            .unwrap_or_else(|e| panic!("{RAFT_PEERS_ENV}: {e}"));
+        if !peers.contains_key(&node_id) {
+            panic!("{RAFT_PEERS_ENV} doesn't list this node ({node_id})");
+        }
+        Some(RaftConfig {
+            node_id,
+            peers,
+            token: std::env::var(RAFT_TOKEN_ENV).ok(),
+            dir: std::env::var(RAFT_DIR_ENV)
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or_else(|| DEFAULT_RAFT_DIR.to_string())
+                .into(),
+        })
+    }
+}
+
+fn read_error(e: &(impl std::error::Error + 'static)) -> StorageError<NodeId> {
+    StorageIOError::read_state_machine(AnyError::new(e)).into()
+}
+
+/// The node's vote and log, in memory and on disk. Every write is synced before it returns:
+/// a node that forgot its vote could vote twice in a term, and one that forgot an entry could
+/// let the cluster lose an order a majority acknowledged.
+struct Log {
+    dir: PathBuf,
+    vote: Option<Vote<NodeId>>,
+    entries: BTreeMap<u64, Entry<TypeConfig>>,
+    last_purged: Option<LogId<NodeId>>,
+    /// `LOG_FILE`, appended to
+    file: File,
+}
+
+/// A line of `LOG_FILE`: an entry, or where the log starts after a purge
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LogRecord<E> {
+    Entry(E),
+    Purged(LogId<NodeId>),
+}
+
+impl Log {
+    /// Reads back the vote and log a previous run left in `dir`, starting empty without one
+    fn open(dir: PathBuf) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let vote = match fs::read(dir.join(VOTE_FILE)) {
+            Ok(data) => Some(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE))?;
+        let mut log = Log {
+            dir,
+            vote,
+            entries: BTreeMap::new(),
+            last_purged: None,
+            file,
+        };
+        let mut reader = BufReader::new(File::open(log.dir.join(LOG_FILE))?);
+        let (mut line, mut complete) = (Vec::new(), 0);
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 || line.last() != Some(&b'\n') {
+                break; // a line cut short by a crash was never acknowledged
+            }
+            match serde_json::from_slice::<LogRecord<Entry<TypeConfig>>>(&line)? {
+                LogRecord::Entry(entry) => {
+                    log.entries.insert(entry.log_id.index, entry);
+                }
+                LogRecord::Purged(log_id) => log.last_purged = Some(log_id),
+            }
+            complete += read as u64;
+        }
+        log.file.set_len(complete)?;
+        Ok(log)
+    }
+
+    fn save_vote(&mut self, vote: Vote<NodeId>) -> io::Result<()> {
+        write_synced(&self.dir.join(VOTE_FILE), &serde_json::to_vec(&vote)?)?;
+        self.vote = Some(vote);
+        Ok(())
+    }
+
+    fn append(&mut self, entries: Vec<Entry<TypeConfig>>) -> io::Result<()> {
+        let mut lines = Vec::new();
+        for entry in &entries {
+            write_record(&mut lines, &LogRecord::Entry(entry))?;
+        }
+        self.file.write_all(&lines)?;
+        self.file.sync_data()?;
+        for entry in entries {
+            self.entries.insert(entry.log_id.index, entry);
+        }
+        Ok(())
+    }
+
+    /// Writes the log anew once entries left it, through a temporary file so a crash leaves
+    /// either version
+    fn rewrite(&mut self) -> io::Result<()> {
+        let mut lines = Vec::new();
+        if let Some(purged) = self.last_purged {
+            write_record(&mut lines, &LogRecord::<&Entry<TypeConfig>>::Purged(purged))?;
+        }
+        for entry in self.entries.values() {
+            write_record(&mut lines, &LogRecord::Entry(entry))?;
+        }
+        let path = self.dir.join(LOG_FILE);
+        write_synced(&path, &lines)?;
+        self.file = OpenOptions::new().append(true).open(path)?;
+        Ok(())
+    }
+}
+
+fn write_record(lines: &mut Vec<u8>, record: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *lines, record)?;
+    lines.push(b'\n');
+    Ok(())
+}
+
+/// Replaces `path` with `data` through a temporary file, synced along with its directory so
+/// that a crash leaves either version whole
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    File::open(path.parent().expect("raft files are in a directory"))?.sync_all()
+}
+
+/// A snapshot's metadata and the engine state it holds
+type StoredSnapshot = (SnapshotMeta<NodeId, BasicNode>, Vec<u8>);
+
+/// The latest snapshot in `dir`: a line of metadata, then the engine state
+fn read_snapshot(dir: &Path) -> io::Result<Option<StoredSnapshot>> {
+    let mut data = match fs::read(dir.join(SNAPSHOT_FILE)) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let end = data
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or_else(|| io::Error::other("snapshot without metadata"))?;
+    let meta = serde_json::from_slice(&data[..end])?;
+    Ok(Some((meta, data.split_off(end + 1))))
+}
+
+fn write_snapshot(
+    dir: &Path,
+    meta: &SnapshotMeta<NodeId, BasicNode>,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut file = serde_json::to_vec(meta).expect("snapshot metadata serializes");
+    file.push(b'\n');
+    file.extend_from_slice(data);
+    write_synced(&dir.join(SNAPSHOT_FILE), &file)
+}
+
+struct StateMachine {
+    engine: MatchingEngine,
+    last_applied: Option<LogId<NodeId>>,
+    membership: StoredMembership<NodeId, BasicNode>,
+    /// The latest snapshot, built locally or received from the leader
+    snapshot: Option<StoredSnapshot>,
+}
+
+/// The Raft log and the engine it applies to, kept in `dir` across restarts. A restarted
+/// node loads its latest snapshot into the engine and applies the committed entries after it
+/// again.
+#[derive(Clone)]
+struct Store {
+    dir: PathBuf,
+    log: Arc<Mutex<Log>>,
+    state_machine: Arc<Mutex<StateMachine>>,
+}
+
+impl Store {
+    async fn open(engine: MatchingEngine, dir: PathBuf) -> io::Result<Self> {
+        let log = Log::open(dir.clone())?;
+        let snapshot: Option<StoredSnapshot> = read_snapshot(&dir)?;
+        let (mut last_applied, mut membership) = (None, StoredMembership::default());
+        if let Some((meta, data)) = &snapshot {
+            engine.import_state(serde_json::from_slice(data)?).await;
+            last_applied = meta.last_log_id;
+            membership = meta.last_membership.clone();
+        }
+        Ok(Store {
+            dir,
+            log: Arc::new(Mutex::new(log)),
+            state_machine: Arc::new(Mutex::new(StateMachine {
+                engine,
+                last_applied,
+                membership,
+                snapshot,
+            })),
+        })
+    }
+}
+
+impl RaftLogReader<TypeConfig> for Store {
+    async fn try_get_log_entries<RB: RangeBounds<u64> + Clone + Debug + Send>(
+        &mut self,
+        range: RB,
+    ) -> Result<Vec<Entry<TypeConfig>>, StorageError<NodeId>> {
+        let log = self.log.lock().await;
+        Ok(log.entries.range(range).map(|(_, e)| e.clone()).collect())
+    }
+}
+
+impl RaftSnapshotBuilder<TypeConfig> for Store {
+    async fn build_snapshot(&mut self) -> Result<Snapshot<TypeConfig>, StorageError<NodeId>> {
+        let mut state_machine = self.state_machine.lock().await;
+        let state = state_machine.engine.export_state().await;
+        let data = serde_json::to_vec(&state).map_err(|e| read_error(&e))?;
+        let last_applied = state_machine.last_applied;
+        let meta = SnapshotMeta {
+            last_log_id: last_applied,
+            last_membership: state_machine.membership.clone(),
+            snapshot_id: format!(
+                "{}-{}",
+                last_applied.map_or(0, |id| id.index),
+                state.last_seq
+            ),
+        };
+        write_snapshot(&self.dir, &meta, &data).map_err(|e| {
+            StorageIOError::write_snapshot(Some(meta.signature()), AnyError::new(&e))
+        })?;
+        state_machine.snapshot = Some((meta.clone(), data.clone()));
+        Ok(Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        })
+    }
+}
+
+impl RaftStorage<TypeConfig> for Store {
+    type LogReader = Store;
+    type SnapshotBuilder = Store;
+
+    async fn save_vote(&mut self, vote: &Vote<NodeId>) -> Result<(), StorageError<NodeId>> {
+        self.log
+            .lock()
+            .await
+            .save_vote(*vote)
+            .map_err(|e| StorageIOError::write_vote(AnyError::new(&e)).into())
+    }
+
+    async fn read_vote(&mut self) -> Result<Option<Vote<NodeId>>, StorageError<NodeId>> {
+        Ok(self.log.lock().await.vote)
+    }
+
+    async fn get_log_state(&mut self) -> Result<LogState<TypeConfig>, StorageError<NodeId>> {
+        let log = self.log.lock().await;
+        let last = log.entries.values().next_back().map(|e| e.log_id);
+        Ok(LogState {
+            last_purged_log_id: log.last_purged,
+            last_log_id: last.or(log.last_purged),
+        })
+    }
+
+    async fn get_log_reader(&mut self) -> Self::LogReader {
+        self.clone()
+    }
+
+    async fn append_to_log<I>(&mut self, entries: I) -> Result<(), StorageError<NodeId>>
+    where
+        I: IntoIterator<Item = Entry<TypeConfig>> + Send,
+    {
+        self.log
+            .lock()
+            .await
+            .append(entries.into_iter().collect())
+            .map_err(|e| StorageIOError::write_logs(AnyError::new(&e)).into())
+    }
+
+    async fn delete_conflict_logs_since(
+        &mut self,
+        log_id: LogId<NodeId>,
+    ) -> Result<(), StorageError<NodeId>> {
+        let mut log = self.log.lock().await;
+        log.entries.split_off(&log_id.index);
+        log.rewrite()
+            .map_err(|e| StorageIOError::write_logs(AnyError::new(&e)).into())
+    }
+
+    async fn purge_logs_upto(&mut self, log_id: LogId<NodeId>) -> Result<(), StorageError<NodeId>> {
+        let mut log = self.log.lock().await;
+        log.entries = log.entries.split_off(&(log_id.index + 1));
+        log.last_purged = Some(log_id);
+        log.rewrite()
+            .map_err(|e| StorageIOError::write_logs(AnyError::new(&e)).into())
+    }
+
+    async fn last_applied_state(
+        &mut self,
+    ) -> Result<(Option<LogId<NodeId>>, StoredMembership<NodeId, BasicNode>), StorageError<NodeId>>
+    {
+        let state_machine = self.state_machine.lock().await;
+        Ok((state_machine.last_applied, state_machine.membership.clone()))
+    }
+
+    async fn apply_to_state_machine(
+        &mut self,
+        entries: &[Entry<TypeConfig>],
+    ) -> Result<Vec<Option<CommandResult>>, StorageError<NodeId>> {
+        let mut state_machine = self.state_machine.lock().await;
+        let mut results = Vec::with_capacity(entries.len());
+        for entry in entries {
+            state_machine.last_applied = Some(entry.log_id);
+            let mut engine = state_machine.engine.clone();
+            let result = match &entry.payload {
+                EntryPayload::Blank => None,
+                EntryPayload::Membership(membership) => {
+                    state_machine.membership =
+                        StoredMembership::new(Some(entry.log_id), membership.clone());
+                    None
+                }
+                EntryPayload::Normal(Stamped { at, command }) => Some(
+                    APPLYING_AT
+                        .scope(*at, apply_command(&mut engine, command.clone()))
+                        .await,
+                ),
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    async fn get_snapshot_builder(&mut self) -> Self::SnapshotBuilder {
+        self.clone()
+    }
+
+    async fn begin_receiving_snapshot(
+        &mut self,
+    ) -> Result<Box<Cursor<Vec<u8>>>, StorageError<NodeId>> {
+        Ok(Box::new(Cursor::new(Vec::new())))
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        meta: &SnapshotMeta<NodeId, BasicNode>,
+        snapshot: Box<Cursor<Vec<u8>>>,
+    ) -> Result<(), StorageError<NodeId>> {
+        let data = snapshot.into_inner();
+        let state: EngineState = serde_json::from_slice(&data).map_err(|e| read_error(&e))?;
+        write_snapshot(&self.dir, meta, &data).map_err(|e| {
+            StorageIOError::write_snapshot(Some(meta.signature()), AnyError::new(&e))
+        })?;
+        let mut state_machine = self.state_machine.lock().await;
+        state_machine.engine.import_state(state).await;
+        state_machine.last_applied = meta.last_log_id;
+        state_machine.membership = meta.last_membership.clone();
+        state_machine.snapshot = Some((meta.clone(), data));
+        Ok(())
+    }
+
+    async fn get_current_snapshot(
+        &mut self,
+    ) -> Result<Option<Snapshot<TypeConfig>>, StorageError<NodeId>> {
+        let state_machine = self.state_machine.lock().await;
+        Ok(state_machine.snapshot.clone().map(|(meta, data)| Snapshot {
+            meta,
+            snapshot: Box::new(Cursor::new(data)),
+        }))
+    }
+}
+
+/// Applies `command` to the engine, which every member does at the same point of the log
+async fn apply_command(engine: &mut MatchingEngine, command: Command) -> CommandResult {
+    match command {
+        Command::Submit { order } => CommandResult::Submitted(engine.submit_order(order).await),
+        Command::Cancel { order_id, owner } => CommandResult::Canceled(match owner {
+            Some(owner) => engine.cancel_owned_order(order_id, &owner).await,
+            None => engine.cancel_order(order_id).await,
+        }),
+        Command::Schedule { order, activate_at } => {
+            CommandResult::Scheduled(engine.schedule_order(order, activate_at).await)
+        }
+        Command::Activate { order_id } => {
+            CommandResult::Submitted(engine.activate_scheduled(&order_id).await)
+        }
+        Command::Quote { quote } => CommandResult::Quoted(engine.submit_quote(quote).await),
+        Command::RequestQuote { rfq } => CommandResult::Rfq(engine.request_quote(rfq).await),
+        Command::RespondRfq { rfq_id, response } => {
+            CommandResult::Rfq(engine.respond_rfq(&rfq_id, response).await)
+        }
+        Command::CloseRfq { rfq_id } => CommandResult::RfqClosed(engine.close_rfq(&rfq_id).await),
+        Command::Kill { owner } => CommandResult::Killed(engine.kill_participant(owner).await),
+        Command::Enable { owner } => {
+            CommandResult::Enabled(engine.enable_participant(&owner).await)
+        }
+        Command::Halt { allow_cancels } => CommandResult::Halted(engine.halt(allow_cancels).await),
+        Command::Resume => CommandResult::Resumed(engine.resume().await),
+        Command::ClearBook { wipe_trades } => {
+            CommandResult::Cleared(engine.clear_book(wipe_trades).await)
+        }
+        Command::BustTrade { trade_id, reason } => {
+            CommandResult::Adjusted(engine.bust_trade(trade_id, reason).await)
+        }
+        Command::CorrectTrade {
+            trade_id,
+            price,
+            quantity,
+            reason,
+        } => CommandResult::Adjusted(
+            engine
+                .correct_trade(trade_id, price, quantity, reason)
+                .await,
+        ),
+        Command::BlockTrade {
+            buyer,
+            seller,
+            price,
+            quantity,
+        } => CommandResult::BlockTraded(
+            engine
+                .report_block_trade(buyer, seller, price, quantity)
+                .await,
+        ),
+        Command::Configure { config } => {
+            engine.update_config(config).await;
+            CommandResult::Applied
+        }
+        Command::SetReferencePrice { price } => {
+            engine.set_reference_price(price).await;
+            CommandResult::Applied
+        }
+        Command::SetRiskLimits { owner, limits } => {
+            engine.set_risk_limits(owner, limits).await;
+            CommandResult::Applied
+        }
+        Command::SetQuoteProtection { owner, protection } => {
+            engine.set_quote_protection(owner, protection).await;
+            CommandResult::Applied
+        }
+        Command::Deposit {
+            owner,
+            asset,
+            amount,
+        } => CommandResult::Balances(Some(engine.deposit(owner, asset, amount).await)),
+        Command::Withdraw {
+            owner,
+            asset,
+            amount,
+        } => CommandResult::Balances(engine.withdraw(&owner, asset, amount).await),
+        Command::CloseSettlement => {
+            CommandResult::SettlementClosed(engine.close_settlement_batch().await)
+        }
+        Command::Settle { batch_id } => CommandResult::Settled(engine.settle_batch(batch_id).await),
+        Command::ImportState { state } => {
+            engine.import_state(*state).await;
+            CommandResult::Applied
+        }
+        Command::SetMarketPhase { phase } => {
+            CommandResult::PhaseChanged(engine.set_market_phase(phase).await)
+        }
+        Command::RefreshFeeTiers => {
+            engine.refresh_fee_tiers().await;
+            CommandResult::Applied
+        }
+    }
+}
+
+/// Connects to the other members' `/raft/*` endpoints over HTTP
+struct Network {
+    /// Path of this exchange under a member's base URL (`/tenants/<name>` for a tenant)
+    prefix: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+struct Peer {
+    id: NodeId,
+    url: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl RaftNetworkFactory<TypeConfig> for Network {
+    type Network = Peer;
+
+    async fn new_client(&mut self, target: NodeId, node: &BasicNode) -> Peer {
+        Peer {
+            id: target,
+            url: format!("{}{}/raft", node.addr, self.prefix),
+            token: self.token.clone(),
+            agent: self.agent.clone(),
+        }
+    }
+}
+
+impl Peer {
+    /// Posts `request` to `/raft/<path>`; the peer answers with the result of handing it to its
+    /// Raft node
+    async fn call<Req, Resp, E>(
+        &self,
+        path: &str,
+        request: Req,
+    ) -> Result<Resp, RPCError<NodeId, BasicNode, RaftError<NodeId, E>>>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned + Send + 'static,
+        E: std::error::Error + DeserializeOwned + Send + 'static,
+    {
+        let body = serde_json::to_vec(&request).map_err(|e| NetworkError::new(&e))?;
+        let url = format!("{}/{path}", self.url);
+        let (agent, token) = (self.agent.clone(), self.token.clone());
+        let sent = tokio::task::spawn_blocking(move || {
+            let mut request = agent.post(&url).header("content-type", "application/json");
+            if let Some(token) = token {
+                request = request.header("authorization", format!("Bearer {token}"));
+            }
+            let mut response = request.send(&body[..])?;
+            let text = response.body_mut().read_to_string()?;
+            Ok::<_, ureq::Error>((url, response.status(), text))
+        })
+        .await
+        .map_err(|e| NetworkError::new(&e))?;
+        let (url, status, text) = sent.map_err(|e| match e {
+            ureq::Error::Io(_) | ureq::Error::ConnectionFailed | ureq::Error::HostNotFound => {
+                RPCError::Unreachable(Unreachable::new(&e))
+            }
+            e => RPCError::Network(NetworkError::new(&e)),
+        })?;
+        if !status.is_success() {
+            let e = std::io::Error::other(format!("{url}: {status} {text}"));
+            return Err(RPCError::Network(NetworkError::new(&e)));
+        }
+        serde_json::from_str::<Result<Resp, RaftError<NodeId, E>>>(&text)
+            .map_err(|e| NetworkError::new(&e))?
+            .map_err(|e| RPCError::RemoteError(RemoteError::new(self.id, e)))
+    }
+}
+
+impl RaftNetwork<TypeConfig> for Peer {
+    async fn append_entries(
+        &mut self,
+        rpc: AppendEntriesRequest<TypeConfig>,
+        _option: RPCOption,
+    ) -> Result<AppendEntriesResponse<NodeId>, RPCError<NodeId, BasicNode, RaftError<NodeId>>> {
+        self.call("append", rpc).await
+    }
+
+    async fn install_snapshot(
+        &mut self,
+        rpc: InstallSnapshotRequest<TypeConfig>,
+        _option: RPCOption,
+    ) -> Result<
+        InstallSnapshotResponse<NodeId>,
+        RPCError<NodeId, BasicNode, RaftError<NodeId, InstallSnapshotError>>,
+    > {
+        self.call("snapshot", rpc).await
+    }
+
+    async fn vote(
+        &mut self,
+        rpc: VoteRequest<NodeId>,
+        _option: RPCOption,
+    ) -> Result<VoteResponse<NodeId>, RPCError<NodeId, BasicNode, RaftError<NodeId>>> {
+        self.call("vote", rpc).await
+    }
+}
+
+/// This node's member of the cluster that orders commands for one exchange
+#[derive(Clone)]
+pub struct Consensus {
+    raft: Raft,
+}
+
+impl Consensus {
+    /// Starts this node's Raft member for `engine`, served under `prefix`. The lowest node id
+    /// forms the cluster from the configured peers; the others wait to hear from the leader.
+    pub async fn start(engine: MatchingEngine, config: RaftConfig, prefix: &str) -> Self {
+        let raft_config = Config {
+            cluster_name: format!("ome{prefix}"),
+            heartbeat_interval: 250,
+            election_timeout_min: 1_000,
+            election_timeout_max: 2_000,
+            ..Default::default()
+        };
+        let raft_config = Arc::new(raft_config.validate().expect("valid raft timings"));
+        let network = Network {
+            prefix: prefix.to_string(),
+            token: config.token,
+            agent: ureq::Agent::config_builder()
+                .http_status_as_error(false)
+                .timeout_global(Some(RPC_TIMEOUT))
+                .build()
+                .into(),
+        };
+        let store = Store::open(engine, config.dir.clone())
+            .await
+            .unwrap_or_else(|e| panic!("{}: raft state unreadable: {e}", config.dir.display()));
+        let (log_store, state_machine) = Adaptor::new(store);
+        let raft = Raft::new(
+            config.node_id,
+            raft_config,
+            network,
+            log_store,
+            state_machine,
+        )
+        .await
+        .expect("raft node starts");
+
+        let members: BTreeMap<NodeId, BasicNode> = config
+            .peers
+            .iter()
+            .map(|(id, url)| (*id, BasicNode::new(url)))
+            .collect();
+        if config.peers.keys().next() == Some(&config.node_id)
+            && let Err(e) = raft.initialize(members).await
+        {
+            println!("raft: not initializing the cluster: {e}");
+        }
+        Consensus { raft }
+    }
+
+    /// Commits `command` to the cluster's log and returns what applying it on this node did
+    async fn write(&self, command: Command) -> Result<CommandResult, ApiError> {
+        let entry = Stamped {
+            at: SystemClock.now_ns(),
+            command,
+        };
+        match self.raft.client_write(entry).await {
+            Ok(response) => response
+                .data
+                .ok_or_else(|| ApiError::new(ErrorCode::Internal, "command had no result")),
+            Err(RaftError::APIError(ClientWriteError::ForwardToLeader(forward))) => {
+                let message = match forward.leader_node {
+                    Some(node) => format!("not the leader, send commands to {}", node.addr),
+                    None => "no leader elected yet, retry shortly".to_string(),
+                };
+                Err(ApiError::new(ErrorCode::NotLeader, message))
+            }
+            Err(e) => Err(ApiError::new(ErrorCode::Internal, e.to_string())),
+        }
+    }
+
+    pub async fn submit_order(&self, order: Order) -> Result<Vec<Trade>, ApiError> {
+        match self.write(Command::Submit { order }).await? {
+            CommandResult::Submitted(result) => Ok(result?),
+            _ => unreachable!("submits produce submit results"),
+        }
+    }
+
+    pub async fn schedule_order(
+        &self,
+        order: Order,
+        activate_at: Timestamp,
+    ) -> Result<(), ApiError> {
+        match self.write(Command::Schedule { order, activate_at }).await? {
+            CommandResult::Scheduled(result) => Ok(result?),
+            _ => unreachable!("schedules produce schedule results"),
+        }
+    }
+
+    pub async fn activate_order(&self, order_id: OrderId) -> Result<Vec<Trade>, ApiError> {
+        match self.write(Command::Activate { order_id }).await? {
+            CommandResult::Submitted(result) => Ok(result?),
+            _ => unreachable!("activations produce submit results"),
+        }
+    }
+
+    pub async fn submit_quote(&self, quote: Quote) -> Result<QuoteAck, ApiError> {
+        match self.write(Command::Quote { quote }).await? {
+            CommandResult::Quoted(result) => Ok(result?),
+            _ => unreachable!("quotes produce quote results"),
+        }
+    }
+
+    pub async fn request_quote(&self, rfq: Rfq) -> Result<(), ApiError> {
+        match self.write(Command::RequestQuote { rfq }).await? {
+            CommandResult::Rfq(result) => Ok(result?),
+            _ => unreachable!("RFQs produce RFQ results"),
+        }
+    }
+
+    pub async fn respond_rfq(&self, rfq_id: RfqId, response: RfqResponse) -> Result<(), ApiError> {
+        match self.write(Command::RespondRfq { rfq_id, response }).await? {
+            CommandResult::Rfq(result) => Ok(result?),
+            _ => unreachable!("RFQ responses produce RFQ results"),
+        }
+    }
+
+    pub async fn close_rfq(&self, rfq_id: RfqId) -> Result<Option<Trade>, ApiError> {
+        match self.write(Command::CloseRfq { rfq_id }).await? {
+            CommandResult::RfqClosed(trade) => Ok(trade),
+            _ => unreachable!("RFQ closes produce RFQ close results"),
+        }
+    }
+
+    pub async fn cancel_order(
+        &self,
+        order_id: OrderId,
+        owner: Option<ParticipantId>,
+    ) -> Result<bool, ApiError> {
+        match self.write(Command::Cancel { order_id, owner }).await? {
+            CommandResult::Canceled(result) => Ok(result?),
+            _ => unreachable!("cancels produce cancel results"),
+        }
+    }
+
+    pub async fn kill_participant(&self, owner: ParticipantId) -> Result<Vec<Order>, ApiError> {
+        match self.write(Command::Kill { owner }).await? {
+            CommandResult::Killed(result) => Ok(result?),
+            _ => unreachable!("kills produce kill results"),
+        }
+    }
+
+    pub async fn enable_participant(&self, owner: ParticipantId) -> Result<bool, ApiError> {
+        match self.write(Command::Enable { owner }).await? {
+            CommandResult::Enabled(result) => Ok(result?),
+            _ => unreachable!("enables produce enable results"),
+        }
+    }
+
+    pub async fn halt(&self, allow_cancels: bool) -> Result<TradingState, ApiError> {
+        match self.write(Command::Halt { allow_cancels }).await? {
+            CommandResult::Halted(state) => Ok(state),
+            _ => unreachable!("halts produce halt results"),
+        }
+    }
+
+    pub async fn resume(&self) -> Result<bool, ApiError> {
+        match self.write(Command::Resume).await? {
+            CommandResult::Resumed(resumed) => Ok(resumed),
+            _ => unreachable!("resumes produce resume results"),
+        }
+    }
+
+    pub async fn clear_book(&self, wipe_trades: bool) -> Result<Vec<Order>, ApiError> {
+        match self.write(Command::ClearBook { wipe_trades }).await? {
+            CommandResult::Cleared(canceled) => Ok(canceled),
+            _ => unreachable!("clears produce clear results"),
+        }
+    }
+
+    /// The outer error is the cluster's, the inner one the engine's
+    pub async fn bust_trade(
+        &self,
+        trade_id: TradeId,
+        reason: String,
+    ) -> Result<Result<Trade, TradeAdjustmentError>, ApiError> {
+        match self.write(Command::BustTrade { trade_id, reason }).await? {
+            CommandResult::Adjusted(result) => Ok(result),
+            _ => unreachable!("busts produce adjustment results"),
+        }
+    }
+
+    /// The outer error is the cluster's, the inner one the engine's
+    pub async fn correct_trade(
+        &self,
+        trade_id: TradeId,
+        price: Option<Price>,
+        quantity: Option<Quantity>,
+        reason: String,
+    ) -> Result<Result<Trade, TradeAdjustmentError>, ApiError> {
+        let command = Command::CorrectTrade {
+            trade_id,
+            price,
+            quantity,
+            reason,
+        };
+        match self.write(command).await? {
+            CommandResult::Adjusted(result) => Ok(result),
+            _ => unreachable!("corrections produce adjustment results"),
+        }
+    }
+
+    pub async fn report_block_trade(
+        &self,
+        buyer: ParticipantId,
+        seller: ParticipantId,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<Trade, ApiError> {
+        let command = Command::BlockTrade {
+            buyer,
+            seller,
+            price,
+            quantity,
+        };
+        match self.write(command).await? {
+            CommandResult::BlockTraded(result) => Ok(result?),
+            _ => unreachable!("block trades produce block trade results"),
+        }
+    }
+
+    /// Commits a command that has nothing to report beyond being applied
+    async fn apply(&self, command: Command) -> Result<(), ApiError> {
+        match self.write(command).await? {
+            CommandResult::Applied => Ok(()),
+            _ => unreachable!("settings produce applied results"),
+        }
+    }
+
+    pub async fn update_config(&self, config: RuntimeConfig) -> Result<(), ApiError> {
+        self.apply(Command::Configure { config }).await
+    }
+
+    pub async fn set_reference_price(&self, price: Option<Price>) -> Result<(), ApiError> {
+        self.apply(Command::SetReferencePrice { price }).await
+    }
+
+    pub async fn set_risk_limits(
+        &self,
+        owner: ParticipantId,
+        limits: RiskLimits,
+    ) -> Result<(), ApiError> {
+        self.apply(Command::SetRiskLimits { owner, limits }).await
+    }
+
+    pub async fn set_quote_protection(
+        &self,
+        owner: ParticipantId,
+        protection: QuoteProtection,
+    ) -> Result<(), ApiError> {
+        self.apply(Command::SetQuoteProtection { owner, protection })
+            .await
+    }
+
+    pub async fn import_state(&self, state: EngineState) -> Result<(), ApiError> {
+        self.apply(Command::ImportState {
+            state: Box::new(state),
+        })
+        .await
+    }
+
+    pub async fn deposit(
+        &self,
+        owner: ParticipantId,
+        asset: Asset,
+        amount: u64,
+    ) -> Result<AccountBalances, ApiError> {
+        let command = Command::Deposit {
+            owner,
+            asset,
+            amount,
+        };
+        match self.write(command).await? {
+            CommandResult::Balances(balances) => {
+                Ok(balances.expect("deposits always credit the account"))
+            }
+            _ => unreachable!("deposits produce balance results"),
+        }
+    }
+
+    pub async fn withdraw(
+        &self,
+        owner: ParticipantId,
+        asset: Asset,
+        amount: u64,
+    ) -> Result<Option<AccountBalances>, ApiError> {
+        let command = Command::Withdraw {
+            owner,
+            asset,
+            amount,
+        };
+        match self.write(command).await? {
+            CommandResult::Balances(balances) => Ok(balances),
+            _ => unreachable!("withdrawals produce balance results"),
+        }
+    }
+
+    pub async fn close_settlement_batch(&self) -> Result<Option<SettlementBatch>, ApiError> {
+        match self.write(Command::CloseSettlement).await? {
+            CommandResult::SettlementClosed(batch) => Ok(batch),
+            _ => unreachable!("settlement closes produce settlement close results"),
+        }
+    }
+
+    /// The outer error is the cluster's, the inner one the engine's
+    pub async fn settle_batch(
+        &self,
+        batch_id: u64,
+    ) -> Result<Result<SettlementBatch, SettlementError>, ApiError> {
+        match self.write(Command::Settle { batch_id }).await? {
+            CommandResult::Settled(result) => Ok(result),
+            _ => unreachable!("settles produce settle results"),
+        }
+    }
+
+    pub async fn set_market_phase(&self, phase: MarketPhase) -> Result<Vec<Trade>, ApiError> {
+        match self.write(Command::SetMarketPhase { phase }).await? {
+            CommandResult::PhaseChanged(trades) => Ok(trades),
+            _ => unreachable!("phase changes produce phase change results"),
+        }
+    }
+
+    pub async fn refresh_fee_tiers(&self) -> Result<(), ApiError> {
+        self.apply(Command::RefreshFeeTiers).await
+    }
+
+    /// Whether this node currently leads the cluster
+    pub fn is_leader(&self) -> bool {
+        let metrics = self.raft.metrics();
+        let metrics = metrics.borrow();
+        metrics.current_leader == Some(metrics.id)
+    }
+}
+
+/// `/raft/*`: the members' RPCs and the node's status, for admins (and the other members)
+pub fn routes(consensus: Consensus, auth: Auth) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/raft/append", post(append_entries))
+        .route("/raft/vote", post(vote))
+        .route("/raft/snapshot", post(install_snapshot))
+        .route("/raft/status", get(get_status))
+        // entries and snapshots carry state imports and whole books
+        .layer(DefaultBodyLimit::max(admin::MAX_STATE_BYTES))
+        .layer(Extension(consensus))
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_admin))
+}
+
+async fn append_entries(
+    Extension(consensus): Extension<Consensus>,
+    payload: Result<Json<AppendEntriesRequest<TypeConfig>>, JsonRejection>,
+) -> Result<Json<Result<AppendEntriesResponse<NodeId>, RaftError<NodeId>>>, ApiError> {
+    let Json(rpc) = payload?;
+    Ok(Json(consensus.raft.append_entries(rpc).await))
+}
+
+async fn vote(
+    Extension(consensus): Extension<Consensus>,
+    payload: Result<Json<VoteRequest<NodeId>>, JsonRejection>,
+) -> Result<Json<Result<VoteResponse<NodeId>, RaftError<NodeId>>>, ApiError> {
+    let Json(rpc) = payload?;
+    Ok(Json(consensus.raft.vote(rpc).await))
+}
+
+async fn install_snapshot(
+    Extension(consensus): Extension<Consensus>,
+    payload: Result<Json<InstallSnapshotRequest<TypeConfig>>, JsonRejection>,
+) -> Result<
+    Json<Result<InstallSnapshotResponse<NodeId>, RaftError<NodeId, InstallSnapshotError>>>,
+    ApiError,
+> {
+    let Json(rpc) = payload?;
+    Ok(Json(consensus.raft.install_snapshot(rpc).await))
+}
+
+async fn get_status(
+    Extension(consensus): Extension<Consensus>,
+) -> Json<RaftMetrics<NodeId, BasicNode>> {
+    Json(consensus.raft.metrics().borrow().clone())
+}
+
+#[cfg(test)]
+mod test {
+    use openraft::testing::log_id;
+
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    #[test]
+    fn test_parse_peers() {
+        let peers = parse_peers("1=http://a:61666/, 2=https://b").unwrap();
+        assert_eq!(peers[&1], "http://a:61666");
+        assert_eq!(peers[&2], "https://b");
+        assert!(parse_peers("1=http://a,1=http://b").is_err());
+        assert!(parse_peers("a=http://a").is_err());
+        assert!(parse_peers("1=a:61666").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_log_clock() {
+        // every member applies an entry at the time the leader stamped on it
+        let applied_at = APPLYING_AT.scope(42, async { LogClock.now_ns() }).await;
+        assert_eq!(applied_at, 42);
+        assert!(LogClock.now_ns() > 42);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ome-raft-{name}-{}", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_log_survives_restart() {
+        let dir = temp_dir("restart");
+        let entry = |index| Entry::<TypeConfig> {
+            log_id: log_id(2, 1, index),
+            payload: EntryPayload::Blank,
+        };
+        let mut store = Store::open(MatchingEngine::new(), dir.clone())
+            .await
+            .unwrap();
+        store.save_vote(&Vote::new(2, 1)).await.unwrap();
+        store
+            .append_to_log([entry(1), entry(2), entry(3)])
+            .await
+            .unwrap();
+        store
+            .delete_conflict_logs_since(log_id(2, 1, 3))
+            .await
+            .unwrap();
+        store.purge_logs_upto(log_id(2, 1, 1)).await.unwrap();
+        store.append_to_log([entry(3), entry(4)]).await.unwrap();
+        drop(store);
+
+        let mut store = Store::open(MatchingEngine::new(), dir.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.read_vote().await.unwrap(), Some(Vote::new(2, 1)));
+        let state = store.get_log_state().await.unwrap();
+        assert_eq!(state.last_purged_log_id, Some(log_id(2, 1, 1)));
+        assert_eq!(state.last_log_id, Some(log_id(2, 1, 4)));
+        let indexes: Vec<u64> = store
+            .try_get_log_entries(0..)
+            .await
+            .unwrap()
+            .iter()
+            .map(|e| e.log_id.index)
+            .collect();
+        assert_eq!(indexes, [2, 3, 4]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_single_node_cluster() {
+        let engine = MatchingEngine::new();
+        let peers = BTreeMap::from([(1, "http://127.0.0.1:1".to_string())]);
+        let dir = temp_dir("single");
+        let config = RaftConfig {
+            node_id: 1,
+            peers,
+            token: None,
+            dir: dir.clone(),
+        };
+        let consensus = Consensus::start(engine.clone(), config, "").await;
+        consensus
+            .raft
+            .wait(Some(Duration::from_secs(10)))
+            .current_leader(1, "elected")
+            .await
+            .unwrap();
+
+        let order = |id: &str, side| {
+            Order::new(id.to_string(), side, OrderType::Limit, 10, 100, 1)
+                .with_owner("alice".to_string())
+        };
+        assert_eq!(
+            consensus
+                .submit_order(order("1", Side::Sell))
+                .await
+                .unwrap(),
+            vec![]
+        );
+        let trades = consensus.submit_order(order("2", Side::Buy)).await.unwrap();
+        assert_eq!((trades.len(), trades[0].quantity), (1, 10));
+        assert!(
+            !consensus
+                .cancel_order("1".to_string(), Some("alice".to_string()))
+                .await
+                .unwrap()
+        );
+        assert!(consensus.is_leader());
+        consensus
+            .schedule_order(order("3", Side::Sell), 5)
+            .await
+            .unwrap();
+        assert_eq!(engine.due_orders(5).await, ["3"]);
+        consensus.activate_order("3".to_string()).await.unwrap();
+        assert!(engine.due_orders(5).await.is_empty());
+        assert_eq!(engine.get_sell_orders().await.len(), 1);
+
+        // admin commands are ordered with the orders, so every member applies them alike
+        let canceled = consensus
+            .kill_participant("alice".to_string())
+            .await
+            .unwrap();
+        assert_eq!(canceled.len(), 1);
+        assert_eq!(engine.blocked_participants().await, ["alice"]);
+        assert!(
+            consensus
+                .enable_participant("alice".to_string())
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            consensus.halt(true).await.unwrap(),
+            TradingState::Halted {
+                allow_cancels: true
+            }
+        );
+        assert!(consensus.resume().await.unwrap());
+        assert_eq!(engine.trading_state().await, TradingState::Open);
+
+        // the snapshot a lagging member would receive rebuilds the same book, and so does the
+        // one a restarted member finds on disk
+        let snapshot = Store::open(engine.clone(), temp_dir("leader"))
+            .await
+            .unwrap()
+            .build_snapshot()
+            .await
+            .unwrap();
+        let replica = MatchingEngine::new();
+        Store::open(replica.clone(), temp_dir("replica"))
+            .await
+            .unwrap()
+            .install_snapshot(&snapshot.meta, snapshot.snapshot)
+            .await
+            .unwrap();
+        assert_eq!(
+            replica.export_state().await.trades,
+            engine.export_state().await.trades
+        );
+        let restarted = MatchingEngine::new();
+        Store::open(restarted.clone(), temp_dir("replica"))
+            .await
+            .unwrap();
+        assert_eq!(
+            restarted.export_state().await.trades,
+            engine.export_state().await.trades
+        );
+        for name in ["single", "leader", "replica"] {
+            std::fs::remove_dir_all(temp_dir(name)).unwrap();
+        }
+    }
+}
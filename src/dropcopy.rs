@@ -0,0 +1,138 @@
+use axum::{
+    Extension, Json, Router,
+    extract::{
+        Query, State,
+        rejection::QueryRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    middleware,
+    response::Response,
+    routing::get,
+};
+use serde::Deserialize;
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    audit::AuditEntry,
+    auth::{self, Auth},
+    error::ApiError,
+    heartbeat::{self, HeartbeatConfig, Liveness},
+    matchingengine::MatchingEngine,
+    state::EngineState,
+};
+
+const CATCH_UP_BATCH: usize = 1_000; // entries read from the log at a time when replaying
+
+#[derive(Debug, Deserialize)]
+struct DropCopyQuery {
+    /// Replay from this sequence number before going live
+    from: Option<u64>,
+}
+
+/// `/drop-copy`: every execution report of every participant, for the `drop-copy` role
+pub fn routes(auth: Auth) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/drop-copy", get(drop_copy_upgrade))
+        .route("/drop-copy/snapshot", get(get_snapshot))
+        .route_layer(middleware::from_fn_with_state(
+            auth,
+            auth::require_drop_copy,
+        ))
+}
+
+async fn drop_copy_upgrade(
+    State(engine): State<MatchingEngine>,
+    Extension(heartbeat): Extension<Option<HeartbeatConfig>>,
+    query: Result<Query<DropCopyQuery>, QueryRejection>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    Ok(ws.on_upgrade(move |socket| {
+        follow_events(socket, engine, query.from, heartbeat, |entry| {
+            Some(serde_json::to_string(entry).expect("audit entries always serialize"))
+        })
+    }))
+}
+
+/// A consistent image of the engine and the sequence number it was taken at, for a replica
+/// to start from before following `/drop-copy?from=<last_seq + 1>`
+async fn get_snapshot(State(engine): State<MatchingEngine>) -> Json<EngineState> {
+    Json(engine.export_state().await)
+}
+
+async fn send(
+    socket: &mut WebSocket,
+    entry: &AuditEntry,
+    render: &impl Fn(&AuditEntry) -> Option<String>,
+) -> bool {
+    match render(entry) {
+        Some(text) => socket.send(Message::Text(text.into())).await.is_ok(),
+        None => true,
+    }
+}
+
+/// Streams entries in sequence without gaps: replays from the log, then follows the live
+/// feed, and goes back to the log whenever the live feed lags or skips ahead. `render` turns
+/// an entry into the message sent for it, `None` skips the entry. A client that stops
+/// answering pings is dropped.
+pub async fn follow_events(
+    mut socket: WebSocket,
+    engine: MatchingEngine,
+    from: Option<u64>,
+    heartbeat: Option<HeartbeatConfig>,
+    render: impl Fn(&AuditEntry) -> Option<String>,
+) {
+    let mut live = engine.subscribe_events();
+    let mut liveness = Liveness::new(heartbeat);
+    let mut next_seq = match from {
+        Some(from) => from.max(1),
+        None => engine.last_seq().await + 1,
+    };
+
+    loop {
+        let backlog = engine.audit_entries(next_seq, CATCH_UP_BATCH).await;
+        for entry in &backlog {
+            if !send(&mut socket, entry, &render).await {
+                return;
+            }
+            next_seq = entry.seq + 1;
+        }
+        if backlog.len() == CATCH_UP_BATCH {
+            continue;
+        }
+
+        loop {
+            let ping_due = liveness.deadline();
+            let entry = tokio::select! {
+                entry = live.recv() => entry,
+                msg = socket.recv() => match msg {
+                    None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+                    Some(Ok(_)) => {
+                        liveness.seen(); // the feed is read-only, but any frame is a sign of life
+                        continue;
+                    }
+                },
+                _ = sleep_until(ping_due.unwrap_or_else(Instant::now)), if ping_due.is_some() => {
+                    if !heartbeat::keep_alive(&mut socket, &mut liveness).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            match entry {
+                Ok(entry) if entry.seq < next_seq => continue, // already replayed
+                Ok(entry) if entry.seq == next_seq => {
+                    if !send(&mut socket, &entry, &render).await {
+                        return;
+                    }
+                    next_seq += 1;
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => break, // catch up from the log
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
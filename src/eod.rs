@@ -0,0 +1,355 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    extract::{Path as UrlPath, State},
+    middleware,
+    routing::get,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::day_of,
+    auth::{self, Auth},
+    bookview::{BookSnapshot, PriceLevel},
+    error::{ApiError, ErrorCode},
+    matchingengine::MatchingEngine,
+    order::{ParticipantId, Price, Quantity, Timestamp},
+    schedule::MarketPhase,
+    tradeexport::Execution,
+};
+
+pub const EOD_REPORT_DIR_ENV: &str = "OME_EOD_REPORT_DIR"; // unset disables end-of-day reports
+
+const EOD_TICK: Duration = Duration::from_secs(1);
+const REPORT_FILE: &str = "eod.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EodConfig {
+    pub dir: PathBuf,
+}
+
+impl EodConfig {
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var(EOD_REPORT_DIR_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())?;
+        Some(EodConfig { dir: dir.into() })
+    }
+}
+
+/// One trading session of one symbol, as it stood at the close
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EodReport {
+    pub symbol: String,
+    /// UTC day the session closed on, `YYYY-MM-DD`
+    pub date: String,
+    /// Unix ms, the session covers trades from `session_start` up to `session_end`
+    pub session_start: Timestamp,
+    pub session_end: Timestamp,
+    /// Of regular trades; `None` without any
+    pub open: Option<Price>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+    pub close: Option<Price>,
+    /// Of every trade that wasn't busted, block and auction trades included
+    pub volume: Quantity,
+    pub trades: usize,
+    /// The book at the close, best price first
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    /// Fees charged per account (cents)
+    pub fees: BTreeMap<ParticipantId, u64>,
+}
+
+impl EodReport {
+    /// The report of the session from `start` to `end` with its `executions` and `book` at
+    /// the close. Corrected trades count as corrected, busted ones not at all.
+    pub fn build(
+        symbol: &str,
+        start: Timestamp,
+        end: Timestamp,
+        executions: &[Execution],
+        book: &BookSnapshot,
+    ) -> Self {
+        let trades: Vec<_> = executions
+            .iter()
+            .map(|e| &e.trade)
+            .filter(|t| !t.is_busted())
+            .collect();
+        let prices: Vec<Price> = trades
+            .iter()
+            .filter(|t| t.trade_type.is_regular())
+            .map(|t| t.price)
+            .collect();
+        let mut fees: BTreeMap<ParticipantId, u64> = BTreeMap::new();
+        for trade in &trades {
+            for (account, fee) in [
+                (&trade.buyer, trade.buyer_fee),
+                (&trade.seller, trade.seller_fee),
+            ] {
+                if let Some(account) = account {
+                    *fees.entry(account.clone()).or_default() += fee;
+                }
+            }
+        }
+        EodReport {
+            symbol: symbol.to_string(),
+            date: date_of(day_of(end.saturating_sub(1))),
+            session_start: start,
+            session_end: end,
+            open: prices.first().copied(),
+            high: prices.iter().max().copied(),
+            low: prices.iter().min().copied(),
+            close: prices.last().copied(),
+            volume: trades.iter().map(|t| t.quantity).sum(),
+            trades: trades.len(),
+            bids: book.bids.clone(),
+            asks: book.asks.clone(),
+            fees,
+        }
+    }
+}
+
+fn date_of(day: Timestamp) -> String {
+    chrono::DateTime::from_timestamp_millis(day as i64)
+        .expect("day within chrono's range")
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// `<dir>/date=YYYY-MM-DD/symbol=<symbol>/eod.json`, next to the day's trade archive
+fn report_path(dir: &Path, date: &str, symbol: &str) -> PathBuf {
+    dir.join(format!("date={date}"))
+        .join(format!("symbol={symbol}"))
+        .join(REPORT_FILE)
+}
+
+/// Writes `report`, replacing an earlier report of the same day
+pub fn write_report(dir: &Path, report: &EodReport) -> io::Result<PathBuf> {
+    let path = report_path(dir, &report.date, &report.symbol);
+    fs::create_dir_all(path.parent().expect("report path has a parent"))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(
+        &tmp,
+        serde_json::to_vec_pretty(report).expect("reports serialize"),
+    )?;
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+pub fn read_report(dir: &Path, date: &str, symbol: &str) -> io::Result<EodReport> {
+    let bytes = fs::read(report_path(dir, date, symbol))?;
+    serde_json::from_slice(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Days with a report of `symbol`, oldest first
+pub fn report_dates(dir: &Path, symbol: &str) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dates = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        if let Some(date) = name.to_str().and_then(|n| n.strip_prefix("date="))
+            && report_path(dir, date, symbol).exists()
+        {
+            dates.push(date.to_string());
+        }
+    }
+    dates.sort();
+    Ok(dates)
+}
+
+/// Reports every session of `engine` when it ends: at the close of the market schedule when
+/// there is one (`scheduled`), at midnight UTC otherwise. A session starts where the last one
+/// ended, or at midnight UTC of the day the server started.
+pub async fn run_eod_reports(
+    engine: MatchingEngine,
+    symbol: String,
+    config: EodConfig,
+    scheduled: bool,
+) {
+    let mut tick = tokio::time::interval(EOD_TICK);
+    let mut session_start = day_of(engine.clock().now_ms());
+    let mut was_closed = engine.market_phase().await == MarketPhase::Closed;
+    loop {
+        tick.tick().await;
+        let now = engine.clock().now_ms();
+        let session_end = if scheduled {
+            let closed = engine.market_phase().await == MarketPhase::Closed;
+            let closing = closed && !was_closed;
+            was_closed = closed;
+            if !closing {
+                continue;
+            }
+            now
+        } else {
+            if day_of(now) <= session_start {
+                continue;
+            }
+            day_of(now)
+        };
+
+        let executions = engine.executions(session_start, session_end).await;
+        let book = engine.settled_book_view().await;
+        let report = EodReport::build(&symbol, session_start, session_end, &executions, &book);
+        let dir = config.dir.clone();
+        let written = tokio::task::spawn_blocking(move || write_report(&dir, &report))
+            .await
+            .expect("report task panicked");
+        match written {
+            Ok(path) => println!("{symbol}: end-of-day report written to {}", path.display()),
+            Err(e) => println!("{symbol}: end-of-day report failed: {e}"),
+        }
+        session_start = session_end;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EodState {
+    config: Option<EodConfig>,
+    symbol: String,
+}
+
+impl EodState {
+    fn dir(&self) -> Result<&Path, ApiError> {
+        match &self.config {
+            Some(config) => Ok(&config.dir),
+            None => Err(ApiError::new(
+                ErrorCode::ReportNotFound,
+                format!("end-of-day reports are off, set {EOD_REPORT_DIR_ENV}"),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReportDatesResponse {
+    dates: Vec<String>,
+}
+
+/// `/admin/reports/eod` lists the days with a report of the exchange's `symbol`,
+/// `/admin/reports/eod/{date}` returns one
+pub fn routes(auth: Auth, config: Option<EodConfig>, symbol: String) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/admin/reports/eod", get(get_report_dates))
+        .route("/admin/reports/eod/{date}", get(get_report))
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_admin))
+        .with_state(EodState { config, symbol })
+}
+
+fn internal(e: impl ToString) -> ApiError {
+    ApiError::new(ErrorCode::Internal, e.to_string())
+}
+
+async fn get_report_dates(
+    State(state): State<EodState>,
+) -> Result<Json<ReportDatesResponse>, ApiError> {
+    let dir = state.dir()?.to_path_buf();
+    let dates = tokio::task::spawn_blocking(move || report_dates(&dir, &state.symbol))
+        .await
+        .map_err(internal)?
+        .map_err(internal)?;
+    Ok(Json(ReportDatesResponse { dates }))
+}
+
+async fn get_report(
+    State(state): State<EodState>,
+    UrlPath(date): UrlPath<String>,
+) -> Result<Json<EodReport>, ApiError> {
+    let dir = state.dir()?.to_path_buf();
+    if NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+        return Err(ApiError::new(
+            ErrorCode::InvalidRequest,
+            format!("{date:?} is not a YYYY-MM-DD date"),
+        ));
+    }
+    let read = tokio::task::spawn_blocking(move || read_report(&dir, &date, &state.symbol))
+        .await
+        .map_err(internal)?;
+    match read {
+        Ok(report) => Ok(Json(report)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(ApiError::new(
+            ErrorCode::ReportNotFound,
+            "no end-of-day report for that day",
+        )),
+        Err(e) => Err(internal(e)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        archive::DAY_MS,
+        order::{Trade, TradeStatus, TradeType},
+    };
+
+    #[test]
+    fn test_session_report() {
+        let day = 1_700_006_400_000; // 2023-11-15
+        let execution = |id, price, quantity| {
+            let mut trade = Trade::new(id, format!("b{id}"), format!("s{id}"), price, quantity);
+            trade.buyer = Some("alice".to_string());
+            trade.seller = Some("bob".to_string());
+            trade.buyer_fee = 2;
+            trade.seller_fee = 1;
+            Execution {
+                seq: id,
+                time: day + id,
+                trade,
+            }
+        };
+        let mut busted = execution(3, 50, 7);
+        busted.trade.status = TradeStatus::Busted {
+            reason: "error".to_string(),
+        };
+        let mut block = execution(4, 200, 10);
+        block.trade.trade_type = TradeType::Block;
+        let executions = vec![
+            execution(1, 101, 5),
+            execution(2, 99, 1),
+            busted,
+            block,
+            execution(5, 100, 2),
+        ];
+        let book = BookSnapshot {
+            bids: vec![PriceLevel {
+                price: 99,
+                quantity: 3,
+                orders: 1,
+            }],
+            ..BookSnapshot::default()
+        };
+
+        let report = EodReport::build("acme", day, day + DAY_MS, &executions, &book);
+        assert_eq!(report.date, "2023-11-15");
+        assert_eq!(
+            (report.open, report.high, report.low, report.close),
+            (Some(101), Some(101), Some(99), Some(100))
+        );
+        assert_eq!((report.volume, report.trades), (18, 4));
+        assert_eq!(report.bids, book.bids);
+        assert_eq!(
+            report.fees,
+            BTreeMap::from([("alice".to_string(), 8), ("bob".to_string(), 4)])
+        );
+
+        let dir = std::env::temp_dir().join(format!("ome-eod-{}", std::process::id()));
+        write_report(&dir, &report).unwrap();
+        assert_eq!(read_report(&dir, "2023-11-15", "acme").unwrap(), report);
+        assert_eq!(report_dates(&dir, "acme").unwrap(), vec!["2023-11-15"]);
+        assert!(report_dates(&dir, "other").unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
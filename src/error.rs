@@ -0,0 +1,222 @@
+use axum::{
+    Json,
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{order::RejectReason, rfq::RfqError, validation::FieldError};
+
+/// Machine-readable error codes returned in the `code` field of every error body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    MalformedJson,
+    InvalidRequest,
+    ValidationFailed,
+    Unauthorized,
+    InvalidSignature,
+    Forbidden,
+    OrderNotFound,
+    RfqNotFound,
+    TradeNotFound,
+    BatchNotFound,
+    ReportNotFound,
+    InvalidTransition,
+    ParticipantBlocked,
+    RateLimited,
+    TradingHalted,
+    MarketClosed,
+    PriceOutsideBands,
+    FatFinger,
+    RiskLimitExceeded,
+    InsufficientBalance,
+    Standby,
+    NotLeader,
+    Overloaded,
+    Timeout,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::MalformedJson => StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidRequest
+            | ErrorCode::ValidationFailed
+            | ErrorCode::PriceOutsideBands
+            | ErrorCode::FatFinger
+            | ErrorCode::RiskLimitExceeded
+            | ErrorCode::InsufficientBalance => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::Unauthorized | ErrorCode::InvalidSignature => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::OrderNotFound
+            | ErrorCode::RfqNotFound
+            | ErrorCode::TradeNotFound
+            | ErrorCode::BatchNotFound
+            | ErrorCode::ReportNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::InvalidTransition => StatusCode::CONFLICT,
+            ErrorCode::ParticipantBlocked => StatusCode::FORBIDDEN,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::TradingHalted
+            | ErrorCode::MarketClosed
+            | ErrorCode::Standby
+            | ErrorCode::NotLeader
+            | ErrorCode::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+    /// Why the engine rejected the order or cancel, in the form `OrderRejected` events use
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RejectReason>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    error: &'a ApiError,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ApiError {
+            code,
+            message: message.into(),
+            details: Vec::new(),
+            reason: None,
+        }
+    }
+
+    pub fn validation(details: Vec<FieldError>) -> Self {
+        ApiError {
+            code: ErrorCode::ValidationFailed,
+            message: String::from("request failed validation"),
+            details,
+            reason: None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.code.status(), Json(ErrorBody { error: &self })).into_response()
+    }
+}
+
+impl From<RejectReason> for ApiError {
+    fn from(reason: RejectReason) -> Self {
+        let code = match reason {
+            RejectReason::Halted => ErrorCode::TradingHalted,
+            RejectReason::MarketClosed | RejectReason::MarketOrderInAuction => {
+                ErrorCode::MarketClosed
+            }
+            RejectReason::ParticipantBlocked => ErrorCode::ParticipantBlocked,
+            RejectReason::NotOrderOwner => ErrorCode::Forbidden,
+            RejectReason::InsufficientBalance => ErrorCode::InsufficientBalance,
+            RejectReason::Standby => ErrorCode::Standby,
+            RejectReason::CrossedQuote => ErrorCode::InvalidRequest,
+            RejectReason::OrderToTradeRatioExceeded { .. } => ErrorCode::RateLimited,
+            RejectReason::Overloaded => ErrorCode::Overloaded,
+            RejectReason::PriceOutsideBands { .. } => ErrorCode::PriceOutsideBands,
+            RejectReason::FatFinger { .. } => ErrorCode::FatFinger,
+            RejectReason::MaxQuantityExceeded { .. }
+            | RejectReason::MaxNotionalExceeded { .. }
+            | RejectReason::PositionLimitExceeded { .. }
+            | RejectReason::RiskCheckFailed => ErrorCode::RiskLimitExceeded,
+        };
+        ApiError {
+            reason: Some(reason),
+            ..ApiError::new(code, reason.to_string())
+        }
+    }
+}
+
+impl From<RfqError> for ApiError {
+    fn from(error: RfqError) -> Self {
+        match error {
+            RfqError::NotFound => ApiError::new(
+                ErrorCode::RfqNotFound,
+                "no RFQ with that id is open for responses",
+            ),
+            RfqError::NotInvited => ApiError::new(
+                ErrorCode::Forbidden,
+                "only the makers the taker selected can respond",
+            ),
+            RfqError::Rejected(reason) => reason.into(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection {
+            JsonRejection::JsonDataError(_) => ErrorCode::InvalidRequest, // well-formed JSON, wrong shape
+            _ => ErrorCode::MalformedJson,
+        };
+        ApiError::new(code, rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::new(ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_status_mapping() {
+        assert_eq!(ErrorCode::MalformedJson.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            ErrorCode::InvalidRequest.status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(ErrorCode::OrderNotFound.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_error_body_format() {
+        let err = ApiError::new(ErrorCode::OrderNotFound, "no order with id 1");
+        let body = serde_json::to_value(ErrorBody { error: &err }).unwrap();
+        assert_eq!(body["error"]["code"], "order_not_found");
+        assert_eq!(body["error"]["message"], "no order with id 1");
+        assert!(body["error"].get("details").is_none());
+
+        let err = ApiError::validation(vec![FieldError::new("quantity", "must be > 0")]);
+        let body = serde_json::to_value(ErrorBody { error: &err }).unwrap();
+        assert_eq!(body["error"]["code"], "validation_failed");
+        assert_eq!(body["error"]["details"][0]["field"], "quantity");
+        assert!(body["error"].get("reason").is_none());
+    }
+
+    #[test]
+    fn test_reject_reason_in_body() {
+        let error = ApiError::from(RejectReason::PriceOutsideBands {
+            lower: 95,
+            upper: 105,
+        });
+        assert_eq!(error.code, ErrorCode::PriceOutsideBands);
+        let body = serde_json::to_value(ErrorBody { error: &error }).unwrap();
+        assert_eq!(
+            body["error"]["reason"],
+            serde_json::json!({"PriceOutsideBands": {"lower": 95, "upper": 105}})
+        );
+        let body = serde_json::to_value(ErrorBody {
+            error: &RejectReason::Halted.into(),
+        })
+        .unwrap();
+        assert_eq!(body["error"]["reason"], "Halted");
+    }
+}
@@ -0,0 +1,68 @@
+use tokio::sync::broadcast;
+
+use crate::audit::AuditEntry;
+
+pub const EVENT_BUS_CAPACITY: usize = 4096; // events a slow subscriber may fall behind before lagging
+
+/// The engine's events as they are sequenced: every order and trade event of the audit log,
+/// in `seq` order. Feeds, the drop copy and anything else following the engine subscribe here
+/// instead of hooking into order entry; a subscriber that lags catches up from the log.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<AuditEntry>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new(EVENT_BUS_CAPACITY)
+    }
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        EventBus {
+            sender: broadcast::channel(capacity).0,
+        }
+    }
+
+    /// Events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<AuditEntry> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, entry: AuditEntry) {
+        let _ = self.sender.send(entry); // fails only without subscribers
+    }
+
+    /// Number of events the slowest subscriber has yet to receive
+    pub fn lag(&self) -> u64 {
+        self.sender.len() as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        audit::{AuditEvent, AuditLog},
+        order::Trade,
+    };
+
+    #[tokio::test]
+    async fn test_bus_lag() {
+        let bus = EventBus::new(8);
+        let mut log = AuditLog::default();
+        let mut slow = bus.subscribe();
+        let mut fast = bus.subscribe();
+        for id in 1..=3 {
+            let trade = Trade::new(id, "b".to_string(), "s".to_string(), 100, 1);
+            bus.publish(log.append(AuditEvent::TradeExecuted { trade }, 0).clone());
+        }
+        for _ in 0..3 {
+            fast.recv().await.unwrap();
+        }
+        assert_eq!(bus.lag(), 3); // slow hasn't read anything yet
+        assert_eq!(slow.recv().await.unwrap().seq, 1);
+        assert_eq!(bus.lag(), 2);
+    }
+}
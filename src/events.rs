@@ -0,0 +1,171 @@
+use axum::{
+    Extension, Json, Router,
+    extract::{Query, State, rejection::QueryRejection},
+    middleware,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    auth::{self, Auth, Principal, Role},
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::ParticipantId,
+    validation::FieldError,
+};
+
+const DEFAULT_PAGE_SIZE: usize = 1_000;
+const MAX_PAGE_SIZE: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    from: Option<u64>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct EventsResponse {
+    events: Vec<AuditEntry>,
+    /// Sequence number of the latest event, to tell whether the client is behind
+    last_seq: u64,
+}
+
+/// Sequenced engine events, for clients to refetch the ones they missed
+pub fn routes(auth: Auth) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/events", get(get_events))
+        .route_layer(middleware::from_fn_with_state(
+            auth,
+            auth::require_read_only,
+        ))
+}
+
+/// Hides the participants of events that don't involve `viewer`
+pub fn redact(entry: &mut AuditEntry, viewer: &str) {
+    let hide = |owner: &mut Option<ParticipantId>| {
+        if owner.as_deref() != Some(viewer) {
+            *owner = None;
+        }
+    };
+    match &mut entry.event {
+        AuditEvent::OrderAccepted { order }
+        | AuditEvent::OrderScheduled { order, .. }
+        | AuditEvent::OrderCanceled { order }
+        | AuditEvent::OrderExpired { order }
+        | AuditEvent::OrderTriggered { order } => hide(&mut order.owner),
+        AuditEvent::RfqCreated { rfq } => rfq.redact(viewer),
+        AuditEvent::RfqQuoted {
+            taker, response, ..
+        } => {
+            if taker.as_deref() != Some(viewer) {
+                hide(&mut response.maker);
+            }
+            hide(taker);
+        }
+        AuditEvent::OrderRejected { owner, .. }
+        | AuditEvent::TriggerMoved { owner, .. }
+        | AuditEvent::RfqClosed { taker: owner, .. }
+        | AuditEvent::QuoteUpdated { owner, .. }
+        | AuditEvent::QuoteProtectionTripped { owner, .. }
+        | AuditEvent::ParticipantBlocked { owner }
+        | AuditEvent::ParticipantUnblocked { owner } => hide(owner),
+        AuditEvent::TradeExecuted { trade }
+        | AuditEvent::TradeBusted { trade }
+        | AuditEvent::TradeCorrected { trade } => {
+            hide(&mut trade.buyer);
+            hide(&mut trade.seller);
+        }
+        AuditEvent::CircuitBreakerTripped { .. }
+        | AuditEvent::CircuitBreakerEnded { .. }
+        | AuditEvent::TradingHalted { .. }
+        | AuditEvent::TradingResumed
+        | AuditEvent::ConfigChanged { .. } => {}
+    }
+}
+
+/// Whether `participant` is a party to the event: the owner of the order or quote, a side of
+/// the trade, the taker or a maker of the RFQ, the blocked participant; everyone for a halt
+/// or the circuit breaker stopping the book and bringing it back
+pub fn involves(entry: &AuditEntry, participant: &str) -> bool {
+    let is = |owner: &Option<ParticipantId>| owner.as_deref() == Some(participant);
+    match &entry.event {
+        AuditEvent::OrderAccepted { order }
+        | AuditEvent::OrderScheduled { order, .. }
+        | AuditEvent::OrderCanceled { order }
+        | AuditEvent::OrderExpired { order }
+        | AuditEvent::OrderTriggered { order } => is(&order.owner),
+        AuditEvent::RfqCreated { rfq } => {
+            is(&rfq.taker) || rfq.makers.iter().any(|m| m == participant)
+        }
+        AuditEvent::RfqQuoted {
+            taker, response, ..
+        } => is(taker) || is(&response.maker),
+        AuditEvent::OrderRejected { owner, .. }
+        | AuditEvent::TriggerMoved { owner, .. }
+        | AuditEvent::RfqClosed { taker: owner, .. }
+        | AuditEvent::QuoteUpdated { owner, .. }
+        | AuditEvent::QuoteProtectionTripped { owner, .. }
+        | AuditEvent::ParticipantBlocked { owner }
+        | AuditEvent::ParticipantUnblocked { owner } => is(owner),
+        AuditEvent::TradeExecuted { trade }
+        | AuditEvent::TradeBusted { trade }
+        | AuditEvent::TradeCorrected { trade } => is(&trade.buyer) || is(&trade.seller),
+        // the book stopping and coming back concerns everyone trading it
+        AuditEvent::CircuitBreakerTripped { .. }
+        | AuditEvent::CircuitBreakerEnded { .. }
+        | AuditEvent::TradingHalted { .. }
+        | AuditEvent::TradingResumed => true,
+        AuditEvent::ConfigChanged { .. } => false,
+    }
+}
+
+async fn get_events(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+    query: Result<Query<EventsQuery>, QueryRejection>,
+) -> Result<Json<EventsResponse>, ApiError> {
+    let Query(query) = query?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "limit",
+            format!("limit must be between 1 and {MAX_PAGE_SIZE}"),
+        )]));
+    }
+
+    let mut events = engine.audit_entries(query.from.unwrap_or(1), limit).await;
+    if !principal.has_role(Role::Admin) {
+        for entry in events.iter_mut() {
+            redact(entry, &principal.subject);
+        }
+    }
+    Ok(Json(EventsResponse {
+        events,
+        last_seq: engine.last_seq().await,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::Trade;
+
+    #[test]
+    fn test_redact() {
+        let trade = Trade::new(1, String::from("b"), String::from("s"), 100, 1)
+            .with_parties(Some(String::from("alice")), Some(String::from("bob")));
+        let mut log = crate::audit::AuditLog::default();
+        log.append(AuditEvent::TradeExecuted { trade }, 0);
+        let mut entry = log.export(1, 1).remove(0);
+
+        redact(&mut entry, "alice");
+        let AuditEvent::TradeExecuted { trade } = &entry.event else {
+            panic!("expected a trade");
+        };
+        assert_eq!(trade.buyer.as_deref(), Some("alice"));
+        assert_eq!(trade.seller, None);
+        assert!(involves(&entry, "alice"));
+        assert!(!involves(&entry, "bob")); // redacted away
+    }
+}
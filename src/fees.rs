@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consensus::Consensus,
+    matchingengine::MatchingEngine,
+    order::{ParticipantId, Side, Timestamp, Trade},
+};
+
+pub const MAKER_FEE_BPS_ENV: &str = "OME_MAKER_FEE_BPS"; // basis points of the notional
+pub const TAKER_FEE_BPS_ENV: &str = "OME_TAKER_FEE_BPS";
+pub const FEE_TIERS_ENV: &str = "OME_FEE_TIERS"; // `min_volume:maker_bps:taker_bps,...`
+pub const FEE_TIER_REFRESH_SECS_ENV: &str = "OME_FEE_TIER_REFRESH_SECS";
+
+const BPS: u128 = 10_000;
+const DAY_MS: Timestamp = 24 * 60 * 60 * 1000;
+const VOLUME_WINDOW_DAYS: u64 = 30;
+const DEFAULT_TIER_REFRESH: Duration = Duration::from_secs(60 * 60);
+
+/// Rates for participants whose rolling 30-day volume (cents) is at least `min_volume`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub min_volume: u64,
+    pub maker_bps: u64,
+    pub taker_bps: u64,
+}
+
+impl FeeTier {
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().split(':').map(|p| p.trim().parse().ok());
+        let tier = FeeTier {
+            min_volume: parts.next()??,
+            maker_bps: parts.next()??,
+            taker_bps: parts.next()??,
+        };
+        parts.next().is_none().then_some(tier)
+    }
+}
+
+/// Maker/taker rates of the book, in basis points. Both sides of an auction fill pay the maker rate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub maker_bps: u64,
+    pub taker_bps: u64,
+    /// Volume tiers overriding the base rates, sorted by `min_volume`
+    #[serde(default)]
+    pub tiers: Vec<FeeTier>,
+}
+
+impl FeeSchedule {
+    pub fn from_env() -> Self {
+        let bps = |name| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0)
+        };
+        let mut tiers: Vec<FeeTier> = std::env::var(FEE_TIERS_ENV)
+            .map(|v| v.split(',').filter_map(FeeTier::parse).collect())
+            .unwrap_or_default();
+        tiers.sort_by_key(|tier| tier.min_volume);
+        FeeSchedule {
+            maker_bps: bps(MAKER_FEE_BPS_ENV),
+            taker_bps: bps(TAKER_FEE_BPS_ENV),
+            tiers,
+        }
+    }
+
+    /// `(maker_bps, taker_bps)` for a participant with `volume` traded over the window
+    pub fn rates(&self, volume: u64) -> (u64, u64) {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| tier.min_volume <= volume)
+            .map_or((self.maker_bps, self.taker_bps), |tier| {
+                (tier.maker_bps, tier.taker_bps)
+            })
+    }
+}
+
+/// Fees paid and rolling traded volume per account; busted trades are refunded
+#[derive(Debug, Clone, Default)]
+pub struct FeeLedger {
+    totals: HashMap<ParticipantId, u64>,
+    /// Notional traded per day (days since the epoch)
+    daily_volume: HashMap<ParticipantId, BTreeMap<u64, u64>>,
+    /// 30-day volume as of the last tier refresh, what the tier is picked by
+    tier_volume: HashMap<ParticipantId, u64>,
+}
+
+impl FeeLedger {
+    pub fn total(&self, owner: &str) -> u64 {
+        self.totals.get(owner).copied().unwrap_or(0)
+    }
+
+    /// Volume the current fee tier of `owner` was picked by
+    pub fn tier_volume(&self, owner: &str) -> u64 {
+        self.tier_volume.get(owner).copied().unwrap_or(0)
+    }
+
+    /// Sets the buyer/seller fees of `trade` from its notional, the aggressor and each side's tier
+    pub fn charge(&self, schedule: &FeeSchedule, trade: &mut Trade) {
+        let notional = trade.price as u128 * trade.quantity as u128;
+        let fee = |bps: u64| (notional * bps as u128 / BPS).min(u64::MAX as u128) as u64;
+        let rates = |owner: &Option<ParticipantId>| {
+            schedule.rates(owner.as_deref().map_or(0, |o| self.tier_volume(o)))
+        };
+        let ((buyer_maker, buyer_taker), (seller_maker, seller_taker)) =
+            (rates(&trade.buyer), rates(&trade.seller));
+        let (buyer_bps, seller_bps) = match trade.aggressor {
+            Some(Side::Buy) => (buyer_taker, seller_maker),
+            Some(Side::Sell) => (buyer_maker, seller_taker),
+            None => (buyer_maker, seller_maker),
+        };
+        trade.buyer_fee = fee(buyer_bps);
+        trade.seller_fee = fee(seller_bps);
+    }
+
+    pub fn apply(&mut self, trade: &Trade, now: Timestamp) {
+        let notional = trade.price.saturating_mul(trade.quantity);
+        for (owner, fee) in [
+            (&trade.buyer, trade.buyer_fee),
+            (&trade.seller, trade.seller_fee),
+        ] {
+            if let Some(owner) = owner {
+                *self.totals.entry(owner.clone()).or_default() += fee;
+                let day = self
+                    .daily_volume
+                    .entry(owner.clone())
+                    .or_default()
+                    .entry(now / DAY_MS)
+                    .or_default();
+                *day = day.saturating_add(notional);
+            }
+        }
+    }
+
+    /// Refunds the fees and takes the notional back off the most recent days' volume
+    pub fn reverse(&mut self, trade: &Trade) {
+        let notional = trade.price.saturating_mul(trade.quantity);
+        for (owner, fee) in [
+            (&trade.buyer, trade.buyer_fee),
+            (&trade.seller, trade.seller_fee),
+        ] {
+            let Some(owner) = owner else {
+                continue;
+            };
+            if let Some(total) = self.totals.get_mut(owner) {
+                *total = total.saturating_sub(fee);
+            }
+            let mut left = notional;
+            for volume in self
+                .daily_volume
+                .get_mut(owner)
+                .into_iter()
+                .flat_map(|days| days.values_mut().rev())
+            {
+                let taken = left.min(*volume);
+                *volume -= taken;
+                left -= taken;
+            }
+        }
+    }
+
+    /// Drops days that left the 30-day window and re-picks every participant's tier volume
+    pub fn refresh_tiers(&mut self, now: Timestamp) {
+        let first_day = (now / DAY_MS).saturating_sub(VOLUME_WINDOW_DAYS - 1);
+        self.daily_volume.retain(|_, days| {
+            days.retain(|day, _| *day >= first_day);
+            !days.is_empty()
+        });
+        self.tier_volume = self
+            .daily_volume
+            .iter()
+            .map(|(owner, days)| (owner.clone(), days.values().sum()))
+            .collect();
+    }
+}
+
+pub fn tier_refresh_from_env() -> Duration {
+    std::env::var(FEE_TIER_REFRESH_SECS_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIER_REFRESH)
+}
+
+/// Re-picks the participants' fee tiers every `period`; in consensus mode the leader commits
+/// the refresh to the log and the other nodes leave it to the leader
+pub async fn run_tier_refresh(
+    engine: MatchingEngine,
+    period: Duration,
+    consensus: Option<Consensus>,
+) {
+    let mut tick = tokio::time::interval(period);
+    loop {
+        tick.tick().await;
+        match &consensus {
+            Some(consensus) if !consensus.is_leader() => {}
+            Some(consensus) => {
+                if let Err(e) = consensus.refresh_fee_tiers().await {
+                    println!("Fee tiers not refreshed: {}", e.message);
+                }
+            }
+            None => engine.refresh_fee_tiers().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trade(aggressor: Option<Side>) -> Trade {
+        let mut trade = Trade::new(1, String::from("b"), String::from("s"), 1_000, 50)
+            .with_parties(Some(String::from("alice")), Some(String::from("bob")));
+        trade.aggressor = aggressor;
+        trade
+    }
+
+    fn flat(maker_bps: u64, taker_bps: u64) -> FeeSchedule {
+        FeeSchedule {
+            maker_bps,
+            taker_bps,
+            tiers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_maker_taker() {
+        let ledger = FeeLedger::default();
+        let schedule = flat(2, 10);
+        let mut buy = trade(Some(Side::Buy));
+        ledger.charge(&schedule, &mut buy);
+        assert_eq!((buy.buyer_fee, buy.seller_fee), (50, 10));
+
+        let mut sell = trade(Some(Side::Sell));
+        ledger.charge(&schedule, &mut sell);
+        assert_eq!((sell.buyer_fee, sell.seller_fee), (10, 50));
+
+        let mut auction = trade(None);
+        ledger.charge(&schedule, &mut auction);
+        assert_eq!((auction.buyer_fee, auction.seller_fee), (10, 10));
+    }
+
+    #[test]
+    fn test_ledger() {
+        let mut ledger = FeeLedger::default();
+        let mut trade = trade(Some(Side::Buy));
+        ledger.charge(&flat(2, 10), &mut trade);
+
+        ledger.apply(&trade, 0);
+        ledger.apply(&trade, 0);
+        assert_eq!(ledger.total("alice"), 100);
+        ledger.reverse(&trade);
+        assert_eq!(ledger.total("alice"), 50);
+        assert_eq!(ledger.total("bob"), 10);
+        assert_eq!(ledger.total("carol"), 0);
+    }
+
+    #[test]
+    fn test_volume_tiers() {
+        let schedule = FeeSchedule {
+            tiers: vec![
+                FeeTier::parse("100000:1:5").unwrap(),
+                FeeTier::parse("200000:0:2").unwrap(),
+            ],
+            ..flat(2, 10)
+        };
+        assert_eq!(schedule.rates(0), (2, 10));
+        assert_eq!(schedule.rates(150_000), (1, 5));
+        assert_eq!(schedule.rates(200_000), (0, 2));
+        assert_eq!(FeeTier::parse("1:2"), None);
+
+        let mut ledger = FeeLedger::default();
+        // 50_000 notional per trade, 3 trades on day 0
+        for _ in 0..3 {
+            ledger.apply(&trade(Some(Side::Buy)), 0);
+        }
+        // tiers only move on refresh
+        assert_eq!(ledger.tier_volume("alice"), 0);
+        ledger.refresh_tiers(DAY_MS);
+        assert_eq!(ledger.tier_volume("alice"), 150_000);
+        let mut next = trade(Some(Side::Buy));
+        ledger.charge(&schedule, &mut next);
+        assert_eq!(next.buyer_fee, 25);
+
+        // day 0 falls out of the window
+        ledger.refresh_tiers(30 * DAY_MS);
+        assert_eq!(ledger.tier_volume("alice"), 0);
+    }
+}
@@ -0,0 +1,317 @@
+//! C API over [`Matcher`], built with `--features ffi`; the header is `include/ome.h`,
+//! regenerated with `cbindgen --config cbindgen.toml --output include/ome.h`.
+//!
+//! Orders are identified by the numeric ids `ome_submit` hands out. Trades queue up inside the
+//! engine until `ome_poll_trades` drains them.
+
+use std::{collections::VecDeque, ffi::CStr, os::raw::c_char, sync::Arc};
+
+use crate::{
+    matching::{InvalidOrder, Matcher},
+    matchingpolicy::{self, PriceTime},
+    order::{OrderType, Side, Trade},
+};
+
+/// Opaque engine handle
+pub struct OmeEngine {
+    matcher: Matcher,
+    trades: VecDeque<Trade>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OmeSide {
+    Buy = 0,
+    Sell = 1,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OmeOrderType {
+    Limit = 0,
+    Market = 1,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OmeStatus {
+    Ok = 0,
+    NullPointer = 1,
+    ZeroQuantity = 2,
+    MissingPrice = 3,
+    /// The order was entered, but the matcher gave it an id that isn't a number
+    UnrepresentableId = 4,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OmeTrade {
+    pub id: u64,
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+    /// Side of the incoming order that took liquidity
+    pub aggressor: OmeSide,
+}
+
+impl From<Side> for OmeSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => OmeSide::Buy,
+            Side::Sell => OmeSide::Sell,
+        }
+    }
+}
+
+impl From<InvalidOrder> for OmeStatus {
+    fn from(e: InvalidOrder) -> Self {
+        match e {
+            InvalidOrder::ZeroQuantity => OmeStatus::ZeroQuantity,
+            InvalidOrder::MissingPrice => OmeStatus::MissingPrice,
+        }
+    }
+}
+
+/// `Matcher` numbers its orders 1, 2, 3...; `None` should that ever change, since a panic
+/// mustn't cross into the caller
+fn numeric_id(id: &str) -> Option<u64> {
+    id.parse().ok()
+}
+
+/// A trade as the C API reports it, `None` when its ids aren't numeric or it has no aggressor
+fn ome_trade(trade: Trade) -> Option<OmeTrade> {
+    Some(OmeTrade {
+        id: trade.id,
+        buy_order_id: numeric_id(&trade.buy_order_id)?,
+        sell_order_id: numeric_id(&trade.sell_order_id)?,
+        price: trade.price,
+        quantity: trade.quantity,
+        aggressor: trade.aggressor?.into(),
+    })
+}
+
+/// Creates an engine; `policy` is one of the server's `OME_MATCHING_POLICY` names (price-time
+/// when NULL). Returns NULL for an unknown policy. Free it with `ome_engine_free`.
+///
+/// # Safety
+/// `policy` must be NULL or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_engine_new(
+    policy: *const c_char,
+    min_quantity: u64,
+) -> *mut OmeEngine {
+    let policy = if policy.is_null() {
+        Some(Arc::new(PriceTime) as _)
+    } else {
+        // SAFETY: the caller passes a valid C string
+        let name = unsafe { CStr::from_ptr(policy) };
+        name.to_str()
+            .ok()
+            .and_then(|name| matchingpolicy::by_name(name, min_quantity))
+    };
+    match policy {
+        Some(policy) => Box::into_raw(Box::new(OmeEngine {
+            matcher: Matcher::new(policy),
+            trades: VecDeque::new(),
+        })),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Destroys an engine created by `ome_engine_new`; NULL is ignored.
+///
+/// # Safety
+/// `engine` must come from `ome_engine_new` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_engine_free(engine: *mut OmeEngine) {
+    if !engine.is_null() {
+        // SAFETY: the pointer came from Box::into_raw in ome_engine_new
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+/// Submits an order (`price` is ignored for market orders) and writes its id to `order_id`
+/// (may be NULL). Resulting trades are queued for `ome_poll_trades`.
+///
+/// # Safety
+/// `engine` must be a live engine and `order_id` NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_submit(
+    engine: *mut OmeEngine,
+    side: OmeSide,
+    order_type: OmeOrderType,
+    price: u64,
+    quantity: u64,
+    order_id: *mut u64,
+) -> OmeStatus {
+    // SAFETY: the caller passes a live engine
+    let Some(engine) = (unsafe { engine.as_mut() }) else {
+        return OmeStatus::NullPointer;
+    };
+    let side = match side {
+        OmeSide::Buy => Side::Buy,
+        OmeSide::Sell => Side::Sell,
+    };
+    let order_type = match order_type {
+        OmeOrderType::Limit => OrderType::Limit,
+        OmeOrderType::Market => OrderType::Market,
+    };
+    match engine.matcher.submit(side, order_type, price, quantity) {
+        Ok(submitted) => {
+            engine.trades.extend(submitted.trades);
+            let Some(id) = numeric_id(&submitted.id) else {
+                return OmeStatus::UnrepresentableId;
+            };
+            if !order_id.is_null() {
+                // SAFETY: the caller passes a writable pointer
+                unsafe { *order_id = id };
+            }
+            OmeStatus::Ok
+        }
+        Err(e) => e.into(),
+    }
+}
+
+/// Cancels a resting order, returning whether it was found.
+///
+/// # Safety
+/// `engine` must be a live engine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_cancel(engine: *mut OmeEngine, order_id: u64) -> bool {
+    // SAFETY: the caller passes a live engine
+    match unsafe { engine.as_mut() } {
+        Some(engine) => engine.matcher.cancel(&order_id.to_string()),
+        None => false,
+    }
+}
+
+/// Moves up to `capacity` queued trades, oldest first, into `out` and returns how many. Trades
+/// an `OmeTrade` can't hold are dropped.
+///
+/// # Safety
+/// `engine` must be a live engine and `out` valid for `capacity` writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_poll_trades(
+    engine: *mut OmeEngine,
+    out: *mut OmeTrade,
+    capacity: usize,
+) -> usize {
+    // SAFETY: the caller passes a live engine
+    let Some(engine) = (unsafe { engine.as_mut() }) else {
+        return 0;
+    };
+    if out.is_null() {
+        return 0;
+    }
+    let mut count = 0;
+    while count < capacity
+        && let Some(trade) = engine.trades.pop_front()
+    {
+        let Some(trade) = ome_trade(trade) else {
+            continue;
+        };
+        // SAFETY: count < capacity, which the caller guarantees `out` can hold
+        unsafe { out.add(count).write(trade) };
+        count += 1;
+    }
+    count
+}
+
+/// Best bid price, 0 when there are no bids.
+///
+/// # Safety
+/// `engine` must be a live engine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_best_bid(engine: *const OmeEngine) -> u64 {
+    // SAFETY: the caller passes a live engine
+    unsafe { engine.as_ref() }
+        .and_then(|engine| engine.matcher.book().best_bid())
+        .unwrap_or(0)
+}
+
+/// Best ask price, 0 when there are no asks.
+///
+/// # Safety
+/// `engine` must be a live engine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ome_best_ask(engine: *const OmeEngine) -> u64 {
+    // SAFETY: the caller passes a live engine
+    unsafe { engine.as_ref() }
+        .and_then(|engine| engine.matcher.book().best_ask())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_c_api() {
+        unsafe {
+            let engine = ome_engine_new(std::ptr::null(), 1);
+            let mut ask = 0;
+            assert_eq!(
+                ome_submit(engine, OmeSide::Sell, OmeOrderType::Limit, 100, 5, &mut ask),
+                OmeStatus::Ok
+            );
+            assert_eq!(
+                ome_submit(
+                    engine,
+                    OmeSide::Buy,
+                    OmeOrderType::Limit,
+                    0,
+                    5,
+                    std::ptr::null_mut()
+                ),
+                OmeStatus::MissingPrice
+            );
+            let mut bid = 0;
+            ome_submit(engine, OmeSide::Buy, OmeOrderType::Limit, 101, 8, &mut bid);
+            assert_eq!((ome_best_bid(engine), ome_best_ask(engine)), (101, 0));
+
+            let mut trades = [OmeTrade {
+                id: 0,
+                buy_order_id: 0,
+                sell_order_id: 0,
+                price: 0,
+                quantity: 0,
+                aggressor: OmeSide::Buy,
+            }; 4];
+            assert_eq!(
+                ome_poll_trades(engine, trades.as_mut_ptr(), trades.len()),
+                1
+            );
+            assert_eq!(
+                trades[0],
+                OmeTrade {
+                    id: 1,
+                    buy_order_id: bid,
+                    sell_order_id: ask,
+                    price: 100,
+                    quantity: 5,
+                    aggressor: OmeSide::Buy,
+                }
+            );
+            // drained
+            assert_eq!(
+                ome_poll_trades(engine, trades.as_mut_ptr(), trades.len()),
+                0
+            );
+
+            assert!(ome_cancel(engine, bid));
+            assert!(!ome_cancel(engine, bid));
+            ome_engine_free(engine);
+
+            assert!(ome_engine_new(c"no_such_policy".as_ptr(), 1).is_null());
+        }
+    }
+
+    #[test]
+    fn test_unrepresentable_ids() {
+        assert_eq!(numeric_id("7"), Some(7));
+        assert_eq!(numeric_id("a-uuid"), None);
+        let trade = Trade::new(1, "2".to_string(), "x".to_string(), 100, 5);
+        assert_eq!(ome_trade(trade), None);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    clock::{Clock, VirtualClock},
+    ids::IdGenerator,
+    matching::{InvalidOrder, Matcher, Submitted},
+    matchingpolicy::MatchingPolicy,
+    order::{Order, OrderId, OrderType, Price, Quantity, Side, Timestamp, Trade},
+    orderbook::OrderBook,
+};
+
+/// Something the harness saw happen, in the order it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// An order as it was submitted, before it matched
+    Accepted {
+        order: Order,
+    },
+    Rejected {
+        side: Side,
+        reason: InvalidOrder,
+    },
+    Traded {
+        trade: Trade,
+    },
+    /// What is left of a limit order once it's done matching
+    Rested {
+        order_id: OrderId,
+        quantity: Quantity,
+    },
+    Canceled {
+        order_id: OrderId,
+    },
+}
+
+/// SplitMix64: small, fast and the same on every platform, which is all a test needs
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `low..=high`
+    pub fn between(&mut self, low: u64, high: u64) -> u64 {
+        assert!(low <= high, "empty range {low}..={high}");
+        match (high - low).checked_add(1) {
+            Some(span) => low + self.next_u64() % span,
+            None => self.next_u64(),
+        }
+    }
+
+    pub fn side(&mut self) -> Side {
+        match self.next_u64() & 1 {
+            0 => Side::Buy,
+            _ => Side::Sell,
+        }
+    }
+}
+
+/// Reproducible tests of a book: a [`Matcher`] driven step by step on a virtual clock, with
+/// ids and random numbers fixed by the setup, recording everything that happened as
+/// [`Event`]s. Time only moves with [`Harness::advance`], so a run with the same seed and steps
+/// yields the same events.
+pub struct Harness {
+    matcher: Matcher,
+    clock: VirtualClock,
+    rng: SeededRng,
+    events: Vec<Event>,
+}
+
+impl Harness {
+    /// At unix time 0 with seed 0 and orders numbered `1`, `2`, ...
+    pub fn new(policy: Arc<dyn MatchingPolicy>) -> Self {
+        let clock = VirtualClock::default();
+        Harness {
+            matcher: Matcher::new(policy).with_clock(Arc::new(clock.clone())),
+            clock,
+            rng: SeededRng::new(0),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = SeededRng::new(seed);
+        self
+    }
+
+    pub fn with_ids(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.matcher = self.matcher.with_ids(ids);
+        self
+    }
+
+    /// Starts the clock at `now_ns` (unix ns)
+    pub fn starting_at(self, now_ns: Timestamp) -> Self {
+        self.clock.set(now_ns);
+        self
+    }
+
+    pub fn now(&self) -> Timestamp {
+        self.clock.now_ns()
+    }
+
+    pub fn advance(&mut self, by: Duration) {
+        self.clock.advance(by);
+    }
+
+    pub fn rng(&mut self) -> &mut SeededRng {
+        &mut self.rng
+    }
+
+    pub fn submit(
+        &mut self,
+        side: Side,
+        order_type: OrderType,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<Submitted, InvalidOrder> {
+        let submitted = match self.matcher.submit(side, order_type, price, quantity) {
+            Ok(submitted) => submitted,
+            Err(reason) => {
+                self.events.push(Event::Rejected { side, reason });
+                return Err(reason);
+            }
+        };
+        let filled: Quantity = submitted.trades.iter().map(|t| t.quantity).sum();
+        let price = match order_type {
+            OrderType::Limit => price,
+            OrderType::Market => 0,
+        };
+        let order = Order::new(
+            submitted.id.clone(),
+            side,
+            order_type,
+            quantity,
+            price,
+            self.clock.now_ns(),
+        );
+        self.events.push(Event::Accepted { order });
+        self.events
+            .extend(submitted.trades.iter().map(|trade| Event::Traded {
+                trade: trade.clone(),
+            }));
+        if order_type == OrderType::Limit && filled < quantity {
+            self.events.push(Event::Rested {
+                order_id: submitted.id.clone(),
+                quantity: quantity - filled,
+            });
+        }
+        Ok(submitted)
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        let canceled = self.matcher.cancel(id);
+        if canceled {
+            self.events.push(Event::Canceled {
+                order_id: id.to_string(),
+            });
+        }
+        canceled
+    }
+
+    /// Submits a random limit order within `spread` of `around`, of up to `max_quantity`
+    pub fn random_limit(
+        &mut self,
+        around: Price,
+        spread: Price,
+        max_quantity: Quantity,
+    ) -> Result<Submitted, InvalidOrder> {
+        let side = self.rng.side();
+        let price = self
+            .rng
+            .between(around.saturating_sub(spread).max(1), around + spread);
+        let quantity = self.rng.between(1, max_quantity.max(1));
+        self.submit(side, OrderType::Limit, price, quantity)
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        self.matcher.book()
+    }
+
+    /// Every event so far
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Events since the last call, for asserting on one step at a time
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Harness::new(Arc::new(crate::matchingpolicy::PriceTime))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ids::SequentialIds;
+
+    #[test]
+    fn test_harness_steps() {
+        let mut harness = Harness::default()
+            .with_ids(Arc::new(SequentialIds::with_prefix("o")))
+            .starting_at(1_000);
+        harness
+            .submit(Side::Sell, OrderType::Limit, 100, 5)
+            .unwrap();
+        harness.take_events();
+
+        harness.advance(Duration::from_nanos(500));
+        harness.submit(Side::Buy, OrderType::Limit, 100, 7).unwrap();
+        let trade = Trade::new(1, "o2".to_string(), "o1".to_string(), 100, 5)
+            .with_aggressor(Side::Buy)
+            .at(1_500);
+        assert_eq!(
+            harness.take_events(),
+            vec![
+                Event::Accepted {
+                    order: Order::new("o2".to_string(), Side::Buy, OrderType::Limit, 7, 100, 1_500)
+                },
+                Event::Traded { trade },
+                Event::Rested {
+                    order_id: "o2".to_string(),
+                    quantity: 2
+                },
+            ]
+        );
+        assert!(harness.cancel("o2"));
+        assert_eq!(
+            harness
+                .submit(Side::Buy, OrderType::Limit, 0, 1)
+                .unwrap_err(),
+            InvalidOrder::MissingPrice
+        );
+        assert_eq!(harness.take_events().len(), 2);
+
+        // the same seed replays the same run
+        let run = |seed| {
+            let mut harness = Harness::default().with_seed(seed);
+            for _ in 0..200 {
+                harness.random_limit(1_000, 20, 10).unwrap();
+                harness.advance(Duration::from_millis(1));
+            }
+            harness.take_events()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use tokio::time::Instant;
+
+pub const WS_PING_INTERVAL_MS_ENV: &str = "OME_WS_PING_INTERVAL_MS"; // 0 disables pings
+pub const WS_PONG_TIMEOUT_MS_ENV: &str = "OME_WS_PONG_TIMEOUT_MS";
+
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_PONG_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often the server pings its WebSocket clients and how long one may stay silent before
+/// it's taken for gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    pub ping_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl HeartbeatConfig {
+    /// `None` when pings are turned off
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        let ping_interval =
+            var(WS_PING_INTERVAL_MS_ENV).map_or(DEFAULT_PING_INTERVAL, Duration::from_millis);
+        if ping_interval.is_zero() {
+            return None;
+        }
+        Some(HeartbeatConfig {
+            ping_interval,
+            timeout: var(WS_PONG_TIMEOUT_MS_ENV)
+                .filter(|ms| *ms > 0)
+                .map_or(DEFAULT_PONG_TIMEOUT, Duration::from_millis),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Beat {
+    /// Time to ping the client
+    Ping,
+    /// Nothing came back within the timeout, the client is gone
+    Gone,
+}
+
+/// Pings of one socket and when its client was last heard from; any frame counts, pongs or not
+#[derive(Debug)]
+pub struct Liveness {
+    config: Option<HeartbeatConfig>,
+    last_seen: Instant,
+    next_ping: Instant,
+}
+
+impl Liveness {
+    pub fn new(config: Option<HeartbeatConfig>) -> Self {
+        let now = Instant::now();
+        Liveness {
+            config,
+            last_seen: now,
+            next_ping: now + config.map_or(Duration::ZERO, |c| c.ping_interval),
+        }
+    }
+
+    pub fn seen(&mut self) {
+        self.last_seen = Instant::now();
+    }
+
+    /// When [`Self::poll`] has something to do, `None` without pings
+    pub fn deadline(&self) -> Option<Instant> {
+        let config = self.config?;
+        Some(self.next_ping.min(self.last_seen + config.timeout))
+    }
+
+    pub fn poll(&mut self, now: Instant) -> Option<Beat> {
+        let config = self.config?;
+        if now >= self.last_seen + config.timeout {
+            Some(Beat::Gone)
+        } else if now >= self.next_ping {
+            self.next_ping = now + config.ping_interval;
+            Some(Beat::Ping)
+        } else {
+            None
+        }
+    }
+}
+
+/// Pings the client when it's time; `false` once it's gone or the ping can't be sent
+pub async fn keep_alive(socket: &mut WebSocket, liveness: &mut Liveness) -> bool {
+    match liveness.poll(Instant::now()) {
+        Some(Beat::Ping) => socket.send(Message::Ping(Default::default())).await.is_ok(),
+        Some(Beat::Gone) => false,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_silent_client_is_gone() {
+        let config = HeartbeatConfig {
+            ping_interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(25),
+        };
+        let mut liveness = Liveness::new(Some(config));
+        let start = liveness.last_seen;
+        assert_eq!(liveness.deadline(), Some(start + config.ping_interval));
+        assert_eq!(liveness.poll(start), None);
+        assert_eq!(
+            liveness.poll(start + Duration::from_secs(10)),
+            Some(Beat::Ping)
+        );
+        assert_eq!(
+            liveness.poll(start + Duration::from_secs(20)),
+            Some(Beat::Ping)
+        );
+        assert_eq!(liveness.deadline(), Some(start + config.timeout));
+        assert_eq!(
+            liveness.poll(start + Duration::from_secs(25)),
+            Some(Beat::Gone)
+        );
+
+        let mut disabled = Liveness::new(None);
+        assert_eq!(disabled.deadline(), None);
+        assert_eq!(disabled.poll(start + Duration::from_secs(3600)), None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::{
+    clock::Clock,
+    order::{OrderId, Timestamp},
+};
+
+/// 2020-01-01 in unix ms, where snowflake time starts
+pub const SNOWFLAKE_EPOCH_MS: Timestamp = 1_577_836_800_000;
+const WORKER_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+
+/// Hands out the ids of new orders. Ids must not repeat for the life of a book.
+pub trait IdGenerator: fmt::Debug + Send + Sync {
+    fn next_id(&self) -> OrderId;
+}
+
+/// `1`, `2`, `3`, ... with an optional prefix, e.g. `sim-1`
+#[derive(Debug)]
+pub struct SequentialIds {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequentialIds {
+    pub fn new() -> Self {
+        SequentialIds::with_prefix("")
+    }
+
+    pub fn with_prefix(prefix: &str) -> Self {
+        SequentialIds {
+            prefix: prefix.to_string(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl Default for SequentialIds {
+    fn default() -> Self {
+        SequentialIds::new()
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self) -> OrderId {
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        format!("{}{n}", self.prefix)
+    }
+}
+
+/// Snowflakes: 41 bits of milliseconds since [`SNOWFLAKE_EPOCH_MS`], 10 bits of worker and a
+/// 12-bit sequence within the millisecond, written as 19 digits so that the ids of a worker sort
+/// by creation time as numbers and as strings. Generators sharing a book need distinct workers.
+#[derive(Debug)]
+pub struct SnowflakeIds {
+    worker: u64,
+    clock: Arc<dyn Clock>,
+    last: Mutex<Option<(Timestamp, u64)>>, // millisecond and sequence of the last id
+}
+
+impl SnowflakeIds {
+    /// Only the low 10 bits of `worker` are used
+    pub fn new(worker: u16, clock: Arc<dyn Clock>) -> Self {
+        SnowflakeIds {
+            worker: u64::from(worker) & ((1 << WORKER_BITS) - 1),
+            clock,
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn next_value(&self) -> u64 {
+        let now = self.clock.now_ms().saturating_sub(SNOWFLAKE_EPOCH_MS);
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        // a clock that went back, or a full sequence, borrows the next millisecond
+        // rather than waiting for it
+        let (ms, sequence) = match *last {
+            Some((ms, _)) if now > ms => (now, 0),
+            Some((ms, sequence)) if sequence + 1 < 1 << SEQUENCE_BITS => (ms, sequence + 1),
+            Some((ms, _)) => (ms + 1, 0),
+            None => (now, 0),
+        };
+        *last = Some((ms, sequence));
+        ms << (WORKER_BITS + SEQUENCE_BITS) | self.worker << SEQUENCE_BITS | sequence
+    }
+}
+
+impl IdGenerator for SnowflakeIds {
+    fn next_id(&self) -> OrderId {
+        format!("{:019}", self.next_value())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::VirtualClock;
+
+    #[test]
+    fn test_snowflake_ids() {
+        let clock = VirtualClock::starting_at((SNOWFLAKE_EPOCH_MS + 2) * 1_000_000);
+        let ids = SnowflakeIds::new(5, Arc::new(clock.clone()));
+        let first = ids.next_value();
+        assert_eq!(first, 2 << 22 | 5 << 12);
+        assert_eq!(ids.next_value(), first + 1);
+
+        clock.set(SNOWFLAKE_EPOCH_MS * 1_000_000); // stepped back
+        let borrowed: Vec<u64> = (0..4096).map(|_| ids.next_value()).collect();
+        assert_eq!(borrowed[4094], 3 << 22 | 5 << 12);
+        assert!(borrowed.windows(2).all(|w| w[0] < w[1]));
+
+        let id = ids.next_id();
+        assert_eq!(id.len(), 19);
+        assert!(id > format!("{:019}", borrowed[4095]));
+        assert_eq!(SequentialIds::with_prefix("t-").next_id(), "t-1");
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    io::ErrorKind,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tokio::runtime::Handle;
+use tungstenite::{Message, stream::MaybeTlsStream};
+
+use crate::{matchingengine::MatchingEngine, order::Price};
+
+pub const INDEX_URL_ENV: &str = "OME_INDEX_URL"; // `http(s)://` is polled, `ws(s)://` subscribed to; `{symbol}` is replaced; unset disables
+pub const INDEX_PRICE_FIELD_ENV: &str = "OME_INDEX_PRICE_FIELD"; // JSON pointer to the price, default `/price`
+pub const INDEX_PRICE_SCALE_ENV: &str = "OME_INDEX_PRICE_SCALE"; // cents per unit of the feed's price, default 1
+pub const INDEX_SUBSCRIBE_ENV: &str = "OME_INDEX_SUBSCRIBE"; // text sent after connecting to a WebSocket feed
+pub const INDEX_POLL_MS_ENV: &str = "OME_INDEX_POLL_MS"; // default 1000
+pub const INDEX_MAX_AGE_MS_ENV: &str = "OME_INDEX_MAX_AGE_MS"; // default 10000
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_INDEX_RESPONSE_BYTES: u64 = 1 << 20;
+
+/// Where the index price of one exchange comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexFeedConfig {
+    pub url: String,
+    pub field: String,
+    pub scale: f64,
+    pub subscribe: Option<String>,
+    pub poll_interval: Duration,
+    /// A price older than this is dropped and the mark falls back to the book
+    pub max_age: Duration,
+}
+
+impl IndexFeedConfig {
+    pub fn from_env(symbol: &str) -> Option<Self> {
+        let url = std::env::var(INDEX_URL_ENV)
+            .ok()
+            .filter(|url| !url.is_empty())?;
+        let millis = |name, default| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&ms: &u64| ms > 0)
+                .map_or(Duration::from_millis(default), Duration::from_millis)
+        };
+        Some(IndexFeedConfig {
+            url: url.replace("{symbol}", symbol),
+            field: std::env::var(INDEX_PRICE_FIELD_ENV).unwrap_or_else(|_| "/price".to_string()),
+            scale: std::env::var(INDEX_PRICE_SCALE_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&scale: &f64| scale > 0.0)
+                .unwrap_or(1.0),
+            subscribe: std::env::var(INDEX_SUBSCRIBE_ENV).ok(),
+            poll_interval: millis(INDEX_POLL_MS_ENV, 1_000),
+            max_age: millis(INDEX_MAX_AGE_MS_ENV, 10_000),
+        })
+    }
+
+    fn is_websocket(&self) -> bool {
+        self.url.starts_with("ws://") || self.url.starts_with("wss://")
+    }
+
+    /// The price in a message of the feed, `None` for messages without one (e.g. heartbeats)
+    pub fn parse_price(&self, text: &str) -> Result<Option<Price>, String> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        let Some(field) = value.pointer(&self.field) else {
+            return Ok(None);
+        };
+        let units = match field {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| format!("{} is not a price: {field}", self.field))?;
+        let price = (units * self.scale).round();
+        if !(price >= 1.0 && price < u64::MAX as f64) {
+            return Err(format!("{} is out of range: {field}", self.field));
+        }
+        Ok(Some(price as Price))
+    }
+}
+
+/// Passes the feed's prices on to the engine and takes the price back once it's stale
+struct IndexFeed {
+    runtime: Handle,
+    engine: MatchingEngine,
+    config: IndexFeedConfig,
+    updated: Option<Instant>,
+}
+
+impl IndexFeed {
+    fn update(&mut self, text: &str) -> Result<(), String> {
+        if let Some(price) = self.config.parse_price(text)? {
+            if self.updated.is_none() {
+                println!("Index price from {}: {price}", self.config.url);
+            }
+            self.updated = Some(Instant::now());
+            self.runtime
+                .block_on(self.engine.set_index_price(Some(price)));
+        }
+        Ok(())
+    }
+
+    fn expire(&mut self) {
+        if self
+            .updated
+            .is_some_and(|at| at.elapsed() > self.config.max_age)
+        {
+            println!("Index price from {} is stale", self.config.url);
+            self.updated = None;
+            self.runtime.block_on(self.engine.set_index_price(None));
+        }
+    }
+
+    /// Fetches one price from a polled feed
+    fn poll(&mut self) -> Result<(), String> {
+        let url = self.config.url.clone();
+        let text = ureq::get(&url)
+            .call()
+            .map_err(|e| format!("{url}: {e}"))?
+            .body_mut()
+            .with_config()
+            .limit(MAX_INDEX_RESPONSE_BYTES)
+            .read_to_string()
+            .map_err(|e| format!("{url}: {e}"))?;
+        self.update(&text).map_err(|e| format!("{url}: {e}"))
+    }
+
+    /// Takes in prices from one WebSocket connection until it drops
+    fn follow(&mut self) -> Result<(), String> {
+        let url = self.config.url.clone();
+        let (mut socket, _) = tungstenite::connect(&url).map_err(|e| format!("{url}: {e}"))?;
+        // wake up to notice a feed that went quiet
+        if let MaybeTlsStream::Plain(stream) = socket.get_mut() {
+            stream
+                .set_read_timeout(Some(self.config.max_age))
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(subscribe) = &self.config.subscribe {
+            socket
+                .send(Message::text(subscribe.clone()))
+                .map_err(|e| format!("{url}: {e}"))?;
+        }
+        println!("Index price following {url}");
+        loop {
+            let message = match socket.read() {
+                Ok(message) => message,
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+                {
+                    self.expire();
+                    continue;
+                }
+                Err(e) => return Err(format!("{url}: {e}")),
+            };
+            self.expire();
+            match message {
+                Message::Text(text) => {
+                    if let Err(e) = self.update(&text) {
+                        println!("Index price from {url} ignored: {e}");
+                    }
+                }
+                Message::Close(_) => return Err(format!("{url}: the feed closed the stream")),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Feeds `engine` the index price from `config`'s URL for as long as the server runs, polling
+/// an HTTP endpoint or following a WebSocket stream and reconnecting when it drops
+pub fn run_index_feed(engine: MatchingEngine, config: IndexFeedConfig) {
+    let mut feed = IndexFeed {
+        runtime: Handle::current(),
+        engine,
+        config,
+        updated: None,
+    };
+    thread::spawn(move || {
+        let mut failing = false;
+        loop {
+            let result = match feed.config.is_websocket() {
+                true => feed.follow(),
+                false => feed.poll(),
+            };
+            // report when the feed starts failing, not every attempt after
+            if let Err(e) = &result
+                && !failing
+            {
+                println!("Index price unavailable: {e}");
+            }
+            failing = result.is_err();
+            feed.expire();
+            thread::sleep(match feed.config.is_websocket() {
+                true => RECONNECT_DELAY,
+                false => feed.config.poll_interval,
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_price() {
+        let config = IndexFeedConfig {
+            url: "https://index.example/btc".to_string(),
+            field: "/data/price".to_string(),
+            scale: 100.0,
+            subscribe: None,
+            poll_interval: Duration::from_secs(1),
+            max_age: Duration::from_secs(10),
+        };
+        assert_eq!(
+            config.parse_price(r#"{"data": {"price": 64123.456}}"#),
+            Ok(Some(6_412_346))
+        );
+        assert_eq!(
+            config.parse_price(r#"{"data": {"price": "101.5"}}"#),
+            Ok(Some(10_150))
+        );
+        assert_eq!(config.parse_price(r#"{"type": "heartbeat"}"#), Ok(None));
+        assert!(config.parse_price(r#"{"data": {"price": -1}}"#).is_err());
+        assert!(config.parse_price(r#"{"data": {"price": true}}"#).is_err());
+        assert!(config.parse_price("not json").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    ids::IdGenerator,
+    matching::TradeListener,
+    matchingpolicy::{self, MatchingPolicy, PriceTime},
+    orderbook::TieBreak,
+    orderids::{self, ORDER_IDS_ENV, UuidV4Ids, WORKER_ID_ENV},
+    risk::RiskCheck,
+};
+
+pub const MATCHING_POLICY_ENV: &str = "OME_MATCHING_POLICY"; // `price_time` | `pro_rata` | `size_priority`
+pub const PRO_RATA_MIN_QTY_ENV: &str = "OME_PRO_RATA_MIN_QTY";
+pub const TIE_BREAK_ENV: &str = "OME_TIE_BREAK"; // `arrival` | `timestamp`
+
+/// How an instrument's book prioritises orders, which checks orders pass before they reach it
+/// and who hears of its executions, fixed when the book is constructed
+#[derive(Clone)]
+pub struct InstrumentConfig {
+    pub policy: Arc<dyn MatchingPolicy>,
+    pub tie_break: TieBreak,
+    /// Run in order after the engine's own fat-finger and limit checks
+    pub risk_checks: Vec<Arc<dyn RiskCheck>>,
+    /// Called after every execution of the book, auction and off-book trades included
+    pub trade_listeners: Vec<Arc<dyn TradeListener>>,
+    /// Stamps orders, trades and events; a simulation swaps in a `VirtualClock`
+    pub clock: Arc<dyn Clock>,
+    /// Names the orders the server enters, see [`orderids::by_name`]
+    pub ids: Arc<dyn IdGenerator>,
+}
+
+impl Default for InstrumentConfig {
+    fn default() -> Self {
+        InstrumentConfig {
+            policy: Arc::new(PriceTime),
+            tie_break: TieBreak::default(),
+            risk_checks: Vec::new(),
+            trade_listeners: Vec::new(),
+            clock: Arc::new(SystemClock),
+            ids: Arc::new(UuidV4Ids),
+        }
+    }
+}
+
+impl InstrumentConfig {
+    /// Config for the root book (`tenant` is `None`) or a tenant's book. A tenant's settings
+    /// are read from `<VAR>_<TENANT>` first (upper case, `-` as `_`), then from `<VAR>`.
+    pub fn from_env(tenant: Option<&str>) -> Self {
+        InstrumentConfig::from_vars(|name| {
+            tenant
+                .and_then(|t| std::env::var(format!("{name}_{}", env_suffix(t))).ok())
+                .or_else(|| std::env::var(name).ok())
+        })
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let mut config = InstrumentConfig::default();
+        let min_quantity = var(PRO_RATA_MIN_QTY_ENV)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+        if let Some(name) = var(MATCHING_POLICY_ENV) {
+            match matchingpolicy::by_name(&name, min_quantity) {
+                Some(policy) => config.policy = policy,
+                None => println!("Ignoring unknown matching policy {name:?}"),
+            }
+        }
+        match var(TIE_BREAK_ENV).as_deref() {
+            Some("timestamp") => config.tie_break = TieBreak::Timestamp,
+            Some("arrival") | None => {}
+            Some(other) => println!("Ignoring unknown tie break {other:?}"),
+        }
+        let worker = var(WORKER_ID_ENV).and_then(|v| v.parse().ok()).unwrap_or(0);
+        if let Some(name) = var(ORDER_IDS_ENV) {
+            match orderids::by_name(&name, worker, config.clock.clone()) {
+                Some(ids) => config.ids = ids,
+                None => println!("Ignoring unknown order id strategy {name:?}"),
+            }
+        }
+        config
+    }
+}
+
+fn env_suffix(tenant: &str) -> String {
+    tenant.to_ascii_uppercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_vars() {
+        let config = InstrumentConfig::from_vars(|_| None);
+        assert_eq!(config.policy.name(), "price_time");
+        assert_eq!(config.tie_break, TieBreak::Arrival);
+
+        let config = InstrumentConfig::from_vars(|name| match name {
+            MATCHING_POLICY_ENV => Some(String::from("size_priority")),
+            TIE_BREAK_ENV => Some(String::from("timestamp")),
+            ORDER_IDS_ENV => Some(String::from("sequential")),
+            _ => None,
+        });
+        assert_eq!(config.policy.name(), "size_priority");
+        assert_eq!(config.tie_break, TieBreak::Timestamp);
+        assert_eq!(config.ids.next_id(), "1");
+
+        assert_eq!(env_suffix("sandbox-1"), "SANDBOX_1");
+    }
+}
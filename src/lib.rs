@@ -0,0 +1,21 @@
+//! Matching core of the engine: orders, the book, matching policies, a synchronous
+//! [`matching::Matcher`] and [`mirror::BookMirror`] to build books from external feeds. It has no async runtime or server dependencies, so it builds for
+//! `wasm32-unknown-unknown` and the language bindings; the `ome-v2` server builds on it.
+
+pub mod bookbackend;
+pub mod clock;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod harness;
+pub mod ids;
+pub mod matching;
+pub mod matchingpolicy;
+pub mod mirror;
+#[cfg(feature = "node")]
+pub mod node;
+pub mod order;
+pub mod orderbook;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, Method, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    error::{ApiError, ErrorCode},
+    matchingengine::MatchingEngine,
+    order::RejectReason,
+};
+
+pub const REQUEST_TIMEOUT_MS_ENV: &str = "OME_REQUEST_TIMEOUT_MS"; // every endpoint's, `0` for none
+pub const REQUEST_TIMEOUTS_ENV: &str = "OME_REQUEST_TIMEOUTS"; // per endpoint, e.g. `/orders=500,/trades=2000`
+pub const SHED_QUEUE_LATENCY_MS_ENV: &str = "OME_SHED_QUEUE_LATENCY_MS";
+
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a request may take before the client is answered `504 timeout`, by route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeoutConfig {
+    pub default: Option<Duration>,
+    /// By route as registered, e.g. `/orders/{id}/cancel`, without the `/tenants/<name>`
+    /// prefix; `None` exempts the route
+    pub routes: HashMap<String, Option<Duration>>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        TimeoutConfig {
+            default: Some(DEFAULT_REQUEST_TIMEOUT),
+            routes: HashMap::new(),
+        }
+    }
+}
+
+fn timeout_of(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+impl TimeoutConfig {
+    pub fn from_env() -> Self {
+        let mut config = TimeoutConfig::default();
+        if let Some(ms) = std::env::var(REQUEST_TIMEOUT_MS_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config.default = timeout_of(ms);
+        }
+        let routes = std::env::var(REQUEST_TIMEOUTS_ENV).unwrap_or_default();
+        for entry in routes.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry
+                .split_once('=')
+                .map(|(r, ms)| (r.trim(), ms.trim().parse()))
+            {
+                Some((route, Ok(ms))) => {
+                    config.routes.insert(route.to_string(), timeout_of(ms));
+                }
+                _ => println!("Ignoring request timeout {entry:?}, expected <route>=<ms>"),
+            }
+        }
+        config
+    }
+
+    /// The timeout of the route registered as `path`
+    pub fn limit(&self, path: &str) -> Option<Duration> {
+        let route = match path.strip_prefix("/tenants/") {
+            Some(rest) => rest.find('/').map_or("/", |i| &rest[i..]),
+            None => path,
+        };
+        self.routes.get(route).copied().unwrap_or(self.default)
+    }
+}
+
+/// Answers `504 timeout` once a request has taken longer than its route's timeout. The
+/// handler runs on as a task of its own rather than being dropped halfway, so an order that
+/// timed out may still be entered: the client checks its orders before trying again.
+pub async fn timeout(
+    State(config): State<Arc<TimeoutConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string());
+    let Some(limit) = path.and_then(|path| config.limit(&path)) else {
+        return next.run(req).await;
+    };
+    let handler = tokio::spawn(next.run(req));
+    match tokio::time::timeout(limit, handler).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => ApiError::new(ErrorCode::Internal, e.to_string()).into_response(),
+        Err(_) => ApiError::new(
+            ErrorCode::Timeout,
+            format!(
+                "no answer within {}ms, the request may still complete",
+                limit.as_millis()
+            ),
+        )
+        .into_response(),
+    }
+}
+
+/// Orders waiting for their result from the engine, to tell how long the oldest has waited
+#[derive(Debug, Default)]
+pub struct WaitGauge {
+    next_ticket: AtomicU64,
+    waiting: Mutex<BTreeMap<u64, Instant>>, // by ticket, so the oldest comes first
+}
+
+/// An order counted as waiting until it's dropped
+pub struct Waiting {
+    gauge: Arc<WaitGauge>,
+    ticket: u64,
+}
+
+impl WaitGauge {
+    pub fn enter(gauge: &Arc<WaitGauge>) -> Waiting {
+        let ticket = gauge.next_ticket.fetch_add(1, Ordering::Relaxed);
+        gauge.lock().insert(ticket, Instant::now());
+        Waiting {
+            gauge: Arc::clone(gauge),
+            ticket,
+        }
+    }
+
+    /// How long the oldest order still waiting has waited, zero when none is
+    pub fn oldest(&self) -> Duration {
+        self.lock()
+            .first_key_value()
+            .map_or(Duration::ZERO, |(_, since)| since.elapsed())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, Instant>> {
+        self.waiting.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.gauge.lock().remove(&self.ticket);
+    }
+}
+
+/// Refuses new work while orders wait longer than `max_queue_latency` for the engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadShedConfig {
+    pub max_queue_latency: Duration,
+}
+
+impl LoadShedConfig {
+    pub fn from_env() -> Option<Self> {
+        let ms: u64 = std::env::var(SHED_QUEUE_LATENCY_MS_ENV)
+            .ok()?
+            .parse()
+            .ok()?;
+        Some(LoadShedConfig {
+            max_queue_latency: timeout_of(ms)?,
+        })
+    }
+}
+
+/// Answers `503 overloaded` without going near the engine while it's behind; cancels
+/// (`DELETE`) always go through, they take load off
+pub async fn shed_load(
+    State((engine, config)): State<(MatchingEngine, Option<LoadShedConfig>)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let latency = engine.queue_latency();
+    match config {
+        Some(config) if latency > config.max_queue_latency && req.method() != Method::DELETE => {
+            let mut resp = ApiError {
+                message: format!(
+                    "orders wait {}ms for the engine, retry later",
+                    latency.as_millis()
+                ),
+                ..ApiError::from(RejectReason::Overloaded)
+            }
+            .into_response();
+            resp.headers_mut().insert(RETRY_AFTER, HeaderValue::from(1));
+            resp
+        }
+        _ => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timeouts_and_waits() {
+        let mut config = TimeoutConfig::default();
+        config
+            .routes
+            .insert("/orders".to_string(), Some(Duration::from_millis(500)));
+        config.routes.insert("/ws".to_string(), None);
+        assert_eq!(config.limit("/orders"), Some(Duration::from_millis(500)));
+        assert_eq!(
+            config.limit("/tenants/acme/orders"),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(config.limit("/tenants/acme/ws"), None);
+        assert_eq!(config.limit("/trades"), Some(DEFAULT_REQUEST_TIMEOUT));
+
+        let gauge = Arc::new(WaitGauge::default());
+        assert_eq!(gauge.oldest(), Duration::ZERO);
+        let first = WaitGauge::enter(&gauge);
+        std::thread::sleep(Duration::from_millis(5));
+        let second = WaitGauge::enter(&gauge);
+        assert!(gauge.oldest() >= Duration::from_millis(5));
+        drop(first);
+        assert!(gauge.oldest() < Duration::from_millis(5));
+        drop(second);
+        assert_eq!(gauge.oldest(), Duration::ZERO);
+    }
+}
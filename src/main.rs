@@ -1,25 +1,135 @@
+mod accounts;
+mod admin;
+mod archive;
+mod auction;
+mod audit;
+mod auth;
+mod batching;
+mod bookview;
+mod candles;
+mod circuitbreaker;
+mod consensus;
+mod dropcopy;
+mod eod;
+mod error;
+mod eventbus;
+mod events;
+mod fees;
+mod heartbeat;
+mod indexprice;
+mod instrument;
+mod loadshed;
+mod marketdata;
+mod marketquality;
+mod markprice;
 mod matchingengine;
-mod order;
-mod orderbook;
+mod mmprotection;
+mod multicast;
+mod objectstore;
+mod orderentry;
+mod orderids;
+mod otr;
+mod positions;
+mod quotes;
+mod ratelimit;
+mod recovery;
+mod replication;
+mod retention;
+mod retransmit;
+mod rfq;
+mod risk;
+mod runtimeconfig;
+mod schedule;
+mod scheduledorders;
+mod seed;
+mod session;
+mod settlement;
+mod signing;
+mod state;
+mod stats;
+mod subscriptions;
+mod surveillance;
+mod tape;
+mod tenant;
+mod tradeexport;
+mod tradehistory;
+mod tradespill;
+mod twap;
+mod validation;
+mod volume;
+mod volumeprofile;
+mod wallet;
+mod ws;
 
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    routing::{delete, get, post},
+    extract::{
+        Extension, Path, Query, State,
+        rejection::{JsonRejection, QueryRejection},
+    },
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
 };
 
-use chrono::{DateTime, Utc};
-use matchingengine::MatchingEngine;
-use order::{Order, OrderType, Side, Trade};
+use ome_v2::{clock, ids, matching, matchingpolicy, order, orderbook};
+
+use archive::ArchiveConfig;
+use auction::Equilibrium;
+use auth::{Auth, Principal, Role};
+use batching::BatchConfig;
+use bookview::PriceLevel;
+use candles::Candles;
+use circuitbreaker::{BreakerTrip, CircuitBreakerConfig};
+use clap::{Parser, Subcommand};
+use consensus::{Consensus, LogClock, RaftConfig};
+use eod::EodConfig;
+use error::{ApiError, ErrorCode};
+use fees::FeeSchedule;
+use heartbeat::HeartbeatConfig;
+use indexprice::IndexFeedConfig;
+use instrument::InstrumentConfig;
+use loadshed::{LoadShedConfig, TimeoutConfig};
+use marketquality::QualityStats;
+use markprice::{MarkPrice, MarkPriceMethod};
+use matchingengine::{MatchingEngine, TradingState};
+use multicast::MulticastConfig;
+use objectstore::UploadConfig;
+use order::{
+    Order, OrderType, Price, RejectReason, Side, TimeInForce, Timestamp, Trade, TriggerKind,
+    TriggerSource,
+};
+use orderentry::{Entry, OrderAck, ResponseType};
+use otr::OtrConfig;
+use quotes::{MAX_QUOTE_ORDERS, Quote};
+use ratelimit::{RateLimitConfig, RateLimiter};
+use recovery::RecoverArgs;
+use replication::StandbyConfig;
+use retention::RetentionConfig;
+use risk::{BandLimits, FatFingerCheck, PriceBands, RiskLimits};
+use schedule::{MarketPhase, MarketSchedule, PhasePolicies};
 use serde::{Deserialize, Serialize};
+use session::SessionRegistry;
+use signing::Signing;
+use stats::Symbol;
+use std::io::Write;
 use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use surveillance::{Surveillance, SurveillanceConfig};
+use tradespill::{TradeHistoryConfig, TradeSpill};
+use twap::Twaps;
+use validation::{FieldError, PriceType};
+use volume::VolumeStats;
+use volumeprofile::PriceVolume;
+use wallet::Wallets;
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(untagged)]
-enum PriceType {
-    Unsigned(u64),
-    Float(f64),
-}
+const PORT_ENV: &str = "OME_PORT";
+const DEFAULT_PORT: u16 = 61666;
+const READINESS_LOCK_TIMEOUT: Duration = Duration::from_millis(250); // max wait on the book lock before /readyz reports not ready
+const DEFAULT_PROFILE_WINDOW_SECS: u64 = 3_600;
 
 #[derive(Debug, Deserialize)]
 struct NewOrderRequest {
@@ -28,12 +138,31 @@ struct NewOrderRequest {
     /// Price in cents; for market orders this can be omitted or 0
     price: Option<PriceType>,
     quantity: u64,
+    /// `GoodTillCancel` when omitted
+    #[serde(default)]
+    time_in_force: TimeInForce,
+    /// Makes the order wait until the market reaches this price
+    trigger_price: Option<PriceType>,
+    /// What the trigger price is compared with, `LastTrade` when omitted
+    trigger_source: Option<TriggerSource>,
+    /// `Stop` (the default) or `Touch` for market-if-touched
+    trigger_kind: Option<TriggerKind>,
+    /// Makes the stop trail the market by this distance
+    trigger_trail: Option<PriceType>,
+    /// Unix ms to hold the order back until; omitted or past submits it right away
+    activate_at: Option<Timestamp>,
+    /// `Ack` answers once the engine accepted the order, `Full` (the default) once it's done
+    /// matching
+    #[serde(default)]
+    response_type: ResponseType,
 }
 
+/// Depth of the book, best price first on each side. Only aggregates per price, so other
+/// participants' orders can't be told apart.
 #[derive(Debug, Serialize)]
 struct OrderBookView {
-    bids: Vec<Order>,
-    asks: Vec<Order>,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,7 +170,83 @@ struct NewOrderResponse {
     // trades: Vec<Trade>,
     // orderbook: OrderBookView,
     id: String,
+    /// Sequence number of the order's `OrderAccepted` event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq: Option<u64>,
     trades: Option<Vec<Trade>>,
+    /// Set when the order was scheduled instead of submitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activate_at: Option<Timestamp>,
+}
+
+/// One side of a quote; the side is a limit order at `price`
+#[derive(Debug, Deserialize)]
+struct QuoteSideRequest {
+    /// Price in cents, or dollars with a decimal point
+    price: PriceType,
+    quantity: u64,
+}
+
+/// A side left out pulls the previous quote's orders on that side without a replacement
+#[derive(Debug, Deserialize)]
+struct QuoteRequest {
+    bid: Option<QuoteSideRequest>,
+    ask: Option<QuoteSideRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MassQuoteEntry {
+    side: Side,
+    price: PriceType,
+    quantity: u64,
+}
+
+/// Every level of the caller's quote; levels left out are pulled
+#[derive(Debug, Deserialize)]
+struct MassQuoteRequest {
+    entries: Vec<MassQuoteEntry>,
+}
+
+/// Ack or nack of one order of a quote
+#[derive(Debug, Serialize)]
+struct QuoteEntryResponse {
+    id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    trades: Vec<Trade>,
+    /// Why the order was rejected; the rest of the quote stands on its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ApiError>,
+}
+
+impl QuoteEntryResponse {
+    fn new(id: String, result: Result<Vec<Trade>, RejectReason>) -> Self {
+        match result {
+            Ok(trades) => QuoteEntryResponse {
+                id,
+                trades,
+                error: None,
+            },
+            Err(reason) => QuoteEntryResponse {
+                id,
+                trades: Vec::new(),
+                error: Some(reason.into()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct QuoteResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bid: Option<QuoteEntryResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ask: Option<QuoteEntryResponse>,
+}
+
+#[derive(Debug, Serialize)]
+struct MassQuoteResponse {
+    /// One per request entry, in the same order
+    entries: Vec<QuoteEntryResponse>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,88 +259,743 @@ struct AllTradesResponse {
     trades: Vec<Trade>,
 }
 
-#[tokio::main]
-async fn main() {
-    let engine = MatchingEngine::new();
+#[derive(Debug, Serialize)]
+struct MarkPriceResponse {
+    mark_price: Option<MarkPrice>,
+    /// Latest external index price, while it's fresh
+    index_price: Option<Price>,
+}
 
-    let app = Router::new()
-        .route("/orderbook", get(get_orderbook))
+#[derive(Debug, Serialize)]
+struct VolumeResponse {
+    windows: Vec<VolumeStats>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeProfileQuery {
+    /// Look back this far, default one hour
+    window_secs: Option<u64>,
+    /// Width of a price bucket in cents, default 1
+    bucket: Option<Price>,
+}
+
+#[derive(Debug, Serialize)]
+struct VolumeProfileResponse {
+    /// Unix timestamps (ms)
+    from: Timestamp,
+    to: Timestamp,
+    bucket: Price,
+    levels: Vec<PriceVolume>,
+}
+
+#[derive(Debug, Serialize)]
+struct MarketStatusResponse {
+    trading: TradingState,
+    phase: MarketPhase,
+    mark_price: Option<MarkPrice>,
+    /// Indicative uncrossing price/volume while an auction is collecting orders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auction: Option<Equilibrium>,
+    /// Set while the circuit breaker keeps the book halted or in a volatility auction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    circuit_breaker: Option<BreakerTrip>,
+    /// Limit up / limit down prices, when price bands are configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    price_bands: Option<BandLimits>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ComponentStatus {
+    Ok,
+    Unavailable,
+    Disabled, // subsystem not configured for this deployment
+}
+
+#[derive(Debug, Serialize)]
+struct HealthResponse {
+    status: ComponentStatus,
+}
+
+#[derive(Debug, Serialize)]
+struct ReadinessResponse {
+    ready: bool,
+    book: ComponentStatus,
+    persistence: ComponentStatus,
+    event_bus: ComponentStatus,
+    /// Number of events the slowest subscriber is behind; `None` while the bus is disabled
+    event_bus_lag: Option<u64>,
+    /// Orders waiting for the engine and how many fit; a full queue refuses new orders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submission_queue: Option<QueueDepth>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueueDepth {
+    depth: usize,
+    size: usize,
+}
+
+/// A fresh engine configured from the environment, with its background tasks running, and the
+/// node's member of its Raft cluster in consensus mode; `tenant` picks up the tenant's
+/// instrument overrides
+async fn configured_engine(tenant: Option<&str>) -> (MatchingEngine, Option<Consensus>) {
+    let mut instrument = InstrumentConfig::from_env(tenant);
+    if RaftConfig::from_env().is_some() {
+        // entries apply at the leader's time, whenever a member gets to them
+        instrument.clock = Arc::new(LogClock);
+    }
+    println!(
+        "{}: matching policy {}, tie break {:?}",
+        tenant.unwrap_or("root"),
+        instrument.policy.name(),
+        instrument.tie_break
+    );
+    if !instrument.risk_checks.is_empty() {
+        let names: Vec<&str> = instrument.risk_checks.iter().map(|c| c.name()).collect();
+        println!("{}: risk checks {names:?}", tenant.unwrap_or("root"));
+    }
+    let engine = MatchingEngine::with_instrument(instrument);
+    engine.set_phase_policies(PhasePolicies::from_env()).await;
+    let breaker = CircuitBreakerConfig::from_env();
+    if breaker.is_enabled() {
+        match RaftConfig::from_env() {
+            // each node would trip and come back at its own point of the log
+            Some(_) => println!(
+                "{}: circuit breaker runs on the node's clock, disabled in consensus mode",
+                tenant.unwrap_or("root")
+            ),
+            None => {
+                engine.set_circuit_breaker(breaker).await;
+                tokio::spawn(circuitbreaker::run_cooldown(engine.clone()));
+            }
+        }
+    }
+    engine.set_price_bands(PriceBands::from_env()).await;
+    engine
+        .set_mark_price_method(MarkPriceMethod::from_env())
+        .await;
+    engine.set_default_risk_limits(RiskLimits::from_env()).await;
+    engine.set_wallets(Wallets::from_env()).await;
+    let fee_schedule = FeeSchedule::from_env();
+    let tiered = !fee_schedule.tiers.is_empty();
+    engine.set_fee_schedule(fee_schedule).await;
+    engine
+        .set_fat_finger_check(FatFingerCheck::from_env())
+        .await;
+    engine.set_otr_config(OtrConfig::from_env()).await;
+    let queue = batching::start(engine.clone(), BatchConfig::from_env());
+    engine.set_submission_queue(queue).await;
+    let schedule = MarketSchedule::from_env();
+    let scheduled = schedule.is_some();
+    let symbol = tenant.unwrap_or(archive::ROOT_SYMBOL).to_string();
+    let history = TradeHistoryConfig::from_env();
+    let spill = history.spill_path(&symbol).and_then(|path| {
+        TradeSpill::create(path.clone())
+            .inspect_err(|e| println!("{}: trades not spilled: {e}", path.display()))
+            .ok()
+    });
+    engine.set_trade_history(history.capacity, spill).await;
+    let archive = ArchiveConfig::from_env();
+    if let Some(config) = UploadConfig::from_env(archive.as_ref().map(|a| a.dir.clone())) {
+        tokio::spawn(objectstore::run_uploader(
+            engine.clone(),
+            symbol.clone(),
+            config,
+        ));
+    }
+    if let Some(config) = RetentionConfig::from_env() {
+        tokio::spawn(retention::run_retention(
+            engine.clone(),
+            symbol.clone(),
+            config,
+            archive.clone(),
+        ));
+    }
+    if let Some(config) = EodConfig::from_env() {
+        tokio::spawn(eod::run_eod_reports(
+            engine.clone(),
+            symbol.clone(),
+            config,
+            scheduled,
+        ));
+    }
+    if let Some(config) = IndexFeedConfig::from_env(&symbol) {
+        match RaftConfig::from_env() {
+            // every node would fire stops off its own reading of the index
+            Some(_) => {
+                println!("{symbol}: index price isn't replicated, ignored in consensus mode")
+            }
+            None => indexprice::run_index_feed(engine.clone(), config),
+        }
+    }
+    let consensus = configured_consensus(&engine, tenant).await;
+    // the tasks that change the engine on their own; in consensus mode the leader runs them
+    // through the log
+    tokio::spawn(scheduledorders::run_release(
+        engine.clone(),
+        consensus.clone(),
+    ));
+    tokio::spawn(rfq::run_rfq_close(engine.clone(), consensus.clone()));
+    if let Some(schedule) = schedule {
+        tokio::spawn(schedule::run_scheduler(
+            engine.clone(),
+            schedule,
+            consensus.clone(),
+        ));
+    }
+    if tiered {
+        tokio::spawn(fees::run_tier_refresh(
+            engine.clone(),
+            fees::tier_refresh_from_env(),
+            consensus.clone(),
+        ));
+    }
+    tokio::spawn(settlement::run_settlement_cycle(
+        engine.clone(),
+        settlement::period_from_env(),
+        consensus.clone(),
+    ));
+    let standby = StandbyConfig::from_env();
+    let replicated = standby.is_some() || consensus.is_some();
+    seed::seed_from_env(&engine, &symbol, replicated).await;
+    if let Some(config) = archive {
+        tokio::spawn(archive::run_archiver(engine.clone(), symbol, config));
+    }
+    if let Some(config) = standby {
+        engine.start_standby().await;
+        replication::run_standby(engine.clone(), config, tenant.map(str::to_string));
+    }
+    (engine, consensus)
+}
+
+/// This node's member of the exchange's Raft cluster, in consensus mode
+async fn configured_consensus(engine: &MatchingEngine, tenant: Option<&str>) -> Option<Consensus> {
+    let mut config = RaftConfig::from_env()?;
+    config.dir.push(tenant.unwrap_or(archive::ROOT_SYMBOL));
+    println!(
+        "{}: consensus mode, node {} of {:?}",
+        tenant.unwrap_or("root"),
+        config.node_id,
+        config.peers.keys().collect::<Vec<_>>()
+    );
+    let prefix = tenant.map_or(String::new(), |name| format!("/tenants/{name}"));
+    Some(Consensus::start(engine.clone(), config, &prefix).await)
+}
+
+/// Every route of one exchange, bound to `engine`; in consensus mode order entry and the admin
+/// commands go through `consensus`
+fn exchange(
+    engine: MatchingEngine,
+    consensus: Option<Consensus>,
+    auth: Auth,
+    signing: Signing,
+    symbol: &str,
+) -> Router {
+    let rate_limiter = RateLimiter::new(RateLimitConfig::from_env());
+    let trader_routes = Router::new()
         .route("/orders", post(post_order))
         .route("/orders/{id}/cancel", delete(cancel_order))
+        .route("/quotes", put(put_quote))
+        .route("/quotes/mass", post(post_mass_quote))
+        .merge(rfq::routes())
+        .merge(twap::routes())
+        // the innermost layer, so it counts requests by the verified key or principal
+        .route_layer(middleware::from_fn_with_state(
+            rate_limiter.clone(),
+            ratelimit::rate_limit,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            auth.clone(),
+            auth::require_trader,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            signing,
+            signing::require_signature,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            (engine.clone(), LoadShedConfig::from_env()),
+            loadshed::shed_load,
+        ))
+        .layer(Extension(consensus.clone()));
+    let candles = Candles::from_env(symbol);
+    tokio::spawn(candles::run_candles(engine.clone(), candles.clone()));
+    let surveillance = Surveillance::new();
+    tokio::spawn(surveillance::run_surveillance(
+        engine.clone(),
+        surveillance.clone(),
+        SurveillanceConfig::from_env(),
+    ));
+
+    Router::new()
+        .route("/orderbook", get(get_orderbook))
         .route("/trades", get(get_all_trades))
-        .with_state(engine);
+        .route("/market", get(get_market_status))
+        .route("/mark-price", get(get_mark_price))
+        .route("/volume", get(get_volume))
+        .route("/volume-profile", get(get_volume_profile))
+        .route("/market-quality", get(get_market_quality))
+        .route("/stats", get(stats::get_stats))
+        .route("/healthz", get(get_health))
+        .route("/readyz", get(get_readiness))
+        .merge(trader_routes)
+        .merge(ws::routes(
+            engine.clone(),
+            consensus.clone(),
+            rate_limiter.clone(),
+            auth.clone(),
+            SessionRegistry::new(),
+            symbol.to_string(),
+        ))
+        .merge(accounts::routes(auth.clone()))
+        .merge(events::routes(auth.clone()))
+        .merge(dropcopy::routes(auth.clone()))
+        .merge(tape::routes())
+        .merge(marketdata::routes())
+        .merge(candles::routes(candles))
+        .merge(tradeexport::routes())
+        .merge(match consensus.clone() {
+            Some(consensus) => consensus::routes(consensus, auth.clone()),
+            None => Router::new(),
+        })
+        .merge(surveillance::routes(auth.clone(), surveillance))
+        .merge(eod::routes(
+            auth.clone(),
+            EodConfig::from_env(),
+            symbol.to_string(),
+        ))
+        .merge(admin::routes(auth, rate_limiter, consensus))
+        .layer(Extension(HeartbeatConfig::from_env()))
+        .layer(Extension(Twaps::new()))
+        .layer(Extension(Symbol(symbol.to_string())))
+        .with_state(engine)
+}
 
-    let addr: SocketAddr = SocketAddr::from(([0, 0, 0, 0], 61666));
+/// Runs the exchange server; `recover` rebuilds a past state offline instead
+#[derive(Debug, Parser)]
+#[command(name = "ome-v2", about = "Order matching engine server")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Mode>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Mode {
+    /// Replay a journal up to a sequence number or time, print the engine state it reached as
+    /// JSON and exit
+    Recover(RecoverArgs),
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    if let Some(Mode::Recover(args)) = Cli::parse().command {
+        return match recovery::recover(&args).await {
+            Ok(state) => {
+                eprintln!("recovered the state after event {}", state.last_seq);
+                let json = serde_json::to_string_pretty(&state).expect("engine state serializes");
+                let _ = writeln!(std::io::stdout(), "{json}"); // a closed stdout (`| head`) is fine
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("recover: {e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let auth = Auth::from_env();
+    let signing = Signing::from_env();
+    if !auth.is_enabled() {
+        println!(
+            "WARNING: {} not set, JWT authentication is disabled",
+            auth::JWT_SECRET_ENV
+        );
+    }
+
+    // the default exchange is served at the root, each tenant is an independent one under its prefix
+    let (engine, consensus) = configured_engine(None).await;
+    if let Some(config) = MulticastConfig::from_env() {
+        tokio::spawn(multicast::run_publisher(engine.clone(), config));
+    }
+    let mut engines = vec![(archive::ROOT_SYMBOL.to_string(), engine.clone())];
+    let mut app = exchange(
+        engine,
+        consensus,
+        auth.clone(),
+        signing.clone(),
+        archive::ROOT_SYMBOL,
+    );
+    for name in tenant::from_env() {
+        println!("Serving tenant {name} under /tenants/{name}");
+        let (engine, consensus) = configured_engine(Some(&name)).await;
+        engines.push((name.clone(), engine.clone()));
+        let tenant = exchange(
+            engine,
+            consensus,
+            auth.for_tenant(&name),
+            signing.clone(),
+            &name,
+        );
+        app = app.nest(&format!("/tenants/{name}"), tenant);
+    }
+    app = app
+        .merge(stats::routes(engines))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(TimeoutConfig::from_env()),
+            loadshed::timeout,
+        ));
+
+    let port = std::env::var(PORT_ENV)
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let addr: SocketAddr = SocketAddr::from(([0, 0, 0, 0], port));
     println!("Starting server on http://{}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+    ExitCode::SUCCESS
 }
 
 async fn get_orderbook(State(engine): State<MatchingEngine>) -> Json<OrderBookView> {
-    let bids = engine.get_buy_orders().await;
-    let asks = engine.get_sell_orders().await;
-    Json(OrderBookView { bids, asks })
+    let view = engine.book_view();
+    Json(OrderBookView {
+        bids: view.bids.clone(),
+        asks: view.asks.clone(),
+    })
 }
 
 async fn post_order(
     State(mut engine): State<MatchingEngine>,
-    Json(req): Json<NewOrderRequest>,
-) -> Json<NewOrderResponse> {
-    let id = uuid::Uuid::new_v4().to_string();
-    let utc_datetime: DateTime<Utc> = Utc::now();
-    let ts = utc_datetime.timestamp_nanos_opt().unwrap_or(0);
-    let price = match req.order_type {
-        OrderType::Limit => {
-            let price = req.price.unwrap_or(PriceType::Unsigned(0));
-            match price {
-                PriceType::Float(f) => (f * 100.0) as u64,
-                PriceType::Unsigned(u) => u,
-            }
-            // req.price.unwrap_or(0)
-        }
-        OrderType::Market => 0,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
+    payload: Result<Json<NewOrderRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(req) = payload?;
+    let price = validation::validate_new_order(req.order_type, req.price.as_ref(), req.quantity)
+        .map_err(ApiError::validation)?;
+    let trigger = validation::validate_trigger(
+        req.trigger_price.as_ref(),
+        req.trigger_source,
+        req.trigger_kind,
+        req.trigger_trail.as_ref(),
+    )
+    .map_err(|e| ApiError::validation(vec![e]))?;
+    let id = engine.next_order_id();
+    let ts = engine.order_timestamp();
+    let order = Order::new(id, req.side, req.order_type, req.quantity, price, ts)
+        .with_owner(principal.subject)
+        .with_time_in_force(req.time_in_force);
+    let order = match trigger {
+        Some(trigger) => order.with_trigger(trigger),
+        None => order,
     };
-    let order = Order::new(
-        id,
-        req.side,
-        req.order_type,
-        req.quantity,
-        price,
-        ts.try_into().unwrap(),
-    );
-
-    let trades = engine.submit_order(order.clone()).await;
 
-    // let bids = engine.get_buy_orders().await;
-    // let asks = engine.get_sell_orders().await;
-    if trades.len() == 0 {
-        Json(NewOrderResponse {
+    if let Some(activate_at) = req.activate_at.filter(|at| *at > engine.clock().now_ms()) {
+        match &consensus {
+            Some(consensus) => consensus.schedule_order(order.clone(), activate_at).await?,
+            None => engine.schedule_order(order.clone(), activate_at).await?,
+        }
+        return Ok(Json(NewOrderResponse {
             id: order.id,
+            seq: None,
             trades: None,
-            // orderbook: OrderBookView { bids, asks },
-        })
-    } else {
-        Json(NewOrderResponse {
-            id: order.id,
-            trades: Some(trades),
-            // orderbook: OrderBookView { bids, asks },
+            activate_at: Some(activate_at),
         })
+        .into_response());
+    }
+
+    let Entry { ack, executions } = orderentry::enter_order(&engine, consensus, order).await?;
+    let (id, seq) = match ack {
+        OrderAck::Accepted { id, seq } => (id, seq),
+        OrderAck::Rejected { reason, .. } => return Err(reason.into()),
+    };
+    if req.response_type == ResponseType::Ack {
+        return Ok(Json(OrderAck::Accepted { id, seq }).into_response());
+    }
+    let trades = executions
+        .await
+        .map_err(|e| ApiError::new(ErrorCode::Internal, e.to_string()))??;
+
+    // let bids = engine.get_buy_orders().await;
+    // let asks = engine.get_sell_orders().await;
+    Ok(Json(NewOrderResponse {
+        id,
+        seq: Some(seq),
+        trades: (!trades.is_empty()).then_some(trades),
+        activate_at: None,
+        // orderbook: OrderBookView { bids, asks },
+    })
+    .into_response())
+}
+
+/// Replaces the caller's quote with the bid and ask in the request
+async fn put_quote(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
+    payload: Result<Json<QuoteRequest>, JsonRejection>,
+) -> Result<Json<QuoteResponse>, ApiError> {
+    let Json(req) = payload?;
+    let ts = engine.order_timestamp();
+    let mut errors = Vec::new();
+    let mut orders = Vec::new();
+    for (side, request) in [(Side::Buy, req.bid), (Side::Sell, req.ask)] {
+        let Some(request) = request else {
+            continue;
+        };
+        match validation::validate_quote_side(side, &request.price, request.quantity) {
+            Ok(price) => orders.push(quote_order(
+                &engine,
+                &principal,
+                side,
+                request.quantity,
+                price,
+                ts,
+            )),
+            Err(e) => errors.extend(e),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let entries = enter_quote(engine, consensus, &principal, orders).await?;
+    let mut response = QuoteResponse {
+        bid: None,
+        ask: None,
+    };
+    for (side, entry) in entries {
+        match side {
+            Side::Buy => response.bid = Some(entry),
+            Side::Sell => response.ask = Some(entry),
+        }
+    }
+    Ok(Json(response))
+}
+
+/// Replaces the caller's quote with every level in the request, acking or nacking each
+async fn post_mass_quote(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
+    payload: Result<Json<MassQuoteRequest>, JsonRejection>,
+) -> Result<Json<MassQuoteResponse>, ApiError> {
+    let Json(req) = payload?;
+    if req.entries.len() > MAX_QUOTE_ORDERS {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "entries",
+            format!("at most {MAX_QUOTE_ORDERS} entries per quote"),
+        )]));
+    }
+    let ts = engine.order_timestamp();
+    let mut errors = Vec::new();
+    let mut orders = Vec::with_capacity(req.entries.len());
+    for (i, entry) in req.entries.iter().enumerate() {
+        match validation::validate_new_order(OrderType::Limit, Some(&entry.price), entry.quantity) {
+            Ok(price) => orders.push(quote_order(
+                &engine,
+                &principal,
+                entry.side,
+                entry.quantity,
+                price,
+                ts,
+            )),
+            Err(e) => errors.extend(
+                e.into_iter()
+                    .map(|e| FieldError::new("entries", format!("entry {i}: {}", e.message))),
+            ),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
     }
+
+    let entries = enter_quote(engine, consensus, &principal, orders).await?;
+    Ok(Json(MassQuoteResponse {
+        entries: entries.into_iter().map(|(_, entry)| entry).collect(),
+    }))
+}
+
+/// A limit order for one level of the caller's quote
+fn quote_order(
+    engine: &MatchingEngine,
+    principal: &Principal,
+    side: Side,
+    quantity: u64,
+    price: Price,
+    ts: Timestamp,
+) -> Order {
+    let id = engine.next_order_id();
+    Order::new(id, side, OrderType::Limit, quantity, price, ts)
+        .with_owner(principal.subject.clone())
+}
+
+/// Enters `orders` as the caller's quote, through `consensus` when there is one; the result
+/// of each order comes back with its side, in order
+async fn enter_quote(
+    mut engine: MatchingEngine,
+    consensus: Option<Consensus>,
+    principal: &Principal,
+    orders: Vec<Order>,
+) -> Result<Vec<(Side, QuoteEntryResponse)>, ApiError> {
+    let sides: Vec<(Side, String)> = orders.iter().map(|o| (o.side, o.id.clone())).collect();
+    let quote = Quote {
+        owner: principal.subject.clone(),
+        orders,
+    };
+    let ack = match &consensus {
+        Some(consensus) => consensus.submit_quote(quote).await?,
+        None => engine.submit_quote(quote).await?,
+    };
+    Ok(sides
+        .into_iter()
+        .zip(ack)
+        .map(|((side, id), result)| (side, QuoteEntryResponse::new(id, result)))
+        .collect())
 }
 
 async fn cancel_order(
-    State(mut engine): State<MatchingEngine>,
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
     Path(order_id): Path<String>,
-) -> Json<CancelResponse> {
-    let result = engine.cancel_order(order_id).await;
-    Json(CancelResponse { result })
+) -> Result<Json<CancelResponse>, ApiError> {
+    let owner = (!principal.has_role(Role::Admin)).then(|| principal.subject.clone());
+    let canceled =
+        orderentry::cancel_order(&engine, consensus.as_ref(), order_id.clone(), owner).await?;
+    if canceled {
+        Ok(Json(CancelResponse { result: true }))
+    } else {
+        Err(ApiError::new(
+            ErrorCode::OrderNotFound,
+            format!("no resting order with id {order_id}"),
+        ))
+    }
 }
 
 async fn get_all_trades(State(engine): State<MatchingEngine>) -> Json<AllTradesResponse> {
-    let trades_guard = engine.trades.read().await;
-    let trades_vec: Vec<Trade> = trades_guard
-        .iter()
-        .map(|arc_trade| (*arc_trade).clone())
+    // public tape, counterparties stay anonymous
+    let trades_vec: Vec<Trade> = engine
+        .trade_history()
+        .await
+        .into_iter()
+        .map(|trade| Trade {
+            buyer: None,
+            seller: None,
+            ..trade
+        })
         .collect();
     Json(AllTradesResponse { trades: trades_vec })
 }
+
+async fn get_market_status(State(engine): State<MatchingEngine>) -> Json<MarketStatusResponse> {
+    let phase = engine.market_phase().await;
+    let auction = match phase {
+        MarketPhase::Continuous => None,
+        _ => engine.indicative_equilibrium().await,
+    };
+    Json(MarketStatusResponse {
+        trading: engine.trading_state().await,
+        phase,
+        mark_price: engine.mark_price().await,
+        auction,
+        circuit_breaker: engine.circuit_breaker_trip().await,
+        price_bands: engine.band_limits().await,
+    })
+}
+
+async fn get_mark_price(State(engine): State<MatchingEngine>) -> Json<MarkPriceResponse> {
+    Json(MarkPriceResponse {
+        mark_price: engine.mark_price().await,
+        index_price: engine.index_price().await,
+    })
+}
+
+async fn get_volume(State(engine): State<MatchingEngine>) -> Json<VolumeResponse> {
+    Json(VolumeResponse {
+        windows: engine.rolling_volume().await,
+    })
+}
+
+async fn get_volume_profile(
+    State(engine): State<MatchingEngine>,
+    query: Result<Query<VolumeProfileQuery>, QueryRejection>,
+) -> Result<Json<VolumeProfileResponse>, ApiError> {
+    let Query(query) = query?;
+    let window_secs = query.window_secs.unwrap_or(DEFAULT_PROFILE_WINDOW_SECS);
+    let bucket = query.bucket.unwrap_or(1);
+    let mut errors = Vec::new();
+    if window_secs == 0 {
+        errors.push(FieldError::new("window_secs", "must be > 0"));
+    }
+    if bucket == 0 {
+        errors.push(FieldError::new("bucket", "must be > 0"));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let to = engine.clock().now_ms();
+    let from = to.saturating_sub(window_secs.saturating_mul(1_000));
+    Ok(Json(VolumeProfileResponse {
+        from,
+        to,
+        bucket,
+        levels: engine.volume_profile(from, bucket).await,
+    }))
+}
+
+async fn get_market_quality(State(engine): State<MatchingEngine>) -> Json<QualityStats> {
+    Json(engine.market_quality().await)
+}
+
+async fn get_health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: ComponentStatus::Ok,
+    })
+}
+
+async fn get_readiness(
+    State(engine): State<MatchingEngine>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    let book_reachable = engine.is_book_reachable(READINESS_LOCK_TIMEOUT).await;
+    let book = if book_reachable {
+        ComponentStatus::Ok
+    } else {
+        ComponentStatus::Unavailable
+    };
+    let submission_queue = engine
+        .submission_queue_depth()
+        .await
+        .map(|(depth, size)| QueueDepth { depth, size });
+    let queue_full = submission_queue.as_ref().is_some_and(|q| q.depth >= q.size);
+    // a failing spill loses history, not orders: reported, but the server stays ready
+    let persistence = match engine.spill_failing().await {
+        Some(false) => ComponentStatus::Ok,
+        Some(true) => ComponentStatus::Unavailable,
+        None => ComponentStatus::Disabled,
+    };
+    let ready = book_reachable && !queue_full;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(ReadinessResponse {
+            ready,
+            book,
+            persistence,
+            event_bus: ComponentStatus::Ok,
+            event_bus_lag: Some(engine.event_bus().lag()),
+            submission_queue,
+        }),
+    )
+}
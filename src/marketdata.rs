@@ -0,0 +1,540 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use axum::{
+    Extension, Router,
+    extract::{
+        Query, State,
+        rejection::QueryRejection,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::Response,
+    routing::get,
+};
+use futures_util::{
+    SinkExt, StreamExt,
+    stream::{SplitSink, SplitStream},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        Mutex, Notify,
+        broadcast::{self, error::RecvError},
+    },
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    bookview::{BookSnapshot, PriceLevel},
+    error::ApiError,
+    heartbeat::{Beat, HeartbeatConfig, Liveness},
+    matchingengine::MatchingEngine,
+    order::{Price, Quantity, Side, Timestamp, TradeId, TradeType},
+};
+
+/// Schema of the binary messages, in the header of every one of them
+pub const SCHEMA_ID: u16 = 1;
+pub const SCHEMA_VERSION: u16 = 1;
+const HEADER_LEN: usize = 8;
+const LEVEL_TEMPLATE_ID: u16 = 1;
+const LEVEL_BLOCK_LEN: u16 = 32;
+const TRADE_TEMPLATE_ID: u16 = 2;
+const TRADE_BLOCK_LEN: u16 = 48;
+/// Messages a subscriber may fall behind before it's dropped
+pub const MAX_PENDING: usize = 10_000;
+
+/// How the feed frames its messages, chosen when subscribing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// A JSON text frame per message
+    #[default]
+    Json,
+    /// A fixed-layout little-endian binary frame per message, see [`MarketDataMessage::encode`]
+    Binary,
+}
+
+#[derive(Debug, Deserialize)]
+struct MarketDataQuery {
+    #[serde(default)]
+    encoding: Encoding,
+    /// Coalesce the level updates a slow subscriber hasn't taken yet
+    #[serde(default)]
+    conflate: bool,
+}
+
+/// A price level as of event `seq`; a level with no quantity left is gone from the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LevelUpdate {
+    pub seq: u64,
+    pub side: Side,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub orders: u32,
+}
+
+/// One execution, without its participants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TradePrint {
+    pub seq: u64,
+    pub trade_id: TradeId,
+    /// Unix timestamp (ms)
+    pub time: Timestamp,
+    pub price: Price,
+    pub size: Quantity,
+    /// `None` for auction fills and block trades
+    pub aggressor: Option<Side>,
+    pub trade_type: TradeType,
+}
+
+impl TradePrint {
+    pub fn from_entry(entry: &AuditEntry) -> Option<Self> {
+        let AuditEvent::TradeExecuted { trade } = &entry.event else {
+            return None;
+        };
+        Some(TradePrint {
+            seq: entry.seq,
+            trade_id: trade.id,
+            time: entry.timestamp,
+            price: trade.price,
+            size: trade.quantity,
+            aggressor: trade.aggressor,
+            trade_type: trade.trade_type,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketDataMessage {
+    Level(LevelUpdate),
+    Trade(TradePrint),
+}
+
+impl MarketDataMessage {
+    /// The binary frame of the message: an 8-byte header (block length, template id, schema
+    /// id, schema version, each a `u16`) followed by a block of fixed-width little-endian
+    /// fields at fixed offsets.
+    ///
+    /// Level, template 1, 32 bytes: `seq` u64, `price` u64, `quantity` u64, `orders` u32,
+    /// `side` u8 (1 buy, 2 sell), 3 bytes padding.
+    ///
+    /// Trade, template 2, 48 bytes: `seq` u64, `trade_id` u64, `time` u64, `price` u64,
+    /// `size` u64, `aggressor` u8 (0 none, 1 buy, 2 sell), `trade_type` u8 (0 regular,
+    /// 1 block), 6 bytes padding.
+    pub fn encode(&self) -> Vec<u8> {
+        let (template_id, block_len) = match self {
+            MarketDataMessage::Level(_) => (LEVEL_TEMPLATE_ID, LEVEL_BLOCK_LEN),
+            MarketDataMessage::Trade(_) => (TRADE_TEMPLATE_ID, TRADE_BLOCK_LEN),
+        };
+        let mut buf = Vec::with_capacity(HEADER_LEN + block_len as usize);
+        for field in [block_len, template_id, SCHEMA_ID, SCHEMA_VERSION] {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        match self {
+            MarketDataMessage::Level(level) => {
+                buf.extend_from_slice(&level.seq.to_le_bytes());
+                buf.extend_from_slice(&level.price.to_le_bytes());
+                buf.extend_from_slice(&level.quantity.to_le_bytes());
+                buf.extend_from_slice(&level.orders.to_le_bytes());
+                buf.push(side_code(Some(level.side)));
+            }
+            MarketDataMessage::Trade(trade) => {
+                for field in [
+                    trade.seq,
+                    trade.trade_id,
+                    trade.time,
+                    trade.price,
+                    trade.size,
+                ] {
+                    buf.extend_from_slice(&field.to_le_bytes());
+                }
+                buf.push(side_code(trade.aggressor));
+                buf.push(match trade.trade_type {
+                    TradeType::Regular => 0,
+                    TradeType::Block => 1,
+                });
+            }
+        }
+        buf.resize(HEADER_LEN + block_len as usize, 0);
+        buf
+    }
+
+    fn frame(&self, encoding: Encoding) -> Message {
+        match encoding {
+            Encoding::Json => Message::Text(
+                serde_json::to_string(self)
+                    .expect("market data always serializes")
+                    .into(),
+            ),
+            Encoding::Binary => Message::Binary(self.encode().into()),
+        }
+    }
+}
+
+fn side_code(side: Option<Side>) -> u8 {
+    match side {
+        None => 0,
+        Some(Side::Buy) => 1,
+        Some(Side::Sell) => 2,
+    }
+}
+
+/// Updates turning `prev` into `next` as of event `seq`: changed and new levels, then the
+/// levels that are gone, with no quantity
+pub fn book_diff(prev: &BookSnapshot, next: &BookSnapshot, seq: u64) -> Vec<MarketDataMessage> {
+    let mut updates = Vec::new();
+    for (side, prev, next) in [
+        (Side::Buy, &prev.bids, &next.bids),
+        (Side::Sell, &prev.asks, &next.asks),
+    ] {
+        let mut gone: HashMap<Price, &PriceLevel> = prev.iter().map(|l| (l.price, l)).collect();
+        let update = |level: &PriceLevel| {
+            MarketDataMessage::Level(LevelUpdate {
+                seq,
+                side,
+                price: level.price,
+                quantity: level.quantity,
+                orders: level.orders as u32,
+            })
+        };
+        for level in next {
+            if gone.remove(&level.price) != Some(level) {
+                updates.push(update(level));
+            }
+        }
+        let mut gone: Vec<Price> = gone.into_keys().collect();
+        gone.sort_unstable();
+        updates.extend(gone.into_iter().map(|price| {
+            update(&PriceLevel {
+                price,
+                quantity: 0,
+                orders: 0,
+            })
+        }));
+    }
+    updates
+}
+
+/// `/market-data`: public feed of book level changes and trades
+pub fn routes() -> Router<MatchingEngine> {
+    Router::new().route("/market-data", get(market_data_upgrade))
+}
+
+async fn market_data_upgrade(
+    State(engine): State<MatchingEngine>,
+    Extension(heartbeat): Extension<Option<HeartbeatConfig>>,
+    query: Result<Query<MarketDataQuery>, QueryRejection>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    Ok(ws.on_upgrade(move |socket| {
+        run_feed(socket, engine, heartbeat, query.encoding, query.conflate)
+    }))
+}
+
+/// Messages waiting for a subscriber's socket, in order. Conflating, a level update takes the
+/// place of the update of the same level still waiting, so a slow subscriber gets the latest
+/// state of each level rather than every step in between; trades are never coalesced.
+#[derive(Debug)]
+struct Outbox {
+    queue: VecDeque<MarketDataMessage>,
+    /// Position of the update waiting for each level, counted from the first message queued
+    waiting: HashMap<(Side, Price), u64>,
+    popped: u64,
+    conflate: bool,
+    /// A ping to send ahead of the messages
+    ping: bool,
+}
+
+impl Outbox {
+    fn new(conflate: bool) -> Self {
+        Outbox {
+            queue: VecDeque::new(),
+            waiting: HashMap::new(),
+            popped: 0,
+            conflate,
+            ping: false,
+        }
+    }
+
+    /// Queues `messages`; `false` once more than [`MAX_PENDING`] are waiting
+    fn push_all(&mut self, messages: Vec<MarketDataMessage>) -> bool {
+        for msg in messages {
+            if self.conflate
+                && let MarketDataMessage::Level(level) = msg
+            {
+                let position = self.popped + self.queue.len() as u64;
+                let waiting = *self
+                    .waiting
+                    .entry((level.side, level.price))
+                    .or_insert(position);
+                if waiting != position {
+                    self.queue[(waiting - self.popped) as usize] = msg;
+                    continue;
+                }
+            }
+            self.queue.push_back(msg);
+        }
+        self.queue.len() <= MAX_PENDING
+    }
+
+    fn pop(&mut self) -> Option<MarketDataMessage> {
+        let msg = self.queue.pop_front()?;
+        if let MarketDataMessage::Level(level) = &msg
+            && self.waiting.get(&(level.side, level.price)) == Some(&self.popped)
+        {
+            self.waiting.remove(&(level.side, level.price));
+        }
+        self.popped += 1;
+        Some(msg)
+    }
+}
+
+/// The engine's book and trades as market data messages, for a feed to send on. Lagging
+/// behind the engine's events catches up from the log, so no trade is left out.
+pub struct MarketDataStream {
+    engine: MatchingEngine,
+    events: broadcast::Receiver<AuditEntry>,
+    next_seq: u64,
+    book: Arc<BookSnapshot>,
+}
+
+impl MarketDataStream {
+    /// Starts following the engine and returns the whole book as updates against an empty one
+    pub async fn start(engine: MatchingEngine) -> (Self, Vec<MarketDataMessage>) {
+        let events = engine.subscribe_events();
+        let next_seq = engine.last_seq().await + 1;
+        let book = engine.settled_book_view().await;
+        let snapshot = book_diff(&BookSnapshot::default(), &book, next_seq - 1);
+        let stream = MarketDataStream {
+            engine,
+            events,
+            next_seq,
+            book,
+        };
+        (stream, snapshot)
+    }
+
+    /// Waits for the engine's next event; cancel safe, nothing is lost when another branch of
+    /// a `select!` wins
+    pub async fn recv(&mut self) -> Result<AuditEntry, RecvError> {
+        self.events.recv().await
+    }
+
+    /// The trades and level changes of what [`Self::recv`] returned, `None` once the engine
+    /// is gone
+    pub async fn messages(
+        &mut self,
+        received: Result<AuditEntry, RecvError>,
+    ) -> Option<Vec<MarketDataMessage>> {
+        let entries = match received {
+            Ok(entry) => vec![entry],
+            Err(RecvError::Lagged(_)) => self.engine.audit_entries(self.next_seq, usize::MAX).await,
+            Err(RecvError::Closed) => return None,
+        };
+        let mut messages = Vec::new();
+        for entry in &entries {
+            if entry.seq < self.next_seq {
+                continue; // already sent before catching up
+            }
+            self.next_seq = entry.seq + 1;
+            messages.extend(TradePrint::from_entry(entry).map(MarketDataMessage::Trade));
+        }
+        let book = self.engine.settled_book_view().await;
+        if !Arc::ptr_eq(&self.book, &book) {
+            messages.extend(book_diff(&self.book, &book, self.next_seq - 1));
+            self.book = book;
+        }
+        Some(messages)
+    }
+}
+
+/// Sends what the outbox holds as fast as the subscriber takes it
+async fn run_writer(
+    mut sink: SplitSink<WebSocket, Message>,
+    outbox: Arc<Mutex<Outbox>>,
+    ready: Arc<Notify>,
+    encoding: Encoding,
+) {
+    loop {
+        let (ping, next) = {
+            let mut outbox = outbox.lock().await;
+            (std::mem::take(&mut outbox.ping), outbox.pop())
+        };
+        if ping && sink.send(Message::Ping(Default::default())).await.is_err() {
+            return;
+        }
+        match next {
+            Some(msg) => {
+                if sink.send(msg.frame(encoding)).await.is_err() {
+                    return;
+                }
+            }
+            None if ping => {} // look again, more may have come meanwhile
+            None => ready.notified().await,
+        }
+    }
+}
+
+/// Follows the engine into the subscriber's outbox while a writer drains it, so a slow
+/// socket never holds the feed up; a subscriber too far behind or not answering pings is
+/// dropped
+async fn run_feed(
+    socket: WebSocket,
+    engine: MatchingEngine,
+    heartbeat: Option<HeartbeatConfig>,
+    encoding: Encoding,
+    conflate: bool,
+) {
+    let (sink, mut incoming): (_, SplitStream<WebSocket>) = socket.split();
+    let (mut stream, snapshot) = MarketDataStream::start(engine).await;
+    let mut outbox = Outbox::new(conflate);
+    outbox.push_all(snapshot); // however deep the book, it's sent whole
+    let outbox = Arc::new(Mutex::new(outbox));
+    let ready = Arc::new(Notify::new());
+    let mut writer = tokio::spawn(run_writer(sink, outbox.clone(), ready.clone(), encoding));
+    let mut liveness = Liveness::new(heartbeat);
+
+    loop {
+        let ping_due = liveness.deadline();
+        tokio::select! {
+            received = stream.recv() => {
+                let Some(messages) = stream.messages(received).await else {
+                    break;
+                };
+                if !outbox.lock().await.push_all(messages) {
+                    break; // too slow
+                }
+                ready.notify_one();
+            }
+            msg = incoming.next() => match msg {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => break,
+                Some(Ok(_)) => liveness.seen(), // the feed is read-only, but a sign of life
+            },
+            _ = sleep_until(ping_due.unwrap_or_else(Instant::now)), if ping_due.is_some() => {
+                match liveness.poll(Instant::now()) {
+                    Some(Beat::Ping) => {
+                        outbox.lock().await.ping = true;
+                        ready.notify_one();
+                    }
+                    Some(Beat::Gone) => break,
+                    None => {}
+                }
+            }
+            _ = &mut writer => break, // the socket is gone
+        }
+    }
+    writer.abort();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn level(price: Price, quantity: Quantity, orders: usize) -> PriceLevel {
+        PriceLevel {
+            price,
+            quantity,
+            orders,
+        }
+    }
+
+    #[test]
+    fn test_book_diff() {
+        let prev = BookSnapshot {
+            bids: vec![level(101, 5, 1), level(100, 7, 2)],
+            asks: vec![level(103, 4, 1)],
+            ..BookSnapshot::default()
+        };
+        let next = BookSnapshot {
+            bids: vec![level(101, 5, 1), level(100, 3, 1)],
+            asks: vec![level(104, 2, 1)],
+            ..BookSnapshot::default()
+        };
+        let prices: Vec<(Side, Price, Quantity)> = book_diff(&prev, &next, 9)
+            .into_iter()
+            .map(|msg| match msg {
+                MarketDataMessage::Level(l) => (l.side, l.price, l.quantity),
+                MarketDataMessage::Trade(_) => panic!("no trades in a book diff"),
+            })
+            .collect();
+        assert_eq!(
+            prices,
+            vec![
+                (Side::Buy, 100, 3),
+                (Side::Sell, 104, 2),
+                (Side::Sell, 103, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outbox_conflation() {
+        let update = |price, quantity| {
+            MarketDataMessage::Level(LevelUpdate {
+                seq: quantity,
+                side: Side::Buy,
+                price,
+                quantity,
+                orders: 1,
+            })
+        };
+        let trade = MarketDataMessage::Trade(TradePrint {
+            seq: 2,
+            trade_id: 1,
+            time: 0,
+            price: 100,
+            size: 1,
+            aggressor: None,
+            trade_type: TradeType::Regular,
+        });
+        let mut outbox = Outbox::new(true);
+        assert!(outbox.push_all(vec![update(100, 5), trade, update(101, 1)]));
+        assert!(outbox.push_all(vec![update(100, 4)]));
+        // the latest state of 100 waits where its first update did
+        assert_eq!(outbox.pop(), Some(update(100, 4)));
+        assert!(outbox.push_all(vec![update(100, 3), update(101, 2)]));
+        assert_eq!(outbox.pop(), Some(trade));
+        assert_eq!(outbox.pop(), Some(update(101, 2)));
+        assert_eq!(outbox.pop(), Some(update(100, 3)));
+        assert_eq!(outbox.pop(), None);
+
+        let mut outbox = Outbox::new(false);
+        let updates = (0..=MAX_PENDING as u64).map(|n| update(100, n)).collect();
+        assert!(!outbox.push_all(updates)); // not conflating, too far behind
+    }
+
+    #[test]
+    fn test_binary_layout() {
+        let trade = MarketDataMessage::Trade(TradePrint {
+            seq: 3,
+            trade_id: 2,
+            time: 1_700_000_000_000,
+            price: 10_050,
+            size: 4,
+            aggressor: Some(Side::Sell),
+            trade_type: TradeType::Regular,
+        });
+        let buf = trade.encode();
+        assert_eq!(buf.len(), HEADER_LEN + TRADE_BLOCK_LEN as usize);
+        assert_eq!(&buf[..4], &[48, 0, 2, 0]); // block length, template id
+        assert_eq!(u64::from_le_bytes(buf[8..16].try_into().unwrap()), 3);
+        assert_eq!(u64::from_le_bytes(buf[32..40].try_into().unwrap()), 10_050);
+        assert_eq!(buf[48], 2); // sold into
+
+        let update = MarketDataMessage::Level(LevelUpdate {
+            seq: 3,
+            side: Side::Buy,
+            price: 10_000,
+            quantity: 0,
+            orders: 0,
+        });
+        let buf = update.encode();
+        assert_eq!(buf.len(), HEADER_LEN + LEVEL_BLOCK_LEN as usize);
+        assert_eq!(buf[36], 1);
+    }
+}
@@ -0,0 +1,173 @@
+use serde::Serialize;
+
+use crate::{
+    order::{Price, Quantity, Side, Timestamp},
+    orderbook::OrderBook,
+};
+
+/// Best prices and the quantity resting at them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Touch {
+    pub bid: Option<Price>,
+    pub ask: Option<Price>,
+    pub bid_size: Quantity,
+    pub ask_size: Quantity,
+}
+
+impl Touch {
+    pub fn of(book: &OrderBook) -> Self {
+        let level = |side| {
+            book.levels(side)
+                .next()
+                .map(|(price, orders)| (price, orders.iter().map(|o| o.quantity).sum()))
+        };
+        let (bid, bid_size) = level(Side::Buy).unzip();
+        let (ask, ask_size) = level(Side::Sell).unzip();
+        Touch {
+            bid,
+            ask,
+            bid_size: bid_size.unwrap_or(0),
+            ask_size: ask_size.unwrap_or(0),
+        }
+    }
+
+    /// Ask minus bid, negative while the book is crossed (during auctions)
+    fn spread(&self) -> Option<i128> {
+        Some(self.ask? as i128 - self.bid? as i128)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityStats {
+    pub touch: Touch,
+    /// Time covered by the statistics (ms)
+    pub observed_ms: u64,
+    /// Time-weighted average spread (cents) while both sides were quoted
+    pub avg_spread: Option<f64>,
+    /// Share of the observed time (percent) with both sides quoted, bid == ask, bid > ask
+    pub two_sided_pct: f64,
+    pub locked_pct: f64,
+    pub crossed_pct: f64,
+    /// Time-weighted average quantity at the best bid / ask
+    pub avg_bid_depth: f64,
+    pub avg_ask_depth: f64,
+}
+
+/// Time-weighted spread and depth statistics, fed the touch after every book update
+#[derive(Debug, Clone, Default)]
+pub struct MarketQuality {
+    touch: Touch,
+    since: Option<Timestamp>, // time of the last update, `None` before the first
+    observed: u64,
+    two_sided: u64,
+    locked: u64,
+    crossed: u64,
+    spread_time: i128,
+    bid_depth_time: u128,
+    ask_depth_time: u128,
+}
+
+impl MarketQuality {
+    /// The book changed to `touch` at `now`
+    pub fn observe(&mut self, touch: Touch, now: Timestamp) {
+        self.accrue(now);
+        self.touch = touch;
+    }
+
+    pub fn stats(&self, now: Timestamp) -> QualityStats {
+        let mut totals = self.clone();
+        totals.accrue(now);
+        let pct = |time: u64| match totals.observed {
+            0 => 0.0,
+            observed => time as f64 * 100.0 / observed as f64,
+        };
+        let average = |time: u128| match totals.observed {
+            0 => 0.0,
+            observed => time as f64 / observed as f64,
+        };
+        QualityStats {
+            touch: self.touch,
+            observed_ms: totals.observed,
+            avg_spread: (totals.two_sided > 0)
+                .then(|| totals.spread_time as f64 / totals.two_sided as f64),
+            two_sided_pct: pct(totals.two_sided),
+            locked_pct: pct(totals.locked),
+            crossed_pct: pct(totals.crossed),
+            avg_bid_depth: average(totals.bid_depth_time),
+            avg_ask_depth: average(totals.ask_depth_time),
+        }
+    }
+
+    /// Books the time since the last update to the touch that was in force
+    fn accrue(&mut self, now: Timestamp) {
+        let elapsed = self.since.map_or(0, |since| now.saturating_sub(since));
+        self.since = Some(now);
+        self.observed += elapsed;
+        self.bid_depth_time += self.touch.bid_size as u128 * elapsed as u128;
+        self.ask_depth_time += self.touch.ask_size as u128 * elapsed as u128;
+        if let Some(spread) = self.touch.spread() {
+            self.two_sided += elapsed;
+            self.spread_time += spread * elapsed as i128;
+            match spread {
+                0 => self.locked += elapsed,
+                s if s < 0 => self.crossed += elapsed,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn touch(bid: Option<Price>, ask: Option<Price>, size: Quantity) -> Touch {
+        Touch {
+            bid,
+            ask,
+            bid_size: if bid.is_some() { size } else { 0 },
+            ask_size: if ask.is_some() { size } else { 0 },
+        }
+    }
+
+    #[test]
+    fn test_time_weighting() {
+        let mut quality = MarketQuality::default();
+        quality.observe(touch(Some(100), Some(104), 10), 0);
+        quality.observe(touch(Some(100), Some(100), 10), 300); // locked
+        quality.observe(touch(Some(100), None, 20), 400); // one-sided
+
+        let stats = quality.stats(1_000);
+        assert_eq!(stats.observed_ms, 1_000);
+        assert_eq!(stats.two_sided_pct, 40.0);
+        assert_eq!(stats.locked_pct, 10.0);
+        assert_eq!(stats.crossed_pct, 0.0);
+        assert_eq!(stats.avg_spread, Some(3.0)); // 4 for 300ms, 0 for 100ms
+        assert_eq!(stats.avg_bid_depth, (10.0 * 400.0 + 20.0 * 600.0) / 1_000.0);
+        assert_eq!(stats.avg_ask_depth, 4.0);
+        assert_eq!(stats.touch.bid_size, 20);
+    }
+
+    #[test]
+    fn test_touch_of_book() {
+        use crate::order::{Order, OrderType};
+        let mut book = OrderBook::new();
+        for (id, side, price) in [
+            ("1", Side::Buy, 99),
+            ("2", Side::Buy, 99),
+            ("3", Side::Sell, 101),
+        ] {
+            book.add_order(Order::new(
+                id.to_string(),
+                side,
+                OrderType::Limit,
+                5,
+                price,
+                1,
+            ));
+        }
+        let touch = Touch::of(&book);
+        assert_eq!((touch.bid, touch.bid_size), (Some(99), 10));
+        assert_eq!((touch.ask, touch.ask_size), (Some(101), 5));
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::order::Price;
+
+pub const MARK_PRICE_METHOD_ENV: &str = "OME_MARK_PRICE_METHOD"; // `last_trade` | `mid` | `index`
+
+/// Which price marks the book; the other one is the fallback when it's missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkPriceMethod {
+    #[default]
+    LastTrade,
+    Mid,
+    /// The external index price, falling back to the last trade and mid while there is none
+    Index,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkPriceSource {
+    LastTrade,
+    Mid,
+    Index,
+    /// Admin-pinned reference price, used before the book has any price of its own
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MarkPrice {
+    pub price: Price,
+    pub source: MarkPriceSource,
+}
+
+impl MarkPriceMethod {
+    pub fn from_env() -> Self {
+        match std::env::var(MARK_PRICE_METHOD_ENV).as_deref() {
+            Ok("mid") => MarkPriceMethod::Mid,
+            Ok("index") => MarkPriceMethod::Index,
+            _ => MarkPriceMethod::default(),
+        }
+    }
+
+    /// Applies the fallback rules: preferred source, then the other one, then the reference.
+    /// The index only counts for [`MarkPriceMethod::Index`].
+    pub fn mark(
+        &self,
+        mid: Option<Price>,
+        last_trade: Option<Price>,
+        index: Option<Price>,
+        reference: Option<Price>,
+    ) -> Option<MarkPrice> {
+        let mid = mid.map(|price| MarkPrice {
+            price,
+            source: MarkPriceSource::Mid,
+        });
+        let last_trade = last_trade.map(|price| MarkPrice {
+            price,
+            source: MarkPriceSource::LastTrade,
+        });
+        let index = index.map(|price| MarkPrice {
+            price,
+            source: MarkPriceSource::Index,
+        });
+        let reference = reference.map(|price| MarkPrice {
+            price,
+            source: MarkPriceSource::Reference,
+        });
+        match self {
+            MarkPriceMethod::LastTrade => last_trade.or(mid),
+            MarkPriceMethod::Mid => mid.or(last_trade),
+            MarkPriceMethod::Index => index.or(last_trade).or(mid),
+        }
+        .or(reference)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fallback_rules() {
+        let last = MarkPriceMethod::LastTrade;
+        assert_eq!(
+            last.mark(Some(100), Some(105), Some(110), None)
+                .unwrap()
+                .source,
+            MarkPriceSource::LastTrade
+        );
+        assert_eq!(
+            last.mark(Some(100), None, None, Some(90)).unwrap(),
+            MarkPrice {
+                price: 100,
+                source: MarkPriceSource::Mid
+            }
+        );
+
+        let mid = MarkPriceMethod::Mid;
+        assert_eq!(
+            mid.mark(Some(100), Some(105), None, None).unwrap().price,
+            100
+        );
+        assert_eq!(mid.mark(None, Some(105), None, None).unwrap().price, 105);
+        assert_eq!(
+            mid.mark(None, None, None, Some(90)).unwrap().source,
+            MarkPriceSource::Reference
+        );
+        assert_eq!(mid.mark(None, None, None, None), None);
+
+        let index = MarkPriceMethod::Index;
+        assert_eq!(
+            index.mark(Some(100), Some(105), Some(110), None).unwrap(),
+            MarkPrice {
+                price: 110,
+                source: MarkPriceSource::Index
+            }
+        );
+        assert_eq!(
+            index.mark(Some(100), Some(105), None, None).unwrap().price,
+            105
+        );
+    }
+}
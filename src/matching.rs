@@ -0,0 +1,326 @@
+use std::{fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{Clock, TickClock},
+    ids::{IdGenerator, SequentialIds},
+    matchingpolicy::MatchingPolicy,
+    order::{Order, OrderId, OrderType, Price, Quantity, Side, Trade, TradeId},
+    orderbook::OrderBook,
+};
+
+/// Matches `order` against the opposite side of `book` level by level, letting `policy` split
+/// each level and `execute` turn every fill into a trade; a limit order's remainder rests.
+/// This is the matching step of the server's engine and of [`Matcher`].
+pub fn match_order(
+    book: &mut OrderBook,
+    mut order: Order,
+    policy: &dyn MatchingPolicy,
+    mut execute: impl FnMut(&Order, &Order, Price, Quantity) -> Trade,
+) -> Vec<Trade> {
+    let mut new_trades = Vec::<Trade>::new();
+
+    loop {
+        let best_opposing = match order.side {
+            Side::Buy => book.peek_best_sell(),
+            Side::Sell => book.peek_best_buy(),
+        };
+
+        let best_opposing = match best_opposing {
+            Some(o) => o,
+            None => break,
+        };
+
+        if !order.can_match(&best_opposing) {
+            break;
+        }
+
+        let execution_price = match (order.order_type, best_opposing.order_type) {
+            (OrderType::Market, _) => best_opposing.price,
+            (_, OrderType::Market) => order.price, // w/ assumption that market orders persist
+            // in the orderbook (this is false, but
+            // compiler complains abt exhaustion)
+            (OrderType::Limit, OrderType::Limit) => best_opposing.price,
+        };
+
+        let level = book.take_level(best_opposing.side, best_opposing.price);
+        let fills = policy.allocate(&level, order.quantity);
+        let mut left = Vec::new();
+
+        for (mut opposing_order, trade_quantity) in level.into_iter().zip(fills) {
+            if trade_quantity > 0 {
+                new_trades.push(execute(
+                    &order,
+                    &opposing_order,
+                    execution_price,
+                    trade_quantity,
+                ));
+                order.fill(trade_quantity);
+                opposing_order.fill(trade_quantity);
+            }
+            if opposing_order.quantity > 0 {
+                left.push(opposing_order);
+            }
+        }
+        book.restore_level(best_opposing.side, best_opposing.price, left);
+
+        if order.quantity == 0 {
+            break;
+        }
+    }
+
+    if order.quantity > 0 && order.order_type == OrderType::Limit {
+        book.add_order(order);
+    }
+
+    new_trades
+}
+
+/// Post-trade hook, told about every execution with the state of both orders right after it:
+/// `quantity` is what is left of them, `filled_quantity` what executed so far. It runs inside
+/// matching, under the book, so it should hand slow work off rather than do it.
+pub trait TradeListener: Send + Sync {
+    fn on_trade(&self, trade: &Trade, buy: &Order, sell: &Order);
+}
+
+/// Tells `listeners` about `trade` between `buy` and `sell`, given as they were before it
+pub fn notify_trade(
+    listeners: &[Arc<dyn TradeListener>],
+    trade: &Trade,
+    buy: &Order,
+    sell: &Order,
+) {
+    if listeners.is_empty() {
+        return;
+    }
+    let (mut buy, mut sell) = (buy.clone(), sell.clone());
+    buy.fill(trade.quantity);
+    sell.fill(trade.quantity);
+    for listener in listeners {
+        listener.on_trade(trade, &buy, &sell);
+    }
+}
+
+/// Why [`Matcher::submit`] refused an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidOrder {
+    ZeroQuantity,
+    MissingPrice,
+}
+
+impl fmt::Display for InvalidOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidOrder::ZeroQuantity => write!(f, "quantity must be greater than 0"),
+            InvalidOrder::MissingPrice => write!(f, "limit orders need a price above 0"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidOrder {}
+
+/// An order as the bindings receive it, the same shape as the server's `POST /orders` body
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderRequest {
+    pub side: Side,
+    pub order_type: OrderType,
+    /// Ignored for market orders
+    #[serde(default)]
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+/// Resting orders of both sides, each lowest price first
+#[derive(Debug, Clone, Serialize)]
+pub struct BookView {
+    pub bids: Vec<Order>,
+    pub asks: Vec<Order>,
+}
+
+/// Outcome of [`Matcher::submit`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Submitted {
+    pub id: OrderId,
+    pub trades: Vec<Trade>,
+}
+
+/// A single book with its own order and trade numbering: the matching core without the server's
+/// runtime, risk checks or persistence, for embedding (wasm, language bindings, simulators).
+/// Time priority follows submission order.
+pub struct Matcher {
+    book: OrderBook,
+    policy: Arc<dyn MatchingPolicy>,
+    ids: Arc<dyn IdGenerator>,
+    next_trade_id: TradeId,
+    clock: Arc<dyn Clock>,
+    listeners: Vec<Arc<dyn TradeListener>>,
+}
+
+impl Matcher {
+    /// Numbers orders `1`, `2`, ... and stamps them with a [`TickClock`]
+    pub fn new(policy: Arc<dyn MatchingPolicy>) -> Self {
+        Matcher {
+            book: OrderBook::new(),
+            policy,
+            ids: Arc::new(SequentialIds::new()),
+            next_trade_id: 1,
+            clock: Arc::new(TickClock::default()),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Stamps orders and trades with `clock` instead, read once per submission
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Takes the ids of new orders from `ids` instead
+    pub fn with_ids(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Calls `listener` after every execution from now on
+    pub fn add_listener(&mut self, listener: Arc<dyn TradeListener>) {
+        self.listeners.push(listener);
+    }
+
+    pub fn submit(
+        &mut self,
+        side: Side,
+        order_type: OrderType,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<Submitted, InvalidOrder> {
+        if quantity == 0 {
+            return Err(InvalidOrder::ZeroQuantity);
+        }
+        if order_type == OrderType::Limit && price == 0 {
+            return Err(InvalidOrder::MissingPrice);
+        }
+        let id = self.ids.next_id();
+        let now = self.clock.now_ns();
+        let price = match order_type {
+            OrderType::Limit => price,
+            OrderType::Market => 0,
+        };
+        let order = Order::new(id.clone(), side, order_type, quantity, price, now);
+
+        let next_trade_id = &mut self.next_trade_id;
+        let listeners = &self.listeners;
+        let trades = match_order(
+            &mut self.book,
+            order,
+            self.policy.as_ref(),
+            |order, resting, price, quantity| {
+                let (buy, sell) = match order.side {
+                    Side::Buy => (order, resting),
+                    Side::Sell => (resting, order),
+                };
+                let trade = Trade::new(
+                    *next_trade_id,
+                    buy.id.clone(),
+                    sell.id.clone(),
+                    price,
+                    quantity,
+                )
+                .with_aggressor(order.side)
+                .at(now);
+                *next_trade_id += 1;
+                notify_trade(listeners, &trade, buy, sell);
+                trade
+            },
+        );
+        Ok(Submitted { id, trades })
+    }
+
+    pub fn submit_request(&mut self, order: OrderRequest) -> Result<Submitted, InvalidOrder> {
+        self.submit(order.side, order.order_type, order.price, order.quantity)
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        self.book.cancel_order(id.to_string())
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn view(&self) -> BookView {
+        BookView {
+            bids: self.book.get_buy_orders(),
+            asks: self.book.get_sell_orders(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matchingpolicy::PriceTime;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_matcher() {
+        let mut matcher = Matcher::new(Arc::new(PriceTime));
+        let ask = matcher
+            .submit(Side::Sell, OrderType::Limit, 100, 5)
+            .unwrap();
+        assert!(ask.trades.is_empty());
+        matcher
+            .submit(Side::Sell, OrderType::Limit, 101, 5)
+            .unwrap();
+
+        let buy = matcher.submit(Side::Buy, OrderType::Market, 0, 7).unwrap();
+        assert_eq!(buy.trades.len(), 2);
+        assert_eq!(buy.trades[0].sell_order_id, ask.id);
+        assert_eq!((buy.trades[0].price, buy.trades[0].quantity), (100, 5));
+        assert_eq!((buy.trades[1].id, buy.trades[1].price), (2, 101));
+        assert_eq!(buy.trades[1].aggressor, Some(Side::Buy));
+        // market orders never rest
+        assert!(matcher.book().get_buy_orders().is_empty());
+        assert_eq!(matcher.book().get_sell_orders()[0].quantity, 3);
+
+        assert_eq!(
+            matcher
+                .submit(Side::Buy, OrderType::Limit, 0, 1)
+                .unwrap_err(),
+            InvalidOrder::MissingPrice
+        );
+        assert_eq!(
+            matcher
+                .submit(Side::Buy, OrderType::Limit, 10, 0)
+                .unwrap_err(),
+            InvalidOrder::ZeroQuantity
+        );
+        assert!(matcher.cancel("2"));
+        assert!(!matcher.cancel("2"));
+    }
+
+    #[test]
+    fn test_trade_listener() {
+        #[derive(Default)]
+        struct Fills(Mutex<Vec<(TradeId, Quantity, Quantity)>>);
+        impl TradeListener for Fills {
+            fn on_trade(&self, trade: &Trade, buy: &Order, sell: &Order) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((trade.id, buy.quantity, sell.filled_quantity));
+            }
+        }
+
+        let fills = Arc::new(Fills::default());
+        let mut matcher = Matcher::new(Arc::new(PriceTime));
+        matcher.add_listener(fills.clone());
+        matcher
+            .submit(Side::Sell, OrderType::Limit, 100, 5)
+            .unwrap();
+        matcher.submit(Side::Buy, OrderType::Limit, 100, 3).unwrap();
+        matcher.submit(Side::Buy, OrderType::Limit, 100, 4).unwrap();
+        // what's left of the buys, what the sell filled so far
+        assert_eq!(*fills.0.lock().unwrap(), vec![(1, 0, 3), (2, 2, 5)]);
+    }
+}
@@ -1,243 +1,4435 @@
 use crate::{
-    order::{Order, OrderId, OrderType, Side, Trade},
+    auction::{self, Equilibrium},
+    audit::{AuditEntry, AuditEvent, AuditLog},
+    batching::SubmissionQueue,
+    bookview::BookSnapshot,
+    circuitbreaker::{BreakerAction, BreakerTrip, CircuitBreaker, CircuitBreakerConfig},
+    clock::Clock,
+    eventbus::EventBus,
+    fees::{FeeLedger, FeeSchedule},
+    ids::IdGenerator,
+    instrument::InstrumentConfig,
+    loadshed::WaitGauge,
+    marketquality::{MarketQuality, QualityStats, Touch},
+    markprice::{MarkPrice, MarkPriceMethod},
+    matching::{self, TradeListener},
+    matchingpolicy::MatchingPolicy,
+    mmprotection::{ProtectionBook, QuoteProtection},
+    order::{
+        Order, OrderId, OrderStatus, OrderType, ParticipantId, Price, Quantity, RejectReason, Side,
+        TimeInForce, Timestamp, Trade, TradeId, TradeStatus, TradeType, TriggerSource,
+    },
     orderbook::OrderBook,
+    orderentry::{AckWaiters, OrderAck},
+    otr::{OtrConfig, OtrStanding, OtrTracker},
+    positions::{Position, PositionBook},
+    quotes::{Quote, QuoteAck},
+    replication::ReplicationError,
+    retention::RetentionStats,
+    rfq::{Rfq, RfqBook, RfqError, RfqId, RfqResponse},
+    risk::{
+        BandLimits, BookContext, Exposure, FatFingerCheck, ParticipantLimits, PriceBands,
+        RiskCheck, RiskLimits,
+    },
+    runtimeconfig::{OrderSizeLimits, RuntimeConfig},
+    schedule::{MarketPhase, OffHoursPolicy, PhasePolicies},
+    scheduledorders::ScheduledOrders,
+    settlement::{
+        BatchStatus, Settlement, SettlementBatch, SettlementError, SettlementInstruction,
+    },
+    state::{EngineState, STATE_VERSION, SpilledTrades},
+    stats::{EngineStats, LatencyHistogram, MemoryEstimate, OrderCounts},
+    tradeexport::{self, Execution},
+    tradehistory::OwnerTradeIndex,
+    tradespill::{self, TradeSpill},
+    volume::{RollingVolume, VolumeStats},
+    volumeprofile::{self, PriceVolume},
+    wallet::{self, AccountBalances, Asset, Wallets},
 };
 
-use std::collections::VecDeque;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, broadcast, oneshot};
 
-pub const TRADE_POOL_SIZE: usize = 500; // defines the size of MatchingEngine::new().trades field
+pub const TRADE_POOL_SIZE: usize = 500; // default size of the in-memory MatchingEngine::trades pool
+
+/// Halted books reject new orders; cancels are only accepted if the halt allows them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state")]
+pub enum TradingState {
+    Open,
+    Halted {
+        allow_cancels: bool,
+    },
+    /// Mirrors a primary's events; orders and cancels are refused until failover promotes it
+    Standby,
+}
 
 pub struct MatchingEngine {
     order_book: Arc<RwLock<OrderBook>>,
+    book_view: Arc<ArcSwap<BookSnapshot>>, // what readers see of the book, without the lock
+    book_view_stale: Arc<AtomicBool>,      // the book changed since book_view was published
     pub trades: Arc<RwLock<VecDeque<Trade>>>,
+    trade_capacity: Arc<AtomicUsize>,
+    trade_spill: Arc<RwLock<Option<TradeSpill>>>, // where trades evicted from the pool go
+    retention_stats: Arc<RwLock<RetentionStats>>,
+    trading_state: Arc<RwLock<TradingState>>,
+    replicated_trading: Arc<RwLock<TradingState>>, // a standby's copy of the primary's
+    next_trade_id: Arc<AtomicU64>,
+    started: Instant,
+    blocked_participants: Arc<RwLock<HashSet<ParticipantId>>>,
+    market_phase: Arc<RwLock<MarketPhase>>,
+    phase_policies: Arc<RwLock<PhasePolicies>>,
+    queued_orders: Arc<RwLock<VecDeque<Order>>>, // accepted outside continuous trading
+    scheduled_orders: Arc<RwLock<ScheduledOrders>>,
+    untriggered_orders: Arc<RwLock<Vec<Order>>>, // stops and market-if-touched, in arrival order
+    quotes: Arc<RwLock<BTreeMap<ParticipantId, Vec<OrderId>>>>, // latest quote of each market maker
+    quote_protection: Arc<RwLock<ProtectionBook>>,
+    rfqs: Arc<RwLock<RfqBook>>, // open for responses
+    otr: Arc<RwLock<OtrTracker>>,
+    submission_queue: Arc<RwLock<Option<SubmissionQueue>>>, // in front of the book for POST /orders
+    circuit_breaker: Arc<RwLock<CircuitBreaker>>,
+    price_bands: Arc<RwLock<PriceBands>>,
+    reference_price: Arc<RwLock<Option<Price>>>, // set by an admin, else the last trade price
+    index_price: Arc<RwLock<Option<Price>>>,     // external, fed by the index adapter
+    fat_finger: Arc<RwLock<FatFingerCheck>>,
+    mark_price_method: Arc<RwLock<MarkPriceMethod>>,
+    risk_limits: Arc<RwLock<ParticipantLimits>>,
+    positions: Arc<RwLock<PositionBook>>,
+    wallets: Arc<RwLock<Wallets>>,
+    settlement: Arc<RwLock<Settlement>>,
+    fee_schedule: Arc<RwLock<FeeSchedule>>,
+    fees: Arc<RwLock<FeeLedger>>,
+    owner_trades: Arc<RwLock<OwnerTradeIndex>>,
+    audit_log: Arc<RwLock<AuditLog>>,
+    event_bus: EventBus, // every audit entry, as it's appended
+    matching_policy: Arc<RwLock<Arc<dyn MatchingPolicy>>>,
+    risk_checks: Arc<Vec<Arc<dyn RiskCheck>>>, // the deployment's, after the built-in ones
+    trade_listeners: Arc<Vec<Arc<dyn TradeListener>>>,
+    rolling_volume: Arc<RwLock<RollingVolume>>,
+    market_quality: Arc<RwLock<MarketQuality>>,
+    clock: Arc<dyn Clock>, // stamps orders without a timestamp, trades and events
+    ids: Arc<dyn IdGenerator>, // names the orders entered through the server
+    order_counts: Arc<RwLock<OrderCounts>>,
+    latency: Arc<RwLock<LatencyHistogram>>, // of submit_in
+    waiting: Arc<WaitGauge>, // orders entered through submit_queued, until they have a result
+    acks: Arc<AckWaiters>,   // orders entered through orderentry, until they're acked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeAdjustmentError {
+    NotFound, // unknown id, or already evicted from the trade pool
+    AlreadyBusted,
 }
 
 impl MatchingEngine {
     pub fn new() -> Self {
+        MatchingEngine::with_instrument(InstrumentConfig::default())
+    }
+
+    /// Engine whose book matches by `config`'s policy and tie break
+    pub fn with_instrument(config: InstrumentConfig) -> Self {
         MatchingEngine {
-            order_book: Arc::new(RwLock::new(OrderBook::new())),
+            order_book: Arc::new(RwLock::new(
+                OrderBook::new().with_tie_break(config.tie_break),
+            )),
+            book_view: Arc::new(ArcSwap::from_pointee(BookSnapshot::default())),
+            book_view_stale: Arc::new(AtomicBool::new(false)),
             trades: Arc::new(RwLock::new(VecDeque::<Trade>::with_capacity(
                 TRADE_POOL_SIZE,
             ))),
+            trade_capacity: Arc::new(AtomicUsize::new(TRADE_POOL_SIZE)),
+            trade_spill: Arc::new(RwLock::new(None)),
+            retention_stats: Arc::new(RwLock::new(RetentionStats::default())),
+            trading_state: Arc::new(RwLock::new(TradingState::Open)),
+            replicated_trading: Arc::new(RwLock::new(TradingState::Open)),
+            next_trade_id: Arc::new(AtomicU64::new(1)),
+            started: Instant::now(),
+            blocked_participants: Arc::new(RwLock::new(HashSet::new())),
+            market_phase: Arc::new(RwLock::new(MarketPhase::Continuous)),
+            phase_policies: Arc::new(RwLock::new(PhasePolicies::default())),
+            queued_orders: Arc::new(RwLock::new(VecDeque::new())),
+            scheduled_orders: Arc::new(RwLock::new(ScheduledOrders::default())),
+            untriggered_orders: Arc::new(RwLock::new(Vec::new())),
+            quotes: Arc::new(RwLock::new(BTreeMap::new())),
+            quote_protection: Arc::new(RwLock::new(ProtectionBook::default())),
+            rfqs: Arc::new(RwLock::new(RfqBook::default())),
+            otr: Arc::new(RwLock::new(OtrTracker::default())),
+            submission_queue: Arc::new(RwLock::new(None)),
+            circuit_breaker: Arc::new(RwLock::new(CircuitBreaker::new(
+                CircuitBreakerConfig::default(),
+            ))),
+            price_bands: Arc::new(RwLock::new(PriceBands::default())),
+            reference_price: Arc::new(RwLock::new(None)),
+            index_price: Arc::new(RwLock::new(None)),
+            fat_finger: Arc::new(RwLock::new(FatFingerCheck::default())),
+            mark_price_method: Arc::new(RwLock::new(MarkPriceMethod::default())),
+            risk_limits: Arc::new(RwLock::new(ParticipantLimits::default())),
+            positions: Arc::new(RwLock::new(PositionBook::new())),
+            wallets: Arc::new(RwLock::new(Wallets::default())),
+            settlement: Arc::new(RwLock::new(Settlement::new(config.clock.now_ms()))),
+            fee_schedule: Arc::new(RwLock::new(FeeSchedule::default())),
+            fees: Arc::new(RwLock::new(FeeLedger::default())),
+            owner_trades: Arc::new(RwLock::new(OwnerTradeIndex::default())),
+            audit_log: Arc::new(RwLock::new(AuditLog::default())),
+            event_bus: EventBus::default(),
+            matching_policy: Arc::new(RwLock::new(config.policy)),
+            risk_checks: Arc::new(config.risk_checks),
+            trade_listeners: Arc::new(config.trade_listeners),
+            rolling_volume: Arc::new(RwLock::new(RollingVolume::default())),
+            market_quality: Arc::new(RwLock::new(MarketQuality::default())),
+            clock: config.clock.clone(),
+            ids: config.ids,
+            order_counts: Arc::new(RwLock::new(OrderCounts::default())),
+            latency: Arc::new(RwLock::new(LatencyHistogram::default())),
+            waiting: Arc::new(WaitGauge::default()),
+            acks: Arc::new(AckWaiters::default()),
+        }
+    }
+
+    /// The time as the engine sees it
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Timestamp of an order entered now (unix ns). Callers stamp orders with it before they
+    /// go through the consensus log, so every node sees the same time; the engine stamps
+    /// orders that come without one (`0`) on entry.
+    pub fn order_timestamp(&self) -> Timestamp {
+        self.clock.now_ns()
+    }
+
+    /// Id of a new order, from the instrument's generator. Like the timestamp, it's assigned
+    /// before the order goes through the consensus log.
+    pub fn next_order_id(&self) -> OrderId {
+        self.ids.next_id()
+    }
+
+    pub async fn submit_order(&mut self, order: Order) -> Result<Vec<Trade>, RejectReason> {
+        let result = self.enter(order).await;
+        self.fire_triggers().await;
+        result
+    }
+
+    /// Submits `order` through the submission queue when the engine has one, like
+    /// [`MatchingEngine::submit_order`] otherwise
+    pub async fn submit_queued(&mut self, order: Order) -> Result<Vec<Trade>, RejectReason> {
+        let _waiting = WaitGauge::enter(&self.waiting);
+        let queue = self.submission_queue.read().await.clone();
+        match queue {
+            Some(queue) => queue.submit(order).await,
+            None => self.submit_order(order).await,
         }
     }
 
-    pub async fn submit_order(&mut self, mut order: Order) -> Vec<Trade> {
-        let mut new_trades = Vec::<Trade>::new();
+    /// How long the oldest order entered with [`MatchingEngine::submit_queued`] that is still
+    /// waiting for its result has waited, for the book lock or in the submission queue
+    pub fn queue_latency(&self) -> Duration {
+        self.waiting.oldest()
+    }
+
+    /// Sends orders from [`MatchingEngine::submit_queued`] to `queue`'s batcher
+    pub async fn set_submission_queue(&self, queue: SubmissionQueue) {
+        *self.submission_queue.write().await = Some(queue);
+    }
+
+    /// Orders waiting in the submission queue and its size, `None` without a queue
+    pub async fn submission_queue_depth(&self) -> Option<(usize, usize)> {
+        self.submission_queue
+            .read()
+            .await
+            .as_ref()
+            .map(SubmissionQueue::depth)
+    }
+
+    /// Submits `orders` in turn under a single acquisition of the book lock, auditing
+    /// rejections, and fires the triggers they touched once at the end
+    pub async fn submit_batch(
+        &mut self,
+        orders: Vec<Order>,
+    ) -> Vec<Result<Vec<Trade>, RejectReason>> {
         let mut order_book = self.order_book.write().await;
+        let mut results = Vec::with_capacity(orders.len());
+        for order in orders {
+            let (order_id, owner) = (order.id.clone(), order.owner.clone());
+            let result = self.submit_in(&mut order_book, order).await;
+            if let Err(reason) = &result {
+                self.audit(AuditEvent::OrderRejected {
+                    order_id,
+                    owner,
+                    reason: *reason,
+                })
+                .await;
+            }
+            results.push(result);
+        }
+        drop(order_book);
+        self.fire_triggers().await;
+        results
+    }
 
-        loop {
-            let best_opposing = match order.side {
-                Side::Buy => order_book.peek_best_sell(),
-                Side::Sell => order_book.peek_best_buy(),
-            };
+    /// Submits `order`, auditing a rejection
+    async fn enter(&self, order: Order) -> Result<Vec<Trade>, RejectReason> {
+        let (order_id, owner) = (order.id.clone(), order.owner.clone());
+        let result = self.submit(order).await;
+        if let Err(reason) = &result {
+            self.audit(AuditEvent::OrderRejected {
+                order_id,
+                owner,
+                reason: *reason,
+            })
+            .await;
+        }
+        result
+    }
 
-            let best_opposing = match best_opposing {
-                Some(o) => o,
-                None => break,
-            };
+    /// Enters every untriggered order the market has touched, until the trades of those stop
+    /// touching further triggers
+    async fn fire_triggers(&self) {
+        loop {
+            let fired = self.take_triggered().await;
+            if fired.is_empty() {
+                return;
+            }
+            for order in fired {
+                let _ = self.enter(order).await; // a rejection is audited, the order is done
+            }
+        }
+    }
 
-            if !order.can_match(&best_opposing) {
-                break;
+    /// Takes out the untriggered orders whose trigger price was touched, their triggers cleared,
+    /// after moving trailing stops along with their reference. Triggers only fire (and trail)
+    /// in continuous trading on an open book.
+    async fn take_triggered(&self) -> Vec<Order> {
+        let order_book = self.order_book.write().await;
+        if *self.trading_state.read().await != TradingState::Open
+            || *self.market_phase.read().await != MarketPhase::Continuous
+        {
+            return Vec::new();
+        }
+        let mut untriggered = self.untriggered_orders.write().await;
+        if untriggered.is_empty() {
+            return Vec::new();
+        }
+        let last = self.last_trade_price().await;
+        let mark = self.mark_price_in(&order_book).await.map(|mark| mark.price);
+        let reference = |order: &Order| match order.trigger.map(|trigger| trigger.source)? {
+            TriggerSource::LastTrade => last,
+            TriggerSource::Mark => mark,
+            TriggerSource::OppositeBbo => match order.side {
+                Side::Buy => order_book.best_ask(),
+                Side::Sell => order_book.best_bid(),
+            },
+        };
+        let mut moved = Vec::new();
+        for order in untriggered.iter_mut() {
+            if let Some(price) = reference(order)
+                && let Some(trigger) = order.trigger.as_mut()
+                && trigger.trail(order.side, price)
+            {
+                moved.push(AuditEvent::TriggerMoved {
+                    order_id: order.id.clone(),
+                    owner: order.owner.clone(),
+                    price: trigger.price,
+                });
             }
+        }
+        let touched = |order: &Order| match (order.trigger, reference(order)) {
+            (Some(trigger), Some(price)) => trigger.is_touched(order.side, price),
+            _ => false,
+        };
+        let (fired, waiting): (Vec<Order>, Vec<Order>) = untriggered.drain(..).partition(touched);
+        *untriggered = waiting;
+        drop(untriggered);
 
-            let execution_price = match (order.order_type, best_opposing.order_type) {
-                (OrderType::Market, _) => best_opposing.price,
-                (_, OrderType::Market) => order.price, // w/ assumption that market orders persist
-                // in the orderbook (this is false, but
-                // compiler complains abt exhaustion)
-                (OrderType::Limit, OrderType::Limit) => best_opposing.price,
+        for event in moved {
+            self.audit(event).await; // standbys follow the trailing stops
+        }
+        let mut entered = Vec::with_capacity(fired.len());
+        for order in fired {
+            let order = Order {
+                trigger: None,
+                ..order
             };
+            self.audit(AuditEvent::OrderTriggered {
+                order: order.clone(),
+            })
+            .await;
+            entered.push(order);
+        }
+        entered
+    }
 
-            let trade_quantity = order.quantity.min(best_opposing.quantity);
+    /// Holds `order` back until `activate_at` (unix ms). It's released by
+    /// [`MatchingEngine::activate_scheduled`] and goes through the checks of a new order then;
+    /// here it's only refused on a standby or for a blocked participant.
+    pub async fn schedule_order(
+        &mut self,
+        order: Order,
+        activate_at: Timestamp,
+    ) -> Result<(), RejectReason> {
+        let (order_id, owner) = (order.id.clone(), order.owner.clone());
+        let result = self.schedule(order, activate_at).await;
+        if let Err(reason) = &result {
+            self.audit(AuditEvent::OrderRejected {
+                order_id,
+                owner,
+                reason: *reason,
+            })
+            .await;
+        }
+        result
+    }
 
-            let mut opposing_order = match order.side {
-                Side::Buy => order_book.pop_best_sell().unwrap(),
-                Side::Sell => order_book.pop_best_buy().unwrap(),
-            };
+    async fn schedule(&self, mut order: Order, activate_at: Timestamp) -> Result<(), RejectReason> {
+        if order.timestamp == 0 {
+            order.timestamp = self.order_timestamp();
+        }
+        let _order_book = self.order_book.write().await;
+        if *self.trading_state.read().await == TradingState::Standby {
+            return Err(RejectReason::Standby);
+        }
+        if let Some(owner) = &order.owner
+            && self.blocked_participants.read().await.contains(owner)
+        {
+            return Err(RejectReason::ParticipantBlocked);
+        }
+        self.audit(AuditEvent::OrderScheduled {
+            order: order.clone(),
+            activate_at,
+        })
+        .await;
+        self.scheduled_orders
+            .write()
+            .await
+            .insert(activate_at, order);
+        Ok(())
+    }
 
-            let trade = match order.side {
-                Side::Buy => Trade::new(
-                    order.id.clone(),
-                    opposing_order.id.clone(),
-                    execution_price,
-                    trade_quantity,
-                ),
-                Side::Sell => Trade::new(
-                    opposing_order.id.clone(),
-                    order.id.clone(),
-                    execution_price,
-                    trade_quantity,
-                ),
-            };
+    /// Scheduled orders due at `now` (unix ms); none while the book isn't open, they wait for
+    /// trading to resume
+    pub async fn due_orders(&self, now: Timestamp) -> Vec<OrderId> {
+        if *self.trading_state.read().await != TradingState::Open {
+            return Vec::new();
+        }
+        self.scheduled_orders.read().await.due(now)
+    }
 
-            new_trades.push(trade);
-            order.quantity -= trade_quantity;
-            opposing_order.quantity -= trade_quantity;
+    /// Submits the scheduled order `order_id` to matching. Nothing happens if it was canceled
+    /// meanwhile or the book isn't open, in which case it stays scheduled.
+    pub async fn activate_scheduled(
+        &mut self,
+        order_id: &OrderId,
+    ) -> Result<Vec<Trade>, RejectReason> {
+        if *self.trading_state.read().await != TradingState::Open {
+            return Ok(Vec::new());
+        }
+        let Some(order) = self.scheduled_orders.write().await.remove(order_id) else {
+            return Ok(Vec::new());
+        };
+        self.submit_order(order).await
+    }
 
-            if opposing_order.quantity > 0 {
-                order_book.add_order(opposing_order);
-            }
+    /// Enters `quote` in place of its owner's previous one, all under one book lock: the old
+    /// orders are pulled if still working, then the new ones go in like new orders, each
+    /// accepted or rejected on its own. The whole quote is refused on a book that isn't open,
+    /// for a blocked participant or when it's crossed, and the previous one then stands.
+    pub async fn submit_quote(&mut self, quote: Quote) -> Result<QuoteAck, RejectReason> {
+        let ack = self.enter_quote(quote).await;
+        self.fire_triggers().await;
+        ack
+    }
 
-            if order.quantity == 0 {
-                break;
+    async fn enter_quote(&self, quote: Quote) -> Result<QuoteAck, RejectReason> {
+        let mut order_book = self.order_book.write().await;
+        match *self.trading_state.read().await {
+            TradingState::Open => {}
+            TradingState::Halted { .. } => return Err(RejectReason::Halted),
+            TradingState::Standby => return Err(RejectReason::Standby),
+        }
+        if self
+            .blocked_participants
+            .read()
+            .await
+            .contains(&quote.owner)
+        {
+            return Err(RejectReason::ParticipantBlocked);
+        }
+        if quote.is_crossed() {
+            return Err(RejectReason::CrossedQuote);
+        }
+
+        let previous = self.quotes.write().await.remove(&quote.owner);
+        for order_id in previous.into_iter().flatten() {
+            self.cancel_in(&mut order_book, order_id, Some(&quote.owner))
+                .await?;
+        }
+
+        let mut ack = QuoteAck::with_capacity(quote.orders.len());
+        let mut ids = Vec::new();
+        for order in quote.orders {
+            let (order_id, owner) = (order.id.clone(), order.owner.clone());
+            let entered = self.submit_in(&mut order_book, order).await;
+            match &entered {
+                Ok(_) => ids.push(order_id), // one that filled is simply not found next time
+                Err(reason) => {
+                    self.audit(AuditEvent::OrderRejected {
+                        order_id,
+                        owner,
+                        reason: *reason,
+                    })
+                    .await
+                }
             }
+            ack.push(entered);
         }
 
-        if order.quantity > 0 && order.order_type == OrderType::Limit {
-            order_book.add_order(order);
+        self.audit(AuditEvent::QuoteUpdated {
+            owner: Some(quote.owner.clone()),
+            orders: ids.clone(),
+        })
+        .await;
+        if !ids.is_empty() {
+            self.quotes.write().await.insert(quote.owner, ids);
+        }
+        Ok(ack)
+    }
+
+    /// Opens `rfq` to responses from its makers until its `expires_at`
+    pub async fn request_quote(&mut self, rfq: Rfq) -> Result<(), RfqError> {
+        let _order_book = self.order_book.write().await;
+        self.check_entry(rfq.taker.as_deref()).await?;
+        self.audit(AuditEvent::RfqCreated { rfq: rfq.clone() })
+            .await;
+        self.rfqs.write().await.insert(rfq);
+        Ok(())
+    }
+
+    /// Records a maker's `response` to the RFQ `rfq_id` in place of their previous one.
+    /// Responses are taken until the RFQ's window closes at the response's timestamp.
+    pub async fn respond_rfq(
+        &mut self,
+        rfq_id: &str,
+        response: RfqResponse,
+    ) -> Result<(), RfqError> {
+        let _order_book = self.order_book.write().await;
+        self.check_entry(response.maker.as_deref()).await?;
+        let mut rfqs = self.rfqs.write().await;
+        let rfq = rfqs
+            .get_mut(rfq_id)
+            .filter(|rfq| rfq.expires_at > response.timestamp)
+            .ok_or(RfqError::NotFound)?;
+        if !response.maker.as_deref().is_some_and(|m| rfq.is_invited(m)) {
+            return Err(RfqError::NotInvited);
         }
+        rfq.respond(response.clone());
+        let taker = rfq.taker.clone();
+        drop(rfqs);
+        self.audit(AuditEvent::RfqQuoted {
+            rfq_id: rfq_id.to_string(),
+            taker,
+            response,
+        })
+        .await;
+        Ok(())
+    }
 
+    /// Refuses entry on a book that isn't open and for a blocked participant
+    async fn check_entry(&self, participant: Option<&str>) -> Result<(), RejectReason> {
+        match *self.trading_state.read().await {
+            TradingState::Open => {}
+            TradingState::Halted { .. } => return Err(RejectReason::Halted),
+            TradingState::Standby => return Err(RejectReason::Standby),
+        }
+        if let Some(participant) = participant
+            && self.blocked_participants.read().await.contains(participant)
         {
-            let mut trades = self.trades.write().await;
-            for trade in new_trades.clone() {
-                if trades.len() >= TRADE_POOL_SIZE {
-                    trades.pop_front();
-                }
+            return Err(RejectReason::ParticipantBlocked);
+        }
+        Ok(())
+    }
 
-                trades.push_back(trade);
-            }
-            // trades.extend(new_trades.clone());
+    /// RFQs whose window closed by `now` (unix ms); none while the book isn't open, they wait
+    /// for trading to resume
+    pub async fn due_rfqs(&self, now: Timestamp) -> Vec<RfqId> {
+        if *self.trading_state.read().await != TradingState::Open {
+            return Vec::new();
         }
+        self.rfqs.read().await.due(now)
+    }
 
-        new_trades
+    /// Open RFQs `participant` asked for or was asked to answer
+    pub async fn rfqs_of(&self, participant: &str) -> Vec<Rfq> {
+        self.rfqs.read().await.visible_to(participant)
     }
 
-    pub async fn cancel_order(&mut self, order_id: OrderId) -> bool {
-        let mut order_book = self.order_book.write().await;
-        order_book.cancel_order(order_id)
+    /// Closes the RFQ `rfq_id` and trades its best response in full, off the book. A response
+    /// whose maker is blocked, over their risk limits or short of balance is passed over for
+    /// the next best. Nothing happens while the book isn't open.
+    pub async fn close_rfq(&mut self, rfq_id: &str) -> Option<Trade> {
+        let trade = self.settle_rfq(rfq_id).await;
+        self.fire_triggers().await;
+        trade
     }
 
-    pub async fn get_buy_orders(&self) -> Vec<Order> {
+    async fn settle_rfq(&self, rfq_id: &str) -> Option<Trade> {
         let order_book = self.order_book.write().await;
-        order_book.get_buy_orders()
+        if *self.trading_state.read().await != TradingState::Open {
+            return None;
+        }
+        let rfq = self.rfqs.write().await.remove(rfq_id)?;
+        let mark = self.mark_price_in(&order_book).await.map(|mark| mark.price);
+        let mut trade = None;
+        for response in rfq.ranked() {
+            trade = self.execute_rfq(&rfq, response, mark).await;
+            if trade.is_some() {
+                break;
+            }
+        }
+        self.audit(AuditEvent::RfqClosed {
+            rfq_id: rfq.id.clone(),
+            taker: rfq.taker.clone(),
+            trade_id: trade.as_ref().map(|t| t.id),
+        })
+        .await;
+        trade
     }
 
-    /// Returns the current state of the order book (all active sell orders)
-    pub async fn get_sell_orders(&self) -> Vec<Order> {
+    /// Trades `rfq` against `response` if both parties may and can settle it
+    async fn execute_rfq(
+        &self,
+        rfq: &Rfq,
+        response: &RfqResponse,
+        mark: Option<Price>,
+    ) -> Option<Trade> {
+        let order = |id: &OrderId, side, owner: &Option<ParticipantId>| Order {
+            owner: owner.clone(),
+            ..Order::new(
+                id.clone(),
+                side,
+                OrderType::Limit,
+                rfq.quantity,
+                response.price,
+                response.timestamp,
+            )
+        };
+        let taker = order(&rfq.id, rfq.side, &rfq.taker);
+        let maker_side = match rfq.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let maker = order(&response.id, maker_side, &response.maker);
+        let (buy, sell) = match rfq.side {
+            Side::Buy => (&taker, &maker),
+            Side::Sell => (&maker, &taker),
+        };
+        let trade = Trade::new(0, buy.id.clone(), sell.id.clone(), 0, 0).with_aggressor(rfq.side);
+        self.execute_off_book(buy, sell, trade, mark).await.ok()
+    }
+
+    /// Books a privately negotiated block trade between `buyer` and `seller`. It bypasses the
+    /// book and its price doesn't count as a last trade, but both parties are checked like
+    /// orders and it's booked and reported like any other execution.
+    pub async fn report_block_trade(
+        &self,
+        buyer: ParticipantId,
+        seller: ParticipantId,
+        price: Price,
+        quantity: Quantity,
+    ) -> Result<Trade, RejectReason> {
         let order_book = self.order_book.write().await;
-        order_book.get_sell_orders()
+        self.check_entry(None).await?;
+        let mark = self.mark_price_in(&order_book).await.map(|mark| mark.price);
+        let timestamp = self.order_timestamp();
+        let order = |side, owner| Order {
+            owner: Some(owner),
+            ..Order::new(
+                self.next_order_id(),
+                side,
+                OrderType::Limit,
+                quantity,
+                price,
+                timestamp,
+            )
+        };
+        let buy = order(Side::Buy, buyer);
+        let sell = order(Side::Sell, seller);
+        let trade =
+            Trade::new(0, buy.id.clone(), sell.id.clone(), 0, 0).with_type(TradeType::Block);
+        self.execute_off_book(&buy, &sell, trade, mark).await
     }
-}
 
-impl Default for MatchingEngine {
-    fn default() -> Self {
-        Self::new()
+    /// Trades `buy` against `sell` at their price and quantity, filling in `trade`, if neither
+    /// owner is blocked, both are within their risk limits and can settle it
+    async fn execute_off_book(
+        &self,
+        buy: &Order,
+        sell: &Order,
+        trade: Trade,
+        mark: Option<Price>,
+    ) -> Result<Trade, RejectReason> {
+        let blocked = self.blocked_participants.read().await;
+        if [buy, sell]
+            .iter()
+            .any(|o| o.owner.as_ref().is_some_and(|p| blocked.contains(p)))
+        {
+            return Err(RejectReason::ParticipantBlocked);
+        }
+        drop(blocked);
+        let limits = self.risk_limits.read().await;
+        for order in [buy, sell] {
+            limits.get(order.owner.as_deref()).check(order, mark)?;
+        }
+        drop(limits);
+
+        let mut wallets = self.wallets.write().await;
+        wallets.place_hold(buy, 0)?;
+        if let Err(reason) = wallets.place_hold(sell, 0) {
+            wallets.release(&buy.id);
+            return Err(reason);
+        }
+        drop(wallets);
+
+        let trade = Trade {
+            id: self.next_trade_id.fetch_add(1, Ordering::Relaxed),
+            price: buy.price,
+            quantity: buy.quantity,
+            ..trade.with_parties(buy.owner.clone(), sell.owner.clone())
+        };
+        matching::notify_trade(&self.trade_listeners, &trade, buy, sell);
+        let mut trades = [trade];
+        self.record_trades(&mut trades).await;
+        let [trade] = trades;
+        Ok(trade)
     }
-}
 
-impl Clone for MatchingEngine {
-    fn clone(&self) -> Self {
-        MatchingEngine {
-            order_book: Arc::clone(&self.order_book),
-            trades: Arc::clone(&self.trades),
+    async fn submit(&self, order: Order) -> Result<Vec<Trade>, RejectReason> {
+        let mut order_book = self.order_book.write().await;
+        self.submit_in(&mut order_book, order).await
+    }
+
+    /// [`MatchingEngine::submit`] on a book the caller has locked, timed for the stats
+    async fn submit_in(
+        &self,
+        order_book: &mut OrderBook,
+        order: Order,
+    ) -> Result<Vec<Trade>, RejectReason> {
+        let started = Instant::now();
+        let result = self.match_in(order_book, order).await;
+        self.latency.write().await.record(started.elapsed());
+        result
+    }
+
+    /// Checks `order`, then matches it against the book and rests what's left
+    async fn match_in(
+        &self,
+        order_book: &mut OrderBook,
+        mut order: Order,
+    ) -> Result<Vec<Trade>, RejectReason> {
+        if order.timestamp == 0 {
+            order.timestamp = self.order_timestamp();
+        }
+        match *self.trading_state.read().await {
+            TradingState::Open => {}
+            TradingState::Halted { .. } => return Err(RejectReason::Halted),
+            TradingState::Standby => return Err(RejectReason::Standby),
+        }
+        if let Some(owner) = &order.owner
+            && self.blocked_participants.read().await.contains(owner)
+        {
+            return Err(RejectReason::ParticipantBlocked);
+        }
+        if let Some(owner) = &order.owner {
+            // order timestamps are unix ns
+            let at = order.timestamp / 1_000_000;
+            self.otr.write().await.order(owner, at)?;
+        }
+        let phase = *self.market_phase.read().await;
+        let policy = self.phase_policies.read().await.off_hours_policy(phase);
+        if order.trigger.is_some() {
+            // a closed market refuses it up front; the other checks apply once it fires
+            if policy == Some(OffHoursPolicy::Reject) {
+                return Err(RejectReason::MarketClosed);
+            }
+            self.audit(AuditEvent::OrderAccepted {
+                order: order.clone(),
+            })
+            .await;
+            self.untriggered_orders.write().await.push(order);
+            return Ok(Vec::new());
+        }
+        let reference = self.reference_price_in(order_book).await;
+        self.price_bands.read().await.apply(&mut order, reference)?;
+        let ctx = BookContext {
+            best_bid: order_book.best_bid(),
+            best_ask: order_book.best_ask(),
+            mid_or_last: MarkPriceMethod::Mid
+                .mark(
+                    order_book.mid_price(),
+                    self.last_trade_price().await,
+                    None,
+                    None,
+                )
+                .map(|mark| mark.price),
+            mark: self.mark_price_in(order_book).await.map(|mark| mark.price),
+            reference,
+            exposure: match &order.owner {
+                Some(owner) => Some(self.exposure(order_book, owner).await),
+                None => None,
+            },
+        };
+        let fat_finger = *self.fat_finger.read().await;
+        let limits = self.risk_limits.read().await.get(order.owner.as_deref());
+        let built_in: [&dyn RiskCheck; 2] = [&fat_finger, &limits];
+        for check in built_in
+            .into_iter()
+            .chain(self.risk_checks.iter().map(|c| c.as_ref()))
+        {
+            check.check(&order, &ctx)?;
         }
+
+        match policy {
+            Some(OffHoursPolicy::Reject) => return Err(RejectReason::MarketClosed),
+            Some(OffHoursPolicy::Auction) if order.order_type == OrderType::Market => {
+                return Err(RejectReason::MarketOrderInAuction);
+            }
+            _ => {}
+        }
+
+        let mut wallets = self.wallets.write().await;
+        // only priced when wallets hold it, walking the asks in place until it's covered
+        let market_cost = match (order.side, order.order_type) {
+            (Side::Buy, OrderType::Market) if wallets.is_enabled() => wallet::sweep_cost(
+                order_book.levels(Side::Sell).flat_map(|(_, level)| level),
+                order.quantity,
+            ),
+            _ => 0,
+        };
+        wallets.place_hold(&order, market_cost)?;
+        drop(wallets);
+        order.arrival = order_book.next_arrival(); // its time priority, should it rest
+        self.audit(AuditEvent::OrderAccepted {
+            order: order.clone(),
+        })
+        .await;
+
+        match policy {
+            Some(OffHoursPolicy::Queue) => {
+                self.queued_orders.write().await.push_back(order);
+                return Ok(Vec::new()); // matched once continuous trading starts
+            }
+            Some(OffHoursPolicy::Auction) => {
+                order_book.add_order(order); // collected, uncrossed when the auction ends
+                self.observe_book(order_book).await;
+                return Ok(Vec::new());
+            }
+            _ => {}
+        }
+
+        let order_id = order.id.clone();
+        let policy = self.matching_policy.read().await.clone();
+        let mut new_trades = self.match_order(order_book, order, policy.as_ref());
+        self.record_trades(&mut new_trades).await;
+        self.release_unless_resting(order_book, &order_id).await;
+        self.protect_quotes(order_book, &new_trades).await;
+        self.observe_book(order_book).await;
+        self.check_circuit_breaker(&new_trades).await;
+        Ok(new_trades)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use rand::Rng;
+    /// Feeds trade prices to the circuit breaker and halts (or auctions) the book if it trips.
+    /// The trades that tripped it stand; [`MatchingEngine::expire_circuit_breaker`] brings the
+    /// book back after the cooldown.
+    async fn check_circuit_breaker(&self, new_trades: &[Trade]) {
+        let now = self.clock.now_ms();
+        let trip = {
+            let mut breaker = self.circuit_breaker.write().await;
+            new_trades
+                .iter()
+                .find_map(|t| breaker.observe(t.price, now))
+        };
+        let Some(trip) = trip else {
+            return;
+        };
 
-    use super::*;
-    #[tokio::test]
-    async fn test_submit_order() {
-        let ob = OrderBook::new();
-        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 2000, 10, 1);
-        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 2000, 200, 2);
-        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 2000, 200, 1);
-        let o4 = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 2000, 500, 1);
-        let o5 = Order::new(
-            String::from("5"),
-            Side::Sell,
-            OrderType::Limit,
-            2000,
-            500,
-            1,
-        );
-        let o6 = Order::new(
-            String::from("6"),
-            Side::Sell,
-            OrderType::Limit,
-            2000,
-            500,
-            1,
+        println!(
+            "Circuit breaker tripped: {} -> {} ({:?} for {}ms)",
+            trip.reference, trip.price, trip.action, trip.cooldown_ms
         );
+        match trip.action {
+            BreakerAction::Halt => {
+                *self.trading_state.write().await = TradingState::Halted {
+                    allow_cancels: true,
+                }
+            }
+            BreakerAction::Auction => {
+                *self.market_phase.write().await = MarketPhase::VolatilityAuction
+            }
+        }
+        self.audit(AuditEvent::CircuitBreakerTripped { trip }).await;
+    }
 
-        let mut me = MatchingEngine::new();
-        me.order_book = Arc::new(RwLock::new(ob));
+    /// Lifts the circuit breaker's trip once its cooldown is over on the engine's clock, unless
+    /// an admin already took over the book; returns whether it did. Standbys keep the
+    /// primary's trip until the primary lifts it.
+    pub async fn expire_circuit_breaker(&self) -> bool {
+        let Some(trip) = self.circuit_breaker.read().await.trip() else {
+            return false;
+        };
+        if self.clock.now_ms() < trip.ends_at() || self.is_standby().await {
+            return false;
+        }
+        {
+            let mut breaker = self.circuit_breaker.write().await;
+            if breaker.trip() != Some(trip) {
+                return false;
+            }
+            breaker.reset();
+        }
+        self.audit(AuditEvent::CircuitBreakerEnded { trip }).await;
+        match trip.action {
+            BreakerAction::Halt => {
+                self.resume().await;
+            }
+            BreakerAction::Auction => {
+                if self.market_phase().await == MarketPhase::VolatilityAuction {
+                    // triggers fire with the next order: firing here would make this task
+                    // enter orders that can trip the breaker again
+                    self.change_market_phase(MarketPhase::Continuous).await;
+                }
+            }
+        }
+        true
+    }
 
-        me.submit_order(o4).await;
-        me.submit_order(o1).await;
-        me.submit_order(o2).await;
-        me.submit_order(o3).await;
-        me.submit_order(o5).await;
-        me.submit_order(o6).await;
+    /// Static reference price, falling back to the mark price
+    pub async fn reference_price(&self) -> Option<Price> {
+        let order_book = self.order_book.read().await;
+        self.reference_price_in(&order_book).await
+    }
 
-        println!("{}", me.order_book.read().await);
-        println!("{}", me.order_book.read().await);
-        println!("{:?}", me.trades);
+    async fn reference_price_in(&self, order_book: &OrderBook) -> Option<Price> {
+        if let Some(price) = *self.reference_price.read().await {
+            return Some(price);
+        }
+        self.mark_price_in(order_book).await.map(|mark| mark.price)
     }
 
-    #[tokio::test]
-    async fn test_market_orders() {
-        let ob = OrderBook::new();
-        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Market, 20, 100, 1);
-        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Market, 200, 100, 2);
+    /// Marks the book by last trade or mid (see [`MarkPriceMethod`]), with the pinned reference
+    /// price as the last resort
+    pub async fn mark_price(&self) -> Option<MarkPrice> {
+        let order_book = self.order_book.read().await;
+        self.mark_price_in(&order_book).await
+    }
 
-        let o3 = Order::new(String::from("3"), Side::Sell, OrderType::Limit, 10, 2000, 1);
+    async fn mark_price_in(&self, order_book: &OrderBook) -> Option<MarkPrice> {
+        let method = *self.mark_price_method.read().await;
+        method.mark(
+            order_book.mid_price(),
+            self.last_trade_price().await,
+            *self.index_price.read().await,
+            *self.reference_price.read().await,
+        )
+    }
 
-        let mut me = MatchingEngine::new();
-        me.order_book = Arc::new(RwLock::new(ob));
+    /// Feeds the external index price, `None` once it has gone stale, and enters the orders
+    /// it triggered. A standby only keeps the price for when it's promoted.
+    pub async fn set_index_price(&self, price: Option<Price>) {
+        *self.index_price.write().await = price;
+        if price.is_some() && !self.is_standby().await {
+            self.fire_triggers().await;
+        }
+    }
 
-        me.submit_order(o3).await;
-        me.submit_order(o1).await;
-        me.submit_order(o2).await;
+    pub async fn index_price(&self) -> Option<Price> {
+        *self.index_price.read().await
+    }
 
-        println!("{}", me.order_book.read().await);
-        println!("TRADES: {:?}", me.trades.read().await);
-        println!("ORDER_MAP: {:?}", me.order_book.read().await.order_map);
+    pub async fn set_mark_price_method(&self, method: MarkPriceMethod) {
+        *self.mark_price_method.write().await = method;
     }
 
-    #[tokio::test]
-    async fn test_trade_pool_size_timestamp() {
-        use rand::rng;
-        let mut rng = rng();
-        let mut engine = MatchingEngine::new();
-        const BUY_MOCK_SIZE: usize = 15000;
-        const SELL_MOCK_SIZE: usize = 15000;
-        for i in 0..BUY_MOCK_SIZE {
-            let price = rng.random_range(800..=1000);
-            let quantity = rng.random_range(100..=200);
-            let order = Order::new(
-                format!("{i}"),
-                Side::Buy,
-                OrderType::Limit,
-                quantity,
-                price,
-                i.try_into().unwrap(),
-            );
-            engine.submit_order(order).await;
+    /// Price of the latest regular trade that stands
+    pub async fn last_trade_price(&self) -> Option<Price> {
+        let trades = self.trades.read().await;
+        trades
+            .iter()
+            .rev()
+            .find(|t| !t.is_busted() && t.trade_type.is_regular())
+            .map(|t| t.price)
+    }
+
+    /// Keeps the latest `capacity` trades in memory; older ones go to `spill` when given and
+    /// are dropped otherwise
+    pub async fn set_trade_history(&self, capacity: usize, spill: Option<TradeSpill>) {
+        *self.trade_spill.write().await = spill;
+        self.set_trade_capacity(capacity).await;
+    }
+
+    async fn set_trade_capacity(&self, capacity: usize) {
+        let mut trades = self.trades.write().await;
+        self.trade_capacity.store(capacity, Ordering::Relaxed);
+        while trades.len() > capacity {
+            let evicted = trades.pop_front().expect("pool over capacity");
+            self.spill_trade(&evicted).await;
+        }
+    }
+
+    /// Adds a new trade to the pool, evicting the oldest one when it's full
+    async fn push_trade(&self, trades: &mut VecDeque<Trade>, trade: Trade) {
+        if trades.len() >= self.trade_capacity.load(Ordering::Relaxed)
+            && let Some(evicted) = trades.pop_front()
+        {
+            self.spill_trade(&evicted).await;
         }
+        trades.push_back(trade);
+    }
 
-        for i in 0..SELL_MOCK_SIZE {
-            let price = rng.random_range(800..=1000);
-            let quantity = rng.random_range(100..=200);
-            let order = Order::new(
-                format!("{i}"),
-                Side::Sell,
-                OrderType::Limit,
-                quantity,
-                price,
-                i.try_into().unwrap(),
+    async fn spill_trade(&self, trade: &Trade) {
+        if let Some(spill) = self.trade_spill.write().await.as_mut()
+            && let Err(e) = spill.append(trade)
+        {
+            println!(
+                "trade {} not spilled to {}: {e}",
+                trade.id,
+                spill.path().display()
             );
-            engine.submit_order(order).await;
         }
+    }
+
+    async fn clear_spilled_trades(&self) {
+        if let Some(spill) = self.trade_spill.write().await.as_mut()
+            && let Err(e) = spill.clear()
+        {
+            println!("{} not cleared: {e}", spill.path().display());
+        }
+    }
 
-        println!("{:?}", engine.trades.read().await);
+    /// Every trade of the engine, oldest first: those spilled to disk, then the pool. Without a
+    /// spill file only the pool is left.
+    pub async fn trade_history(&self) -> Vec<Trade> {
+        let trades = self.trades.read().await;
+        let spilled = self.spilled_so_far().await;
+        let recent: Vec<Trade> = trades.iter().cloned().collect();
+        drop(trades);
 
-        println!("\n{}", engine.trades.read().await.len());
+        let mut history = read_spill(spilled).await;
+        history.extend(recent);
+        history
+    }
+
+    /// Where the spill file is and how much of it is written; taken with the pool held, so
+    /// that no trade is in both or neither
+    async fn spilled_so_far(&self) -> Option<(PathBuf, u64)> {
+        self.trade_spill
+            .read()
+            .await
+            .as_ref()
+            .map(|spill| (spill.path().to_path_buf(), spill.written()))
+    }
+
+    /// Where this engine spills evicted trades, `None` without a spill file
+    pub async fn spill_path(&self) -> Option<PathBuf> {
+        self.trade_spill
+            .read()
+            .await
+            .as_ref()
+            .map(|spill| spill.path().to_path_buf())
+    }
+
+    /// Whether the latest write to the spill file failed, `None` without one
+    pub async fn spill_failing(&self) -> Option<bool> {
+        self.trade_spill
+            .read()
+            .await
+            .as_ref()
+            .map(TradeSpill::is_failing)
+    }
+
+    /// Trades in the pool and in the spill file
+    pub async fn hot_trades(&self) -> (usize, usize) {
+        let pool = self.trades.read().await.len();
+        let spilled = self
+            .trade_spill
+            .read()
+            .await
+            .as_ref()
+            .map_or(0, |s| s.trades());
+        (pool, spilled)
+    }
+
+    /// Drops the trades executed before `before` (unix ms) from the pool and the spill file and
+    /// returns how many went. Trades whose time the audit log no longer knows are kept.
+    pub async fn compact_trades(&self, before: Timestamp) -> usize {
+        let mut trades = self.trades.write().await;
+        let Some(first_kept) = self.audit_log.read().await.seq_at(before) else {
+            return 0;
+        };
+        let pooled = trades.len();
+        trades.retain(|t| t.seq >= first_kept);
+        let mut dropped = pooled - trades.len();
+        if let Some(spill) = self.trade_spill.write().await.as_mut() {
+            match spill.retain(|t| t.seq >= first_kept) {
+                Ok(compacted) => dropped += compacted,
+                Err(e) => println!("{} not compacted: {e}", spill.path().display()),
+            }
+        }
+        dropped
+    }
+
+    pub async fn retention_stats(&self) -> RetentionStats {
+        self.retention_stats.read().await.clone()
+    }
+
+    pub async fn set_retention_stats(&self, stats: RetentionStats) {
+        *self.retention_stats.write().await = stats;
+    }
+
+    pub async fn set_fat_finger_check(&self, check: FatFingerCheck) {
+        *self.fat_finger.write().await = check;
+    }
+
+    /// Limits participants' order-to-trade ratio, counting starts over
+    pub async fn set_otr_config(&self, config: OtrConfig) {
+        self.otr.write().await.set_config(config);
+    }
+
+    /// The order-to-trade ratio limit and the participants currently over it
+    pub async fn otr_offenders(&self) -> (OtrConfig, Vec<OtrStanding>) {
+        let mut otr = self.otr.write().await;
+        (otr.config(), otr.offenders())
+    }
+
+    /// Pins the reference price (e.g. to the previous close); `None` goes back to the mark price
+    pub async fn set_reference_price(&self, price: Option<Price>) {
+        *self.reference_price.write().await = price;
+    }
+
+    pub async fn set_price_bands(&self, bands: PriceBands) {
+        *self.price_bands.write().await = bands;
+    }
+
+    /// Band limits in force right now, `None` if the bands are off or there is no reference yet
+    pub async fn band_limits(&self) -> Option<BandLimits> {
+        let bands = *self.price_bands.read().await;
+        if !bands.is_enabled() {
+            return None;
+        }
+        Some(bands.limits(self.reference_price().await?))
+    }
+
+    /// Limits applied to participants without an override
+    pub async fn set_default_risk_limits(&self, limits: RiskLimits) {
+        self.risk_limits.write().await.default = limits;
+    }
+
+    /// The limits `/admin/config` changes that the engine enforces; the rate limits are the
+    /// server's
+    pub async fn runtime_config(&self) -> RuntimeConfig {
+        let limits = self.risk_limits.read().await.default;
+        RuntimeConfig {
+            rate_limit: None,
+            price_bands: Some(*self.price_bands.read().await),
+            order_size: Some(OrderSizeLimits {
+                max_order_notional: limits.max_order_notional,
+                max_order_quantity: limits.max_order_quantity,
+            }),
+            trade_pool_size: Some(self.trade_capacity.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Applies the sections of `update` the engine enforces and records the whole update in
+    /// the audit log, so standbys follow and operators can trace the change
+    pub async fn update_config(&self, update: RuntimeConfig) {
+        self.apply_config(&update).await;
+        self.audit(AuditEvent::ConfigChanged { config: update })
+            .await;
+    }
+
+    async fn apply_config(&self, config: &RuntimeConfig) {
+        if let Some(bands) = config.price_bands {
+            self.set_price_bands(bands).await;
+        }
+        if let Some(order_size) = config.order_size {
+            let mut limits = self.risk_limits.write().await;
+            limits.default.max_order_notional = order_size.max_order_notional;
+            limits.default.max_order_quantity = order_size.max_order_quantity;
+        }
+        if let Some(capacity) = config.trade_pool_size {
+            self.set_trade_capacity(capacity).await;
+        }
+    }
+
+    pub async fn set_risk_limits(&self, owner: ParticipantId, limits: RiskLimits) {
+        self.risk_limits.write().await.set(owner, limits);
+    }
+
+    /// Limits in force for `owner`
+    pub async fn risk_limits(&self, owner: &str) -> RiskLimits {
+        self.risk_limits.read().await.get(Some(owner))
+    }
+
+    /// Sets `owner`'s market maker protection; one without limits turns it off
+    pub async fn set_quote_protection(&self, owner: ParticipantId, protection: QuoteProtection) {
+        self.quote_protection.write().await.set(owner, protection);
+    }
+
+    pub async fn quote_protection(&self, owner: &str) -> Option<QuoteProtection> {
+        self.quote_protection.read().await.get(owner)
+    }
+
+    /// Counts the fills of resting quote orders in `new_trades` against their owners'
+    /// protections and pulls the rest of every quote whose protection tripped
+    async fn protect_quotes(&self, order_book: &mut OrderBook, new_trades: &[Trade]) {
+        let mut quotes = self.quotes.write().await;
+        if quotes.is_empty() {
+            return;
+        }
+        let mut protection = self.quote_protection.write().await;
+        let now = self.clock.now_ms();
+        let mut trips = Vec::new();
+        for trade in new_trades {
+            let fills = [
+                (&trade.buyer, &trade.buy_order_id),
+                (&trade.seller, &trade.sell_order_id),
+            ];
+            for (owner, order_id) in fills {
+                let Some(owner) = owner else {
+                    continue;
+                };
+                if !quotes.get(owner).is_some_and(|ids| ids.contains(order_id)) {
+                    continue;
+                }
+                if let Some(trip) = protection.record(owner, trade.quantity, now) {
+                    trips.push((owner.clone(), trip));
+                }
+            }
+        }
+        drop(protection);
+
+        for (owner, trip) in trips {
+            let Some(ids) = quotes.remove(&owner) else {
+                continue; // tripped twice by the same trades
+            };
+            let pulled: Vec<Order> = ids
+                .into_iter()
+                .filter_map(|id| {
+                    let order = order_book.get_order(&id)?;
+                    order_book.cancel_order(id);
+                    Some(order)
+                })
+                .collect();
+            self.record_cancels(&pulled).await;
+            self.audit(AuditEvent::QuoteProtectionTripped {
+                owner: Some(owner),
+                fills: trip.fills,
+                volume: trip.volume,
+                canceled: pulled.into_iter().map(|o| o.id).collect(),
+            })
+            .await;
+        }
+    }
+
+    pub async fn set_circuit_breaker(&self, config: CircuitBreakerConfig) {
+        *self.circuit_breaker.write().await = CircuitBreaker::new(config);
+    }
+
+    /// The active circuit breaker trip, if the book is currently stopped by it
+    pub async fn circuit_breaker_trip(&self) -> Option<BreakerTrip> {
+        self.circuit_breaker.read().await.trip()
+    }
+
+    /// Runs the matching loop for `order`, splitting each level by `policy`, and rests any
+    /// unfilled limit quantity
+    fn match_order(
+        &self,
+        order_book: &mut OrderBook,
+        order: Order,
+        policy: &dyn MatchingPolicy,
+    ) -> Vec<Trade> {
+        matching::match_order(
+            order_book,
+            order,
+            policy,
+            |order, resting, price, quantity| self.execute(order, resting, price, quantity),
+        )
+    }
+
+    /// Trade between the incoming `order` and a resting order, the incoming side is the aggressor
+    fn execute(&self, order: &Order, resting: &Order, price: Price, quantity: Quantity) -> Trade {
+        let trade_id = self.next_trade_id.fetch_add(1, Ordering::Relaxed);
+        let (buy, sell) = match order.side {
+            Side::Buy => (order, resting),
+            Side::Sell => (resting, order),
+        };
+        let trade = Trade::new(trade_id, buy.id.clone(), sell.id.clone(), price, quantity)
+            .with_parties(buy.owner.clone(), sell.owner.clone())
+            .with_aggressor(order.side);
+        matching::notify_trade(&self.trade_listeners, &trade, buy, sell);
+        trade
+    }
+
+    /// Market orders and fully filled orders don't rest, so nothing stays held for them
+    async fn release_unless_resting(&self, order_book: &OrderBook, order_id: &OrderId) {
+        if order_book.get_order(order_id).is_none() {
+            self.wallets.write().await.release(order_id);
+        }
+    }
+
+    /// Releases the holds of orders pulled in bulk and audits the cancels
+    async fn record_cancels(&self, canceled: &[Order]) {
+        let mut wallets = self.wallets.write().await;
+        for order in canceled {
+            wallets.release(&order.id);
+        }
+        drop(wallets);
+        for order in canceled {
+            self.audit(AuditEvent::OrderCanceled {
+                order: order.clone(),
+            })
+            .await;
+        }
+    }
+
+    /// Called after every change to the book: marks the book view stale and feeds the touch
+    /// to the market quality statistics
+    async fn observe_book(&self, order_book: &OrderBook) {
+        self.book_view_stale.store(true, Ordering::Release);
+        self.market_quality
+            .write()
+            .await
+            .observe(Touch::of(order_book), self.clock.now_ms());
+    }
+
+    /// Book totals from the published book view and counters kept as the engine goes, so
+    /// neither the book nor the trades are scanned
+    pub async fn stats(&self) -> EngineStats {
+        let view = self.book_view();
+        let resting = view.bid_stats.orders + view.ask_stats.orders;
+        let trades = self.trades.read().await.len();
+        let audit_entries = self.audit_log.read().await.len() as usize;
+        EngineStats {
+            bids: view.bid_stats,
+            asks: view.ask_stats,
+            orders: *self.order_counts.read().await,
+            trades_executed: self.next_trade_id.load(Ordering::Relaxed) - 1,
+            latency: self.latency.read().await.summary(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            memory: MemoryEstimate::of(resting, trades, audit_entries),
+        }
+    }
+
+    /// Time-weighted spread and depth at the touch since the engine started
+    pub async fn market_quality(&self) -> QualityStats {
+        self.market_quality.read().await.stats(self.clock.now_ms())
+    }
+
+    /// Appends `event` to the audit log and publishes it on the event bus, in the same order
+    async fn audit(&self, event: AuditEvent) {
+        let mut audit_log = self.audit_log.write().await;
+        self.order_counts.write().await.count(&event);
+        let entry = audit_log.append(event, self.clock.now_ms());
+        self.acks.notify(entry);
+        self.event_bus.publish(entry.clone());
+    }
+
+    /// Resolves with the ack of `order_id` once this engine appends it
+    pub fn watch_ack(&self, order_id: OrderId) -> oneshot::Receiver<OrderAck> {
+        self.acks.watch(order_id)
+    }
+
+    pub fn forget_ack(&self, order_id: &str) {
+        self.acks.forget(order_id);
+    }
+
+    /// Audit entries from sequence number `from`
+    pub async fn audit_entries(&self, from: u64, limit: usize) -> Vec<AuditEntry> {
+        self.audit_log.read().await.export(from, limit)
+    }
+
+    /// Live audit entries, i.e. execution reports of every participant
+    pub fn subscribe_events(&self) -> broadcast::Receiver<AuditEntry> {
+        self.event_bus.subscribe()
+    }
+
+    pub fn event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    /// Sequence number of the latest engine event, `0` before the first
+    pub async fn last_seq(&self) -> u64 {
+        self.audit_log.read().await.last_seq()
+    }
+
+    /// Entry count, head hash and the first entry breaking the chain, if any
+    pub async fn verify_audit_log(&self) -> (u64, String, Result<(), u64>) {
+        let log = self.audit_log.read().await;
+        (log.len(), log.head().to_string(), log.verify())
+    }
+
+    /// Net position plus everything `owner` has resting or queued
+    async fn exposure(&self, order_book: &OrderBook, owner: &str) -> Exposure {
+        let (mut open_buy, mut open_sell) = order_book.owner_open_quantity(owner);
+        for queued in self.queued_orders.read().await.iter() {
+            if queued.owner.as_deref() == Some(owner) {
+                match queued.side {
+                    Side::Buy => open_buy += queued.quantity,
+                    Side::Sell => open_sell += queued.quantity,
+                }
+            }
+        }
+        Exposure {
+            net_position: self.positions.read().await.net_position(owner),
+            open_buy,
+            open_sell,
+        }
+    }
+
+    pub async fn set_wallets(&self, wallets: Wallets) {
+        *self.wallets.write().await = wallets;
+    }
+
+    pub async fn balances(&self, owner: &str) -> AccountBalances {
+        self.wallets.read().await.balances(owner)
+    }
+
+    pub async fn deposit(
+        &self,
+        owner: ParticipantId,
+        asset: Asset,
+        amount: u64,
+    ) -> AccountBalances {
+        self.wallets.write().await.deposit(owner, asset, amount)
+    }
+
+    /// `None` if `owner` doesn't have `amount` available
+    pub async fn withdraw(
+        &self,
+        owner: &str,
+        asset: Asset,
+        amount: u64,
+    ) -> Option<AccountBalances> {
+        self.wallets.write().await.withdraw(owner, asset, amount)
+    }
+
+    pub async fn set_fee_schedule(&self, schedule: FeeSchedule) {
+        *self.fee_schedule.write().await = schedule;
+    }
+
+    /// Fees `owner` paid on trades that weren't busted, and the volume their tier is based on
+    pub async fn fees_paid(&self, owner: &str) -> (u64, u64) {
+        let fees = self.fees.read().await;
+        (fees.total(owner), fees.tier_volume(owner))
+    }
+
+    /// `(maker_bps, taker_bps)` `owner` currently pays
+    pub async fn fee_rates(&self, owner: &str) -> (u64, u64) {
+        let volume = self.fees.read().await.tier_volume(owner);
+        self.fee_schedule.read().await.rates(volume)
+    }
+
+    pub async fn refresh_fee_tiers(&self) {
+        self.fees.write().await.refresh_tiers(self.clock.now_ms());
+    }
+
+    /// Cuts off the current settlement batch, `None` if nothing traded since the last one
+    pub async fn close_settlement_batch(&self) -> Option<SettlementBatch> {
+        self.settlement
+            .write()
+            .await
+            .close_batch(self.clock.now_ms())
+    }
+
+    pub async fn settle_batch(&self, id: u64) -> Result<SettlementBatch, SettlementError> {
+        self.settlement
+            .write()
+            .await
+            .settle(id, self.clock.now_ms())
+    }
+
+    pub async fn settlement_batches(&self, status: Option<BatchStatus>) -> Vec<SettlementBatch> {
+        self.settlement.read().await.batches(status)
+    }
+
+    /// `owner`'s debits/credits per closed batch
+    pub async fn settlement_obligations(
+        &self,
+        owner: &str,
+    ) -> Vec<(u64, BatchStatus, SettlementInstruction)> {
+        self.settlement.read().await.obligations(owner)
+    }
+
+    pub async fn net_position(&self, owner: &str) -> i64 {
+        self.positions.read().await.net_position(owner)
+    }
+
+    /// Net position and open order quantity of `owner`
+    pub async fn owner_exposure(&self, owner: &str) -> Exposure {
+        let order_book = self.order_book.read().await;
+        self.exposure(&order_book, owner).await
+    }
+
+    pub async fn position(&self, owner: &str) -> Position {
+        self.positions.read().await.position(owner)
+    }
+
+    async fn record_trades(&self, new_trades: &mut [Trade]) {
+        let fee_schedule = self.fee_schedule.read().await;
+        let mut fees = self.fees.write().await;
+        let executed_at = self.clock.now_ns();
+        let now = executed_at / 1_000_000;
+        for trade in new_trades.iter_mut() {
+            trade.timestamp = executed_at;
+            fees.charge(&fee_schedule, trade);
+            fees.apply(trade, now);
+        }
+        drop(fee_schedule);
+        drop(fees);
+
+        let mut positions = self.positions.write().await;
+        let mut wallets = self.wallets.write().await;
+        let mut settlement = self.settlement.write().await;
+        for trade in new_trades.iter() {
+            positions.apply(trade);
+            wallets.settle(trade);
+            settlement.record(trade, 1);
+        }
+        drop(positions);
+        drop(wallets);
+        drop(settlement);
+
+        let mut audit_log = self.audit_log.write().await;
+        for trade in new_trades.iter_mut() {
+            trade.seq = audit_log.next_seq();
+            let entry = audit_log.append(
+                AuditEvent::TradeExecuted {
+                    trade: trade.clone(),
+                },
+                now,
+            );
+            self.event_bus.publish(entry.clone());
+        }
+        drop(audit_log);
+
+        let mut owner_trades = self.owner_trades.write().await;
+        let mut rolling_volume = self.rolling_volume.write().await;
+        let mut otr = self.otr.write().await;
+        for trade in new_trades.iter() {
+            owner_trades.record(trade);
+            rolling_volume.record(trade, now);
+            for party in [&trade.buyer, &trade.seller].into_iter().flatten() {
+                otr.trade(party);
+            }
+        }
+        drop(owner_trades);
+        drop(rolling_volume);
+        drop(otr);
+
+        let mut trades = self.trades.write().await;
+        for trade in new_trades.iter().cloned() {
+            self.push_trade(&mut trades, trade).await;
+        }
+        // trades.extend(new_trades.clone());
+    }
+
+    pub async fn cancel_order(&mut self, order_id: OrderId) -> Result<bool, RejectReason> {
+        self.cancel(order_id, None).await
+    }
+
+    /// Cancels on behalf of `owner`, refusing to touch other participants' orders
+    pub async fn cancel_owned_order(
+        &mut self,
+        order_id: OrderId,
+        owner: &str,
+    ) -> Result<bool, RejectReason> {
+        self.cancel(order_id, Some(owner)).await
+    }
+
+    async fn cancel(&self, order_id: OrderId, owner: Option<&str>) -> Result<bool, RejectReason> {
+        let mut order_book = self.order_book.write().await;
+        self.cancel_in(&mut order_book, order_id, owner).await
+    }
+
+    /// [`MatchingEngine::cancel`] on a book the caller has locked
+    async fn cancel_in(
+        &self,
+        order_book: &mut OrderBook,
+        order_id: OrderId,
+        owner: Option<&str>,
+    ) -> Result<bool, RejectReason> {
+        match *self.trading_state.read().await {
+            TradingState::Halted {
+                allow_cancels: false,
+            } => return Err(RejectReason::Halted),
+            TradingState::Standby => return Err(RejectReason::Standby),
+            _ => {}
+        }
+        if let Some(owner) = owner {
+            self.otr.write().await.cancel(owner);
+        }
+
+        let mut queued = self.queued_orders.write().await;
+        let mut scheduled = self.scheduled_orders.write().await;
+        let mut untriggered = self.untriggered_orders.write().await;
+        let order = queued
+            .iter()
+            .chain(untriggered.iter())
+            .find(|o| o.id == order_id)
+            .cloned()
+            .or_else(|| scheduled.get(&order_id).cloned())
+            .or_else(|| order_book.get_order(&order_id));
+        if let (Some(owner), Some(order)) = (owner, &order)
+            && order.owner.as_deref() != Some(owner)
+        {
+            return Err(RejectReason::NotOrderOwner);
+        }
+
+        let canceled = if let Some(pos) = queued.iter().position(|o| o.id == order_id) {
+            queued.remove(pos).is_some()
+        } else if let Some(pos) = untriggered.iter().position(|o| o.id == order_id) {
+            untriggered.remove(pos);
+            true
+        } else {
+            scheduled.remove(&order_id).is_some() || order_book.cancel_order(order_id.clone())
+        };
+        if canceled {
+            self.observe_book(order_book).await;
+            self.wallets.write().await.release(&order_id);
+            if let Some(order) = order {
+                self.audit(AuditEvent::OrderCanceled { order }).await;
+            }
+        }
+        Ok(canceled)
+    }
+
+    /// Resting, queued, scheduled and untriggered orders of `owner`
+    pub async fn owner_orders(&self, owner: &str) -> Vec<(Order, OrderStatus)> {
+        let order_book = self.order_book.read().await;
+        let is_owner = |o: &&Order| o.owner.as_deref() == Some(owner);
+        let mut orders: Vec<(Order, OrderStatus)> = order_book
+            .get_buy_orders()
+            .iter()
+            .chain(order_book.get_sell_orders().iter())
+            .filter(is_owner)
+            .map(|o| (o.clone(), o.status()))
+            .collect();
+        orders.extend(
+            self.queued_orders
+                .read()
+                .await
+                .iter()
+                .filter(is_owner)
+                .map(|o| (o.clone(), OrderStatus::Queued)),
+        );
+        orders.extend(
+            self.scheduled_orders
+                .read()
+                .await
+                .iter()
+                .filter(is_owner)
+                .map(|o| (o.clone(), OrderStatus::Scheduled)),
+        );
+        orders.extend(
+            self.untriggered_orders
+                .read()
+                .await
+                .iter()
+                .filter(is_owner)
+                .map(|o| (o.clone(), OrderStatus::Untriggered)),
+        );
+        orders
+    }
+
+    /// Trades where `owner` was the buyer or the seller, newest first, older than `before`
+    pub async fn owner_trades(
+        &self,
+        owner: &str,
+        before: Option<TradeId>,
+        limit: usize,
+    ) -> Vec<Trade> {
+        self.owner_trades.read().await.page(owner, before, limit)
+    }
+
+    /// Cancels every resting order in one step and optionally drops the trade history
+    pub async fn clear_book(&self, wipe_trades: bool) -> Vec<Order> {
+        let mut order_book = self.order_book.write().await;
+        let mut canceled: Vec<Order> = self.queued_orders.write().await.drain(..).collect();
+        canceled.extend(self.scheduled_orders.write().await.clear());
+        canceled.extend(self.untriggered_orders.write().await.drain(..));
+        canceled.extend(order_book.clear());
+        if wipe_trades {
+            self.trades.write().await.clear();
+            self.clear_spilled_trades().await;
+            self.owner_trades.write().await.clear();
+            self.rolling_volume.write().await.clear();
+        }
+        self.record_cancels(&canceled).await;
+        self.observe_book(&order_book).await;
+        canceled
+    }
+
+    /// Image of the market for `GET /admin/state`
+    pub async fn export_state(&self) -> EngineState {
+        let order_book = self.order_book.read().await;
+        let side = |side| {
+            order_book
+                .levels(side)
+                .flat_map(|(_, orders)| orders.iter().cloned())
+                .collect()
+        };
+        let mut blocked_participants: Vec<ParticipantId> = self
+            .blocked_participants
+            .read()
+            .await
+            .iter()
+            .cloned()
+            .collect();
+        blocked_participants.sort();
+        // trades before the audit log, the order busts and corrections take them in; holding
+        // the trades keeps an adjustment from landing between them and the sequence number
+        let trades = self.trades.read().await;
+        // spilled trades by reference, the file only grows past what's written now: an import
+        // reads them back and spills them again
+        let spilled_trades = self
+            .trade_spill
+            .read()
+            .await
+            .as_ref()
+            .map(|spill| SpilledTrades {
+                path: spill.path().to_path_buf(),
+                len: spill.written(),
+                trades: spill.trades(),
+            });
+        let positions = self.positions.read().await.clone();
+        let wallets = self.wallets.read().await.snapshot();
+        let (last_seq, audit_head) = {
+            let audit_log = self.audit_log.read().await;
+            (audit_log.last_seq(), audit_log.head().to_string())
+        };
+        EngineState {
+            version: STATE_VERSION,
+            trading_state: *self.trading_state.read().await,
+            market_phase: *self.market_phase.read().await,
+            bids: side(Side::Buy),
+            asks: side(Side::Sell),
+            queued_orders: self.queued_orders.read().await.iter().cloned().collect(),
+            scheduled_orders: self.scheduled_orders.read().await.snapshot(),
+            untriggered_orders: self.untriggered_orders.read().await.clone(),
+            quotes: self.quotes.read().await.clone(),
+            rfqs: self.rfqs.read().await.snapshot(),
+            trades: trades.iter().cloned().collect(),
+            spilled_trades,
+            next_trade_id: self.next_trade_id.load(Ordering::Relaxed),
+            last_seq,
+            audit_head,
+            blocked_participants,
+            reference_price: *self.reference_price.read().await,
+            positions,
+            wallets,
+        }
+    }
+
+    /// Replaces the market with a validated `state`. The current resting and queued orders are
+    /// dropped without cancel events, rolling volume starts over and the audit log continues
+    /// after the exported sequence number.
+    pub async fn import_state(&self, mut state: EngineState) {
+        // before anything is locked or cleared, the reference may be to this engine's own spill
+        let own_spill = self.spill_path().await;
+        if let Err(e) = state.load_spilled_trades(own_spill.as_deref()).await {
+            println!("{}: {}, importing the pool only", e.field, e.message);
+        }
+        let mut order_book = self.order_book.write().await;
+        order_book.clear();
+        // re-adding in priority order keeps the queue position of every order
+        for order in state.bids.into_iter().chain(state.asks) {
+            order_book.add_order(order);
+        }
+        for order in &state.queued_orders {
+            order_book.note_arrival(order.arrival);
+        }
+        *self.queued_orders.write().await = state.queued_orders.into();
+        self.scheduled_orders
+            .write()
+            .await
+            .restore(state.scheduled_orders);
+        *self.untriggered_orders.write().await = state.untriggered_orders;
+        *self.quotes.write().await = state.quotes;
+        self.rfqs.write().await.restore(state.rfqs);
+
+        let mut owner_trades = self.owner_trades.write().await;
+        owner_trades.clear();
+        for trade in &state.trades {
+            owner_trades.record(trade);
+        }
+        drop(owner_trades);
+        // the imported trades are the whole history, past the pool they're spilled again
+        let mut trades = self.trades.write().await;
+        trades.clear();
+        self.clear_spilled_trades().await;
+        for trade in state.trades {
+            self.push_trade(&mut trades, trade).await;
+        }
+        drop(trades);
+        self.rolling_volume.write().await.clear();
+        self.next_trade_id
+            .store(state.next_trade_id, Ordering::Relaxed);
+        self.audit_log
+            .write()
+            .await
+            .resume_after(state.last_seq, state.audit_head);
+
+        *self.trading_state.write().await = state.trading_state;
+        *self.market_phase.write().await = state.market_phase;
+        *self.blocked_participants.write().await = state.blocked_participants.into_iter().collect();
+        *self.reference_price.write().await = state.reference_price;
+        *self.positions.write().await = state.positions;
+        self.wallets.write().await.restore(state.wallets);
+        self.observe_book(&order_book).await;
+    }
+
+    /// Marks a trade as busted; it stays in the history but no longer counts as executed
+    pub async fn bust_trade(
+        &self,
+        trade_id: TradeId,
+        reason: String,
+    ) -> Result<Trade, TradeAdjustmentError> {
+        let mut trades = self.trades.write().await;
+        let trade = trades
+            .iter_mut()
+            .find(|t| t.id == trade_id)
+            .ok_or(TradeAdjustmentError::NotFound)?;
+        if trade.is_busted() {
+            return Err(TradeAdjustmentError::AlreadyBusted);
+        }
+        trade.status = TradeStatus::Busted { reason };
+        self.positions.write().await.reverse(trade);
+        self.wallets.write().await.reverse(trade);
+        self.settlement.write().await.record(trade, -1);
+        self.fees.write().await.reverse(trade);
+        self.owner_trades.write().await.update(trade);
+        self.rolling_volume.write().await.update(trade);
+        self.audit(AuditEvent::TradeBusted {
+            trade: trade.clone(),
+        })
+        .await;
+        Ok(trade.clone())
+    }
+
+    /// Overwrites the price/quantity of a trade, remembering the originally executed values
+    pub async fn correct_trade(
+        &self,
+        trade_id: TradeId,
+        price: Option<Price>,
+        quantity: Option<Quantity>,
+        reason: String,
+    ) -> Result<Trade, TradeAdjustmentError> {
+        let mut trades = self.trades.write().await;
+        let trade = trades
+            .iter_mut()
+            .find(|t| t.id == trade_id)
+            .ok_or(TradeAdjustmentError::NotFound)?;
+        let (original_price, original_quantity) = match &trade.status {
+            TradeStatus::Active => (trade.price, trade.quantity),
+            TradeStatus::Corrected {
+                original_price,
+                original_quantity,
+                ..
+            } => (*original_price, *original_quantity),
+            TradeStatus::Busted { .. } => return Err(TradeAdjustmentError::AlreadyBusted),
+        };
+
+        let mut positions = self.positions.write().await;
+        let mut wallets = self.wallets.write().await;
+        let mut settlement = self.settlement.write().await;
+        let fee_schedule = self.fee_schedule.read().await;
+        let mut fees = self.fees.write().await;
+        positions.reverse(trade);
+        fees.reverse(trade);
+        wallets.reverse(trade);
+        settlement.record(trade, -1);
+        trade.price = price.unwrap_or(trade.price);
+        trade.quantity = quantity.unwrap_or(trade.quantity);
+        positions.apply(trade);
+        wallets.apply(trade);
+        settlement.record(trade, 1);
+        fees.charge(&fee_schedule, trade);
+        fees.apply(trade, self.clock.now_ms());
+        trade.status = TradeStatus::Corrected {
+            reason,
+            original_price,
+            original_quantity,
+        };
+        self.owner_trades.write().await.update(trade);
+        self.rolling_volume.write().await.update(trade);
+        self.audit(AuditEvent::TradeCorrected {
+            trade: trade.clone(),
+        })
+        .await;
+        Ok(trade.clone())
+    }
+
+    /// Traded volume over the last minute, 5 minutes and hour, busted trades excluded
+    pub async fn rolling_volume(&self) -> Vec<VolumeStats> {
+        self.rolling_volume.read().await.stats(self.clock.now_ms())
+    }
+
+    /// Volume-at-price of the trades executed since `since` (ms), in `bucket`-wide price ranges
+    pub async fn volume_profile(&self, since: Timestamp, bucket: Price) -> Vec<PriceVolume> {
+        volumeprofile::profile(self.audit_log.read().await.since(since), bucket)
+    }
+
+    /// Trades executed in `[from, to)` (unix ms) with their current status, oldest first
+    pub async fn executions(&self, from: Timestamp, to: Timestamp) -> Vec<Execution> {
+        tradeexport::executions(self.audit_log.read().await.since(from), to)
+    }
+
+    /// Pulls every resting order of `owner` without blocking further submissions
+    pub async fn cancel_participant_orders(&self, owner: &str) -> Vec<Order> {
+        let mut order_book = self.order_book.write().await;
+        if *self.trading_state.read().await == TradingState::Standby {
+            return Vec::new(); // the primary decides what gets canceled
+        }
+        let mut canceled = self.drain_queued_orders(owner).await;
+        canceled.extend(order_book.cancel_owner_orders(owner));
+        self.record_cancels(&canceled).await;
+        self.observe_book(&order_book).await;
+        canceled
+    }
+
+    /// Takes the queued, scheduled and untriggered orders of `owner` out
+    async fn drain_queued_orders(&self, owner: &str) -> Vec<Order> {
+        let mut queued = self.queued_orders.write().await;
+        let (removed, kept) = queued
+            .drain(..)
+            .partition(|o| o.owner.as_deref() == Some(owner));
+        *queued = kept;
+        let mut removed: Vec<Order> = removed.into();
+        removed.extend(self.scheduled_orders.write().await.drain_owner(owner));
+        let mut untriggered = self.untriggered_orders.write().await;
+        let (untriggered_removed, kept): (Vec<Order>, Vec<Order>) = untriggered
+            .drain(..)
+            .partition(|o| o.owner.as_deref() == Some(owner));
+        *untriggered = kept;
+        removed.extend(untriggered_removed);
+        removed
+    }
+
+    /// Kill switch: blocks `owner` from submitting and pulls all of their resting orders
+    pub async fn kill_participant(&self, owner: ParticipantId) -> Result<Vec<Order>, RejectReason> {
+        let mut order_book = self.order_book.write().await;
+        if self.is_standby().await {
+            return Err(RejectReason::Standby);
+        }
+        let mut canceled = self.drain_queued_orders(&owner).await;
+        canceled.extend(order_book.cancel_owner_orders(&owner));
+        self.record_cancels(&canceled).await;
+        self.observe_book(&order_book).await;
+        self.blocked_participants
+            .write()
+            .await
+            .insert(owner.clone());
+        self.audit(AuditEvent::ParticipantBlocked { owner: Some(owner) })
+            .await;
+        Ok(canceled)
+    }
+
+    /// Lifts the kill switch, `Ok(false)` if `owner` wasn't blocked
+    pub async fn enable_participant(&self, owner: &str) -> Result<bool, RejectReason> {
+        let _order_book = self.order_book.write().await; // ordered with the replicated events
+        if self.is_standby().await {
+            return Err(RejectReason::Standby);
+        }
+        if !self.blocked_participants.write().await.remove(owner) {
+            return Ok(false);
+        }
+        self.audit(AuditEvent::ParticipantUnblocked {
+            owner: Some(owner.to_string()),
+        })
+        .await;
+        Ok(true)
+    }
+
+    pub async fn blocked_participants(&self) -> Vec<ParticipantId> {
+        let mut blocked: Vec<ParticipantId> = self
+            .blocked_participants
+            .read()
+            .await
+            .iter()
+            .cloned()
+            .collect();
+        blocked.sort();
+        blocked
+    }
+
+    pub async fn market_phase(&self) -> MarketPhase {
+        *self.market_phase.read().await
+    }
+
+    pub async fn set_phase_policies(&self, policies: PhasePolicies) {
+        *self.phase_policies.write().await = policies;
+    }
+
+    /// Moves the book into `phase`. Leaving an auction uncrosses the book at the equilibrium
+    /// price; entering continuous trading then matches the queued orders in arrival order and
+    /// fires the triggers the market touched meanwhile.
+    pub async fn set_market_phase(&self, phase: MarketPhase) -> Vec<Trade> {
+        let trades = self.change_market_phase(phase).await;
+        self.fire_triggers().await;
+        trades
+    }
+
+    async fn change_market_phase(&self, phase: MarketPhase) -> Vec<Trade> {
+        let mut order_book = self.order_book.write().await;
+        let previous = std::mem::replace(&mut *self.market_phase.write().await, phase);
+        if previous == phase {
+            return Vec::new();
+        }
+        if *self.trading_state.read().await == TradingState::Standby {
+            // the primary's auction and queue fills arrive as trades; what is left rests
+            if phase == MarketPhase::Continuous {
+                for order in self.queued_orders.write().await.drain(..) {
+                    if order.order_type == OrderType::Limit {
+                        order_book.add_order(order);
+                    }
+                }
+                self.observe_book(&order_book).await;
+            }
+            return Vec::new();
+        }
+
+        let mut new_trades = Vec::new();
+        let policies = *self.phase_policies.read().await;
+        if policies.off_hours_policy(previous) == Some(OffHoursPolicy::Auction) {
+            new_trades.extend(self.uncross(&mut order_book));
+        }
+
+        if phase == MarketPhase::Continuous {
+            let queued: Vec<Order> = self.queued_orders.write().await.drain(..).collect();
+            let policy = self.matching_policy.read().await.clone();
+            for order in queued {
+                let order_id = order.id.clone();
+                new_trades.extend(self.match_order(&mut order_book, order, policy.as_ref()));
+                self.release_unless_resting(&order_book, &order_id).await;
+            }
+        }
+        self.record_trades(&mut new_trades).await;
+        self.protect_quotes(&mut order_book, &new_trades).await;
+        if phase == MarketPhase::Closed {
+            self.expire_day_orders(&mut order_book).await;
+        }
+        self.observe_book(&order_book).await;
+        new_trades
+    }
+
+    /// Session close: pulls what is left of every resting, queued or untriggered DAY order,
+    /// after the closing auction had its chance to fill them
+    async fn expire_day_orders(&self, order_book: &mut OrderBook) -> Vec<Order> {
+        let is_day = |o: &Order| o.time_in_force == TimeInForce::Day;
+        let mut queued = self.queued_orders.write().await;
+        let (mut expired, kept): (Vec<Order>, Vec<Order>) = queued.drain(..).partition(is_day);
+        *queued = kept.into();
+        drop(queued);
+        let mut untriggered = self.untriggered_orders.write().await;
+        let (untriggered_expired, kept): (Vec<Order>, Vec<Order>) =
+            untriggered.drain(..).partition(is_day);
+        *untriggered = kept;
+        drop(untriggered);
+        expired.extend(untriggered_expired);
+        expired.extend(order_book.cancel_matching(is_day));
+
+        let mut wallets = self.wallets.write().await;
+        for order in &expired {
+            wallets.release(&order.id);
+        }
+        drop(wallets);
+        for order in &expired {
+            self.audit(AuditEvent::OrderExpired {
+                order: order.clone(),
+            })
+            .await;
+        }
+        expired
+    }
+
+    /// Price the auction would uncross at if it ended now
+    pub async fn indicative_equilibrium(&self) -> Option<Equilibrium> {
+        let order_book = self.order_book.read().await;
+        auction::equilibrium(&order_book)
+    }
+
+    /// Executes every crossed order at the single equilibrium price, in price/time priority
+    fn uncross(&self, order_book: &mut OrderBook) -> Vec<Trade> {
+        let mut new_trades = Vec::new();
+        let price = match auction::equilibrium(order_book) {
+            Some(eq) => eq.price,
+            None => return new_trades,
+        };
+
+        while let (Some(bid), Some(ask)) = (order_book.peek_best_buy(), order_book.peek_best_sell())
+        {
+            if bid.price < price || ask.price > price {
+                break;
+            }
+            let mut bid = order_book.pop_best_buy().unwrap();
+            let mut ask = order_book.pop_best_sell().unwrap();
+            let quantity = bid.quantity.min(ask.quantity);
+            let trade = Trade::new(
+                self.next_trade_id.fetch_add(1, Ordering::Relaxed),
+                bid.id.clone(),
+                ask.id.clone(),
+                price,
+                quantity,
+            )
+            .with_parties(bid.owner.clone(), ask.owner.clone());
+            matching::notify_trade(&self.trade_listeners, &trade, &bid, &ask);
+            new_trades.push(trade);
+
+            bid.fill(quantity);
+            ask.fill(quantity);
+            // partially filled orders keep their place at the front
+            if bid.quantity > 0 {
+                order_book.restore_level(Side::Buy, bid.price, vec![bid]);
+            }
+            if ask.quantity > 0 {
+                order_book.restore_level(Side::Sell, ask.price, vec![ask]);
+            }
+        }
+        new_trades
+    }
+
+    pub async fn trading_state(&self) -> TradingState {
+        *self.trading_state.read().await
+    }
+
+    /// Halts trading (or updates whether cancels are allowed on an already halted book).
+    /// A manual halt takes over from the circuit breaker, which no longer resumes on its own.
+    pub async fn halt(&self, allow_cancels: bool) -> TradingState {
+        let mut state = self.trading_state.write().await;
+        if *state == TradingState::Standby {
+            return *state; // trading is stopped anyway, and a halt would end the replication
+        }
+        self.take_over_breaker_halt().await;
+        let halted = TradingState::Halted { allow_cancels };
+        *state = halted;
+        drop(state);
+        self.audit(AuditEvent::TradingHalted { allow_cancels })
+            .await;
+        halted
+    }
+
+    /// Resumes trading, returns `false` if the book wasn't halted
+    pub async fn resume(&self) -> bool {
+        let mut state = self.trading_state.write().await;
+        if !matches!(*state, TradingState::Halted { .. }) {
+            return false;
+        }
+        self.take_over_breaker_halt().await;
+        *state = TradingState::Open;
+        drop(state);
+        self.audit(AuditEvent::TradingResumed).await;
+        true
+    }
+
+    /// Drops a halt of the circuit breaker, which an operator's halt or resume takes over from
+    async fn take_over_breaker_halt(&self) {
+        let mut breaker = self.circuit_breaker.write().await;
+        if breaker
+            .trip()
+            .is_some_and(|t| t.action == BreakerAction::Halt)
+        {
+            breaker.reset();
+        }
+    }
+
+    /// Turns the engine into a hot standby that only changes through
+    /// [`MatchingEngine::apply_replicated`]
+    pub async fn start_standby(&self) {
+        *self.trading_state.write().await = TradingState::Standby;
+    }
+
+    pub async fn is_standby(&self) -> bool {
+        *self.trading_state.read().await == TradingState::Standby
+    }
+
+    /// Seeds a standby that hasn't applied any event with a snapshot of the primary; replication
+    /// then continues after `state.last_seq`. The engine stays a standby whatever the trading
+    /// state in the snapshot.
+    pub async fn restore_standby(&self, state: EngineState) -> Result<(), ReplicationError> {
+        if !self.is_standby().await {
+            return Err(ReplicationError::NotStandby);
+        }
+        let last_seq = self.last_seq().await;
+        if last_seq != 0 {
+            return Err(ReplicationError::AlreadyFollowing { last_seq });
+        }
+        state.validate().map_err(|errors| {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|e| format!("{}: {}", e.field, e.message))
+                .collect();
+            ReplicationError::InvalidSnapshot(messages.join(", "))
+        })?;
+        // the primary's state is taken over on promotion, a standby's own is never exported
+        *self.replicated_trading.write().await = match state.trading_state {
+            TradingState::Standby => TradingState::Open,
+            trading => trading,
+        };
+        self.import_state(EngineState {
+            trading_state: TradingState::Standby,
+            ..state
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Failover: a standby takes over where the primary left off, open for trading or halted
+    /// as the primary was. Returns `false` if the engine wasn't a standby.
+    pub async fn promote(&self) -> bool {
+        let _order_book = self.order_book.write().await; // no replicated event is half applied
+        let mut state = self.trading_state.write().await;
+        if *state != TradingState::Standby {
+            return false;
+        }
+        // a halt by the primary's breaker carries on here until its cooldown is over
+        *state = *self.replicated_trading.read().await;
+        true
+    }
+
+    /// Applies an event of the primary's audit log to a standby: accepted orders rest (or queue
+    /// outside trading hours), trades fill the orders they name, cancels pull them and busts or
+    /// corrections update the trade. The entry joins the local log unchanged, so both chains
+    /// stay identical; entries already applied are skipped. Wallet balances aren't replicated.
+    pub async fn apply_replicated(&self, entry: AuditEntry) -> Result<(), ReplicationError> {
+        let mut order_book = self.order_book.write().await;
+        if *self.trading_state.read().await != TradingState::Standby {
+            return Err(ReplicationError::NotStandby);
+        }
+        let event = entry.event.clone();
+        let mut audit_log = self.audit_log.write().await;
+        if !audit_log.append_replicated(entry.clone())? {
+            return Ok(());
+        }
+        self.event_bus.publish(entry);
+        drop(audit_log);
+
+        match event {
+            AuditEvent::OrderScheduled { order, activate_at } => {
+                self.scheduled_orders
+                    .write()
+                    .await
+                    .insert(activate_at, order);
+            }
+            AuditEvent::OrderAccepted { order } if order.trigger.is_some() => {
+                self.untriggered_orders.write().await.push(order);
+            }
+            AuditEvent::OrderTriggered { order } => {
+                self.untriggered_orders
+                    .write()
+                    .await
+                    .retain(|o| o.id != order.id);
+            }
+            AuditEvent::TriggerMoved {
+                order_id, price, ..
+            } => {
+                let mut untriggered = self.untriggered_orders.write().await;
+                if let Some(trigger) = untriggered
+                    .iter_mut()
+                    .find(|o| o.id == order_id)
+                    .and_then(|o| o.trigger.as_mut())
+                {
+                    trigger.price = price;
+                }
+            }
+            AuditEvent::OrderAccepted { order } => {
+                self.scheduled_orders.write().await.remove(&order.id); // released
+                let phase = *self.market_phase.read().await;
+                let policy = self.phase_policies.read().await.off_hours_policy(phase);
+                if policy == Some(OffHoursPolicy::Queue) {
+                    order_book.note_arrival(order.arrival);
+                    self.queued_orders.write().await.push_back(order);
+                } else if order.order_type == OrderType::Limit {
+                    order_book.add_order(order);
+                }
+            }
+            AuditEvent::OrderCanceled { order } | AuditEvent::OrderExpired { order } => {
+                let mut queued = self.queued_orders.write().await;
+                let mut untriggered = self.untriggered_orders.write().await;
+                if let Some(pos) = queued.iter().position(|o| o.id == order.id) {
+                    queued.remove(pos);
+                } else if let Some(pos) = untriggered.iter().position(|o| o.id == order.id) {
+                    untriggered.remove(pos);
+                } else if self
+                    .scheduled_orders
+                    .write()
+                    .await
+                    .remove(&order.id)
+                    .is_none()
+                {
+                    order_book.cancel_order(order.id);
+                }
+            }
+            AuditEvent::QuoteUpdated {
+                owner: Some(owner),
+                orders,
+            } => {
+                let mut quotes = self.quotes.write().await;
+                if orders.is_empty() {
+                    quotes.remove(&owner);
+                } else {
+                    quotes.insert(owner, orders);
+                }
+            }
+            AuditEvent::QuoteProtectionTripped {
+                owner: Some(owner), ..
+            } => {
+                self.quotes.write().await.remove(&owner); // the pulled orders came as cancels
+            }
+            AuditEvent::RfqCreated { rfq } => self.rfqs.write().await.insert(rfq),
+            AuditEvent::RfqQuoted {
+                rfq_id, response, ..
+            } => {
+                if let Some(rfq) = self.rfqs.write().await.get_mut(&rfq_id) {
+                    rfq.respond(response);
+                }
+            }
+            AuditEvent::RfqClosed { rfq_id, .. } => {
+                self.rfqs.write().await.remove(&rfq_id); // its trade came as an execution
+            }
+            AuditEvent::QuoteUpdated { owner: None, .. }
+            | AuditEvent::QuoteProtectionTripped { owner: None, .. } => {}
+            AuditEvent::TradeExecuted { trade } => {
+                let mut queued = self.queued_orders.write().await;
+                for order_id in [&trade.buy_order_id, &trade.sell_order_id] {
+                    fill_replicated(&mut order_book, &mut queued, order_id, trade.quantity);
+                }
+                drop(queued);
+                self.next_trade_id
+                    .fetch_max(trade.id + 1, Ordering::Relaxed);
+                self.apply_replicated_trade(trade).await;
+            }
+            AuditEvent::TradeBusted { trade } | AuditEvent::TradeCorrected { trade } => {
+                self.apply_replicated_adjustment(trade).await;
+            }
+            AuditEvent::OrderRejected { order_id, .. } => {
+                self.scheduled_orders.write().await.remove(&order_id); // refused on release
+            }
+            AuditEvent::CircuitBreakerTripped { trip } => {
+                if trip.action == BreakerAction::Halt {
+                    *self.replicated_trading.write().await = TradingState::Halted {
+                        allow_cancels: true,
+                    };
+                }
+                self.circuit_breaker.write().await.restore(trip);
+            }
+            AuditEvent::CircuitBreakerEnded { .. } => {
+                self.circuit_breaker.write().await.reset();
+            }
+            AuditEvent::ConfigChanged { config } => self.apply_config(&config).await,
+            AuditEvent::ParticipantBlocked { owner: Some(owner) } => {
+                self.blocked_participants.write().await.insert(owner);
+            }
+            AuditEvent::ParticipantUnblocked { owner: Some(owner) } => {
+                self.blocked_participants.write().await.remove(&owner);
+            }
+            AuditEvent::ParticipantBlocked { owner: None }
+            | AuditEvent::ParticipantUnblocked { owner: None } => {}
+            AuditEvent::TradingHalted { allow_cancels } => {
+                self.take_over_breaker_halt().await;
+                *self.replicated_trading.write().await = TradingState::Halted { allow_cancels };
+            }
+            AuditEvent::TradingResumed => {
+                self.take_over_breaker_halt().await;
+                *self.replicated_trading.write().await = TradingState::Open;
+            }
+        }
+        self.observe_book(&order_book).await;
+        Ok(())
+    }
+
+    /// Books a replicated trade the way [`MatchingEngine::record_trades`] books a local one,
+    /// fees as charged by the primary
+    async fn apply_replicated_trade(&self, trade: Trade) {
+        let now = self.clock.now_ms();
+        self.fees.write().await.apply(&trade, now);
+        self.positions.write().await.apply(&trade);
+        self.settlement.write().await.record(&trade, 1);
+        self.owner_trades.write().await.record(&trade);
+        self.rolling_volume.write().await.record(&trade, now);
+        let mut trades = self.trades.write().await;
+        self.push_trade(&mut trades, trade).await;
+    }
+
+    /// Swaps in a busted or corrected trade, reversing the original
+    async fn apply_replicated_adjustment(&self, adjusted: Trade) {
+        let mut trades = self.trades.write().await;
+        let Some(trade) = trades.iter_mut().find(|t| t.id == adjusted.id) else {
+            return; // already out of the pool
+        };
+        let mut positions = self.positions.write().await;
+        let mut settlement = self.settlement.write().await;
+        let mut fees = self.fees.write().await;
+        positions.reverse(trade);
+        settlement.record(trade, -1);
+        fees.reverse(trade);
+        *trade = adjusted;
+        if !trade.is_busted() {
+            positions.apply(trade);
+            settlement.record(trade, 1);
+            fees.apply(trade, self.clock.now_ms());
+        }
+        self.owner_trades.write().await.update(trade);
+        self.rolling_volume.write().await.update(trade);
+    }
+
+    /// Checks that the book lock can be acquired within `timeout`, i.e. the matcher is not wedged
+    pub async fn is_book_reachable(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, self.order_book.read())
+            .await
+            .is_ok()
+    }
+
+    /// The latest published view of the book. A stale view is republished only when the book
+    /// lock is free; while the matcher holds it, readers get the view as of the last change
+    /// they could see rather than wait for matching.
+    pub fn book_view(&self) -> Arc<BookSnapshot> {
+        if self.book_view_stale.load(Ordering::Acquire)
+            && let Ok(order_book) = self.order_book.try_read()
+        {
+            self.publish_book_view(&order_book);
+        }
+        self.book_view.load_full()
+    }
+
+    /// The view of the book as of every change published so far, waiting for the matcher if
+    /// it holds the book. For feeds that must not miss the last change of a burst.
+    pub async fn settled_book_view(&self) -> Arc<BookSnapshot> {
+        // the change behind an event may still be under way when the event is out
+        let order_book = self.order_book.read().await;
+        if self.book_view_stale.load(Ordering::Acquire) {
+            self.publish_book_view(&order_book);
+        }
+        self.book_view.load_full()
+    }
+
+    fn publish_book_view(&self, order_book: &OrderBook) {
+        // the book can't change while the caller holds the read lock
+        self.book_view.store(Arc::new(BookSnapshot::of(order_book)));
+        self.book_view_stale.store(false, Ordering::Release);
+    }
+
+    /// Resting buy orders, lowest price first; market data only sees [`Self::book_view`]
+    #[cfg(test)]
+    pub async fn get_buy_orders(&self) -> Vec<Order> {
+        self.order_book.read().await.get_buy_orders()
+    }
+
+    /// Resting sell orders, lowest price first
+    #[cfg(test)]
+    pub async fn get_sell_orders(&self) -> Vec<Order> {
+        self.order_book.read().await.get_sell_orders()
+    }
+}
+
+impl Default for MatchingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for MatchingEngine {
+    fn clone(&self) -> Self {
+        MatchingEngine {
+            order_book: Arc::clone(&self.order_book),
+            book_view: Arc::clone(&self.book_view),
+            book_view_stale: Arc::clone(&self.book_view_stale),
+            trades: Arc::clone(&self.trades),
+            trade_capacity: Arc::clone(&self.trade_capacity),
+            trade_spill: Arc::clone(&self.trade_spill),
+            retention_stats: Arc::clone(&self.retention_stats),
+            trading_state: Arc::clone(&self.trading_state),
+            replicated_trading: Arc::clone(&self.replicated_trading),
+            next_trade_id: Arc::clone(&self.next_trade_id),
+            started: self.started,
+            blocked_participants: Arc::clone(&self.blocked_participants),
+            market_phase: Arc::clone(&self.market_phase),
+            phase_policies: Arc::clone(&self.phase_policies),
+            queued_orders: Arc::clone(&self.queued_orders),
+            scheduled_orders: Arc::clone(&self.scheduled_orders),
+            untriggered_orders: Arc::clone(&self.untriggered_orders),
+            quotes: Arc::clone(&self.quotes),
+            quote_protection: Arc::clone(&self.quote_protection),
+            rfqs: Arc::clone(&self.rfqs),
+            otr: Arc::clone(&self.otr),
+            submission_queue: Arc::clone(&self.submission_queue),
+            circuit_breaker: Arc::clone(&self.circuit_breaker),
+            price_bands: Arc::clone(&self.price_bands),
+            reference_price: Arc::clone(&self.reference_price),
+            index_price: Arc::clone(&self.index_price),
+            fat_finger: Arc::clone(&self.fat_finger),
+            mark_price_method: Arc::clone(&self.mark_price_method),
+            risk_limits: Arc::clone(&self.risk_limits),
+            positions: Arc::clone(&self.positions),
+            wallets: Arc::clone(&self.wallets),
+            settlement: Arc::clone(&self.settlement),
+            fee_schedule: Arc::clone(&self.fee_schedule),
+            fees: Arc::clone(&self.fees),
+            owner_trades: Arc::clone(&self.owner_trades),
+            audit_log: Arc::clone(&self.audit_log),
+            event_bus: self.event_bus.clone(),
+            matching_policy: Arc::clone(&self.matching_policy),
+            risk_checks: Arc::clone(&self.risk_checks),
+            trade_listeners: Arc::clone(&self.trade_listeners),
+            rolling_volume: Arc::clone(&self.rolling_volume),
+            market_quality: Arc::clone(&self.market_quality),
+            clock: Arc::clone(&self.clock),
+            ids: Arc::clone(&self.ids),
+            order_counts: Arc::clone(&self.order_counts),
+            latency: Arc::clone(&self.latency),
+            waiting: Arc::clone(&self.waiting),
+            acks: Arc::clone(&self.acks),
+        }
+    }
+}
+
+/// Takes a replicated fill off the resting or queued order `order_id`, which leaves once
+/// nothing is left of it. Market orders never rest, so their side finds nothing.
+fn fill_replicated(
+    order_book: &mut OrderBook,
+    queued: &mut VecDeque<Order>,
+    order_id: &OrderId,
+    quantity: Quantity,
+) {
+    if let Some(order) = order_book.get_order(order_id) {
+        let mut level = order_book.take_level(order.side, order.price);
+        if let Some(resting) = level.iter_mut().find(|o| &o.id == order_id) {
+            resting.fill(quantity);
+        }
+        level.retain(|o| o.quantity > 0);
+        if !level.is_empty() {
+            order_book.restore_level(order.side, order.price, level);
+        }
+    } else if let Some(pos) = queued.iter().position(|o| &o.id == order_id) {
+        queued[pos].fill(quantity);
+        if queued[pos].quantity == 0 {
+            queued.remove(pos);
+        }
+    }
+}
+
+/// The trades of the spill file at `spilled`, up to the length written when it was taken
+async fn read_spill(spilled: Option<(PathBuf, u64)>) -> Vec<Trade> {
+    let Some((path, len)) = spilled else {
+        return Vec::new();
+    };
+    tokio::task::spawn_blocking(move || tradespill::read_spilled(&path, len))
+        .await
+        .expect("spill read panicked")
+        .unwrap_or_else(|e| {
+            println!("spilled trades unreadable: {e}");
+            Vec::new()
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::markprice::MarkPriceSource;
+    use crate::matchingpolicy::{ProRata, SizePriority};
+    use crate::order::{Trigger, TriggerKind};
+    use crate::risk::BandMode;
+    #[tokio::test]
+    async fn test_submit_order() {
+        let ob = OrderBook::new();
+        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 2000, 10, 1);
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 2000, 200, 2);
+        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 2000, 200, 1);
+        let o4 = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 2000, 500, 1);
+        let o5 = Order::new(
+            String::from("5"),
+            Side::Sell,
+            OrderType::Limit,
+            2000,
+            500,
+            1,
+        );
+        let o6 = Order::new(
+            String::from("6"),
+            Side::Sell,
+            OrderType::Limit,
+            2000,
+            500,
+            1,
+        );
+
+        let mut me = MatchingEngine::new();
+        me.order_book = Arc::new(RwLock::new(ob));
+
+        me.submit_order(o4).await.unwrap();
+        me.submit_order(o1).await.unwrap();
+        me.submit_order(o2).await.unwrap();
+        me.submit_order(o3).await.unwrap();
+        me.submit_order(o5).await.unwrap();
+        me.submit_order(o6).await.unwrap();
+
+        println!("{}", me.order_book.read().await);
+        println!("{}", me.order_book.read().await);
+        println!("{:?}", me.trades);
+    }
+
+    #[tokio::test]
+    async fn test_market_orders() {
+        let ob = OrderBook::new();
+        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Market, 20, 100, 1);
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Market, 200, 100, 2);
+
+        let o3 = Order::new(String::from("3"), Side::Sell, OrderType::Limit, 10, 2000, 1);
+
+        let mut me = MatchingEngine::new();
+        me.order_book = Arc::new(RwLock::new(ob));
+
+        me.submit_order(o3).await.unwrap();
+        me.submit_order(o1).await.unwrap();
+        me.submit_order(o2).await.unwrap();
+
+        println!("{}", me.order_book.read().await);
+        println!("TRADES: {:?}", me.trades.read().await);
+        println!("ORDER_MAP: {:?}", me.order_book.read().await.order_map);
+    }
+
+    #[tokio::test]
+    async fn test_trade_pool_size_timestamp() {
+        use rand::rng;
+        let mut rng = rng();
+        let mut engine = MatchingEngine::new();
+        const BUY_MOCK_SIZE: usize = 15000;
+        const SELL_MOCK_SIZE: usize = 15000;
+        for i in 0..BUY_MOCK_SIZE {
+            let price = rng.random_range(800..=1000);
+            let quantity = rng.random_range(100..=200);
+            let order = Order::new(
+                format!("{i}"),
+                Side::Buy,
+                OrderType::Limit,
+                quantity,
+                price,
+                i.try_into().unwrap(),
+            );
+            engine.submit_order(order).await.unwrap();
+        }
+
+        for i in 0..SELL_MOCK_SIZE {
+            let price = rng.random_range(800..=1000);
+            let quantity = rng.random_range(100..=200);
+            let order = Order::new(
+                format!("{i}"),
+                Side::Sell,
+                OrderType::Limit,
+                quantity,
+                price,
+                i.try_into().unwrap(),
+            );
+            engine.submit_order(order).await.unwrap();
+        }
+
+        println!("{:?}", engine.trades.read().await);
+
+        println!("\n{}", engine.trades.read().await.len());
+    }
+
+    #[tokio::test]
+    async fn test_book_reachable() {
+        let engine = MatchingEngine::new();
+        assert!(engine.is_book_reachable(Duration::from_millis(50)).await);
+
+        let _guard = engine.order_book.write().await; // simulate a wedged matcher
+        assert!(!engine.is_book_reachable(Duration::from_millis(50)).await);
+    }
+
+    #[tokio::test]
+    async fn test_book_view_without_lock() {
+        let mut engine = MatchingEngine::new();
+        let order = |id: &str| Order::new(id.to_string(), Side::Buy, OrderType::Limit, 10, 100, 1);
+        engine.submit_order(order("1")).await.unwrap();
+        assert_eq!(engine.book_view().bids[0].quantity, 10);
+
+        engine.submit_order(order("2")).await.unwrap();
+        {
+            let _guard = engine.order_book.write().await; // the matcher is busy
+            assert_eq!(engine.book_view().bids[0].quantity, 10);
+        }
+        assert_eq!(engine.book_view().bids[0].orders, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let mut engine = MatchingEngine::new();
+        let order = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+        };
+        engine
+            .submit_order(order("b1", Side::Buy, 10, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("b2", Side::Buy, 5, 99))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("s1", Side::Sell, 4, 100))
+            .await
+            .unwrap();
+        engine.halt(false).await;
+        assert!(
+            engine
+                .submit_order(order("s2", Side::Sell, 1, 100))
+                .await
+                .is_err()
+        );
+
+        let stats = engine.stats().await;
+        assert_eq!((stats.bids.orders, stats.bids.quantity), (2, 11));
+        assert_eq!(stats.bids.levels, 2);
+        assert_eq!(stats.asks.orders, 0);
+        assert_eq!(stats.trades_executed, 1);
+        assert_eq!((stats.orders.accepted, stats.orders.rejected), (3, 1));
+        assert_eq!(stats.latency.samples, 4);
+        assert!(stats.latency.max_ns >= stats.latency.p50_ns);
+        assert!(stats.memory.book_bytes > 0 && stats.memory.trade_pool_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_halt_and_resume() {
+        let mut engine = MatchingEngine::new();
+        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 10, 100, 1);
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 10, 100, 2);
+        engine.submit_order(o1).await.unwrap();
+
+        engine.halt(false).await;
+        assert_eq!(
+            engine.submit_order(o2.clone()).await,
+            Err(RejectReason::Halted)
+        );
+        assert_eq!(
+            engine.cancel_order(String::from("1")).await,
+            Err(RejectReason::Halted)
+        );
+
+        engine.halt(true).await;
+        assert_eq!(engine.cancel_order(String::from("1")).await, Ok(true));
+
+        assert!(engine.resume().await);
+        assert!(!engine.resume().await);
+        assert!(engine.submit_order(o2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bust_and_correct_trades() {
+        let mut engine = MatchingEngine::new();
+        let o1 = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 10, 100, 1);
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 4, 100, 2);
+        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 6, 100, 3);
+        engine.submit_order(o1).await.unwrap();
+        let t1 = engine.submit_order(o2).await.unwrap().remove(0);
+        let t2 = engine.submit_order(o3).await.unwrap().remove(0);
+        assert_ne!(t1.id, t2.id);
+
+        let busted = engine
+            .bust_trade(t1.id, String::from("erroneous"))
+            .await
+            .unwrap();
+        assert!(busted.is_busted());
+        assert_eq!(
+            engine.bust_trade(t1.id, String::from("again")).await,
+            Err(TradeAdjustmentError::AlreadyBusted)
+        );
+
+        let corrected = engine
+            .correct_trade(t2.id, Some(95), None, String::from("wrong price"))
+            .await
+            .unwrap();
+        assert_eq!(corrected.price, 95);
+        assert_eq!(
+            corrected.status,
+            TradeStatus::Corrected {
+                reason: String::from("wrong price"),
+                original_price: 100,
+                original_quantity: 6,
+            }
+        );
+        assert_eq!(engine.trades.read().await[1], corrected);
+
+        assert_eq!(
+            engine.bust_trade(999, String::new()).await,
+            Err(TradeAdjustmentError::NotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch() {
+        let mut engine = MatchingEngine::new();
+        let alice = String::from("alice");
+        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 10, 100, 1)
+            .with_owner(alice.clone());
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 10, 100, 2)
+            .with_owner(alice.clone());
+        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 10, 100, 3)
+            .with_owner(String::from("bob"));
+        engine.submit_order(o1).await.unwrap();
+        engine.submit_order(o3).await.unwrap();
+
+        let canceled = engine.kill_participant(alice.clone()).await.unwrap();
+        assert_eq!(canceled.len(), 1);
+        assert_eq!(engine.get_buy_orders().await.len(), 1);
+        assert_eq!(
+            engine.submit_order(o2.clone()).await,
+            Err(RejectReason::ParticipantBlocked)
+        );
+        assert_eq!(engine.blocked_participants().await, vec![alice.clone()]);
+
+        assert_eq!(engine.enable_participant(&alice).await, Ok(true));
+        assert_eq!(engine.enable_participant(&alice).await, Ok(false));
+        assert!(engine.submit_order(o2).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_market_phases() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_phase_policies(PhasePolicies {
+                pre_open: OffHoursPolicy::Queue,
+                closed: OffHoursPolicy::Reject,
+            })
+            .await;
+
+        engine.set_market_phase(MarketPhase::Closed).await;
+        let o1 = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 10, 100, 1);
+        assert_eq!(
+            engine.submit_order(o1.clone()).await,
+            Err(RejectReason::MarketClosed)
+        );
+        let stop = Order::new(String::from("s"), Side::Sell, OrderType::Market, 1, 0, 1)
+            .with_trigger(Trigger {
+                price: 90,
+                source: TriggerSource::LastTrade,
+                kind: TriggerKind::Stop,
+                trail: None,
+            });
+        assert_eq!(
+            engine.submit_order(stop).await,
+            Err(RejectReason::MarketClosed)
+        );
+
+        engine.set_market_phase(MarketPhase::PreOpen).await;
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 4, 100, 2);
+        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 4, 100, 3);
+        assert_eq!(engine.submit_order(o1).await, Ok(vec![]));
+        assert_eq!(engine.submit_order(o2).await, Ok(vec![]));
+        assert_eq!(engine.submit_order(o3).await, Ok(vec![]));
+        assert!(engine.get_sell_orders().await.is_empty()); // nothing rests before the open
+        assert_eq!(engine.cancel_order(String::from("3")).await, Ok(true));
+
+        let trades = engine.set_market_phase(MarketPhase::Continuous).await;
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].buy_order_id, "2");
+        assert_eq!(engine.get_sell_orders().await[0].quantity, 6);
+    }
+
+    #[tokio::test]
+    async fn test_opening_auction_uncross() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_phase_policies(PhasePolicies {
+                pre_open: OffHoursPolicy::Auction,
+                closed: OffHoursPolicy::Reject,
+            })
+            .await;
+        engine.set_market_phase(MarketPhase::PreOpen).await;
+
+        let orders = [
+            ("b1", Side::Buy, 102, 10),
+            ("b2", Side::Buy, 101, 20),
+            ("b3", Side::Buy, 100, 30),
+            ("s1", Side::Sell, 99, 15),
+            ("s2", Side::Sell, 100, 20),
+            ("s3", Side::Sell, 101, 40),
+        ];
+        for (i, (id, side, price, quantity)) in orders.into_iter().enumerate() {
+            let order = Order::new(
+                id.to_string(),
+                side,
+                OrderType::Limit,
+                quantity,
+                price,
+                i as u64,
+            );
+            assert_eq!(engine.submit_order(order).await, Ok(vec![])); // no matching while collecting
+        }
+        let market = Order::new(String::from("m"), Side::Buy, OrderType::Market, 5, 0, 9);
+        assert_eq!(
+            engine.submit_order(market).await,
+            Err(RejectReason::MarketOrderInAuction)
+        );
+        assert_eq!(engine.indicative_equilibrium().await.unwrap().price, 100);
+
+        let trades = engine.set_market_phase(MarketPhase::Continuous).await;
+        assert!(trades.iter().all(|t| t.price == 100));
+        assert_eq!(trades.iter().map(|t| t.quantity).sum::<u64>(), 35);
+
+        // the book is no longer crossed: b3 keeps 25 @ 100, s3 rests @ 101
+        assert_eq!(engine.indicative_equilibrium().await, None);
+        assert_eq!(engine.get_buy_orders().await.last().unwrap().quantity, 25);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let clock = VirtualClock::starting_at(1_000_000_000);
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            clock: Arc::new(clock.clone()),
+            ..Default::default()
+        });
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        engine
+            .set_circuit_breaker(CircuitBreakerConfig {
+                max_move_pct: 5.0,
+                cooldown: Duration::from_millis(50),
+                ..Default::default()
+            })
+            .await;
+
+        let orders = [
+            ("1", Side::Sell, 100),
+            ("2", Side::Buy, 100),
+            ("3", Side::Sell, 120),
+            ("4", Side::Buy, 120),
+        ];
+        for (i, (id, side, price)) in orders.into_iter().enumerate() {
+            let order = Order::new(id.to_string(), side, OrderType::Limit, 1, price, i as u64);
+            assert!(engine.submit_order(order).await.is_ok());
+        }
+        let trip = engine.circuit_breaker_trip().await.unwrap();
+        assert_eq!((trip.reference, trip.price), (100, 120));
+        assert_eq!(trip.tripped_at, 1_000);
+        assert_eq!(
+            engine.trading_state().await,
+            TradingState::Halted {
+                allow_cancels: true
+            }
+        );
+        let last = engine.audit_entries(1, usize::MAX).await.pop().unwrap();
+        assert!(matches!(last.event, AuditEvent::CircuitBreakerTripped { trip: t } if t == trip));
+
+        // the cooldown runs on the engine's clock
+        clock.advance(Duration::from_millis(49));
+        assert!(!engine.expire_circuit_breaker().await);
+        clock.advance(Duration::from_millis(1));
+        assert!(engine.expire_circuit_breaker().await);
+        assert_eq!(engine.trading_state().await, TradingState::Open);
+        assert_eq!(engine.circuit_breaker_trip().await, None);
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(matches!(
+            entries.last().unwrap().event,
+            AuditEvent::CircuitBreakerEnded { trip: t } if t == trip
+        ));
+
+        // a standby follows the trip and leaves lifting it to the primary
+        for entry in entries.iter().take(entries.len() - 1).cloned() {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert_eq!(standby.circuit_breaker_trip().await, Some(trip));
+        assert!(!standby.expire_circuit_breaker().await);
+        standby
+            .apply_replicated(entries.last().unwrap().clone())
+            .await
+            .unwrap();
+        assert_eq!(standby.circuit_breaker_trip().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_volatility_auction() {
+        let clock = VirtualClock::starting_at(1_000_000_000);
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            clock: Arc::new(clock.clone()),
+            ..Default::default()
+        });
+        engine
+            .set_circuit_breaker(CircuitBreakerConfig {
+                max_move_pct: 5.0,
+                cooldown: Duration::from_millis(50),
+                action: BreakerAction::Auction,
+                ..Default::default()
+            })
+            .await;
+
+        let o1 = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 1, 100, 1);
+        let o2 = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 1, 100, 2);
+        let o3 = Order::new(String::from("3"), Side::Sell, OrderType::Limit, 1, 80, 3);
+        let o4 = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 1, 80, 4);
+        for order in [o1, o2, o3, o4] {
+            engine.submit_order(order).await.unwrap();
+        }
+        assert_eq!(engine.market_phase().await, MarketPhase::VolatilityAuction);
+
+        // collected without matching during the auction, uncrossed after the cooldown
+        let o5 = Order::new(String::from("5"), Side::Sell, OrderType::Limit, 2, 90, 5);
+        let o6 = Order::new(String::from("6"), Side::Buy, OrderType::Limit, 2, 90, 6);
+        assert_eq!(engine.submit_order(o5).await, Ok(vec![]));
+        assert_eq!(engine.submit_order(o6).await, Ok(vec![]));
+
+        clock.advance(Duration::from_millis(50));
+        assert!(engine.expire_circuit_breaker().await);
+        assert_eq!(engine.market_phase().await, MarketPhase::Continuous);
+        let trades = engine.trades.read().await;
+        assert_eq!(trades.back().unwrap().price, 90);
+        assert_eq!(trades.back().unwrap().quantity, 2);
+    }
+
+    #[tokio::test]
+    async fn test_price_bands() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_price_bands(PriceBands {
+                pct: 10.0,
+                mode: BandMode::Reject,
+            })
+            .await;
+
+        // no reference price yet, anything goes
+        let o1 = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 1, 5000, 1);
+        assert!(engine.submit_order(o1).await.is_ok());
+
+        engine.set_reference_price(Some(1000)).await;
+        let o2 = Order::new(String::from("2"), Side::Sell, OrderType::Limit, 1, 1200, 2);
+        assert_eq!(
+            engine.submit_order(o2).await,
+            Err(RejectReason::PriceOutsideBands {
+                lower: 900,
+                upper: 1100
+            })
+        );
+
+        // falls back to the last trade once the static reference is cleared
+        engine.set_reference_price(None).await;
+        let o3 = Order::new(String::from("3"), Side::Buy, OrderType::Market, 1, 0, 3);
+        engine.submit_order(o3).await.unwrap();
+        assert_eq!(engine.reference_price().await, Some(5000));
+        assert_eq!(engine.band_limits().await.unwrap().lower, 4500);
+    }
+
+    #[tokio::test]
+    async fn test_fat_finger() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_fat_finger_check(FatFingerCheck {
+                max_deviation_pct: 5.0,
+            })
+            .await;
+
+        let bid = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 1, 990, 1);
+        let ask = Order::new(String::from("2"), Side::Sell, OrderType::Limit, 1, 1010, 2);
+        engine.submit_order(bid).await.unwrap();
+        engine.submit_order(ask).await.unwrap();
+
+        // mid is 1000
+        let fat = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 1, 1100, 3);
+        assert_eq!(
+            engine.submit_order(fat).await,
+            Err(RejectReason::FatFinger { reference: 1000 })
+        );
+        let ok = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 1, 1010, 4);
+        assert_eq!(engine.submit_order(ok).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_mark_price() {
+        let mut engine = MatchingEngine::new();
+        assert_eq!(engine.mark_price().await, None);
+
+        let bid = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 2, 98, 1);
+        let ask = Order::new(String::from("2"), Side::Sell, OrderType::Limit, 2, 102, 2);
+        engine.submit_order(bid).await.unwrap();
+        engine.submit_order(ask).await.unwrap();
+        assert_eq!(
+            engine.mark_price().await,
+            Some(MarkPrice {
+                price: 100,
+                source: MarkPriceSource::Mid
+            })
+        );
+
+        let hit = Order::new(String::from("3"), Side::Sell, OrderType::Market, 1, 0, 3);
+        engine.submit_order(hit).await.unwrap();
+        assert_eq!(engine.mark_price().await.unwrap().price, 98);
+
+        engine.set_mark_price_method(MarkPriceMethod::Mid).await;
+        assert_eq!(engine.mark_price().await.unwrap().price, 100);
+    }
+
+    #[tokio::test]
+    async fn test_virtual_clock_stamps() {
+        let clock = VirtualClock::starting_at(1_000_000_000);
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            clock: Arc::new(clock.clone()),
+            ..Default::default()
+        });
+        let ask = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 2, 100, 0);
+        engine.submit_order(ask).await.unwrap();
+        clock.advance(Duration::from_millis(5));
+        let bid = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 1, 100, 0);
+        let trades = engine.submit_order(bid).await.unwrap();
+
+        assert_eq!(trades[0].timestamp, 1_005_000_000);
+        assert_eq!(engine.get_sell_orders().await[0].timestamp, 1_000_000_000);
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.timestamp == 1_000 || e.timestamp == 1_005)
+        );
+        assert_eq!(entries.last().unwrap().timestamp, 1_005);
+    }
+
+    #[tokio::test]
+    async fn test_arrival_priority() {
+        let mut engine = MatchingEngine::new();
+        // an earlier timestamp doesn't jump the queue
+        for (id, timestamp) in [("first", 5), ("spoofed", 1)] {
+            let ask = Order::new(
+                id.to_string(),
+                Side::Sell,
+                OrderType::Limit,
+                1,
+                100,
+                timestamp,
+            );
+            engine.submit_order(ask).await.unwrap();
+        }
+        let asks = engine.get_sell_orders().await;
+        assert_eq!((asks[0].arrival, asks[1].arrival), (1, 2));
+
+        let bid = Order::new(String::from("bid"), Side::Buy, OrderType::Limit, 1, 100, 9);
+        let trades = engine.submit_order(bid).await.unwrap();
+        assert_eq!(trades[0].sell_order_id, "first");
+
+        let state = engine.export_state().await;
+        let mut restored = MatchingEngine::new();
+        restored.import_state(state).await;
+        let ask = Order::new(
+            String::from("late"),
+            Side::Sell,
+            OrderType::Limit,
+            1,
+            100,
+            0,
+        );
+        restored.submit_order(ask).await.unwrap();
+        let asks = restored.get_sell_orders().await;
+        assert_eq!(asks[0].id, "spoofed");
+        assert_eq!(asks[1].arrival, 3);
+    }
+
+    #[tokio::test]
+    async fn test_index_price_triggers() {
+        let mut engine = MatchingEngine::new();
+        engine.set_mark_price_method(MarkPriceMethod::Index).await;
+        let ask = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 2, 102, 1);
+        engine.submit_order(ask).await.unwrap();
+        let touch = Order::new(String::from("2"), Side::Buy, OrderType::Market, 1, 0, 2)
+            .with_trigger(Trigger {
+                price: 100,
+                source: TriggerSource::Mark,
+                kind: TriggerKind::Touch,
+                trail: None,
+            });
+        engine.submit_order(touch).await.unwrap();
+
+        engine.set_index_price(Some(105)).await;
+        assert_eq!(engine.last_trade_price().await, None);
+        engine.set_index_price(Some(99)).await;
+        assert_eq!(engine.last_trade_price().await, Some(102));
+        assert_eq!(
+            engine.mark_price().await,
+            Some(MarkPrice {
+                price: 99,
+                source: MarkPriceSource::Index
+            })
+        );
+
+        // stale, back to the book
+        engine.set_index_price(None).await;
+        assert_eq!(engine.mark_price().await.unwrap().price, 102);
+    }
+
+    #[tokio::test]
+    async fn test_risk_limits() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_default_risk_limits(RiskLimits {
+                max_order_quantity: Some(100),
+                ..Default::default()
+            })
+            .await;
+        engine
+            .set_risk_limits(String::from("mm"), RiskLimits::default())
+            .await;
+
+        let big = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 500, 10, 1);
+        assert_eq!(
+            engine
+                .submit_order(big.clone().with_owner(String::from("alice")))
+                .await,
+            Err(RejectReason::MaxQuantityExceeded { limit: 100 })
+        );
+        assert!(
+            engine
+                .submit_order(big.with_owner(String::from("mm")))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_config() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        for id in ["1", "2", "3"] {
+            let bid = Order::new(id.to_string(), Side::Buy, OrderType::Limit, 1, 90, 1);
+            engine.submit_order(bid.clone()).await.unwrap();
+            let ask = Order::new(format!("s{id}"), Side::Sell, OrderType::Limit, 1, 90, 1);
+            engine.submit_order(ask).await.unwrap();
+        }
+        let update = RuntimeConfig {
+            order_size: Some(OrderSizeLimits {
+                max_order_notional: None,
+                max_order_quantity: Some(100),
+            }),
+            trade_pool_size: Some(2),
+            ..Default::default()
+        };
+        engine.update_config(update).await;
+
+        let config = engine.runtime_config().await;
+        assert_eq!(config.order_size, update.order_size);
+        assert_eq!(config.trade_pool_size, Some(2));
+        assert_eq!(config.price_bands, Some(PriceBands::default())); // left alone
+        assert_eq!(engine.trades.read().await.len(), 2);
+        let big = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 500, 10, 2);
+        assert_eq!(
+            engine.submit_order(big).await,
+            Err(RejectReason::MaxQuantityExceeded { limit: 100 })
+        );
+
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(entries.iter().any(|entry| matches!(
+            entry.event,
+            AuditEvent::ConfigChanged { config } if config == update
+        )));
+        for entry in entries {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert_eq!(standby.runtime_config().await, config);
+    }
+
+    #[tokio::test]
+    async fn test_position_limits() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_default_risk_limits(RiskLimits {
+                max_long_position: Some(10),
+                ..Default::default()
+            })
+            .await;
+        let alice = String::from("alice");
+        let bob = String::from("bob");
+
+        let ask = Order::new(String::from("1"), Side::Sell, OrderType::Limit, 20, 100, 1)
+            .with_owner(bob.clone());
+        engine.submit_order(ask).await.unwrap();
+        let buy = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 6, 100, 2)
+            .with_owner(alice.clone());
+        let trades = engine.submit_order(buy).await.unwrap();
+        assert_eq!(trades[0].buyer.as_deref(), Some("alice"));
+        assert_eq!(engine.net_position(&alice).await, 6);
+        assert_eq!(engine.net_position(&bob).await, -6);
+
+        // resting bid counts towards the worst case
+        let bid = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 3, 90, 3)
+            .with_owner(alice.clone());
+        engine.submit_order(bid).await.unwrap();
+        let over = Order::new(String::from("4"), Side::Buy, OrderType::Limit, 2, 100, 4)
+            .with_owner(alice.clone());
+        assert_eq!(
+            engine.submit_order(over.clone()).await,
+            Err(RejectReason::PositionLimitExceeded { limit: 10 })
+        );
+
+        // busting the fill frees up the room again
+        engine
+            .bust_trade(trades[0].id, String::from("error"))
+            .await
+            .unwrap();
+        assert_eq!(engine.net_position(&alice).await, 0);
+        assert!(engine.submit_order(over).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_order_ownership() {
+        let mut engine = MatchingEngine::new();
+        let alice = String::from("alice");
+        let o1 = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 5, 100, 1)
+            .with_owner(alice.clone());
+        let o2 = Order::new(String::from("2"), Side::Sell, OrderType::Limit, 2, 100, 2)
+            .with_owner(String::from("bob"));
+        engine.submit_order(o1).await.unwrap();
+        engine.submit_order(o2).await.unwrap();
+
+        let orders = engine.owner_orders(&alice).await;
+        assert_eq!(orders[0].0.quantity, 3);
+        assert_eq!(orders[0].0.filled_quantity, 2);
+        assert_eq!(orders[0].1, OrderStatus::PartiallyFilled);
+        assert_eq!(engine.owner_trades(&alice, None, 10).await.len(), 1);
+        assert!(engine.owner_orders("bob").await.is_empty());
+
+        assert_eq!(
+            engine.cancel_owned_order(String::from("1"), "bob").await,
+            Err(RejectReason::NotOrderOwner)
+        );
+        assert_eq!(
+            engine.cancel_owned_order(String::from("1"), &alice).await,
+            Ok(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wallet_holds() {
+        let mut engine = MatchingEngine::new();
+        engine.set_wallets(Wallets::new(true)).await;
+        let alice = String::from("alice");
+        let bob = String::from("bob");
+        engine.deposit(alice.clone(), Asset::Quote, 1_000).await;
+        engine.deposit(bob.clone(), Asset::Base, 5).await;
+
+        let too_big = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 11, 100, 1)
+            .with_owner(alice.clone());
+        assert_eq!(
+            engine.submit_order(too_big).await,
+            Err(RejectReason::InsufficientBalance)
+        );
+
+        let bid = Order::new(String::from("2"), Side::Buy, OrderType::Limit, 8, 100, 2)
+            .with_owner(alice.clone());
+        engine.submit_order(bid).await.unwrap();
+        assert_eq!(engine.balances(&alice).await.quote.held, 800);
+
+        let ask = Order::new(String::from("3"), Side::Sell, OrderType::Limit, 5, 100, 3)
+            .with_owner(bob.clone());
+        engine.submit_order(ask).await.unwrap();
+        let balances = engine.balances(&alice).await;
+        assert_eq!((balances.quote.total, balances.quote.held), (500, 300));
+        assert_eq!(balances.base.total, 5);
+        assert_eq!(engine.balances(&bob).await.quote.total, 500);
+
+        // canceling the rest of the bid frees the remaining hold
+        engine.cancel_order(String::from("2")).await.unwrap();
+        assert_eq!(engine.balances(&alice).await.quote.available(), 500);
+    }
+
+    #[tokio::test]
+    async fn test_maker_taker_fees() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_fee_schedule(FeeSchedule {
+                maker_bps: 5,
+                taker_bps: 20,
+                tiers: Vec::new(),
+            })
+            .await;
+        let bid = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 10, 1_000, 1)
+            .with_owner(String::from("alice"));
+        let ask = Order::new(
+            String::from("2"),
+            Side::Sell,
+            OrderType::Limit,
+            10,
+            1_000,
+            2,
+        )
+        .with_owner(String::from("bob"));
+        engine.submit_order(bid).await.unwrap();
+        let trades = engine.submit_order(ask).await.unwrap();
+
+        // bob took liquidity
+        assert_eq!((trades[0].buyer_fee, trades[0].seller_fee), (5, 20));
+        assert_eq!(engine.fees_paid("bob").await.0, 20);
+
+        engine
+            .bust_trade(trades[0].id, String::from("test"))
+            .await
+            .unwrap();
+        assert_eq!(engine.fees_paid("bob").await.0, 0);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let mut engine = MatchingEngine::new();
+        let bid = Order::new(String::from("1"), Side::Buy, OrderType::Limit, 10, 100, 1);
+        let ask = Order::new(String::from("2"), Side::Sell, OrderType::Limit, 4, 100, 2);
+        engine.submit_order(bid).await.unwrap();
+        let trades = engine.submit_order(ask).await.unwrap();
+        assert_eq!(trades[0].seq, 3); // after both accepts
+        engine.cancel_order(String::from("1")).await.unwrap();
+        engine.halt(false).await;
+        let rejected = Order::new(String::from("3"), Side::Buy, OrderType::Limit, 1, 100, 3);
+        assert!(engine.submit_order(rejected).await.is_err());
+
+        let types: Vec<&str> = engine
+            .audit_entries(1, 100)
+            .await
+            .iter()
+            .map(|e| match e.event {
+                AuditEvent::OrderAccepted { .. } => "accepted",
+                AuditEvent::TradeExecuted { .. } => "trade",
+                AuditEvent::OrderCanceled { .. } => "canceled",
+                AuditEvent::OrderRejected { .. } => "rejected",
+                AuditEvent::TradingHalted { .. } => "halted",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                "accepted", "accepted", "trade", "canceled", "halted", "rejected"
+            ]
+        );
+        let (entries, _, result) = engine.verify_audit_log().await;
+        assert_eq!((entries, result), (6, Ok(())));
+        assert_eq!(engine.last_seq().await, 6);
+    }
+
+    #[tokio::test]
+    async fn test_deployment_risk_check() {
+        /// Refuses orders that would take the best ask by more than a tick
+        struct NoSweep;
+        impl RiskCheck for NoSweep {
+            fn name(&self) -> &'static str {
+                "no_sweep"
+            }
+            fn check(&self, order: &Order, ctx: &BookContext) -> Result<(), RejectReason> {
+                match ctx.best_ask {
+                    Some(ask) if order.side == Side::Buy && order.price > ask + 1 => {
+                        Err(RejectReason::RiskCheckFailed)
+                    }
+                    _ => Ok(()),
+                }
+            }
+        }
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            risk_checks: vec![Arc::new(NoSweep)],
+            ..Default::default()
+        });
+        let order =
+            |id: &str, side, price| Order::new(id.to_string(), side, OrderType::Limit, 5, price, 1);
+        engine
+            .submit_order(order("s1", Side::Sell, 100))
+            .await
+            .unwrap();
+        assert_eq!(
+            engine.submit_order(order("b1", Side::Buy, 105)).await,
+            Err(RejectReason::RiskCheckFailed)
+        );
+        assert_eq!(
+            engine
+                .submit_order(order("b2", Side::Buy, 101))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // the built-in checks run first
+        engine
+            .set_fat_finger_check(FatFingerCheck {
+                max_deviation_pct: 1.0,
+            })
+            .await;
+        engine
+            .submit_order(order("s2", Side::Sell, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("b3", Side::Buy, 99))
+            .await
+            .unwrap();
+        assert!(matches!(
+            engine.submit_order(order("b4", Side::Buy, 150)).await,
+            Err(RejectReason::FatFinger { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pro_rata_allocation() {
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            policy: Arc::new(ProRata { min_quantity: 1 }),
+            ..Default::default()
+        });
+        for (id, quantity) in [("a", 200), ("b", 100), ("c", 100)] {
+            let ask = Order::new(
+                id.to_string(),
+                Side::Sell,
+                OrderType::Limit,
+                quantity,
+                100,
+                1,
+            );
+            engine.submit_order(ask).await.unwrap();
+        }
+        let better = Order::new(String::from("d"), Side::Sell, OrderType::Limit, 10, 99, 2);
+        engine.submit_order(better).await.unwrap();
+
+        let buy = Order::new(
+            String::from("buy"),
+            Side::Buy,
+            OrderType::Limit,
+            110,
+            100,
+            3,
+        );
+        let trades = engine.submit_order(buy).await.unwrap();
+        let fills: Vec<(&str, u64)> = trades
+            .iter()
+            .map(|t| (t.sell_order_id.as_str(), t.quantity))
+            .collect();
+        // the better priced level fills first, the rest is split by size
+        assert_eq!(fills, vec![("d", 10), ("a", 50), ("b", 25), ("c", 25)]);
+        let asks = engine.get_sell_orders().await;
+        assert_eq!(
+            asks.iter().map(|o| o.quantity).collect::<Vec<_>>(),
+            vec![150, 75, 75]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_size_priority() {
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            policy: Arc::new(SizePriority),
+            ..Default::default()
+        });
+        for (id, quantity, ts) in [("a", 5, 1), ("b", 20, 2), ("c", 20, 3)] {
+            let ask = Order::new(
+                id.to_string(),
+                Side::Sell,
+                OrderType::Limit,
+                quantity,
+                100,
+                ts,
+            );
+            engine.submit_order(ask).await.unwrap();
+        }
+        let buy = Order::new(String::from("buy"), Side::Buy, OrderType::Market, 30, 0, 4);
+        let trades = engine.submit_order(buy).await.unwrap();
+        let fills: Vec<(&str, u64)> = trades
+            .iter()
+            .map(|t| (t.sell_order_id.as_str(), t.quantity))
+            .collect();
+        assert_eq!(fills, vec![("b", 20), ("c", 10)]);
+        let asks = engine.get_sell_orders().await;
+        assert_eq!(
+            asks.iter()
+                .map(|o| (o.id.as_str(), o.quantity))
+                .collect::<Vec<_>>(),
+            vec![("a", 5), ("c", 10)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_state_round_trip() {
+        let mut engine = MatchingEngine::new();
+        for (id, price, ts) in [("a", 100, 1), ("b", 100, 2), ("c", 101, 3)] {
+            let ask = Order::new(id.to_string(), Side::Sell, OrderType::Limit, 10, price, ts)
+                .with_owner("alice".to_string());
+            engine.submit_order(ask).await.unwrap();
+        }
+        let buy = Order::new(String::from("d"), Side::Buy, OrderType::Limit, 4, 100, 4)
+            .with_owner("bob".to_string());
+        engine.submit_order(buy).await.unwrap();
+        engine.halt(true).await;
+
+        let state = engine.export_state().await;
+        assert_eq!(state.last_seq, engine.last_seq().await);
+        // round trips through JSON, as over `/admin/state`
+        let state: EngineState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert_eq!(state.validate(), Ok(()));
+
+        let mut restored = MatchingEngine::new();
+        restored.import_state(state).await;
+        let ids = |orders: Vec<Order>| -> Vec<(OrderId, Quantity)> {
+            orders.into_iter().map(|o| (o.id, o.quantity)).collect()
+        };
+        assert_eq!(
+            ids(restored.get_sell_orders().await),
+            ids(engine.get_sell_orders().await)
+        );
+        assert_eq!(restored.trading_state().await, engine.trading_state().await);
+        assert_eq!(restored.net_position("bob").await, 4);
+        assert_eq!(restored.last_seq().await, engine.last_seq().await);
+
+        // numbering and the audit chain carry on where the original stopped
+        restored.resume().await;
+        let buy = Order::new(String::from("e"), Side::Buy, OrderType::Limit, 6, 100, 5);
+        let trades = restored.submit_order(buy).await.unwrap();
+        assert_eq!(trades[0].id, 2);
+        assert_eq!(trades[0].sell_order_id, "a"); // "a" kept its time priority
+        let (_, _, verified) = restored.verify_audit_log().await;
+        assert_eq!(verified, Ok(()));
+        let first = restored.audit_entries(0, 1).await;
+        assert_eq!(first[0].seq, engine.last_seq().await + 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_export_alongside_busts() {
+        let mut engine = MatchingEngine::new();
+        for i in 0..200 {
+            let ask = Order::new(format!("s{i}"), Side::Sell, OrderType::Limit, 1, 100, 1);
+            engine.submit_order(ask).await.unwrap();
+            let bid = Order::new(format!("b{i}"), Side::Buy, OrderType::Limit, 1, 100, 1);
+            engine.submit_order(bid).await.unwrap();
+        }
+        let mut tasks = Vec::new();
+        for trade_id in 1..=200 {
+            let busting = engine.clone();
+            tasks.push(tokio::spawn(async move {
+                busting
+                    .bust_trade(trade_id, String::from("error"))
+                    .await
+                    .unwrap();
+            }));
+            let exporting = engine.clone();
+            tasks.push(tokio::spawn(async move {
+                let state = exporting.export_state().await;
+                // the busts the export saw are the ones its sequence number covers
+                let busted = state.trades.iter().filter(|t| t.is_busted()).count() as u64;
+                assert_eq!(state.last_seq, 600 + busted);
+            }));
+        }
+        let all = futures_util::future::join_all(tasks);
+        for task in tokio::time::timeout(Duration::from_secs(10), all)
+            .await
+            .expect("export deadlocked with a bust")
+        {
+            task.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hot_standby() {
+        let mut primary = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        for (id, price, ts) in [("a", 100, 1), ("b", 100, 2), ("c", 101, 3)] {
+            let ask = Order::new(id.to_string(), Side::Sell, OrderType::Limit, 10, price, ts)
+                .with_owner("alice".to_string());
+            primary.submit_order(ask).await.unwrap();
+        }
+        let buy = Order::new(String::from("d"), Side::Buy, OrderType::Market, 14, 0, 4)
+            .with_owner("bob".to_string());
+        primary.submit_order(buy).await.unwrap();
+        primary.cancel_order(String::from("c")).await.unwrap();
+        primary.bust_trade(1, String::from("error")).await.unwrap();
+
+        let entries = primary.audit_entries(1, usize::MAX).await;
+        // a gap is refused, replays of applied entries are skipped
+        assert_eq!(
+            standby.apply_replicated(entries[1].clone()).await,
+            Err(ReplicationError::OutOfSequence {
+                expected: 1,
+                got: 2
+            })
+        );
+        for entry in entries.iter().chain(&entries[..2]) {
+            standby.apply_replicated(entry.clone()).await.unwrap();
+        }
+        let ids = |orders: Vec<Order>| -> Vec<(OrderId, Quantity)> {
+            orders.into_iter().map(|o| (o.id, o.quantity)).collect()
+        };
+        assert_eq!(
+            ids(standby.get_sell_orders().await),
+            vec![("b".to_string(), 6)]
+        );
+        assert_eq!(standby.last_seq().await, primary.last_seq().await);
+        assert_eq!(
+            standby
+                .audit_entries(1, usize::MAX)
+                .await
+                .last()
+                .unwrap()
+                .hash,
+            entries.last().unwrap().hash
+        );
+        assert_eq!(standby.net_position("bob").await, 4); // the busted fill is reversed
+        assert!(standby.trades.read().await[0].is_busted());
+
+        let sell = Order::new(String::from("e"), Side::Sell, OrderType::Limit, 1, 100, 5);
+        assert_eq!(
+            standby.clone().submit_order(sell.clone()).await,
+            Err(RejectReason::Standby)
+        );
+        assert!(standby.promote().await);
+        assert!(!standby.promote().await);
+        assert_eq!(
+            standby.apply_replicated(entries[0].clone()).await,
+            Err(ReplicationError::NotStandby)
+        );
+        standby.clone().submit_order(sell).await.unwrap();
+        let (_, _, verified) = standby.verify_audit_log().await;
+        assert_eq!(verified, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_standby_follows_admin_commands() {
+        let primary = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        assert_eq!(
+            standby.kill_participant("mallory".to_string()).await,
+            Err(RejectReason::Standby)
+        );
+        assert_eq!(
+            standby.enable_participant("mallory").await,
+            Err(RejectReason::Standby)
+        );
+
+        primary
+            .kill_participant("mallory".to_string())
+            .await
+            .unwrap();
+        primary.kill_participant("eve".to_string()).await.unwrap();
+        assert_eq!(primary.enable_participant("eve").await, Ok(true));
+        primary.halt(false).await;
+        for entry in primary.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert_eq!(standby.blocked_participants().await, ["mallory"]);
+
+        // the promoted standby stays halted like its primary, until an operator resumes it
+        assert!(standby.promote().await);
+        assert_eq!(
+            standby.trading_state().await,
+            TradingState::Halted {
+                allow_cancels: false
+            }
+        );
+        assert!(standby.resume().await);
+        let blocked = Order::new(String::from("m"), Side::Buy, OrderType::Limit, 1, 100, 1)
+            .with_owner("mallory".to_string());
+        assert_eq!(
+            standby.clone().submit_order(blocked).await,
+            Err(RejectReason::ParticipantBlocked)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_standby_snapshot_catch_up() {
+        let mut primary = MatchingEngine::new();
+        let order = |id: &str, side, ts| {
+            Order::new(id.to_string(), side, OrderType::Limit, 10, 100, ts)
+                .with_owner("alice".to_string())
+        };
+        primary
+            .submit_order(order("a", Side::Sell, 1))
+            .await
+            .unwrap();
+        primary
+            .submit_order(order("b", Side::Sell, 2))
+            .await
+            .unwrap();
+        let snapshot = primary.export_state().await;
+        let snapshot_seq = snapshot.last_seq;
+        primary
+            .submit_order(order("c", Side::Buy, 3))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            MatchingEngine::new()
+                .restore_standby(primary.export_state().await)
+                .await,
+            Err(ReplicationError::NotStandby)
+        );
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        standby.restore_standby(snapshot).await.unwrap();
+        assert!(standby.is_standby().await);
+        for entry in primary.audit_entries(snapshot_seq + 1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert_eq!(
+            standby.restore_standby(primary.export_state().await).await,
+            Err(ReplicationError::AlreadyFollowing {
+                last_seq: primary.last_seq().await
+            })
+        );
+        let ids = |orders: Vec<Order>| -> Vec<(OrderId, Quantity)> {
+            orders.into_iter().map(|o| (o.id, o.quantity)).collect()
+        };
+        assert_eq!(
+            ids(standby.get_sell_orders().await),
+            vec![("b".to_string(), 10)]
+        );
+        assert_eq!(standby.trades.read().await.len(), 1);
+        let (_, _, verified) = standby.verify_audit_log().await;
+        assert_eq!(verified, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_trade_spill() {
+        let mut engine = MatchingEngine::new();
+        let dir = std::env::temp_dir().join(format!("ome-history-{}", std::process::id()));
+        let spill = TradeSpill::create(dir.join("root.trades.jsonl")).unwrap();
+        engine.set_trade_history(2, Some(spill)).await;
+        for ts in 1..=3 {
+            let ask = Order::new(format!("s{ts}"), Side::Sell, OrderType::Limit, 1, 100, ts);
+            let bid = Order::new(format!("b{ts}"), Side::Buy, OrderType::Limit, 1, 100, ts);
+            engine.submit_order(ask).await.unwrap();
+            engine.submit_order(bid).await.unwrap();
+        }
+        let ids = |trades: Vec<Trade>| trades.iter().map(|t| t.id).collect::<Vec<_>>();
+        let pool: Vec<Trade> = engine.trades.read().await.iter().cloned().collect();
+        assert_eq!(ids(pool), [2, 3]);
+        assert_eq!(ids(engine.trade_history().await), [1, 2, 3]);
+
+        // shrinking the pool spills the overflow
+        let spill = TradeSpill::create(dir.join("shrunk.trades.jsonl")).unwrap();
+        engine.set_trade_history(1, Some(spill)).await;
+        assert_eq!(ids(engine.trade_history().await), [2, 3]);
+
+        // an export refers to the spilled trades, so importing it loses none
+        let state = engine.export_state().await;
+        assert_eq!(ids(state.trades.clone()), [3]);
+        assert_eq!(state.spilled_trades.as_ref().map(|s| s.trades), Some(1));
+        engine.import_state(state).await;
+        assert_eq!(engine.hot_trades().await, (1, 1));
+        assert_eq!(ids(engine.trade_history().await), [2, 3]);
+
+        // once the spill file is emptied an earlier export no longer imports
+        let mut stale = engine.export_state().await;
+        engine.clear_book(true).await;
+        assert!(engine.trade_history().await.is_empty());
+        // a reference to any other file is refused without opening it
+        let err = stale
+            .clone()
+            .load_spilled_trades(Some(&dir.join("root.trades.jsonl")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.field, "spilled_trades");
+        assert!(!err.message.contains("root.trades.jsonl"));
+        assert!(stale.clone().load_spilled_trades(None).await.is_err());
+        let own_spill = engine.spill_path().await;
+        assert!(
+            stale
+                .load_spilled_trades(own_spill.as_deref())
+                .await
+                .is_err()
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compact_trades() {
+        let mut engine = MatchingEngine::new();
+        let dir = std::env::temp_dir().join(format!("ome-compact-{}", std::process::id()));
+        let spill = TradeSpill::create(dir.join("root.trades.jsonl")).unwrap();
+        engine.set_trade_history(2, Some(spill)).await;
+        // nothing is older than the first event, so nothing is known to be before it
+        assert_eq!(engine.compact_trades(engine.clock().now_ms()).await, 0);
+
+        let mut cutoff = 0;
+        for ts in 1..=3 {
+            if ts == 3 {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                cutoff = engine.clock().now_ms();
+            }
+            let ask = Order::new(format!("s{ts}"), Side::Sell, OrderType::Limit, 1, 100, ts);
+            let bid = Order::new(format!("b{ts}"), Side::Buy, OrderType::Limit, 1, 100, ts);
+            engine.submit_order(ask).await.unwrap();
+            engine.submit_order(bid).await.unwrap();
+        }
+        assert_eq!(engine.hot_trades().await, (2, 1));
+        // one trade from the spill file, one from the pool
+        assert_eq!(engine.compact_trades(cutoff).await, 2);
+        assert_eq!(engine.hot_trades().await, (1, 0));
+        let history = engine.trade_history().await;
+        assert_eq!(history.iter().map(|t| t.id).collect::<Vec<_>>(), [3]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_orders() {
+        let mut primary = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let alice = |id: &str, side| {
+            Order::new(id.to_string(), side, OrderType::Limit, 10, 100, 1)
+                .with_owner("alice".to_string())
+        };
+        primary
+            .schedule_order(alice("a", Side::Sell), 100)
+            .await
+            .unwrap();
+        primary
+            .schedule_order(alice("b", Side::Buy), 200)
+            .await
+            .unwrap();
+        assert!(primary.get_sell_orders().await.is_empty());
+        assert!(primary.due_orders(99).await.is_empty());
+        assert_eq!(primary.due_orders(100).await, ["a"]);
+        let statuses: Vec<OrderStatus> = primary
+            .owner_orders("alice")
+            .await
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect();
+        assert_eq!(statuses, [OrderStatus::Scheduled, OrderStatus::Scheduled]);
+
+        // held while halted
+        primary.halt(true).await;
+        assert!(primary.due_orders(100).await.is_empty());
+        assert!(
+            primary
+                .activate_scheduled(&"a".to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        primary.resume().await;
+        primary.activate_scheduled(&"a".to_string()).await.unwrap();
+        assert_eq!(primary.get_sell_orders().await.len(), 1);
+        assert!(primary.cancel_order("b".to_string()).await.unwrap());
+        assert!(primary.due_orders(u64::MAX).await.is_empty());
+
+        // a standby mirrors the schedule until the primary releases or cancels the orders
+        let entries = primary.audit_entries(1, usize::MAX).await;
+        for entry in &entries[..2] {
+            standby.apply_replicated(entry.clone()).await.unwrap();
+        }
+        assert_eq!(standby.export_state().await.scheduled_orders.len(), 2);
+        for entry in &entries[2..] {
+            standby.apply_replicated(entry.clone()).await.unwrap();
+        }
+        assert!(standby.export_state().await.scheduled_orders.is_empty());
+        assert_eq!(standby.get_sell_orders().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_day_orders_expire_at_close() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let day = Order::new(String::from("d"), Side::Sell, OrderType::Limit, 10, 101, 1)
+            .with_time_in_force(TimeInForce::Day);
+        let gtc = Order::new(String::from("g"), Side::Sell, OrderType::Limit, 10, 102, 2);
+        let buy = Order::new(String::from("b"), Side::Buy, OrderType::Limit, 4, 101, 3);
+        for order in [day, gtc, buy] {
+            engine.submit_order(order).await.unwrap();
+        }
+
+        engine.set_market_phase(MarketPhase::Closed).await;
+        let asks = engine.get_sell_orders().await;
+        assert_eq!(asks.iter().map(|o| &o.id).collect::<Vec<_>>(), ["g"]);
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        let AuditEvent::OrderExpired { order } = &entries.last().unwrap().event else {
+            panic!("expected an expiry");
+        };
+        assert_eq!((order.id.as_str(), order.quantity), ("d", 6));
+
+        for entry in entries {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert_eq!(standby.get_sell_orders().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stop_orders() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let limit = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+        };
+        let stop = |id: &str, side, price, source, trail| {
+            Order::new(id.to_string(), side, OrderType::Market, 5, 0, 1)
+                .with_owner("carol".to_string())
+                .with_trigger(Trigger {
+                    price,
+                    source,
+                    kind: TriggerKind::Stop,
+                    trail,
+                })
+        };
+        engine
+            .submit_order(limit("bid", Side::Buy, 1, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(limit("ask", Side::Sell, 10, 110))
+            .await
+            .unwrap();
+        let trailing = stop(
+            "trailing",
+            Side::Sell,
+            90,
+            TriggerSource::LastTrade,
+            Some(10),
+        );
+        // a buy stop fires when the ask rises to it, not while it's below
+        let buy = stop("buy", Side::Buy, 120, TriggerSource::OppositeBbo, None);
+        assert_eq!(engine.submit_order(trailing).await, Ok(vec![]));
+        assert_eq!(engine.submit_order(buy).await, Ok(vec![]));
+
+        // the stop follows a trade at 105 up to 95 and stays there when the price falls back
+        engine
+            .submit_order(limit("s1", Side::Sell, 1, 105))
+            .await
+            .unwrap();
+        engine
+            .submit_order(limit("b1", Side::Buy, 1, 105))
+            .await
+            .unwrap();
+        engine
+            .submit_order(limit("s2", Side::Sell, 1, 100))
+            .await
+            .unwrap();
+        let state = engine.export_state().await;
+        let prices: Vec<Price> = state
+            .untriggered_orders
+            .iter()
+            .map(|o| o.trigger.unwrap().price)
+            .collect();
+        assert_eq!(prices, [95, 120]);
+        for entry in engine.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        let state = standby.export_state().await;
+        assert_eq!(state.untriggered_orders[0].trigger.unwrap().price, 95);
+        let statuses: Vec<OrderStatus> = engine
+            .owner_orders("carol")
+            .await
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect();
+        assert_eq!(statuses, [OrderStatus::Untriggered; 2]);
+
+        // trading at the stop fires the sell into the bid
+        engine
+            .submit_order(limit("b3", Side::Buy, 3, 95))
+            .await
+            .unwrap();
+        let trades = engine
+            .submit_order(limit("s3", Side::Sell, 1, 95))
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1); // the triggered order's trades aren't the submitter's
+        assert!(engine.get_buy_orders().await.is_empty());
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(entries.iter().any(|e| matches!(
+            &e.event,
+            AuditEvent::OrderTriggered { order } if order.id == "trailing" && order.trigger.is_none()
+        )));
+        assert!(engine.cancel_order("buy".to_string()).await.unwrap());
+
+        for entry in engine.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        let state = standby.export_state().await;
+        assert!(state.untriggered_orders.is_empty());
+        assert!(state.bids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_market_if_touched() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let limit = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+        };
+        let mit = |id: &str, side, price, source| {
+            Order::new(id.to_string(), side, OrderType::Market, 5, 0, 1)
+                .with_owner("carol".to_string())
+                .with_trigger(Trigger {
+                    price,
+                    source,
+                    kind: TriggerKind::Touch,
+                    trail: None,
+                })
+        };
+        engine
+            .submit_order(limit("bid", Side::Buy, 10, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(limit("ask", Side::Sell, 10, 110))
+            .await
+            .unwrap();
+        let sell = mit("sell", Side::Sell, 105, TriggerSource::LastTrade);
+        let buy = mit("buy", Side::Buy, 95, TriggerSource::OppositeBbo);
+        assert_eq!(engine.submit_order(sell).await, Ok(vec![]));
+        assert_eq!(engine.submit_order(buy).await, Ok(vec![]));
+        // a trade below a sell trigger doesn't touch it
+        engine
+            .submit_order(limit("s1", Side::Sell, 1, 100))
+            .await
+            .unwrap();
+        let statuses: Vec<OrderStatus> = engine
+            .owner_orders("carol")
+            .await
+            .into_iter()
+            .map(|(_, status)| status)
+            .collect();
+        assert_eq!(statuses, [OrderStatus::Untriggered; 2]);
+
+        // trading at the trigger fires the sell into the bid
+        engine
+            .submit_order(limit("s2", Side::Sell, 1, 105))
+            .await
+            .unwrap();
+        let trades = engine
+            .submit_order(limit("b2", Side::Buy, 1, 105))
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 1); // the triggered order's trades aren't the submitter's
+        assert_eq!(engine.get_buy_orders().await[0].quantity, 4);
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(entries.iter().any(|e| matches!(
+            &e.event,
+            AuditEvent::OrderTriggered { order } if order.id == "sell" && order.trigger.is_none()
+        )));
+        assert!(engine.cancel_order("buy".to_string()).await.unwrap());
+
+        for entry in engine.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        let state = standby.export_state().await;
+        assert!(state.untriggered_orders.is_empty());
+        assert_eq!(state.bids[0].quantity, 4);
+    }
+
+    #[tokio::test]
+    async fn test_quote_replaces_previous() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let level = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+                .with_owner("mm".to_string())
+        };
+        let quote = |orders: Vec<Order>| Quote {
+            owner: "mm".to_string(),
+            orders,
+        };
+        engine
+            .set_risk_limits(
+                "mm".to_string(),
+                RiskLimits {
+                    max_order_quantity: Some(10),
+                    ..RiskLimits::default()
+                },
+            )
+            .await;
+        let ack = engine
+            .submit_quote(quote(vec![
+                level("b1", Side::Buy, 5, 99),
+                level("b2", Side::Buy, 50, 98),
+                level("a1", Side::Sell, 5, 101),
+            ]))
+            .await
+            .unwrap();
+        assert_eq!(ack[0], Ok(vec![]));
+        assert_eq!(ack[1], Err(RejectReason::MaxQuantityExceeded { limit: 10 }));
+        assert_eq!(ack[2], Ok(vec![]));
+
+        // a crossed quote is refused and the previous one stands
+        let crossed = quote(vec![
+            level("b3", Side::Buy, 5, 98),
+            level("b4", Side::Buy, 5, 101),
+            level("a3", Side::Sell, 5, 101),
+        ]);
+        assert_eq!(
+            engine.submit_quote(crossed).await.unwrap_err(),
+            RejectReason::CrossedQuote
+        );
+        assert_eq!(engine.get_buy_orders().await[0].id, "b1");
+
+        // the new quote pulls every old level; the bid alone replaces them
+        engine
+            .submit_order(level("x", Side::Sell, 5, 100).with_owner("other".to_string()))
+            .await
+            .unwrap();
+        let ack = engine
+            .submit_quote(quote(vec![level("b5", Side::Buy, 5, 100)]))
+            .await
+            .unwrap();
+        assert_eq!(ack[0].as_ref().unwrap()[0].sell_order_id, "x");
+        assert!(engine.get_sell_orders().await.is_empty());
+        assert!(engine.get_buy_orders().await.is_empty());
+
+        engine
+            .submit_quote(quote(vec![
+                level("b6", Side::Buy, 5, 98),
+                level("a6", Side::Sell, 5, 102),
+            ]))
+            .await
+            .unwrap();
+        for entry in engine.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        let state = standby.export_state().await;
+        assert_eq!(state.bids.len(), 1);
+        assert_eq!(state.asks.len(), 1);
+        assert_eq!(state.quotes["mm"], ["b6", "a6"]);
+    }
+
+    #[tokio::test]
+    async fn test_quote_protection_pulls_quote() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let order = |id: &str, owner: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+                .with_owner(owner.to_string())
+        };
+        engine
+            .set_quote_protection(
+                "mm".to_string(),
+                QuoteProtection {
+                    max_fills: None,
+                    max_volume: Some(8),
+                    interval_ms: 60_000,
+                },
+            )
+            .await;
+        engine
+            .submit_quote(Quote {
+                owner: "mm".to_string(),
+                orders: vec![
+                    order("b1", "mm", Side::Buy, 5, 99),
+                    order("b2", "mm", Side::Buy, 5, 98),
+                    order("a1", "mm", Side::Sell, 5, 101),
+                ],
+            })
+            .await
+            .unwrap();
+        // fills of other participants' orders don't count
+        engine
+            .submit_order(order("o1", "other", Side::Buy, 5, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("s1", "taker", Side::Sell, 5, 100))
+            .await
+            .unwrap();
+        assert_eq!(engine.get_sell_orders().await.len(), 1);
+
+        engine
+            .submit_order(order("s2", "taker", Side::Sell, 3, 99))
+            .await
+            .unwrap();
+        assert_eq!(engine.get_sell_orders().await.len(), 1); // 3 of 8
+        let trades = engine
+            .submit_order(order("s3", "taker", Side::Sell, 7, 98))
+            .await
+            .unwrap();
+        assert_eq!(trades.len(), 2); // 2 left of b1, 5 of b2
+        assert!(engine.get_sell_orders().await.is_empty()); // a1 pulled
+        assert!(engine.get_buy_orders().await.is_empty());
+        let entries = engine.audit_entries(1, usize::MAX).await;
+        assert!(entries.iter().any(|e| matches!(
+            &e.event,
+            AuditEvent::QuoteProtectionTripped { fills: 3, volume: 10, canceled, .. }
+                if canceled == &["a1"]
+        )));
+
+        for entry in entries {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        let state = standby.export_state().await;
+        assert!(state.asks.is_empty());
+        assert!(state.quotes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rfq_trades_best_response() {
+        let mut engine = MatchingEngine::new();
+        let standby = MatchingEngine::new();
+        standby.start_standby().await;
+        let rfq = Rfq {
+            id: "r1".to_string(),
+            taker: Some("taker".to_string()),
+            side: Side::Buy,
+            quantity: 10,
+            makers: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            expires_at: 1_000,
+            responses: Vec::new(),
+        };
+        let response = |maker: &str, price, timestamp| RfqResponse {
+            id: format!("{maker}-quote"),
+            maker: Some(maker.to_string()),
+            price,
+            timestamp,
+        };
+        engine.request_quote(rfq).await.unwrap();
+        assert_eq!(
+            engine.respond_rfq("r1", response("d", 90, 10)).await,
+            Err(RfqError::NotInvited)
+        );
+        assert_eq!(
+            engine.respond_rfq("r1", response("a", 90, 1_000)).await,
+            Err(RfqError::NotFound) // the window is over
+        );
+        engine
+            .respond_rfq("r1", response("a", 95, 10))
+            .await
+            .unwrap();
+        engine
+            .respond_rfq("r1", response("b", 101, 20))
+            .await
+            .unwrap();
+        engine
+            .respond_rfq("r1", response("c", 99, 30))
+            .await
+            .unwrap();
+        assert_eq!(engine.due_rfqs(999).await.len(), 0);
+        assert_eq!(engine.due_rfqs(1_000).await, ["r1"]);
+
+        // the best maker is blocked, the next best trades
+        engine.kill_participant("a".to_string()).await.unwrap();
+        let trade = engine.close_rfq("r1").await.unwrap();
+        assert_eq!(trade.price, 99);
+        assert_eq!(trade.quantity, 10);
+        assert_eq!(trade.buyer.as_deref(), Some("taker"));
+        assert_eq!(trade.seller.as_deref(), Some("c"));
+        assert_eq!(engine.trade_history().await[0].id, trade.id);
+        assert!(engine.close_rfq("r1").await.is_none());
+        assert!(engine.get_buy_orders().await.is_empty());
+
+        for entry in engine.audit_entries(1, usize::MAX).await {
+            standby.apply_replicated(entry).await.unwrap();
+        }
+        assert!(standby.export_state().await.rfqs.is_empty());
+        assert_eq!(standby.position("c").await.net, -10);
+    }
+
+    #[tokio::test]
+    async fn test_trade_listeners() {
+        #[derive(Default)]
+        struct Executions(std::sync::Mutex<Vec<(TradeId, Quantity, Quantity)>>);
+        impl TradeListener for Executions {
+            fn on_trade(&self, trade: &Trade, buy: &Order, sell: &Order) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((trade.id, buy.quantity, sell.quantity));
+            }
+        }
+        let executions = Arc::new(Executions::default());
+        let mut engine = MatchingEngine::with_instrument(InstrumentConfig {
+            trade_listeners: vec![executions.clone()],
+            ..Default::default()
+        });
+        let order = |id: &str, side, quantity| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, 100, 1)
+        };
+        engine
+            .submit_order(order("s1", Side::Sell, 5))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("b1", Side::Buy, 8))
+            .await
+            .unwrap();
+        engine
+            .report_block_trade("alice".to_string(), "bob".to_string(), 150, 1_000)
+            .await
+            .unwrap();
+        // quantities left on both orders after each execution
+        assert_eq!(*executions.0.lock().unwrap(), vec![(1, 3, 0), (2, 0, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_block_trade_bypasses_book() {
+        let mut engine = MatchingEngine::new();
+        let order =
+            |id: &str, side, price| Order::new(id.to_string(), side, OrderType::Limit, 5, price, 1);
+        engine
+            .submit_order(order("s1", Side::Sell, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("b1", Side::Buy, 100))
+            .await
+            .unwrap();
+        engine
+            .submit_order(order("s2", Side::Sell, 101))
+            .await
+            .unwrap();
+
+        let trade = engine
+            .report_block_trade("alice".to_string(), "bob".to_string(), 150, 1_000)
+            .await
+            .unwrap();
+        assert_eq!(trade.trade_type, TradeType::Block);
+        assert_eq!((trade.price, trade.quantity), (150, 1_000));
+        assert_eq!(trade.aggressor, None);
+        assert_eq!(engine.trade_history().await.len(), 2);
+        assert_eq!(engine.position("alice").await.net, 1_000);
+        // the resting ask is untouched and the last trade price ignores the block
+        assert_eq!(engine.get_sell_orders().await.len(), 1);
+        assert_eq!(engine.last_trade_price().await, Some(100));
+
+        engine.kill_participant("bob".to_string()).await.unwrap();
+        assert_eq!(
+            engine
+                .report_block_trade("alice".to_string(), "bob".to_string(), 150, 1_000)
+                .await,
+            Err(RejectReason::ParticipantBlocked)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_otr_throttles_new_orders() {
+        let mut engine = MatchingEngine::new();
+        engine
+            .set_otr_config(OtrConfig {
+                max_ratio: 1,
+                min_messages: 2,
+                ..OtrConfig::default()
+            })
+            .await;
+        let order = |id: &str| {
+            Order::new(id.to_string(), Side::Buy, OrderType::Limit, 5, 100, 1)
+                .with_owner("hft".to_string())
+        };
+        engine.submit_order(order("1")).await.unwrap();
+        assert_eq!(
+            engine.submit_order(order("2")).await,
+            Err(RejectReason::OrderToTradeRatioExceeded { limit: 1 })
+        );
+        // cancels go through
+        assert_eq!(
+            engine.cancel_owned_order("1".to_string(), "hft").await,
+            Ok(true)
+        );
+        let (_, offenders) = engine.otr_offenders().await;
+        assert_eq!(offenders[0].participant, "hft");
+        assert_eq!(offenders[0].messages, 3);
     }
 }
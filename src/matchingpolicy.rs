@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use crate::order::{Order, Quantity};
+
+/// Decides how an incoming order's quantity is split across the resting orders of the best
+/// price level; price priority between levels is up to the engine
+pub trait MatchingPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Quantity each order of `level` (in time priority) gets out of `quantity`, index for
+    /// index. Must hand out `quantity` in full unless the level is smaller than that.
+    fn allocate(&self, level: &[Order], quantity: Quantity) -> Vec<Quantity>;
+}
+
+/// The oldest order fills first
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PriceTime;
+
+/// Proportional to size; shares below `min_quantity` get nothing, the remainder goes FIFO
+#[derive(Debug, Clone, Copy)]
+pub struct ProRata {
+    pub min_quantity: Quantity,
+}
+
+/// The largest order fills first, ties in time priority
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SizePriority;
+
+impl MatchingPolicy for PriceTime {
+    fn name(&self) -> &'static str {
+        "price_time"
+    }
+
+    fn allocate(&self, level: &[Order], quantity: Quantity) -> Vec<Quantity> {
+        fill_in_order(level, 0..level.len(), quantity)
+    }
+}
+
+impl MatchingPolicy for ProRata {
+    fn name(&self) -> &'static str {
+        "pro_rata"
+    }
+
+    fn allocate(&self, level: &[Order], quantity: Quantity) -> Vec<Quantity> {
+        let total: u128 = level.iter().map(|o| o.quantity as u128).sum();
+        if quantity as u128 >= total {
+            return level.iter().map(|o| o.quantity).collect();
+        }
+
+        let mut allocations: Vec<Quantity> = level
+            .iter()
+            .map(|o| {
+                let share = (quantity as u128 * o.quantity as u128 / total) as Quantity;
+                if share < self.min_quantity { 0 } else { share }
+            })
+            .collect();
+
+        let mut left = quantity - allocations.iter().sum::<Quantity>();
+        for (allocation, order) in allocations.iter_mut().zip(level) {
+            if left == 0 {
+                break;
+            }
+            let extra = (order.quantity - *allocation).min(left);
+            *allocation += extra;
+            left -= extra;
+        }
+        allocations
+    }
+}
+
+impl MatchingPolicy for SizePriority {
+    fn name(&self) -> &'static str {
+        "size_priority"
+    }
+
+    fn allocate(&self, level: &[Order], quantity: Quantity) -> Vec<Quantity> {
+        let mut by_size: Vec<usize> = (0..level.len()).collect();
+        by_size.sort_by_key(|&i| std::cmp::Reverse(level[i].quantity)); // stable, keeps time order on ties
+        fill_in_order(level, by_size, quantity)
+    }
+}
+
+/// Fills the orders of `level` one after the other in the order of `indices`
+fn fill_in_order(
+    level: &[Order],
+    indices: impl IntoIterator<Item = usize>,
+    quantity: Quantity,
+) -> Vec<Quantity> {
+    let mut allocations = vec![0; level.len()];
+    let mut left = quantity;
+    for i in indices {
+        if left == 0 {
+            break;
+        }
+        allocations[i] = level[i].quantity.min(left);
+        left -= allocations[i];
+    }
+    allocations
+}
+
+/// Policy by its [`MatchingPolicy::name`]; `min_quantity` only applies to pro-rata
+pub fn by_name(name: &str, min_quantity: Quantity) -> Option<Arc<dyn MatchingPolicy>> {
+    match name {
+        "price_time" => Some(Arc::new(PriceTime)),
+        "pro_rata" => Some(Arc::new(ProRata { min_quantity })),
+        "size_priority" => Some(Arc::new(SizePriority)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    fn level(sizes: &[Quantity]) -> Vec<Order> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &q)| {
+                Order::new(
+                    i.to_string(),
+                    Side::Sell,
+                    OrderType::Limit,
+                    q,
+                    100,
+                    i as u64,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_price_time() {
+        assert_eq!(PriceTime.allocate(&level(&[5, 10, 5]), 12), vec![5, 7, 0]);
+    }
+
+    #[test]
+    fn test_pro_rata() {
+        let pro_rata = ProRata { min_quantity: 1 };
+        // 100 over 200/100/100 -> 50/25/25
+        assert_eq!(
+            pro_rata.allocate(&level(&[200, 100, 100]), 100),
+            vec![50, 25, 25]
+        );
+        // rounding remainder goes to the oldest order
+        assert_eq!(pro_rata.allocate(&level(&[10, 10, 10]), 10), vec![4, 3, 3]);
+        // shares under the minimum are dropped and handed out FIFO
+        let pro_rata = ProRata { min_quantity: 2 };
+        assert_eq!(pro_rata.allocate(&level(&[90, 5, 5]), 20), vec![20, 0, 0]);
+        // enough to fill everyone
+        assert_eq!(pro_rata.allocate(&level(&[3, 4]), 10), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_size_priority() {
+        assert_eq!(
+            SizePriority.allocate(&level(&[5, 10, 10]), 15),
+            vec![0, 10, 5]
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock, RwLockReadGuard},
+    thread::{self, JoinHandle},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    order::{Order, OrderType, Price, Quantity, Side, Timestamp},
+    orderbook::OrderBook,
+};
+
+/// One change to an external venue's price-level (L2) book
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedUpdate {
+    /// The whole book, replacing whatever was mirrored before; levels as `(price, quantity)`
+    Snapshot {
+        bids: Vec<(Price, Quantity)>,
+        asks: Vec<(Price, Quantity)>,
+    },
+    /// The total quantity at `price` on `side` is now `quantity`; `0` removes the level
+    Level {
+        side: Side,
+        price: Price,
+        quantity: Quantity,
+    },
+}
+
+/// Where the updates of an external L2 feed come from, e.g. a venue's WebSocket stream or a
+/// recorded session. A source that notices a gap in the venue's sequence numbers should resync
+/// and hand out a fresh [`FeedUpdate::Snapshot`] rather than carry on.
+pub trait FeedSource {
+    type Error;
+
+    /// The next update, waiting for one if need be; `Ok(None)` once the feed has ended
+    fn next_update(&mut self) -> Result<Option<FeedUpdate>, Self::Error>;
+}
+
+/// A mirrored price level stands in the book as a single order keyed by its side and price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LevelKey {
+    pub side: Side,
+    pub price: Price,
+}
+
+impl fmt::Display for LevelKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.side {
+            Side::Buy => write!(f, "bid@{}", self.price),
+            Side::Sell => write!(f, "ask@{}", self.price),
+        }
+    }
+}
+
+/// A local copy of an external venue's book, one order per price level. Nothing ever matches
+/// in it: a momentarily crossed feed stays crossed until the venue says otherwise.
+#[derive(Default)]
+pub struct BookMirror {
+    book: OrderBook<LevelKey>,
+    updates: u64,
+}
+
+impl BookMirror {
+    pub fn new() -> Self {
+        BookMirror::default()
+    }
+
+    /// The mirrored book, for reading only; the levels' orders have `timestamp` set to the
+    /// number of the update that last changed them
+    pub fn book(&self) -> &OrderBook<LevelKey> {
+        &self.book
+    }
+
+    /// Updates applied so far
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+
+    pub fn apply(&mut self, update: FeedUpdate) {
+        self.updates += 1;
+        match update {
+            FeedUpdate::Snapshot { bids, asks } => {
+                self.book.clear();
+                let levels = bids
+                    .into_iter()
+                    .map(|(price, quantity)| (Side::Buy, price, quantity))
+                    .chain(asks.into_iter().map(|(p, q)| (Side::Sell, p, q)));
+                for (side, price, quantity) in levels {
+                    self.set_level(side, price, quantity);
+                }
+            }
+            FeedUpdate::Level {
+                side,
+                price,
+                quantity,
+            } => self.set_level(side, price, quantity),
+        }
+    }
+
+    fn set_level(&mut self, side: Side, price: Price, quantity: Quantity) {
+        let key = LevelKey { side, price };
+        self.book.cancel_order(key);
+        if quantity > 0 {
+            let time = self.updates as Timestamp;
+            let level = Order::new(key, side, OrderType::Limit, quantity, price, time);
+            self.book.add_order(level);
+        }
+    }
+
+    /// Applies every update of `source` until it ends, calling `on_update` after each
+    pub fn ingest<S: FeedSource>(
+        &mut self,
+        source: &mut S,
+        mut on_update: impl FnMut(&BookMirror),
+    ) -> Result<(), S::Error> {
+        while let Some(update) = source.next_update()? {
+            self.apply(update);
+            on_update(self);
+        }
+        Ok(())
+    }
+}
+
+/// Read access to a book mirrored by [`spawn_mirror`]
+#[derive(Clone)]
+pub struct MirroredBook {
+    mirror: Arc<RwLock<BookMirror>>,
+}
+
+impl MirroredBook {
+    /// Holds off the ingestion thread for as long as the guard lives
+    pub fn read(&self) -> RwLockReadGuard<'_, BookMirror> {
+        self.mirror.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Mirrors `source` on a thread of its own, which ends with the feed or its first error
+pub fn spawn_mirror<S>(mut source: S) -> (MirroredBook, JoinHandle<Result<(), S::Error>>)
+where
+    S: FeedSource + Send + 'static,
+    S::Error: Send + 'static,
+{
+    let mirror = Arc::new(RwLock::new(BookMirror::new()));
+    let writer = Arc::clone(&mirror);
+    let handle = thread::spawn(move || {
+        while let Some(update) = source.next_update()? {
+            writer
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .apply(update);
+        }
+        Ok(())
+    });
+    (MirroredBook { mirror }, handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Recorded(std::vec::IntoIter<FeedUpdate>);
+
+    impl FeedSource for Recorded {
+        type Error = String;
+
+        fn next_update(&mut self) -> Result<Option<FeedUpdate>, String> {
+            Ok(self.0.next())
+        }
+    }
+
+    #[test]
+    fn test_mirror_levels() {
+        let updates = vec![
+            FeedUpdate::Snapshot {
+                bids: vec![(99, 5), (98, 10)],
+                asks: vec![(101, 3), (102, 0)],
+            },
+            FeedUpdate::Level {
+                side: Side::Buy,
+                price: 99,
+                quantity: 7,
+            },
+            FeedUpdate::Level {
+                side: Side::Sell,
+                price: 101,
+                quantity: 0,
+            },
+            // crossed for a moment, nothing matches
+            FeedUpdate::Level {
+                side: Side::Sell,
+                price: 97,
+                quantity: 2,
+            },
+        ];
+        let mut mirror = BookMirror::new();
+        let mut seen = Vec::new();
+        mirror
+            .ingest(&mut Recorded(updates.into_iter()), |m| {
+                seen.push(m.book().best_ask())
+            })
+            .unwrap();
+
+        assert_eq!(seen, vec![Some(101), Some(101), None, Some(97)]);
+        let book = mirror.book();
+        let bids: Vec<_> = book
+            .levels(Side::Buy)
+            .map(|(price, level)| (price, level[0].quantity))
+            .collect();
+        assert_eq!(bids, vec![(99, 7), (98, 10)]);
+        assert_eq!(book.side_stats(Side::Sell).quantity, 2);
+        assert_eq!(mirror.updates(), 4);
+
+        let snapshot = FeedUpdate::Snapshot {
+            bids: vec![],
+            asks: vec![(105, 1)],
+        };
+        let (mirrored, handle) = spawn_mirror(Recorded(vec![snapshot].into_iter()));
+        handle.join().unwrap().unwrap();
+        assert_eq!(mirrored.read().book().best_ask(), Some(105));
+        assert_eq!(mirrored.read().book().best_bid(), None);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::{ParticipantId, Quantity, Timestamp};
+
+pub const DEFAULT_PROTECTION_INTERVAL_MS: u64 = 1_000;
+
+/// Market maker protection of one participant: once fills of their quotes reach `max_fills`
+/// or `max_volume` within `interval_ms`, whatever is left of the quote is pulled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteProtection {
+    #[serde(default)]
+    pub max_fills: Option<u32>,
+    #[serde(default)]
+    pub max_volume: Option<Quantity>,
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+}
+
+fn default_interval_ms() -> u64 {
+    DEFAULT_PROTECTION_INTERVAL_MS
+}
+
+impl Default for QuoteProtection {
+    fn default() -> Self {
+        QuoteProtection {
+            max_fills: None,
+            max_volume: None,
+            interval_ms: DEFAULT_PROTECTION_INTERVAL_MS,
+        }
+    }
+}
+
+impl QuoteProtection {
+    fn is_off(&self) -> bool {
+        self.max_fills.is_none() && self.max_volume.is_none()
+    }
+}
+
+/// Fills and volume within the interval when a protection tripped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtectionTrip {
+    pub fills: u32,
+    pub volume: Quantity,
+}
+
+/// Protections of every participant that set one, with the recent fills of their quotes
+#[derive(Debug, Default)]
+pub struct ProtectionBook {
+    protections: HashMap<ParticipantId, QuoteProtection>,
+    fills: HashMap<ParticipantId, VecDeque<(Timestamp, Quantity)>>,
+}
+
+impl ProtectionBook {
+    /// Sets `owner`'s protection, counting starts over; one without limits removes it
+    pub fn set(&mut self, owner: ParticipantId, protection: QuoteProtection) {
+        self.fills.remove(&owner);
+        if protection.is_off() {
+            self.protections.remove(&owner);
+        } else {
+            self.protections.insert(owner, protection);
+        }
+    }
+
+    pub fn get(&self, owner: &str) -> Option<QuoteProtection> {
+        self.protections.get(owner).copied()
+    }
+
+    /// Counts a fill of `quantity` on `owner`'s quote at `now` (ms). When that reaches a
+    /// limit the trip is returned and counting starts over.
+    pub fn record(
+        &mut self,
+        owner: &str,
+        quantity: Quantity,
+        now: Timestamp,
+    ) -> Option<ProtectionTrip> {
+        let protection = self.protections.get(owner)?;
+        let fills = self.fills.entry(owner.to_string()).or_default();
+        fills.push_back((now, quantity));
+        let since = now.saturating_sub(protection.interval_ms);
+        while fills.front().is_some_and(|(at, _)| *at < since) {
+            fills.pop_front();
+        }
+
+        let trip = ProtectionTrip {
+            fills: u32::try_from(fills.len()).unwrap_or(u32::MAX),
+            volume: fills.iter().map(|(_, q)| q).sum(),
+        };
+        let tripped = protection.max_fills.is_some_and(|max| trip.fills >= max)
+            || protection.max_volume.is_some_and(|max| trip.volume >= max);
+        if !tripped {
+            return None;
+        }
+        fills.clear();
+        Some(trip)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_protection_trips() {
+        let mut book = ProtectionBook::default();
+        assert_eq!(book.record("mm", 5, 0), None); // no protection set
+        book.set(
+            "mm".to_string(),
+            QuoteProtection {
+                max_fills: Some(3),
+                max_volume: Some(100),
+                interval_ms: 1_000,
+            },
+        );
+        assert_eq!(book.record("mm", 5, 0), None);
+        assert_eq!(book.record("mm", 5, 500), None);
+        // the first fill left the interval
+        assert_eq!(book.record("mm", 5, 1_200), None);
+        assert_eq!(
+            book.record("mm", 5, 1_300),
+            Some(ProtectionTrip {
+                fills: 3,
+                volume: 15
+            })
+        );
+        // counting starts over after a trip
+        assert_eq!(
+            book.record("mm", 100, 1_400),
+            Some(ProtectionTrip {
+                fills: 1,
+                volume: 100
+            })
+        );
+
+        book.set("mm".to_string(), QuoteProtection::default());
+        assert_eq!(book.get("mm"), None);
+    }
+}
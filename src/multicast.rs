@@ -0,0 +1,221 @@
+use std::{net::SocketAddr, time::Duration};
+
+use tokio::{net::UdpSocket, time::Instant};
+
+use crate::{
+    marketdata::{MarketDataMessage, MarketDataStream},
+    matchingengine::MatchingEngine,
+    retransmit::{self, RetransmitBuffer, RetransmitConfig},
+    settlement,
+};
+
+pub const MULTICAST_GROUP_ENV: &str = "OME_MULTICAST_GROUP"; // unset disables the publisher
+pub const MULTICAST_HEARTBEAT_MS_ENV: &str = "OME_MULTICAST_HEARTBEAT_MS";
+pub const MULTICAST_TTL_ENV: &str = "OME_MULTICAST_TTL";
+
+const DEFAULT_HEARTBEAT: Duration = Duration::from_secs(1);
+const DEFAULT_TTL: u32 = 1; // stays on the local network
+pub const PACKET_HEADER_LEN: usize = 18;
+/// Packets stay below a typical Ethernet MTU so they aren't fragmented
+pub const MAX_PACKET_LEN: usize = 1_400;
+
+/// Where the market data of the default exchange is multicast, how often an idle feed
+/// sends a heartbeat, and where missed messages can be asked for again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticastConfig {
+    pub group: SocketAddr,
+    pub heartbeat: Duration,
+    pub ttl: u32,
+    pub retransmit: Option<RetransmitConfig>,
+}
+
+impl MulticastConfig {
+    pub fn from_env() -> Option<Self> {
+        let group = std::env::var(MULTICAST_GROUP_ENV).ok()?;
+        let Ok(group) = group.parse::<SocketAddr>() else {
+            println!("{MULTICAST_GROUP_ENV}={group} is not an address and port, not multicasting");
+            return None;
+        };
+        let var = |name| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Some(MulticastConfig {
+            group,
+            heartbeat: var(MULTICAST_HEARTBEAT_MS_ENV)
+                .filter(|ms| *ms > 0)
+                .map_or(DEFAULT_HEARTBEAT, Duration::from_millis),
+            ttl: var(MULTICAST_TTL_ENV).map_or(DEFAULT_TTL, |ttl| ttl as u32),
+            retransmit: RetransmitConfig::from_env(),
+        })
+    }
+}
+
+/// Packs the binary frames of sequenced messages into packets: an 18-byte header (`session`
+/// u64, `seq` u64 of the first message, `count` u16, little-endian) followed by the frames.
+/// Without frames that's a single packet of no messages, naming `first_seq`.
+pub fn packets(session: u64, first_seq: u64, frames: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let header = |seq: u64, count: u16| {
+        let mut packet = Vec::with_capacity(MAX_PACKET_LEN);
+        packet.extend_from_slice(&session.to_le_bytes());
+        packet.extend_from_slice(&seq.to_le_bytes());
+        packet.extend_from_slice(&count.to_le_bytes());
+        packet
+    };
+    if frames.is_empty() {
+        return vec![header(first_seq, 0)];
+    }
+    let mut packets = Vec::new();
+    let mut seq = first_seq;
+    let mut frames = frames.iter().peekable();
+    while frames.peek().is_some() {
+        let mut body = Vec::new();
+        let mut count: u16 = 0;
+        while let Some(frame) = frames.next_if(|frame| {
+            count == 0 || PACKET_HEADER_LEN + body.len() + frame.len() <= MAX_PACKET_LEN
+        }) {
+            body.extend_from_slice(frame);
+            count += 1;
+        }
+        let mut packet = header(seq, count);
+        packet.extend_from_slice(&body);
+        packets.push(packet);
+        seq += count as u64;
+    }
+    packets
+}
+
+/// Numbers the messages of one run of the publisher. A packet without messages is a
+/// heartbeat and carries the sequence number of the next message.
+#[derive(Debug)]
+pub struct Packetizer {
+    /// Identifies one run of the publisher, sequence numbers start over in the next
+    pub session: u64,
+    next_seq: u64,
+}
+
+impl Packetizer {
+    pub fn new(session: u64) -> Self {
+        Packetizer {
+            session,
+            next_seq: 1,
+        }
+    }
+
+    /// Sequence number of the next message packed
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Numbers `frames` on from the last ones and packs as many into each packet as fit
+    pub fn pack(&mut self, frames: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        if frames.is_empty() {
+            return Vec::new();
+        }
+        let packets = packets(self.session, self.next_seq, frames);
+        self.next_seq += frames.len() as u64;
+        packets
+    }
+
+    pub fn heartbeat(&self) -> Vec<u8> {
+        packets(self.session, self.next_seq, &[]).remove(0)
+    }
+}
+
+/// Multicasts the market data of `engine` as long as the server runs, starting with the
+/// whole book; a heartbeat goes out whenever nothing was sent for the heartbeat interval.
+/// Messages are kept for retransmission before they are sent, when that's configured.
+pub async fn run_publisher(engine: MatchingEngine, config: MulticastConfig) {
+    let bind: SocketAddr = match config.group {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            println!("multicast to {}: {e}", config.group);
+            return;
+        }
+    };
+    if config.group.is_ipv4()
+        && let Err(e) = socket.set_multicast_ttl_v4(config.ttl)
+    {
+        println!("multicast to {}: {e}", config.group);
+    }
+    println!("Multicasting market data to {}", config.group);
+
+    let mut packetizer = Packetizer::new(settlement::now_ms());
+    let buffer = config.retransmit.map(|retransmit| {
+        let buffer = RetransmitBuffer::new(packetizer.session, retransmit.capacity);
+        tokio::spawn(retransmit::run_server(retransmit, buffer.clone()));
+        buffer
+    });
+    let (mut stream, snapshot) = MarketDataStream::start(engine).await;
+    let mut pending = snapshot;
+    let mut last_sent = Instant::now();
+    loop {
+        let frames: Vec<Vec<u8>> = pending.iter().map(MarketDataMessage::encode).collect();
+        if let Some(buffer) = &buffer {
+            buffer.extend(packetizer.next_seq(), &frames).await;
+        }
+        for packet in packetizer.pack(&frames) {
+            if let Err(e) = socket.send_to(&packet, config.group).await {
+                println!("multicast to {}: {e}", config.group); // the next packets may get through
+            }
+            last_sent = Instant::now();
+        }
+
+        let received = tokio::select! {
+            received = stream.recv() => received,
+            _ = tokio::time::sleep_until(last_sent + config.heartbeat) => {
+                let _ = socket.send_to(&packetizer.heartbeat(), config.group).await;
+                last_sent = Instant::now();
+                pending = Vec::new();
+                continue;
+            }
+        };
+        match stream.messages(received).await {
+            Some(messages) => pending = messages,
+            None => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{marketdata::LevelUpdate, order::Side};
+
+    #[test]
+    fn test_packets_are_sequenced() {
+        let level = |price| {
+            MarketDataMessage::Level(LevelUpdate {
+                seq: 1,
+                side: Side::Buy,
+                price,
+                quantity: 1,
+                orders: 1,
+            })
+        };
+        let frame_len = level(0).encode().len();
+        let per_packet = (MAX_PACKET_LEN - PACKET_HEADER_LEN) / frame_len;
+        let messages: Vec<_> = (0..per_packet as u64 + 1).map(level).collect();
+
+        let frames: Vec<_> = messages.iter().map(MarketDataMessage::encode).collect();
+        let mut packetizer = Packetizer::new(7);
+        let packets = packetizer.pack(&frames);
+        assert_eq!(packets.len(), 2);
+        assert!(packets[0].len() <= MAX_PACKET_LEN);
+        let header = |packet: &[u8]| {
+            (
+                u64::from_le_bytes(packet[0..8].try_into().unwrap()),
+                u64::from_le_bytes(packet[8..16].try_into().unwrap()),
+                u16::from_le_bytes(packet[16..18].try_into().unwrap()),
+            )
+        };
+        assert_eq!(header(&packets[0]), (7, 1, per_packet as u16));
+        assert_eq!(header(&packets[1]), (7, per_packet as u64 + 1, 1));
+        // the heartbeat announces the next sequence number
+        assert_eq!(
+            header(&packetizer.heartbeat()),
+            (7, per_packet as u64 + 2, 0)
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Node.js addon over [`Matcher`], built with `--features node` (napi-rs). Every method returns
+//! a promise and runs on the libuv thread pool, so the event loop never waits on the book:
+//!
+//! ```js
+//! const { Matcher } = require("./ome_v2.node");
+//! const m = new Matcher("pro_rata", 1);
+//! await m.submit({ side: "Sell", order_type: "Limit", price: 100, quantity: 5 });
+//! await m.submit({ side: "Buy", order_type: "Market", quantity: 2 }); // { id, trades: [...] }
+//! await m.book(); // { bids: [...], asks: [...] }
+//! await m.cancel("1"); // true if it was resting
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use napi::{
+    Env, Error, Result, Status, Task,
+    bindgen_prelude::{AsyncTask, Unknown},
+};
+use napi_derive::napi;
+use serde_json::Value;
+
+use crate::{
+    matching::{Matcher, OrderRequest},
+    matchingpolicy::{self, PriceTime},
+};
+
+pub enum Operation {
+    Submit(OrderRequest),
+    Cancel(String),
+    Book,
+}
+
+/// One call against the shared matcher, off the JavaScript thread
+pub struct MatcherTask {
+    matcher: Arc<Mutex<Matcher>>,
+    operation: Option<Operation>,
+}
+
+impl Task for MatcherTask {
+    type Output = Value;
+    type JsValue = Unknown<'static>;
+
+    fn compute(&mut self) -> Result<Value> {
+        let mut matcher = self.matcher.lock().expect("matcher lock poisoned");
+        match self.operation.take().expect("a task runs once") {
+            Operation::Submit(order) => matcher
+                .submit_request(order)
+                .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+                .and_then(|submitted| serde_json::to_value(submitted).map_err(Error::from)),
+            Operation::Cancel(id) => Ok(Value::Bool(matcher.cancel(&id))),
+            Operation::Book => serde_json::to_value(matcher.view()).map_err(Error::from),
+        }
+    }
+
+    fn resolve(&mut self, env: Env, output: Value) -> Result<Unknown<'static>> {
+        env.to_js_value(&output)
+    }
+}
+
+#[napi(js_name = "Matcher")]
+pub struct NodeMatcher {
+    matcher: Arc<Mutex<Matcher>>,
+}
+
+#[napi]
+impl NodeMatcher {
+    /// `policy` is one of the server's `OME_MATCHING_POLICY` names, price-time when omitted
+    #[napi(constructor)]
+    pub fn new(policy: Option<String>, min_quantity: Option<u32>) -> Result<Self> {
+        let policy = match policy {
+            Some(name) => matchingpolicy::by_name(&name, min_quantity.unwrap_or(1).into())
+                .ok_or_else(|| {
+                    Error::new(
+                        Status::InvalidArg,
+                        format!("unknown matching policy {name}"),
+                    )
+                })?,
+            None => Arc::new(PriceTime),
+        };
+        Ok(NodeMatcher {
+            matcher: Arc::new(Mutex::new(Matcher::new(policy))),
+        })
+    }
+
+    fn task(&self, operation: Operation) -> AsyncTask<MatcherTask> {
+        AsyncTask::new(MatcherTask {
+            matcher: Arc::clone(&self.matcher),
+            operation: Some(operation),
+        })
+    }
+
+    /// Resolves to `{ id, trades }`, rejects for invalid orders
+    #[napi(ts_return_type = "Promise<{ id: string, trades: object[] }>")]
+    pub fn submit(&self, order: Value) -> Result<AsyncTask<MatcherTask>> {
+        let order: OrderRequest = serde_json::from_value(order)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        Ok(self.task(Operation::Submit(order)))
+    }
+
+    #[napi(ts_return_type = "Promise<boolean>")]
+    pub fn cancel(&self, id: String) -> AsyncTask<MatcherTask> {
+        self.task(Operation::Cancel(id))
+    }
+
+    #[napi(ts_return_type = "Promise<{ bids: object[], asks: object[] }>")]
+    pub fn book(&self) -> AsyncTask<MatcherTask> {
+        self.task(Operation::Book)
+    }
+}
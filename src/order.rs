@@ -1,12 +1,21 @@
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, fmt};
+use std::{cmp::Ordering, fmt, hash::Hash};
 
 pub type Quantity = u64;
 pub type Price = u64;
 pub type Timestamp = u64;
 pub type OrderId = String;
+pub type TradeId = u64;
+pub type ParticipantId = String;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+/// What orders (and the trades between them) are identified by. The server uses [`OrderId`]
+/// strings; embedders can key the book by `u64`, a `Uuid`, etc. to avoid cloning strings.
+pub trait OrderKey: Clone + Eq + Hash + fmt::Debug + fmt::Display + Send + Sync + 'static {}
+
+impl<T> OrderKey for T where T: Clone + Eq + Hash + fmt::Debug + fmt::Display + Send + Sync + 'static
+{}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub enum Side {
     Buy,
     Sell,
@@ -18,19 +27,127 @@ pub enum OrderType {
     Market,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Order {
-    pub id: OrderId,
+/// How long an order keeps working
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Until filled or canceled
+    #[default]
+    GoodTillCancel,
+    /// What is left expires when the trading session closes
+    Day,
+}
+
+impl TimeInForce {
+    pub fn is_good_till_cancel(&self) -> bool {
+        *self == TimeInForce::GoodTillCancel
+    }
+}
+
+/// Price a [`Trigger`] is compared with
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum TriggerSource {
+    #[default]
+    LastTrade,
+    Mark,
+    /// Best ask for a buy, best bid for a sell
+    OppositeBbo,
+}
+
+/// Which way the reference has to reach a [`Trigger`]'s price
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum TriggerKind {
+    /// From the adverse side, at or above it for a buy and at or below it for a sell
+    #[default]
+    Stop,
+    /// Market-if-touched: from the favorable side, at or below it for a buy and at or above it
+    /// for a sell
+    Touch,
+}
+
+/// The order rests untriggered until the `source` price reaches `price` the way `kind` says,
+/// and then enters the book like a new order
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Trigger {
+    pub price: Price,
+    #[serde(default)]
+    pub source: TriggerSource,
+    #[serde(default)]
+    pub kind: TriggerKind,
+    /// Trailing stop: the price follows the reference at this distance as it moves in the
+    /// order's favor, and never moves back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trail: Option<Price>,
+}
+
+impl Trigger {
+    /// Whether `reference` reached the trigger of an order on `side`
+    pub fn is_touched(&self, side: Side, reference: Price) -> bool {
+        match (self.kind, side) {
+            (TriggerKind::Stop, Side::Buy) | (TriggerKind::Touch, Side::Sell) => {
+                reference >= self.price
+            }
+            (TriggerKind::Stop, Side::Sell) | (TriggerKind::Touch, Side::Buy) => {
+                reference <= self.price
+            }
+        }
+    }
+
+    /// Moves a trailing stop's price after `reference`: down towards it for a buy, up for a
+    /// sell. Other triggers stay where they are. Returns whether the price moved.
+    pub fn trail(&mut self, side: Side, reference: Price) -> bool {
+        let Some(trail) = self.trail else {
+            return false;
+        };
+        let price = match side {
+            Side::Buy => self.price.min(reference.saturating_add(trail)),
+            Side::Sell => self.price.max(reference.saturating_sub(trail)),
+        };
+        std::mem::replace(&mut self.price, price) != price
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Order<Id = OrderId> {
+    pub id: Id,
     pub quantity: Quantity,
     pub price: Price,
     pub timestamp: Timestamp,
     pub side: Side,
     pub order_type: OrderType,
+    /// Participant that entered the order; `None` for orders submitted without attribution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<ParticipantId>,
+    /// Executed so far; `quantity` is what is left
+    #[serde(default)]
+    pub filled_quantity: Quantity,
+    #[serde(default, skip_serializing_if = "TimeInForce::is_good_till_cancel")]
+    pub time_in_force: TimeInForce,
+    /// Set while the order waits for its trigger, cleared once it fires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<Trigger>,
+    /// Where the order stands in the sequence of orders the book accepted, its time priority
+    /// at a price; `0` until it's accepted
+    #[serde(default)]
+    pub arrival: u64,
+}
+
+/// Where a working (not yet filled or canceled) order stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    /// Accepted outside continuous trading, waiting for the open
+    Queued,
+    /// Waiting for its activation time
+    Scheduled,
+    /// Waiting for the market to reach its trigger price
+    Untriggered,
+    New,
+    PartiallyFilled,
 }
 
-impl Order {
+impl<Id: OrderKey> Order<Id> {
     pub fn new(
-        id: OrderId,
+        id: Id,
         side: Side,
         order_type: OrderType,
         quantity: Quantity,
@@ -38,16 +155,50 @@ impl Order {
         timestamp: Timestamp,
     ) -> Self {
         Order {
-            id: id,
-            quantity: quantity,
-            price: price,
-            side: side,
-            order_type: order_type,
-            timestamp: timestamp,
+            id,
+            quantity,
+            price,
+            side,
+            order_type,
+            timestamp,
+            owner: None,
+            filled_quantity: 0,
+            time_in_force: TimeInForce::GoodTillCancel,
+            trigger: None,
+            arrival: 0,
+        }
+    }
+
+    pub fn fill(&mut self, quantity: Quantity) {
+        self.quantity -= quantity;
+        self.filled_quantity += quantity;
+    }
+
+    /// Status of an order in the book
+    pub fn status(&self) -> OrderStatus {
+        if self.filled_quantity > 0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::New
         }
     }
 
-    pub fn can_match(&self, other: &Order) -> bool {
+    pub fn with_owner(mut self, owner: ParticipantId) -> Self {
+        self.owner = Some(owner);
+        self
+    }
+
+    pub fn with_time_in_force(mut self, time_in_force: TimeInForce) -> Self {
+        self.time_in_force = time_in_force;
+        self
+    }
+
+    pub fn with_trigger(mut self, trigger: Trigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn can_match(&self, other: &Order<Id>) -> bool {
         if self.side == other.side {
             return false;
         }
@@ -69,7 +220,7 @@ impl Order {
     }
 }
 
-impl fmt::Display for Order {
+impl<Id: OrderKey> fmt::Display for Order<Id> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match (&self.side, &self.order_type) {
             (Side::Buy, OrderType::Limit) => write!(
@@ -96,40 +247,228 @@ impl fmt::Display for Order {
     }
 }
 
+/// Trades are `Active` unless an admin busted (voided) or corrected them after the fact
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Trade {
-    pub buy_order_id: OrderId,
-    pub sell_order_id: OrderId,
+#[serde(tag = "status")]
+pub enum TradeStatus {
+    Active,
+    Busted {
+        reason: String,
+    },
+    Corrected {
+        reason: String,
+        original_price: Price,
+        original_quantity: Quantity,
+    },
+}
+
+/// How a trade came about
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeType {
+    /// Matched by the engine
+    #[default]
+    Regular,
+    /// Negotiated privately and reported to the engine, never on the book
+    Block,
+}
+
+impl TradeType {
+    pub fn is_regular(&self) -> bool {
+        *self == TradeType::Regular
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trade<Id = OrderId> {
+    pub id: TradeId,
+    pub buy_order_id: Id,
+    pub sell_order_id: Id,
     pub price: Price,
     pub quantity: Quantity,
+    #[serde(flatten)]
+    pub status: TradeStatus,
+    /// Owners of the buy and sell orders, where known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buyer: Option<ParticipantId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seller: Option<ParticipantId>,
+    /// Side of the incoming order that took liquidity, `None` for auction fills and block trades
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggressor: Option<Side>,
+    /// Fees charged to each side (cents)
+    #[serde(default)]
+    pub buyer_fee: u64,
+    #[serde(default)]
+    pub seller_fee: u64,
+    /// Global event sequence number of the execution
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(default, skip_serializing_if = "TradeType::is_regular")]
+    pub trade_type: TradeType,
+    /// Unix ns the engine executed the trade at, `0` where the engine keeps no time
+    #[serde(default)]
+    pub timestamp: Timestamp,
 }
 
-impl Trade {
+impl<Id: OrderKey> Trade<Id> {
     pub fn new(
-        buy_order_id: OrderId,
-        sell_order_id: OrderId,
+        id: TradeId,
+        buy_order_id: Id,
+        sell_order_id: Id,
         price: Price,
         quantity: Quantity,
     ) -> Self {
         Trade {
+            id,
             buy_order_id,
             sell_order_id,
             price,
             quantity,
+            status: TradeStatus::Active,
+            buyer: None,
+            seller: None,
+            aggressor: None,
+            buyer_fee: 0,
+            seller_fee: 0,
+            seq: 0,
+            trade_type: TradeType::Regular,
+            timestamp: 0,
         }
     }
+
+    pub fn with_parties(
+        mut self,
+        buyer: Option<ParticipantId>,
+        seller: Option<ParticipantId>,
+    ) -> Self {
+        self.buyer = buyer;
+        self.seller = seller;
+        self
+    }
+
+    pub fn with_aggressor(mut self, side: Side) -> Self {
+        self.aggressor = Some(side);
+        self
+    }
+
+    pub fn with_type(mut self, trade_type: TradeType) -> Self {
+        self.trade_type = trade_type;
+        self
+    }
+
+    /// Executed at `timestamp` (unix ns)
+    pub fn at(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Busted trades never happened as far as volume, positions or statistics are concerned
+    pub fn is_busted(&self) -> bool {
+        matches!(self.status, TradeStatus::Busted { .. })
+    }
 }
 
-impl fmt::Display for Trade {
+impl<Id: OrderKey> fmt::Display for Trade<Id> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "\nTrade\nBuy ID: {}\nSell ID: {}\nPrice: {}\nQuantity: {}\n",
-            self.buy_order_id, self.sell_order_id, self.price, self.quantity
+            "\nTrade {}\nBuy ID: {}\nSell ID: {}\nPrice: {}\nQuantity: {}\n",
+            self.id, self.buy_order_id, self.sell_order_id, self.price, self.quantity
         )
     }
 }
 
+/// Why the engine refused to accept an order (or a cancel)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RejectReason {
+    Halted,
+    MarketClosed,
+    MarketOrderInAuction,
+    ParticipantBlocked,
+    PriceOutsideBands {
+        lower: Price,
+        upper: Price,
+    },
+    FatFinger {
+        reference: Price,
+    },
+    MaxQuantityExceeded {
+        limit: Quantity,
+    },
+    MaxNotionalExceeded {
+        limit: u64,
+    },
+    PositionLimitExceeded {
+        limit: u64,
+    },
+    NotOrderOwner,
+    InsufficientBalance,
+    /// The engine mirrors a primary and takes no orders until it is promoted
+    Standby,
+    /// A quote's bid is at or above its ask
+    CrossedQuote,
+    /// The participant sent more than `limit` messages per trade within the window
+    OrderToTradeRatioExceeded {
+        limit: u32,
+    },
+    /// The engine's submission queue is full
+    Overloaded,
+    /// Refused by one of the deployment's own pre-trade checks
+    RiskCheckFailed,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::Halted => write!(f, "trading is halted"),
+            RejectReason::MarketClosed => write!(f, "market is not in continuous trading"),
+            RejectReason::MarketOrderInAuction => {
+                write!(f, "market orders are not accepted during an auction")
+            }
+            RejectReason::ParticipantBlocked => {
+                write!(f, "participant is blocked by the kill switch")
+            }
+            RejectReason::PriceOutsideBands { lower, upper } => {
+                write!(f, "limit price outside the price bands [{lower}, {upper}]")
+            }
+            RejectReason::FatFinger { reference } => {
+                write!(
+                    f,
+                    "limit price deviates too far from the reference price {reference}"
+                )
+            }
+            RejectReason::MaxQuantityExceeded { limit } => {
+                write!(f, "order quantity exceeds the maximum of {limit}")
+            }
+            RejectReason::MaxNotionalExceeded { limit } => {
+                write!(f, "order notional exceeds the maximum of {limit}")
+            }
+            RejectReason::InsufficientBalance => {
+                write!(f, "insufficient available balance for the order")
+            }
+            RejectReason::NotOrderOwner => {
+                write!(f, "order belongs to another participant")
+            }
+            RejectReason::PositionLimitExceeded { limit } => {
+                write!(f, "order could take the position past the limit of {limit}")
+            }
+            RejectReason::Standby => {
+                write!(f, "engine is a standby, send orders to the primary")
+            }
+            RejectReason::CrossedQuote => write!(f, "quote bid must be below its ask"),
+            RejectReason::Overloaded => write!(f, "engine is overloaded, retry later"),
+            RejectReason::RiskCheckFailed => write!(f, "order refused by a pre-trade risk check"),
+            RejectReason::OrderToTradeRatioExceeded { limit } => {
+                write!(
+                    f,
+                    "order-to-trade ratio over the limit of {limit} messages per trade"
+                )
+            }
+        }
+    }
+}
+
 pub fn _compare_buy_orders(o1: &Order, o2: &Order) -> Ordering {
     match o1.price.cmp(&o2.price) {
         Ordering::Less => Ordering::Greater,
@@ -157,7 +496,7 @@ mod test {
 
     #[test]
     fn test_trade_display_format() {
-        let t1 = Trade::new("1".to_string(), "1".to_string(), 10, 2000);
+        let t1 = Trade::new(1, "1".to_string(), "1".to_string(), 10, 2000);
         println!("{}", t1);
     }
 
@@ -186,4 +525,46 @@ mod test {
 
         assert_eq!(_compare_sell_orders(&ot1, &ot2), Ordering::Less);
     }
+
+    #[test]
+    fn test_trigger_touched() {
+        let mut trigger = Trigger {
+            price: 100,
+            source: TriggerSource::LastTrade,
+            kind: TriggerKind::Stop,
+            trail: None,
+        };
+        assert!(trigger.is_touched(Side::Buy, 100));
+        assert!(trigger.is_touched(Side::Buy, 101));
+        assert!(!trigger.is_touched(Side::Buy, 99));
+        assert!(trigger.is_touched(Side::Sell, 99));
+        assert!(!trigger.is_touched(Side::Sell, 101));
+
+        trigger.kind = TriggerKind::Touch;
+        assert!(trigger.is_touched(Side::Buy, 100));
+        assert!(trigger.is_touched(Side::Buy, 99));
+        assert!(!trigger.is_touched(Side::Buy, 101));
+        assert!(trigger.is_touched(Side::Sell, 101));
+        assert!(!trigger.is_touched(Side::Sell, 99));
+    }
+
+    #[test]
+    fn test_trailing_stop() {
+        let mut trigger = Trigger {
+            price: 90,
+            source: TriggerSource::LastTrade,
+            kind: TriggerKind::Stop,
+            trail: Some(10),
+        };
+        assert!(trigger.trail(Side::Sell, 105));
+        assert_eq!(trigger.price, 95);
+        assert!(!trigger.trail(Side::Sell, 98)); // never moves back
+        assert_eq!(trigger.price, 95);
+        assert!(trigger.is_touched(Side::Sell, 95));
+
+        trigger.trail(Side::Buy, 80);
+        assert_eq!(trigger.price, 90);
+        trigger.trail(Side::Buy, 85);
+        assert_eq!(trigger.price, 90);
+    }
 }
@@ -1,231 +1,316 @@
-use std::{
-    collections::{BTreeMap, HashMap},
-    fmt,
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+
+use crate::{
+    bookbackend::{BTreeBackend, OrderBookBackend},
+    order::{Order, OrderId, OrderKey, Price, Quantity, Side},
 };
 
-use crate::order::{Order, OrderId, Price, Quantity, Side};
+/// Time priority between orders at the same price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Order timestamp, earliest first, trusting whoever stamped the orders
+    Timestamp,
+    /// The order in which the book accepted the orders, by their `arrival` sequence
+    #[default]
+    Arrival,
+}
+
+impl TieBreak {
+    /// Where `order` goes in `level`
+    fn position<Id>(&self, level: &[Order<Id>], order: &Order<Id>) -> usize {
+        match self {
+            TieBreak::Timestamp => level
+                .iter()
+                .position(|ele| ele.timestamp > order.timestamp)
+                .unwrap_or(level.len()), // iterate over the vector to find the first timestamp
+            // greater than the current timestamp and return the position
+            // from the back: a new order almost always goes last
+            TieBreak::Arrival => level
+                .iter()
+                .rposition(|ele| ele.arrival <= order.arrival)
+                .map_or(0, |i| i + 1),
+        }
+    }
+}
+
+/// Resting orders, their total quantity and the number of price levels on one side of a book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SideStats {
+    pub orders: usize,
+    pub quantity: Quantity,
+    pub levels: usize,
+}
 
-pub struct OrderBook {
-    pub bids: BTreeMap<Price, Vec<Order>>,
-    pub asks: BTreeMap<Price, Vec<Order>>,
-    pub order_map: HashMap<OrderId, (Quantity, Price, Side)>,
+/// Resting orders keyed by `Id` (see [`OrderKey`]), stored in backend `B`
+pub struct OrderBook<Id: OrderKey = OrderId, B: OrderBookBackend<Id> = BTreeBackend<Id>> {
+    levels: B,
+    pub order_map: HashMap<Id, (Quantity, Price, Side)>,
+    tie_break: TieBreak,
+    resting: [(usize, Quantity); 2], // orders and quantity of the bids, then the asks
+    last_arrival: u64,               // highest arrival sequence handed out or seen
 }
 
-impl OrderBook {
+impl<Id: OrderKey> OrderBook<Id> {
     pub fn new() -> Self {
+        OrderBook::with_backend(BTreeBackend::default())
+    }
+}
+
+impl<Id: OrderKey> Default for OrderBook<Id> {
+    fn default() -> Self {
+        OrderBook::new()
+    }
+}
+
+impl<Id: OrderKey, B: OrderBookBackend<Id>> OrderBook<Id, B> {
+    /// Empty book storing its levels in `backend`
+    pub fn with_backend(backend: B) -> Self {
         OrderBook {
-            bids: BTreeMap::<Price, Vec<Order>>::new(),
-            asks: BTreeMap::<Price, Vec<Order>>::new(),
+            levels: backend,
+            tie_break: TieBreak::default(),
+            resting: [(0, 0); 2],
+            last_arrival: 0,
             order_map: HashMap::new(), // keep track of ALL the orders in the book, regardless of
                                        // side
         }
     }
 
-    pub fn add_order(&mut self, order: Order) {
-        let side = order.side;
-
-        match side {
-            Side::Buy => {
-                let queue = self.bids.entry(order.price).or_insert_with(Vec::new);
-
-                let pos = queue
-                    .iter()
-                    .position(|ele| ele.timestamp > order.timestamp)
-                    .unwrap_or(queue.len()); // iterate over the vector to find the first timestamp
-                // greater than the current timestamp and return the position
-
-                queue.insert(pos, order.clone());
-            }
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
 
-            Side::Sell => {
-                let queue = self.asks.entry(order.price).or_insert_with(Vec::new);
+    /// The arrival sequence of an order accepted now, strictly greater than any before it
+    pub fn next_arrival(&mut self) -> u64 {
+        self.last_arrival += 1;
+        self.last_arrival
+    }
 
-                let pos = queue
-                    .iter()
-                    .position(|ele| ele.timestamp > order.timestamp)
-                    .unwrap_or(queue.len()); // iterate over the vector to find the first timestamp
-                // greater than the current timestamp and return the position
+    /// Keeps later arrivals after `arrival`, that of an order accepted elsewhere, e.g. on a
+    /// primary, or waiting outside the book
+    pub fn note_arrival(&mut self, arrival: u64) {
+        self.last_arrival = self.last_arrival.max(arrival);
+    }
 
-                queue.insert(pos, order.clone());
-            }
+    /// Rests `order` behind the orders at its price that arrived before it. An order without
+    /// an arrival sequence (`0`) is given the next one.
+    pub fn add_order(&mut self, mut order: Order<Id>) {
+        if order.arrival == 0 {
+            order.arrival = self.next_arrival();
+        } else {
+            self.note_arrival(order.arrival);
         }
-
         // insert orders to the heap ONLY if they are of LIMIT type
         // if order.order_type != OrderType::Market {
         self.order_map
-            .insert(order.id, (order.quantity, order.price, order.side));
+            .insert(order.id.clone(), (order.quantity, order.price, order.side));
         // }
+
+        self.count(&order, true);
+        let tie_break = self.tie_break;
+        let incoming = order.clone();
+        self.levels
+            .add(order, |level| tie_break.position(level, &incoming));
     }
 
-    pub fn peek_best_buy(&mut self) -> Option<Order> {
-        loop {
-            let (best_price, _) = match self.bids.last_key_value() {
-                Some((p, q)) => (*p, q),
-                None => return None,
-            };
-
-            if let Some(q) = self.bids.get_mut(&best_price) {
-                if q.is_empty() {
-                    self.bids.remove(&best_price);
-                    continue;
-                }
-                if let Some(front) = q.first() {
-                    return Some(front.clone());
-                }
-            }
-        }
+    pub fn peek_best_buy(&self) -> Option<Order<Id>> {
+        self.levels.peek(Side::Buy).cloned()
     }
 
-    pub fn pop_best_buy(&mut self) -> Option<Order> {
-        loop {
-            let (best_price, _) = match self.bids.last_key_value() {
-                Some((p, q)) => (*p, q),
-                None => return None,
-            };
-
-            if let Some(q) = self.bids.get_mut(&best_price) {
-                if q.is_empty() {
-                    self.bids.remove(&best_price);
-                    continue;
-                }
-                if let Some(_) = q.first() {
-                    let front = q.remove(0);
-                    return Some(front);
-                }
-            }
-        }
+    pub fn pop_best_buy(&mut self) -> Option<Order<Id>> {
+        let order = self.levels.pop(Side::Buy)?;
+        self.order_map.remove(&order.id);
+        self.count(&order, false);
+        Some(order)
     }
 
-    pub fn peek_best_sell(&mut self) -> Option<Order> {
-        loop {
-            let (best_price, _) = match self.asks.first_key_value() {
-                Some((p, q)) => (*p, q),
-                None => return None,
-            };
-
-            if let Some(q) = self.asks.get_mut(&best_price) {
-                if q.is_empty() {
-                    self.asks.remove(&best_price);
-                    continue;
-                }
-                if let Some(front) = q.first() {
-                    return Some(front.clone());
-                }
-            }
+    pub fn peek_best_sell(&self) -> Option<Order<Id>> {
+        self.levels.peek(Side::Sell).cloned()
+    }
+
+    pub fn pop_best_sell(&mut self) -> Option<Order<Id>> {
+        let order = self.levels.pop(Side::Sell)?;
+        self.order_map.remove(&order.id);
+        self.count(&order, false);
+        Some(order)
+    }
+
+    /// Removes and returns every order resting at `price` on `side`, in time priority
+    pub fn take_level(&mut self, side: Side, price: Price) -> Vec<Order<Id>> {
+        let level = self.levels.take_level(side, price);
+        for order in &level {
+            self.order_map.remove(&order.id);
+            self.count(order, false);
         }
+        level
     }
 
-    pub fn pop_best_sell(&mut self) -> Option<Order> {
-        loop {
-            let (best_price, _) = match self.asks.first_key_value() {
-                Some((p, q)) => (*p, q),
-                None => return None,
-            };
-
-            if let Some(q) = self.asks.get_mut(&best_price) {
-                if q.is_empty() {
-                    self.asks.remove(&best_price);
-                    continue;
-                }
-                if let Some(_) = q.first() {
-                    let front = q.remove(0);
-                    return Some(front);
-                }
-            }
+    /// Puts what is left of a level taken with [`OrderBook::take_level`] back in front of the
+    /// level, keeping the orders' priority
+    pub fn restore_level(&mut self, side: Side, price: Price, orders: Vec<Order<Id>>) {
+        for order in &orders {
+            self.order_map
+                .insert(order.id.clone(), (order.quantity, order.price, order.side));
+            self.count(order, true);
         }
+        self.levels.restore_level(side, price, orders);
+    }
+
+    /// Non-empty levels of `side`, best price first
+    pub fn levels(&self, side: Side) -> impl Iterator<Item = (Price, &[Order<Id>])> {
+        self.levels.levels(side)
+    }
+
+    /// Total resting (buy, sell) quantity of `owner`
+    pub fn owner_open_quantity(&self, owner: &str) -> (Quantity, Quantity) {
+        let sum = |side| {
+            self.levels(side)
+                .flat_map(|(_, level)| level)
+                .filter(|o| o.owner.as_deref() == Some(owner))
+                .map(|o| o.quantity)
+                .sum::<Quantity>()
+        };
+        (sum(Side::Buy), sum(Side::Sell))
     }
 
-    pub fn cancel_order(&mut self, order_id: OrderId) -> bool {
+    pub fn best_bid(&self) -> Option<Price> {
+        self.levels(Side::Buy).next().map(|(p, _)| p)
+    }
+
+    pub fn best_ask(&self) -> Option<Price> {
+        self.levels(Side::Sell).next().map(|(p, _)| p)
+    }
+
+    /// Midpoint of the touch, `None` unless both sides have orders
+    pub fn mid_price(&self) -> Option<Price> {
+        Some((self.best_bid()? + self.best_ask()?) / 2)
+    }
+
+    pub fn cancel_order(&mut self, order_id: Id) -> bool {
         // we extract the side from the order_map
-        if let Some(ord) = self.order_map.get(&order_id) {
-            let side = ord.2; // side
-            let price = ord.1; // price
-            let removed = match side {
-                Side::Buy => {
-                    if let Some(q) = self.bids.get_mut(&price) {
-                        if let Some(ind) = q.iter().position(|e| e.id == order_id) {
-                            q.remove(ind);
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                }
-                Side::Sell => {
-                    if let Some(q) = self.asks.get_mut(&price) {
-                        if let Some(ind) = q.iter().position(|e| e.id == order_id) {
-                            q.remove(ind);
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    }
-                }
-            };
-
-            if removed {
-                self.order_map.remove(&order_id);
-            }
+        let Some(&(_, price, side)) = self.order_map.get(&order_id) else {
+            return false;
+        };
+        let Some(order) = self.levels.cancel(side, price, &order_id) else {
+            return false;
+        };
+        self.order_map.remove(&order_id);
+        self.count(&order, false);
+        true
+    }
 
-            removed
-        } else {
-            false
-        }
+    /// Cancels every resting order entered by `owner`, returning the removed orders
+    pub fn cancel_owner_orders(&mut self, owner: &str) -> Vec<Order<Id>> {
+        self.cancel_matching(|o| o.owner.as_deref() == Some(owner))
     }
 
-    pub fn get_buy_orders(&self) -> Vec<Order> {
-        let mut buy_orders = Vec::<Order>::new();
-        for (_, v) in self.bids.iter() {
-            for bo in v {
-                buy_orders.push(bo.clone());
+    /// Cancels every resting order `f` accepts, returning the removed orders
+    pub fn cancel_matching(&mut self, f: impl Fn(&Order<Id>) -> bool) -> Vec<Order<Id>> {
+        let ids: Vec<Id> = self
+            .levels(Side::Buy)
+            .chain(self.levels(Side::Sell))
+            .flat_map(|(_, level)| level)
+            .filter(|o| f(o))
+            .map(|o| o.id.clone())
+            .collect();
+
+        let mut removed = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(order) = self.get_order(&id) {
+                self.cancel_order(id);
+                removed.push(order);
             }
         }
+        removed
+    }
 
-        buy_orders
+    pub fn get_order(&self, order_id: &Id) -> Option<Order<Id>> {
+        let (_, price, side) = self.order_map.get(order_id)?;
+        self.levels.get(*side, *price, order_id).cloned()
     }
 
-    pub fn get_sell_orders(&self) -> Vec<Order> {
-        let mut sell_orders = Vec::<Order>::new();
-        for (_, v) in self.asks.iter() {
-            for bo in v {
-                sell_orders.push(bo.clone());
-            }
+    /// Removes every resting order, returning them bids first (best to worst), then asks
+    pub fn clear(&mut self) -> Vec<Order<Id>> {
+        let mut removed = self.get_buy_orders();
+        removed.reverse();
+        removed.extend(self.get_sell_orders());
+
+        self.levels.clear();
+        self.order_map.clear();
+        self.resting = [(0, 0); 2];
+        removed
+    }
+
+    /// Resting orders, quantity and levels of `side`, kept up to date as the book changes
+    /// rather than counted
+    pub fn side_stats(&self, side: Side) -> SideStats {
+        let (orders, quantity) = self.resting[side as usize];
+        SideStats {
+            orders,
+            quantity,
+            levels: self.levels.level_count(side),
         }
-        sell_orders
+    }
+
+    /// Counts `order` in or out of the totals of its side
+    fn count(&mut self, order: &Order<Id>, resting: bool) {
+        let (orders, quantity) = &mut self.resting[order.side as usize];
+        if resting {
+            *orders += 1;
+            *quantity += order.quantity;
+        } else {
+            *orders -= 1;
+            *quantity -= order.quantity;
+        }
+    }
+
+    /// Resting buy orders, lowest price first
+    pub fn get_buy_orders(&self) -> Vec<Order<Id>> {
+        let levels: Vec<&[Order<Id>]> = self.levels(Side::Buy).map(|(_, level)| level).collect();
+        levels.into_iter().rev().flatten().cloned().collect()
+    }
+
+    /// Resting sell orders, lowest price first
+    pub fn get_sell_orders(&self) -> Vec<Order<Id>> {
+        self.levels(Side::Sell)
+            .flat_map(|(_, level)| level)
+            .cloned()
+            .collect()
     }
 }
 
-impl fmt::Display for OrderBook {
+impl<Id: OrderKey, B: OrderBookBackend<Id>> fmt::Display for OrderBook<Id, B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // let mut bfstr = String::from("");
         // let mut afstr = String::from("");
-        let _ = write!(f, "\n");
-        let _ = write!(f, "Buy:\n");
-        for (price, queue) in self.bids.iter() {
-            let _ = write!(f, "{} -> {:?}\n", price, queue);
+        let _ = writeln!(f);
+        let _ = writeln!(f, "Buy:");
+        let bids: Vec<_> = self.levels(Side::Buy).collect();
+        for (price, queue) in bids.into_iter().rev() {
+            let _ = writeln!(f, "{} -> {:?}", price, queue);
         }
 
-        let _ = write!(f, "\n");
-        let _ = write!(f, "Sell:\n");
-        for (price, queue) in self.asks.iter() {
-            let _ = write!(f, "{} -> {:?}\n", price, queue);
+        let _ = writeln!(f);
+        let _ = writeln!(f, "Sell:");
+        for (price, queue) in self.levels(Side::Sell) {
+            let _ = writeln!(f, "{} -> {:?}", price, queue);
         }
 
-        write!(f, "\n")
+        writeln!(f)
         // write!(f, bfstr)
     }
 }
 
-impl Clone for OrderBook {
+impl<Id: OrderKey, B: OrderBookBackend<Id>> Clone for OrderBook<Id, B> {
     fn clone(&self) -> Self {
         OrderBook {
-            bids: self.bids.clone(),
-            asks: self.asks.clone(),
+            levels: self.levels.clone(),
             order_map: self.order_map.clone(),
+            tie_break: self.tie_break,
+            resting: self.resting,
+            last_arrival: self.last_arrival,
         }
     }
 }
@@ -416,5 +501,171 @@ mod test {
 
         println!("{}", ob);
         println!("Order_Map: {:?}", ob.order_map);
+
+        // popped orders leave the index like canceled ones, so they can't be canceled again
+        let popped = ob.pop_best_sell().unwrap();
+        assert!(!ob.order_map.contains_key(&popped.id));
+        assert!(!ob.cancel_order(popped.id));
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut ob = OrderBook::new();
+        ob.add_order(Order::new(
+            String::from("1"),
+            Side::Buy,
+            OrderType::Limit,
+            10,
+            100,
+            1,
+        ));
+        ob.add_order(Order::new(
+            String::from("2"),
+            Side::Buy,
+            OrderType::Limit,
+            10,
+            200,
+            2,
+        ));
+        ob.add_order(Order::new(
+            String::from("3"),
+            Side::Sell,
+            OrderType::Limit,
+            10,
+            300,
+            3,
+        ));
+
+        let removed: Vec<OrderId> = ob.clear().into_iter().map(|o| o.id).collect();
+        assert_eq!(removed, vec!["2", "1", "3"]);
+        assert!(ob.peek_best_buy().is_none());
+        assert!(ob.peek_best_sell().is_none());
+        assert!(ob.order_map.is_empty());
+    }
+
+    #[test]
+    fn test_cancel_owner_orders() {
+        let mut ob = OrderBook::new();
+        ob.add_order(
+            Order::new(String::from("1"), Side::Buy, OrderType::Limit, 10, 100, 1)
+                .with_owner(String::from("alice")),
+        );
+        ob.add_order(
+            Order::new(String::from("2"), Side::Sell, OrderType::Limit, 10, 200, 2)
+                .with_owner(String::from("alice")),
+        );
+        ob.add_order(
+            Order::new(String::from("3"), Side::Buy, OrderType::Limit, 10, 100, 3)
+                .with_owner(String::from("bob")),
+        );
+
+        let removed = ob.cancel_owner_orders("alice");
+        assert_eq!(removed.len(), 2);
+        assert!(ob.get_order(&String::from("1")).is_none());
+        assert!(ob.get_order(&String::from("2")).is_none());
+        assert!(ob.get_order(&String::from("3")).is_some());
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let order =
+            |id: &str, ts| Order::new(id.to_string(), Side::Sell, OrderType::Limit, 1, 100, ts);
+        let mut by_time = OrderBook::new().with_tie_break(TieBreak::Timestamp);
+        let mut by_arrival = OrderBook::new();
+        for ob in [&mut by_time, &mut by_arrival] {
+            ob.add_order(order("late", 2));
+            ob.add_order(order("early", 1));
+        }
+        assert_eq!(by_time.peek_best_sell().unwrap().id, "early");
+        assert_eq!(by_arrival.peek_best_sell().unwrap().id, "late");
+        assert_eq!(
+            by_arrival
+                .get_order(&String::from("early"))
+                .unwrap()
+                .arrival,
+            2
+        );
+
+        // a sequence assigned on acceptance counts, whatever the timestamp says
+        let (first, second) = (by_arrival.next_arrival(), by_arrival.next_arrival());
+        by_arrival.add_order(Order {
+            arrival: second,
+            ..order("second", 0)
+        });
+        by_arrival.add_order(Order {
+            arrival: first,
+            ..order("first", u64::MAX)
+        });
+        let ids: Vec<String> = by_arrival
+            .get_sell_orders()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+        assert_eq!(ids, vec!["late", "early", "first", "second"]);
+        assert_eq!(by_arrival.next_arrival(), 5);
+        for id in ["first", "second"] {
+            by_arrival.cancel_order(id.to_string());
+        }
+
+        // a partially matched level goes back ahead of anything else
+        let mut level = by_arrival.take_level(Side::Sell, 100);
+        level.remove(0);
+        by_arrival.add_order(order("new", 0));
+        by_arrival.restore_level(Side::Sell, 100, level);
+        let ids: Vec<String> = by_arrival
+            .get_sell_orders()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+        assert_eq!(ids, vec!["early", "new"]);
+        assert!(by_arrival.get_order(&String::from("early")).is_some());
+    }
+
+    #[test]
+    fn test_u64_ids() {
+        let mut ob: OrderBook<u64> = OrderBook::new();
+        ob.add_order(Order::new(1, Side::Buy, OrderType::Limit, 10, 100, 1));
+        ob.add_order(Order::new(2, Side::Buy, OrderType::Limit, 10, 101, 2));
+        assert_eq!(ob.get_order(&1).unwrap().price, 100);
+        assert_eq!(ob.pop_best_buy().unwrap().id, 2);
+        assert!(ob.cancel_order(1));
+        assert!(ob.peek_best_buy().is_none());
+    }
+
+    #[test]
+    fn test_side_stats() {
+        let order = |id: &str, side, quantity, price| {
+            Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+        };
+        let mut ob = OrderBook::new();
+        ob.add_order(order("b1", Side::Buy, 5, 100));
+        ob.add_order(order("b2", Side::Buy, 3, 100));
+        ob.add_order(order("b3", Side::Buy, 2, 99));
+        ob.add_order(order("a1", Side::Sell, 4, 105));
+        assert_eq!(
+            ob.side_stats(Side::Buy),
+            SideStats {
+                orders: 3,
+                quantity: 10,
+                levels: 2
+            }
+        );
+
+        let mut level = ob.take_level(Side::Buy, 100);
+        level[0].quantity = 1; // partially filled
+        ob.restore_level(Side::Buy, 100, level);
+        assert!(ob.cancel_order(String::from("b3")));
+        assert_eq!(ob.pop_best_sell().unwrap().id, "a1");
+        assert_eq!(
+            ob.side_stats(Side::Buy),
+            SideStats {
+                orders: 2,
+                quantity: 4,
+                levels: 1
+            }
+        );
+        assert_eq!(ob.side_stats(Side::Sell), SideStats::default());
+        ob.clear();
+        assert_eq!(ob.side_stats(Side::Buy), SideStats::default());
     }
 }
@@ -0,0 +1,235 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    consensus::Consensus,
+    error::{ApiError, ErrorCode},
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, ParticipantId, RejectReason, Trade},
+};
+
+/// How much of an order's outcome the response to a new order waits for
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    /// The ack and the order's executions on entry
+    #[default]
+    Full,
+    /// Only the ack; executions follow as events
+    Ack,
+}
+
+/// The engine's first answer to an order, before any execution: the `seq` of its
+/// `OrderAccepted` or `OrderRejected` event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum OrderAck {
+    Accepted {
+        id: OrderId,
+        seq: u64,
+    },
+    Rejected {
+        id: OrderId,
+        seq: u64,
+        reason: RejectReason,
+    },
+}
+
+impl OrderAck {
+    /// The ack of `order_id`, if that's what `entry` is
+    pub fn from_entry(entry: &AuditEntry, order_id: &str) -> Option<Self> {
+        match &entry.event {
+            AuditEvent::OrderAccepted { order } if order.id == order_id => {
+                Some(OrderAck::Accepted {
+                    id: order.id.clone(),
+                    seq: entry.seq,
+                })
+            }
+            AuditEvent::OrderRejected {
+                order_id: id,
+                reason,
+                ..
+            } if id == order_id => Some(OrderAck::Rejected {
+                id: id.clone(),
+                seq: entry.seq,
+                reason: *reason,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Orders whose entry waits for the ack, by id; the engine hands each its ack as the event is
+/// appended, so entry doesn't have to look for it among everyone's events
+#[derive(Debug, Default)]
+pub struct AckWaiters {
+    waiting: Mutex<HashMap<OrderId, oneshot::Sender<OrderAck>>>,
+}
+
+impl AckWaiters {
+    /// Resolves with the ack of `order_id` once the engine appends it
+    pub fn watch(&self, order_id: OrderId) -> oneshot::Receiver<OrderAck> {
+        let (tx, rx) = oneshot::channel();
+        self.lock().insert(order_id, tx);
+        rx
+    }
+
+    /// Stops waiting for the ack of `order_id`, e.g. once its submission failed
+    pub fn forget(&self, order_id: &str) {
+        self.lock().remove(order_id);
+    }
+
+    /// Hands `entry` to the order waiting for it, if it's an ack
+    pub fn notify(&self, entry: &AuditEntry) {
+        let order_id = match &entry.event {
+            AuditEvent::OrderAccepted { order } => &order.id,
+            AuditEvent::OrderRejected { order_id, .. } => order_id,
+            _ => return,
+        };
+        let Some(tx) = self.lock().remove(order_id) else {
+            return;
+        };
+        if let Some(ack) = OrderAck::from_entry(entry, order_id) {
+            let _ = tx.send(ack); // the entry may have given up already
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<OrderId, oneshot::Sender<OrderAck>>> {
+        self.waiting.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stops waiting for the ack of an order when its entry is done, whether it got the ack,
+/// was refused, or was dropped before either (request timeout, client gone)
+struct AckGuard<'a> {
+    engine: &'a MatchingEngine,
+    order_id: OrderId,
+}
+
+impl Drop for AckGuard<'_> {
+    fn drop(&mut self) {
+        self.engine.forget_ack(&self.order_id);
+    }
+}
+
+/// An order the engine answered; `executions` resolves to its trades on entry once it's
+/// done matching
+pub struct Entry {
+    pub ack: OrderAck,
+    pub executions: JoinHandle<Result<Vec<Trade>, ApiError>>,
+}
+
+/// Sends `order` to the engine, through the log in consensus mode, and returns as soon as
+/// the engine acked it, while the order goes on to match. The ack is the engine's event, so
+/// it's the same the order's owner sees on `/events` and the drop copy.
+pub async fn enter_order(
+    engine: &MatchingEngine,
+    consensus: Option<Consensus>,
+    order: Order,
+) -> Result<Entry, ApiError> {
+    let mut acked = engine.watch_ack(order.id.clone());
+    let _guard = AckGuard {
+        engine,
+        order_id: order.id.clone(),
+    };
+    let mut submitter = engine.clone();
+    let mut executions = tokio::spawn(async move {
+        match consensus {
+            Some(consensus) => consensus.submit_order(order).await,
+            None => Ok(submitter.submit_queued(order).await?),
+        }
+    });
+
+    tokio::select! {
+        biased; // the ack is sent before the submission finishes, take it when both are ready
+        Ok(ack) = &mut acked => Ok(Entry { ack, executions }),
+        done = &mut executions => {
+            // finished without an ack, because the order was refused before reaching the
+            // engine (e.g. not the leader)
+            let result =
+                done.unwrap_or_else(|e| Err(ApiError::new(ErrorCode::Internal, e.to_string())));
+            match acked.try_recv() {
+                Ok(ack) => Ok(Entry {
+                    ack,
+                    executions: tokio::spawn(async move { result }),
+                }),
+                Err(_) => Err(result.err().unwrap_or_else(|| {
+                    ApiError::new(ErrorCode::Internal, "order entered without an ack")
+                })),
+            }
+        }
+    }
+}
+
+/// Cancels `order_id`, through the log in consensus mode. With an `owner` only an order of
+/// theirs is canceled; `Ok(false)` when there is no such resting order.
+pub async fn cancel_order(
+    engine: &MatchingEngine,
+    consensus: Option<&Consensus>,
+    order_id: OrderId,
+    owner: Option<ParticipantId>,
+) -> Result<bool, ApiError> {
+    let mut engine = engine.clone();
+    Ok(match (consensus, owner) {
+        (Some(consensus), owner) => consensus.cancel_order(order_id, owner).await?,
+        (None, Some(owner)) => engine.cancel_owned_order(order_id, &owner).await?,
+        (None, None) => engine.cancel_order(order_id).await?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    #[tokio::test]
+    async fn test_ack_before_executions() {
+        let mut engine = MatchingEngine::new();
+        let order = |id: &str, side| Order::new(id.to_string(), side, OrderType::Limit, 5, 100, 1);
+        engine.submit_order(order("s1", Side::Sell)).await.unwrap();
+
+        let entry = enter_order(&engine, None, order("b1", Side::Buy))
+            .await
+            .unwrap();
+        assert_eq!(
+            entry.ack,
+            OrderAck::Accepted {
+                id: "b1".to_string(),
+                seq: 2
+            }
+        );
+        let trades = entry.executions.await.unwrap().unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seq, 3); // executions come after the ack
+
+        engine
+            .kill_participant("mallory".to_string())
+            .await
+            .unwrap();
+        let blocked = order("m1", Side::Buy).with_owner("mallory".to_string());
+        let entry = enter_order(&engine, None, blocked).await.unwrap();
+        assert!(matches!(
+            entry.ack,
+            OrderAck::Rejected {
+                reason: RejectReason::ParticipantBlocked,
+                ..
+            }
+        ));
+        assert!(entry.executions.await.unwrap().is_err());
+
+        engine.start_standby().await;
+        let entry = enter_order(&engine, None, order("b2", Side::Buy))
+            .await
+            .unwrap();
+        assert!(matches!(
+            entry.ack,
+            OrderAck::Rejected {
+                reason: RejectReason::Standby,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+
+use uuid::{ContextV7, Uuid};
+
+use crate::{
+    clock::Clock,
+    ids::{IdGenerator, SequentialIds, SnowflakeIds},
+    order::OrderId,
+};
+
+pub const ORDER_IDS_ENV: &str = "OME_ORDER_IDS"; // `uuid_v4` | `uuid_v7` | `snowflake` | `sequential`
+pub const WORKER_ID_ENV: &str = "OME_WORKER_ID"; // 0-1023, the snowflake worker of this node
+
+/// Random UUIDs, the server's ids unless configured otherwise
+#[derive(Debug, Default)]
+pub struct UuidV4Ids;
+
+impl IdGenerator for UuidV4Ids {
+    fn next_id(&self) -> OrderId {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// Time-ordered UUIDs, stamped by the engine's clock; ids from the same generator sort by
+/// creation time, within a millisecond too
+#[derive(Debug)]
+pub struct UuidV7Ids {
+    clock: Arc<dyn Clock>,
+    context: Mutex<ContextV7>,
+}
+
+impl UuidV7Ids {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        UuidV7Ids {
+            clock,
+            context: Mutex::new(ContextV7::new()),
+        }
+    }
+}
+
+impl IdGenerator for UuidV7Ids {
+    fn next_id(&self) -> OrderId {
+        let now = self.clock.now_ns();
+        let context = self.context.lock().unwrap_or_else(|e| e.into_inner());
+        let timestamp = uuid::Timestamp::from_unix(
+            &*context,
+            now / 1_000_000_000,
+            (now % 1_000_000_000) as u32,
+        );
+        Uuid::new_v7(timestamp).to_string()
+    }
+}
+
+/// The generator called `name`, as in [`ORDER_IDS_ENV`]
+pub fn by_name(name: &str, worker: u16, clock: Arc<dyn Clock>) -> Option<Arc<dyn IdGenerator>> {
+    match name {
+        "uuid_v4" => Some(Arc::new(UuidV4Ids)),
+        "uuid_v7" => Some(Arc::new(UuidV7Ids::new(clock))),
+        "snowflake" => Some(Arc::new(SnowflakeIds::new(worker, clock))),
+        "sequential" => Some(Arc::new(SequentialIds::new())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::clock::VirtualClock;
+
+    #[test]
+    fn test_by_name() {
+        let clock = Arc::new(VirtualClock::starting_at(1_700_000_000_000_000_000));
+        let v7 = by_name("uuid_v7", 0, clock.clone()).unwrap();
+        let ids: Vec<OrderId> = (0..100).map(|_| v7.next_id()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        let uuid = Uuid::parse_str(&ids[0]).unwrap();
+        assert_eq!(uuid.get_version_num(), 7);
+        assert_eq!(uuid.get_timestamp().unwrap().to_unix(), (1_700_000_000, 0));
+
+        let v4 = by_name("uuid_v4", 0, clock.clone()).unwrap().next_id();
+        assert_eq!(Uuid::parse_str(&v4).unwrap().get_version_num(), 4);
+        assert_eq!(
+            by_name("sequential", 0, clock.clone()).unwrap().next_id(),
+            "1"
+        );
+        assert_eq!(
+            by_name("snowflake", 3, clock.clone())
+                .unwrap()
+                .next_id()
+                .len(),
+            19
+        );
+        assert!(by_name("ulid", 0, clock).is_none());
+    }
+}
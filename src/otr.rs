@@ -0,0 +1,245 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::{ParticipantId, RejectReason, Timestamp};
+
+pub const OTR_MAX_RATIO_ENV: &str = "OME_OTR_MAX_RATIO";
+pub const OTR_WINDOW_SECS_ENV: &str = "OME_OTR_WINDOW_SECS";
+pub const OTR_MIN_MESSAGES_ENV: &str = "OME_OTR_MIN_MESSAGES";
+pub const OTR_ACTION_ENV: &str = "OME_OTR_ACTION";
+
+/// What happens to a participant over the ratio
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OtrAction {
+    /// New orders are rejected until the ratio is back under the limit; cancels still go through
+    #[default]
+    Throttle,
+    /// Only listed among the offenders
+    Warn,
+}
+
+/// Order-to-trade ratio limit: a participant may send at most `max_ratio` messages (orders
+/// and cancels) per trade they took part in within `window_ms`, checked once they sent
+/// `min_messages` in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtrConfig {
+    /// `0` turns the check off
+    pub max_ratio: u32,
+    pub window_ms: u64,
+    pub min_messages: u32,
+    pub action: OtrAction,
+}
+
+impl Default for OtrConfig {
+    fn default() -> Self {
+        OtrConfig {
+            max_ratio: 0,
+            window_ms: 60_000,
+            min_messages: 50,
+            action: OtrAction::Throttle,
+        }
+    }
+}
+
+impl OtrConfig {
+    pub fn from_env() -> Self {
+        let default = OtrConfig::default();
+        let var = |name| std::env::var(name).ok();
+        OtrConfig {
+            max_ratio: var(OTR_MAX_RATIO_ENV)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_ratio),
+            window_ms: var(OTR_WINDOW_SECS_ENV)
+                .and_then(|v| v.parse::<u64>().ok())
+                .map_or(default.window_ms, |secs| secs * 1_000),
+            min_messages: var(OTR_MIN_MESSAGES_ENV)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_messages),
+            action: match var(OTR_ACTION_ENV).as_deref() {
+                Some("warn") => OtrAction::Warn,
+                _ => default.action,
+            },
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_ratio > 0
+    }
+}
+
+/// A participant's messages and trades within the window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OtrStanding {
+    pub participant: ParticipantId,
+    pub messages: usize,
+    pub trades: usize,
+    /// Messages per trade, messages when there was no trade
+    pub ratio: f64,
+    /// Whether new orders are currently rejected
+    pub throttled: bool,
+}
+
+#[derive(Debug, Default)]
+struct Activity {
+    messages: VecDeque<Timestamp>,
+    trades: VecDeque<Timestamp>,
+}
+
+impl Activity {
+    fn expire(&mut self, since: Timestamp) {
+        while self.messages.front().is_some_and(|at| *at < since) {
+            self.messages.pop_front();
+        }
+        while self.trades.front().is_some_and(|at| *at < since) {
+            self.trades.pop_front();
+        }
+    }
+
+    fn is_over(&self, config: &OtrConfig) -> bool {
+        self.messages.len() >= config.min_messages as usize
+            && self.messages.len() > config.max_ratio as usize * self.trades.len().max(1)
+    }
+}
+
+/// Messages and trades of every participant. Time is the latest order timestamp seen rather
+/// than the wall clock, so replicas applying the same orders throttle the same ones.
+#[derive(Debug, Default)]
+pub struct OtrTracker {
+    config: OtrConfig,
+    now: Timestamp,
+    activity: HashMap<ParticipantId, Activity>,
+}
+
+impl OtrTracker {
+    pub fn config(&self) -> OtrConfig {
+        self.config
+    }
+
+    /// Takes `config`, counting starts over
+    pub fn set_config(&mut self, config: OtrConfig) {
+        self.config = config;
+        self.activity.clear();
+    }
+
+    /// Counts an order from `owner` sent at `at` (unix ms). Refuses it when that puts a
+    /// throttled participant over the ratio.
+    pub fn order(&mut self, owner: &str, at: Timestamp) -> Result<(), RejectReason> {
+        self.now = self.now.max(at);
+        if !self.config.is_enabled() {
+            return Ok(());
+        }
+        let over = self.record_message(owner);
+        if over && self.config.action == OtrAction::Throttle {
+            return Err(RejectReason::OrderToTradeRatioExceeded {
+                limit: self.config.max_ratio,
+            });
+        }
+        Ok(())
+    }
+
+    /// Counts a cancel from `owner`; cancels are never refused
+    pub fn cancel(&mut self, owner: &str) {
+        if self.config.is_enabled() {
+            self.record_message(owner);
+        }
+    }
+
+    /// Counts a trade `participant` took part in
+    pub fn trade(&mut self, participant: &str) {
+        if !self.config.is_enabled() {
+            return;
+        }
+        let now = self.now;
+        let activity = self.activity.entry(participant.to_string()).or_default();
+        activity.trades.push_back(now);
+    }
+
+    /// Counts a message from `owner` now, returns whether that puts them over the ratio
+    fn record_message(&mut self, owner: &str) -> bool {
+        let since = self.now.saturating_sub(self.config.window_ms);
+        let activity = self.activity.entry(owner.to_string()).or_default();
+        activity.messages.push_back(self.now);
+        activity.expire(since);
+        activity.is_over(&self.config)
+    }
+
+    /// Participants over the ratio, worst first; idle ones are forgotten
+    pub fn offenders(&mut self) -> Vec<OtrStanding> {
+        let since = self.now.saturating_sub(self.config.window_ms);
+        self.activity.retain(|_, activity| {
+            activity.expire(since);
+            !activity.messages.is_empty() || !activity.trades.is_empty()
+        });
+        let mut offenders: Vec<OtrStanding> = self
+            .activity
+            .iter()
+            .filter(|(_, activity)| activity.is_over(&self.config))
+            .map(|(participant, activity)| OtrStanding {
+                participant: participant.clone(),
+                messages: activity.messages.len(),
+                trades: activity.trades.len(),
+                ratio: activity.messages.len() as f64 / activity.trades.len().max(1) as f64,
+                throttled: self.config.action == OtrAction::Throttle,
+            })
+            .collect();
+        offenders.sort_by(|a, b| b.ratio.total_cmp(&a.ratio));
+        offenders
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_otr_throttles_over_ratio() {
+        let mut tracker = OtrTracker::default();
+        tracker.set_config(OtrConfig {
+            max_ratio: 2,
+            window_ms: 1_000,
+            min_messages: 3,
+            action: OtrAction::Throttle,
+        });
+        assert!(tracker.order("hft", 0).is_ok());
+        assert!(tracker.order("hft", 10).is_ok());
+        tracker.cancel("hft"); // third message, over two per (at least one) trade
+        assert_eq!(
+            tracker.order("hft", 20),
+            Err(RejectReason::OrderToTradeRatioExceeded { limit: 2 })
+        );
+        assert_eq!(tracker.offenders()[0].messages, 4);
+
+        // trades bring the ratio back down
+        for _ in 0..3 {
+            tracker.trade("hft");
+        }
+        assert!(tracker.order("hft", 30).is_ok());
+        assert!(tracker.offenders().is_empty());
+
+        // the window moves on with the orders of anyone
+        for _ in 0..3 {
+            tracker.order("hft", 40).ok();
+        }
+        assert!(tracker.order("hft", 50).is_err());
+        assert!(tracker.order("other", 2_000).is_ok());
+        assert!(tracker.order("hft", 2_000).is_ok());
+    }
+
+    #[test]
+    fn test_otr_warn_only() {
+        let mut tracker = OtrTracker::default();
+        tracker.set_config(OtrConfig {
+            max_ratio: 1,
+            min_messages: 1,
+            action: OtrAction::Warn,
+            ..OtrConfig::default()
+        });
+        assert!(tracker.order("hft", 0).is_ok());
+        assert!(tracker.order("hft", 0).is_ok());
+        let offenders = tracker.offenders();
+        assert_eq!(offenders.len(), 1);
+        assert!(!offenders[0].throttled);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::{ParticipantId, Price, Trade};
+
+/// Net position (bought minus sold) with its average-cost basis and realized PnL, in cents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub net: i64,
+    /// Signed cost of the open position, `net * average entry price`
+    pub cost: i128,
+    pub realized_pnl: i128,
+}
+
+impl Position {
+    pub fn average_entry_price(&self) -> Option<Price> {
+        (self.net != 0)
+            .then(|| (self.cost.unsigned_abs() / self.net.unsigned_abs() as u128) as Price)
+    }
+
+    /// PnL of the open position if it were closed at `mark`
+    pub fn unrealized_pnl(&self, mark: Price) -> i128 {
+        self.net as i128 * mark as i128 - self.cost
+    }
+
+    /// Books a fill of `quantity` (negative for sells) at `price`; reducing the position
+    /// realizes PnL against the average entry price
+    fn fill(&mut self, quantity: i64, price: Price) {
+        let price = price as i128;
+        if self.net == 0 || self.net.signum() == quantity.signum() {
+            self.net += quantity;
+            self.cost += quantity as i128 * price;
+            return;
+        }
+
+        let closed = quantity.unsigned_abs().min(self.net.unsigned_abs()) as i128;
+        let closed_cost = self.cost * closed / self.net.unsigned_abs() as i128;
+        self.realized_pnl += self.net.signum() as i128 * closed * price - closed_cost;
+        self.cost -= closed_cost;
+        self.net += quantity.signum() * closed as i64;
+
+        let opened = quantity.unsigned_abs() as i128 - closed;
+        if opened > 0 {
+            self.net += quantity.signum() * opened as i64;
+            self.cost += quantity.signum() as i128 * opened * price;
+        }
+    }
+}
+
+/// Positions per participant, maintained from executed trades
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionBook {
+    positions: HashMap<ParticipantId, Position>,
+}
+
+impl PositionBook {
+    pub fn new() -> Self {
+        PositionBook::default()
+    }
+
+    pub fn net_position(&self, owner: &str) -> i64 {
+        self.position(owner).net
+    }
+
+    pub fn position(&self, owner: &str) -> Position {
+        self.positions.get(owner).copied().unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, trade: &Trade) {
+        self.adjust(trade, 1);
+    }
+
+    /// Undoes a trade that was busted (or is about to be corrected) by booking the offsetting
+    /// fill at the same price
+    pub fn reverse(&mut self, trade: &Trade) {
+        self.adjust(trade, -1);
+    }
+
+    fn adjust(&mut self, trade: &Trade, sign: i64) {
+        let quantity = trade.quantity as i64 * sign;
+        if let Some(buyer) = &trade.buyer {
+            self.positions
+                .entry(buyer.clone())
+                .or_default()
+                .fill(quantity, trade.price);
+        }
+        if let Some(seller) = &trade.seller {
+            self.positions
+                .entry(seller.clone())
+                .or_default()
+                .fill(-quantity, trade.price);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trade(buyer: &str, seller: &str, quantity: u64) -> Trade {
+        Trade::new(1, String::from("b"), String::from("s"), 100, quantity)
+            .with_parties(Some(buyer.to_string()), Some(seller.to_string()))
+    }
+
+    #[test]
+    fn test_apply_and_reverse() {
+        let mut positions = PositionBook::new();
+        positions.apply(&trade("alice", "bob", 10));
+        positions.apply(&trade("bob", "alice", 4));
+        assert_eq!(positions.net_position("alice"), 6);
+        assert_eq!(positions.net_position("bob"), -6);
+        assert_eq!(positions.net_position("carol"), 0);
+
+        positions.reverse(&trade("alice", "bob", 10));
+        assert_eq!(positions.net_position("alice"), -4);
+    }
+
+    #[test]
+    fn test_pnl() {
+        let mut position = Position::default();
+        position.fill(10, 100);
+        position.fill(10, 110);
+        assert_eq!(position.average_entry_price(), Some(105));
+        assert_eq!(position.unrealized_pnl(120), 300);
+
+        position.fill(-5, 120);
+        assert_eq!(position.realized_pnl, 75);
+        assert_eq!(position.net, 15);
+        assert_eq!(position.average_entry_price(), Some(105));
+
+        // flips short, the new 5 short are entered at 90
+        position.fill(-20, 90);
+        assert_eq!(position.realized_pnl, 75 - 225);
+        assert_eq!(position.net, -5);
+        assert_eq!(position.average_entry_price(), Some(90));
+        assert_eq!(position.unrealized_pnl(80), 50);
+    }
+}
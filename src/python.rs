@@ -0,0 +1,155 @@
+//! Python bindings of [`Matcher`], built with `--features python` into the `ome_v2` module, e.g.
+//!
+//! ```python
+//! from ome_v2 import MatchingEngine
+//! engine = MatchingEngine("pro_rata", min_quantity=1)
+//! engine.submit("sell", "limit", 5, price=100)
+//! engine.submit("buy", "market", 2)   # {"id": "2", "trades": [{...}]}
+//! engine.book()                       # {"bids": [...], "asks": [...]}
+//! ```
+
+use std::sync::Arc;
+
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+
+use crate::{
+    matching::{InvalidOrder, Matcher},
+    matchingpolicy::{self, PriceTime},
+    order::{Order, OrderType, Price, Quantity, Side, Trade},
+};
+
+impl From<InvalidOrder> for PyErr {
+    fn from(e: InvalidOrder) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+fn parse_side(side: &str) -> PyResult<Side> {
+    match side.to_ascii_lowercase().as_str() {
+        "buy" => Ok(Side::Buy),
+        "sell" => Ok(Side::Sell),
+        _ => Err(PyValueError::new_err(format!("unknown side {side}"))),
+    }
+}
+
+fn parse_order_type(order_type: &str) -> PyResult<OrderType> {
+    match order_type.to_ascii_lowercase().as_str() {
+        "limit" => Ok(OrderType::Limit),
+        "market" => Ok(OrderType::Market),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown order type {order_type}"
+        ))),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+fn trade_dict<'py>(py: Python<'py>, trade: &Trade) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", trade.id)?;
+    dict.set_item("buy_order_id", &trade.buy_order_id)?;
+    dict.set_item("sell_order_id", &trade.sell_order_id)?;
+    dict.set_item("price", trade.price)?;
+    dict.set_item("quantity", trade.quantity)?;
+    dict.set_item("aggressor", trade.aggressor.map(side_name))?;
+    Ok(dict)
+}
+
+fn order_dict<'py>(py: Python<'py>, order: &Order) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", &order.id)?;
+    dict.set_item("side", side_name(order.side))?;
+    dict.set_item("price", order.price)?;
+    dict.set_item("quantity", order.quantity)?;
+    dict.set_item("filled_quantity", order.filled_quantity)?;
+    dict.set_item("timestamp", order.timestamp)?;
+    Ok(dict)
+}
+
+/// The matching core as a Python class; orders and trades come back as dicts
+#[pyclass(name = "MatchingEngine")]
+pub struct PyMatchingEngine(Matcher);
+
+#[pymethods]
+impl PyMatchingEngine {
+    /// `policy` is one of the server's `OME_MATCHING_POLICY` names, price-time when omitted
+    #[new]
+    #[pyo3(signature = (policy = None, min_quantity = 1))]
+    fn new(policy: Option<&str>, min_quantity: Quantity) -> PyResult<Self> {
+        let policy = match policy {
+            Some(name) => matchingpolicy::by_name(name, min_quantity)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown matching policy {name}")))?,
+            None => Arc::new(PriceTime),
+        };
+        Ok(PyMatchingEngine(Matcher::new(policy)))
+    }
+
+    /// Returns `{"id": ..., "trades": [...]}`; raises `ValueError` for invalid orders
+    #[pyo3(signature = (side, order_type, quantity, price = 0))]
+    fn submit<'py>(
+        &mut self,
+        py: Python<'py>,
+        side: &str,
+        order_type: &str,
+        quantity: Quantity,
+        price: Price,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let submitted = self.0.submit(
+            parse_side(side)?,
+            parse_order_type(order_type)?,
+            price,
+            quantity,
+        )?;
+        let trades = submitted
+            .trades
+            .iter()
+            .map(|t| trade_dict(py, t))
+            .collect::<PyResult<Vec<_>>>()?;
+        let dict = PyDict::new(py);
+        dict.set_item("id", submitted.id)?;
+        dict.set_item("trades", PyList::new(py, trades)?)?;
+        Ok(dict)
+    }
+
+    fn cancel(&mut self, id: &str) -> bool {
+        self.0.cancel(id)
+    }
+
+    /// Resting orders, `bids` and `asks` each lowest price first
+    fn book<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let book = self.0.book();
+        let side = |orders: Vec<Order>| {
+            let orders = orders
+                .iter()
+                .map(|o| order_dict(py, o))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, orders)
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("bids", side(book.get_buy_orders())?)?;
+        dict.set_item("asks", side(book.get_sell_orders())?)?;
+        Ok(dict)
+    }
+
+    fn best_bid(&self) -> Option<Price> {
+        self.0.book().best_bid()
+    }
+
+    fn best_ask(&self) -> Option<Price> {
+        self.0.book().best_ask()
+    }
+}
+
+#[pymodule]
+fn ome_v2(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMatchingEngine>()
+}
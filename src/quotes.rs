@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use crate::order::{Order, ParticipantId, RejectReason, Side, Trade};
+
+/// Most levels a single quote may carry
+pub const MAX_QUOTE_ORDERS: usize = 100;
+
+/// A market maker's bids and asks, any number of levels each, entered as one operation in
+/// place of the owner's previous quote. Levels the new quote leaves out are pulled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub owner: ParticipantId,
+    pub orders: Vec<Order>,
+}
+
+/// What each order of an entered quote did, in the quote's order
+pub type QuoteAck = Vec<Result<Vec<Trade>, RejectReason>>;
+
+impl Quote {
+    /// Whether the best bid would trade against the best ask of the same quote
+    pub fn is_crossed(&self) -> bool {
+        let best = |side| {
+            self.orders
+                .iter()
+                .filter(move |o| o.side == side)
+                .map(|o| o.price)
+        };
+        match (best(Side::Buy).max(), best(Side::Sell).min()) {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::OrderType;
+
+    #[test]
+    fn test_quote_crossed() {
+        let quote = |bids: &[u64], asks: &[u64]| Quote {
+            owner: "mm".to_string(),
+            orders: bids
+                .iter()
+                .map(|p| (Side::Buy, p))
+                .chain(asks.iter().map(|p| (Side::Sell, p)))
+                .map(|(side, p)| Order::new(format!("{p}"), side, OrderType::Limit, 5, *p, 1))
+                .collect(),
+        };
+        assert!(!quote(&[99], &[101]).is_crossed());
+        assert!(quote(&[101], &[101]).is_crossed());
+        assert!(!quote(&[101], &[]).is_crossed());
+        assert!(!quote(&[], &[99]).is_crossed());
+        assert!(!quote(&[98, 99], &[100, 102]).is_crossed());
+        assert!(quote(&[98, 100], &[99, 102]).is_crossed());
+    }
+}
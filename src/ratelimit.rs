@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderValue, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{
+    auth::Principal,
+    error::{ApiError, ErrorCode},
+    signing::VerifiedApiKey,
+};
+
+pub const RATE_LIMIT_BURST_ENV: &str = "OME_RATE_LIMIT_BURST";
+pub const RATE_LIMIT_PER_SEC_ENV: &str = "OME_RATE_LIMIT_PER_SEC";
+
+const MAX_TRACKED_CLIENTS: usize = 10_000; // buckets are pruned past this many clients
+const PRUNED_TO: usize = MAX_TRACKED_CLIENTS * 9 / 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Bucket size, i.e. how many requests a client may burst
+    pub burst: u32,
+    /// Tokens refilled per second; `0` disables rate limiting
+    pub per_second: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            burst: 100,
+            per_second: 50.0,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn from_env() -> Self {
+        let default = RateLimitConfig::default();
+        RateLimitConfig {
+            burst: std::env::var(RATE_LIMIT_BURST_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.burst),
+            per_second: std::env::var(RATE_LIMIT_PER_SEC_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.per_second),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket rate limiter keyed by API key, falling back to the client IP
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: Arc<RwLock<RateLimitConfig>>,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config: Arc::new(RwLock::new(config)),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn config(&self) -> RateLimitConfig {
+        *self.config.read().await
+    }
+
+    /// Takes effect on the next request; clients keep the tokens they have, up to the new burst
+    pub async fn set_config(&self, config: RateLimitConfig) {
+        *self.config.write().await = config;
+    }
+
+    /// Takes a token for `client`, or returns how long until one is available
+    pub async fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let config = *self.config.read().await;
+        if config.per_second <= 0.0 {
+            return Ok(());
+        }
+        let burst = config.burst.max(1) as f64;
+
+        let mut buckets = self.buckets.lock().await;
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            prune(&mut buckets, now, config.per_second, burst);
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+        let elapsed = now
+            .saturating_duration_since(bucket.refilled_at)
+            .as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * config.per_second).min(burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / config.per_second,
+            ))
+        }
+    }
+}
+
+/// Makes room for new clients: drops the buckets that have refilled, which a new bucket would
+/// match, then the least recently used ones, down to [`PRUNED_TO`] so pruning is rare
+fn prune(buckets: &mut HashMap<String, Bucket>, now: Instant, per_second: f64, burst: f64) {
+    buckets.retain(|_, b| {
+        b.tokens + now.saturating_duration_since(b.refilled_at).as_secs_f64() * per_second < burst
+    });
+    if buckets.len() <= PRUNED_TO {
+        return;
+    }
+    let mut refilled: Vec<Instant> = buckets.values().map(|b| b.refilled_at).collect();
+    let excess = buckets.len() - PRUNED_TO;
+    let (_, cutoff, _) = refilled.select_nth_unstable(excess - 1);
+    let cutoff = *cutoff;
+    buckets.retain(|_, b| b.refilled_at > cutoff);
+}
+
+/// Who a request counts against: the API key its signature was verified with, else the
+/// authenticated participant, else the client IP. Runs after the signature and auth checks,
+/// so a client can't get a fresh bucket by sending another key.
+fn client_of(req: &Request, addr: SocketAddr) -> String {
+    let extensions = req.extensions();
+    if let Some(VerifiedApiKey(key)) = extensions.get::<VerifiedApiKey>() {
+        return format!("key:{key}");
+    }
+    principal_client(extensions.get::<Principal>(), addr)
+}
+
+/// The bucket of an unsigned caller: the authenticated participant, else the client IP. A
+/// WebSocket session charges its order entry messages to it, sharing it with the REST API.
+pub fn principal_client(principal: Option<&Principal>, addr: SocketAddr) -> String {
+    match principal {
+        Some(principal) if !principal.is_anonymous() => format!("sub:{}", principal.subject),
+        _ => format!("ip:{}", addr.ip()),
+    }
+}
+
+/// The error for a request `retry_after` too early, with the whole seconds to wait
+pub fn rate_limited(retry_after: Duration) -> (ApiError, u64) {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let error = ApiError::new(
+        ErrorCode::RateLimited,
+        format!("rate limit exceeded, retry in {secs}s"),
+    );
+    (error, secs)
+}
+
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client = client_of(&req, addr);
+
+    match limiter.check(&client, Instant::now()).await {
+        Ok(()) => next.run(req).await,
+        Err(retry_after) => {
+            let (error, secs) = rate_limited(retry_after);
+            let mut resp = error.into_response();
+            resp.headers_mut()
+                .insert(RETRY_AFTER, HeaderValue::from(secs));
+            resp
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bucket_exhaustion_and_refill() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            burst: 2,
+            per_second: 1.0,
+        });
+        let t0 = Instant::now();
+
+        assert!(limiter.check("a", t0).await.is_ok());
+        assert!(limiter.check("a", t0).await.is_ok());
+        let retry = limiter.check("a", t0).await.unwrap_err();
+        assert!(retry <= Duration::from_secs(1));
+
+        // other clients have their own bucket
+        assert!(limiter.check("b", t0).await.is_ok());
+
+        assert!(
+            limiter
+                .check("a", t0 + Duration::from_secs(1))
+                .await
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check("a", t0 + Duration::from_secs(1))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disabled_limiter() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            burst: 1,
+            per_second: 0.0,
+        });
+        let t0 = Instant::now();
+        for _ in 0..10 {
+            assert!(limiter.check("a", t0).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clients_and_pruning() {
+        let addr: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let mut req = Request::new(axum::body::Body::empty());
+        req.headers_mut().insert(
+            crate::signing::API_KEY_HEADER,
+            "unverified".parse().unwrap(),
+        );
+        assert_eq!(client_of(&req, addr), "ip:10.0.0.1");
+        req.extensions_mut().insert(Principal {
+            subject: crate::auth::ANONYMOUS.to_string(),
+            roles: Vec::new(),
+        });
+        assert_eq!(client_of(&req, addr), "ip:10.0.0.1");
+        req.extensions_mut().insert(Principal {
+            subject: "alice".to_string(),
+            roles: Vec::new(),
+        });
+        assert_eq!(client_of(&req, addr), "sub:alice");
+        req.extensions_mut()
+            .insert(VerifiedApiKey("k1".to_string()));
+        assert_eq!(client_of(&req, addr), "key:k1");
+
+        // clients seen once are still draining their bucket, the least recent ones make room
+        let limiter = RateLimiter::new(RateLimitConfig {
+            burst: 10,
+            per_second: 0.001,
+        });
+        let t0 = Instant::now();
+        for i in 0..MAX_TRACKED_CLIENTS {
+            let at = t0 + Duration::from_millis(i as u64);
+            limiter.check(&format!("c{i}"), at).await.unwrap();
+        }
+        let later = t0 + Duration::from_secs(60);
+        limiter.check("new", later).await.unwrap();
+        let buckets = limiter.buckets.lock().await;
+        assert_eq!(buckets.len(), PRUNED_TO + 1);
+        assert!(!buckets.contains_key("c999"));
+        assert!(buckets.contains_key("c1000"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use serde::Deserialize;
+
+use crate::{
+    audit::AuditEntry, matchingengine::MatchingEngine, order::Timestamp, schedule::PhasePolicies,
+    state::EngineState,
+};
+
+/// `ome-v2 recover`: rebuilds the engine from a journal as it stood at a past event
+#[derive(Debug, Args)]
+pub struct RecoverArgs {
+    /// Journal files: segments uploaded to object storage (one audit entry per line) or
+    /// `GET /admin/audit` exports, in any order
+    #[arg(required = true)]
+    pub journals: Vec<PathBuf>,
+    /// Engine state to start from (`GET /admin/state`, `GET /drop-copy/snapshot` or an uploaded
+    /// snapshot) instead of the first event
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+    /// Stop after the event with this sequence number
+    #[arg(long)]
+    pub until_seq: Option<u64>,
+    /// Stop after the last event at or before this time, unix ms or RFC 3339
+    #[arg(long, value_parser = parse_time)]
+    pub until_time: Option<Timestamp>,
+}
+
+/// `GET /admin/audit` response
+#[derive(Debug, Deserialize)]
+struct AuditExport {
+    entries: Vec<AuditEntry>,
+}
+
+/// Unix ms, or an RFC 3339 date-time such as `2024-03-01T14:30:00Z`
+pub fn parse_time(value: &str) -> Result<Timestamp, String> {
+    if let Ok(ms) = value.parse() {
+        return Ok(ms);
+    }
+    let time = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| format!("expected unix ms or an RFC 3339 time: {e}"))?;
+    Timestamp::try_from(time.timestamp_millis()).map_err(|_| "time before 1970".to_string())
+}
+
+/// Entries of one journal file, either an audit export or one entry per line
+fn read_journal(text: &str) -> Result<Vec<AuditEntry>, String> {
+    if let Ok(export) = serde_json::from_str::<AuditExport>(text) {
+        return Ok(export.entries);
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+/// Whether `entry` happened at or before the requested point
+fn within(args: &RecoverArgs, entry: &AuditEntry) -> bool {
+    args.until_seq.is_none_or(|seq| entry.seq <= seq)
+        && args.until_time.is_none_or(|time| entry.timestamp <= time)
+}
+
+/// Replays the journals onto a fresh engine up to the requested point, checking the hash chain
+/// on the way, and returns the state it reached: the book, trades and positions as they were.
+/// Events before the snapshot are skipped; a missing event stops the replay with an error.
+pub async fn recover(args: &RecoverArgs) -> Result<EngineState, String> {
+    let mut entries = Vec::new();
+    for path in &args.journals {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        entries.extend(read_journal(&text).map_err(|e| format!("{}: {e}", path.display()))?);
+    }
+    entries.sort_by_key(|e| e.seq);
+    entries.dedup_by_key(|e| e.seq);
+
+    let engine = MatchingEngine::new();
+    engine.set_phase_policies(PhasePolicies::from_env()).await;
+    engine.start_standby().await;
+    if let Some(path) = &args.snapshot {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let state: EngineState =
+            serde_json::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        engine
+            .restore_standby(state)
+            .await
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+    }
+    for entry in entries.into_iter().take_while(|e| within(args, e)) {
+        let seq = entry.seq;
+        engine
+            .apply_replicated(entry)
+            .await
+            .map_err(|e| format!("event {seq}: {e}"))?;
+    }
+    engine.promote().await;
+    Ok(engine.export_state().await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{Order, OrderType, Side};
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("1700006400000"), Ok(1_700_006_400_000));
+        assert_eq!(parse_time("2023-11-15T00:00:00Z"), Ok(1_700_006_400_000));
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_recover() {
+        let mut primary = MatchingEngine::new();
+        for (id, side, ts) in [
+            ("a", Side::Sell, 1),
+            ("b", Side::Sell, 2),
+            ("c", Side::Buy, 3),
+        ] {
+            let order = Order::new(id.to_string(), side, OrderType::Limit, 10, 100, ts);
+            primary.submit_order(order).await.unwrap();
+        }
+        let entries = primary.audit_entries(1, usize::MAX).await;
+        let dir = std::env::temp_dir().join(format!("ome-recover-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // the first events as an audit export, the rest as a segment
+        let export = dir.join("audit.json");
+        let entries_json = serde_json::to_string(&entries[..2]).unwrap();
+        fs::write(&export, format!("{{\"entries\": {entries_json}}}")).unwrap();
+        let segment = dir.join("segment.jsonl");
+        let lines: Vec<String> = entries[1..]
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap())
+            .collect();
+        fs::write(&segment, lines.join("\n")).unwrap();
+
+        let mut args = RecoverArgs {
+            journals: vec![segment.clone(), export.clone()],
+            snapshot: None,
+            until_seq: Some(2),
+            until_time: None,
+        };
+        let state = recover(&args).await.unwrap();
+        assert_eq!((state.last_seq, state.asks.len()), (2, 2));
+        assert!(state.trades.is_empty());
+
+        args.until_seq = None;
+        let state = recover(&args).await.unwrap();
+        assert_eq!((state.asks.len(), state.trades.len()), (1, 1));
+
+        // without the first events there is nothing to chain onto
+        args.journals = vec![segment];
+        assert_eq!(
+            recover(&args).await.unwrap_err(),
+            "event 2: expected event 1, got 2"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use std::{fmt, thread, time::Duration};
+
+use tokio::runtime::Handle;
+use tungstenite::ClientRequestBuilder;
+
+use crate::{
+    admin::MAX_STATE_BYTES, audit::AuditEntry, matchingengine::MatchingEngine, state::EngineState,
+};
+
+pub const STANDBY_OF_ENV: &str = "OME_STANDBY_OF"; // base URL of the primary, e.g. `http://primary:61666`
+pub const STANDBY_TOKEN_ENV: &str = "OME_STANDBY_TOKEN"; // token with the `drop-copy` role on the primary
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Why a replicated audit entry can't be applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationError {
+    /// The engine was promoted (or never was a standby)
+    NotStandby,
+    /// The entry doesn't follow the latest applied one
+    OutOfSequence { expected: u64, got: u64 },
+    /// The entry doesn't chain onto the local log, or its hash is wrong
+    HashMismatch { seq: u64 },
+    /// A snapshot can only seed a standby that hasn't applied any event yet
+    AlreadyFollowing { last_seq: u64 },
+    /// The primary's snapshot failed validation
+    InvalidSnapshot(String),
+}
+
+impl fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplicationError::NotStandby => write!(f, "engine is not a standby"),
+            ReplicationError::OutOfSequence { expected, got } => {
+                write!(f, "expected event {expected}, got {got}")
+            }
+            ReplicationError::HashMismatch { seq } => {
+                write!(f, "event {seq} doesn't match the local audit chain")
+            }
+            ReplicationError::AlreadyFollowing { last_seq } => {
+                write!(f, "standby already applied events up to {last_seq}")
+            }
+            ReplicationError::InvalidSnapshot(e) => write!(f, "invalid snapshot: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandbyConfig {
+    /// Base URL of the primary, without a trailing `/`
+    pub primary: String,
+    pub token: Option<String>,
+}
+
+impl StandbyConfig {
+    pub fn from_env() -> Option<Self> {
+        let primary = std::env::var(STANDBY_OF_ENV)
+            .ok()
+            .filter(|url| !url.is_empty())?;
+        Some(StandbyConfig {
+            primary: primary.trim_end_matches('/').to_string(),
+            token: std::env::var(STANDBY_TOKEN_ENV).ok(),
+        })
+    }
+
+    /// The primary's exchange `tenant` (the default one when `None`)
+    fn exchange_url(&self, tenant: Option<&str>) -> String {
+        match tenant {
+            Some(tenant) => format!("{}/tenants/{tenant}", self.primary),
+            None => self.primary.clone(),
+        }
+    }
+
+    /// Drop copy of the primary's exchange `tenant` from `from`
+    pub fn drop_copy_url(&self, tenant: Option<&str>, from: u64) -> String {
+        let base = self.exchange_url(tenant).replacen("http", "ws", 1);
+        format!("{base}/drop-copy?from={from}")
+    }
+
+    /// State of the primary's exchange `tenant`, along with the sequence number it was taken at
+    pub fn snapshot_url(&self, tenant: Option<&str>) -> String {
+        format!("{}/drop-copy/snapshot", self.exchange_url(tenant))
+    }
+}
+
+fn fetch_snapshot(config: &StandbyConfig, tenant: Option<&str>) -> Result<EngineState, String> {
+    let url = config.snapshot_url(tenant);
+    let mut request = ureq::get(&url);
+    if let Some(token) = &config.token {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let mut response = request.call().map_err(|e| format!("{url}: {e}"))?;
+    let text = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_STATE_BYTES as u64)
+        .read_to_string()
+        .map_err(|e| format!("{url}: {e}"))?;
+    serde_json::from_str(&text).map_err(|e| format!("{url}: {e}"))
+}
+
+/// Seeds a standby that hasn't applied anything with a snapshot of the primary, so it follows
+/// from the snapshot's sequence number instead of replaying the primary's whole history
+fn catch_up(
+    runtime: &Handle,
+    engine: &MatchingEngine,
+    config: &StandbyConfig,
+    tenant: Option<&str>,
+) -> Result<(), String> {
+    let state = fetch_snapshot(config, tenant)?;
+    let last_seq = state.last_seq;
+    if last_seq == 0 {
+        return Ok(()); // nothing happened on the primary yet
+    }
+    runtime
+        .block_on(engine.restore_standby(state))
+        .map_err(|e| e.to_string())?;
+    println!("Standby restored the primary's snapshot at event {last_seq}");
+    Ok(())
+}
+
+/// Applies the primary's events from one drop copy connection until it drops or an entry
+/// can't be applied
+fn follow(
+    runtime: &Handle,
+    engine: &MatchingEngine,
+    config: &StandbyConfig,
+    tenant: Option<&str>,
+) -> Result<(), String> {
+    if runtime.block_on(engine.last_seq()) == 0 {
+        catch_up(runtime, engine, config, tenant)?;
+    }
+    let from = runtime.block_on(engine.last_seq()) + 1;
+    let url = config.drop_copy_url(tenant, from);
+    let mut request = ClientRequestBuilder::new(url.parse().map_err(|e| format!("{url}: {e}"))?);
+    if let Some(token) = &config.token {
+        request = request.with_header("authorization", format!("Bearer {token}"));
+    }
+    let (mut socket, _) = tungstenite::connect(request).map_err(|e| format!("{url}: {e}"))?;
+    println!("Standby following {url}");
+    loop {
+        let message = socket.read().map_err(|e| e.to_string())?;
+        if message.is_close() {
+            return Err("the primary closed the stream".to_string());
+        }
+        if !message.is_text() {
+            continue;
+        }
+        let text = message.to_text().map_err(|e| e.to_string())?;
+        let entry: AuditEntry = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        runtime
+            .block_on(engine.apply_replicated(entry))
+            .map_err(|e| e.to_string())?;
+    }
+}
+
+/// Keeps `engine` (a standby) in step with the primary's event stream, reconnecting from the
+/// last applied event whenever the connection drops, until the engine is promoted
+pub fn run_standby(engine: MatchingEngine, config: StandbyConfig, tenant: Option<String>) {
+    let runtime = Handle::current();
+    thread::spawn(move || {
+        while runtime.block_on(engine.is_standby()) {
+            if let Err(e) = follow(&runtime, &engine, &config, tenant.as_deref()) {
+                if !runtime.block_on(engine.is_standby()) {
+                    break;
+                }
+                println!("Standby lost the primary: {e}, reconnecting");
+            }
+            thread::sleep(RECONNECT_DELAY);
+        }
+        println!("Standby promoted, replication stopped");
+    });
+}
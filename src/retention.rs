@@ -0,0 +1,200 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use serde::Serialize;
+
+use crate::{
+    archive::{self, ArchiveConfig, DAY_MS},
+    matchingengine::MatchingEngine,
+    order::Timestamp,
+    settlement,
+};
+
+pub const RETENTION_HOT_DAYS_ENV: &str = "OME_RETENTION_HOT_DAYS"; // days of trades kept in the pool and spill file
+pub const RETENTION_DELETE_DAYS_ENV: &str = "OME_RETENTION_DELETE_DAYS"; // days of archives kept on disk
+pub const RETENTION_INTERVAL_SECS_ENV: &str = "OME_RETENTION_INTERVAL_SECS";
+
+const DEFAULT_RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionConfig {
+    /// Trades of the last `hot_days` UTC days (today included) stay hot, `None` keeps them all
+    pub hot_days: Option<u64>,
+    /// Archived days older than `delete_days` are deleted, `None` keeps them all
+    pub delete_days: Option<u64>,
+    /// How often the maintenance task runs
+    pub interval: Duration,
+}
+
+impl RetentionConfig {
+    /// `None` unless a hot or delete period is set
+    pub fn from_env() -> Option<Self> {
+        let days = |name: &str| std::env::var(name).ok().and_then(|v| v.parse().ok());
+        let hot_days = days(RETENTION_HOT_DAYS_ENV).filter(|days| *days > 0);
+        let delete_days = days(RETENTION_DELETE_DAYS_ENV);
+        if hot_days.is_none() && delete_days.is_none() {
+            return None;
+        }
+        let interval = std::env::var(RETENTION_INTERVAL_SECS_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RETENTION_INTERVAL);
+        Some(RetentionConfig {
+            hot_days,
+            delete_days,
+            interval,
+        })
+    }
+}
+
+/// Progress of the maintenance task since the server started, for `GET /admin/retention`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetentionStats {
+    /// Unix ms of the latest run
+    pub last_run: Option<Timestamp>,
+    /// Trades executed before this (unix ms) have left the pool and the spill file
+    pub hot_since: Option<Timestamp>,
+    /// Days written to the archive ahead of leaving hot storage
+    pub days_archived: u64,
+    /// Trades dropped from the pool and the spill file
+    pub trades_compacted: u64,
+    /// Archive partitions deleted for being past the delete period
+    pub partitions_deleted: u64,
+    /// Why the latest run stopped short, if it did
+    pub last_error: Option<String>,
+}
+
+/// Deletes the `symbol` partitions of days before `before` (unix ms) from the archive at
+/// `dir`, and date directories left empty; returns the number of partitions deleted
+pub fn delete_partitions(dir: &Path, symbol: &str, before: Timestamp) -> io::Result<u64> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut deleted = 0;
+    for entry in entries {
+        let date_dir = entry?.path();
+        let Some(day) = date_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("date="))
+            .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(|date| {
+                date.and_time(chrono::NaiveTime::MIN)
+                    .and_utc()
+                    .timestamp_millis()
+            })
+        else {
+            continue;
+        };
+        if day < 0 || day as Timestamp >= before {
+            continue;
+        }
+        let partition = date_dir.join(format!("symbol={symbol}"));
+        if partition.exists() {
+            fs::remove_dir_all(&partition)?;
+            deleted += 1;
+        }
+        let _ = fs::remove_dir(&date_dir); // only succeeds once no symbol is left
+    }
+    Ok(deleted)
+}
+
+/// Every `config.interval`: archives the days about to leave hot storage (when archiving is
+/// on), drops their trades from the pool and the spill file, and deletes archived days past the
+/// delete period. Trades only leave hot storage once their day is archived.
+pub async fn run_retention(
+    engine: MatchingEngine,
+    symbol: String,
+    config: RetentionConfig,
+    archive: Option<ArchiveConfig>,
+) {
+    let mut tick = tokio::time::interval(config.interval);
+    let mut archived_until = 0; // days before this were archived by this task
+    loop {
+        tick.tick().await;
+        let now = settlement::now_ms();
+        let today = archive::day_of(now);
+        let mut stats = engine.retention_stats().await;
+        stats.last_run = Some(now);
+        stats.last_error = None;
+
+        if let Some(hot_days) = config.hot_days {
+            let cutoff = today.saturating_sub((hot_days - 1) * DAY_MS);
+            let mut archived = true;
+            if let Some(archive) = &archive
+                && cutoff > archived_until
+            {
+                let executions = engine.executions(archived_until, cutoff).await;
+                let (dir, symbol) = (archive.dir.clone(), symbol.clone());
+                let result = tokio::task::spawn_blocking(move || {
+                    archive::archive_days(&dir, &symbol, executions)
+                })
+                .await
+                .expect("archive task panicked");
+                match result {
+                    Ok(written) => {
+                        stats.days_archived += written as u64;
+                        archived_until = cutoff;
+                    }
+                    Err(e) => {
+                        stats.last_error = Some(format!("archiving failed: {e}"));
+                        archived = false;
+                    }
+                }
+            }
+            if archived {
+                stats.trades_compacted += engine.compact_trades(cutoff).await as u64;
+                stats.hot_since = Some(cutoff);
+            }
+        }
+
+        if let (Some(delete_days), Some(archive)) = (config.delete_days, &archive) {
+            let before = today.saturating_sub(delete_days * DAY_MS);
+            let (dir, symbol) = (archive.dir.clone(), symbol.clone());
+            let result =
+                tokio::task::spawn_blocking(move || delete_partitions(&dir, &symbol, before))
+                    .await
+                    .expect("retention task panicked");
+            match result {
+                Ok(deleted) => stats.partitions_deleted += deleted,
+                Err(e) => stats.last_error = Some(format!("deleting archives failed: {e}")),
+            }
+        }
+
+        if let Some(e) = &stats.last_error {
+            println!("{symbol}: retention run incomplete, retrying next run: {e}");
+        }
+        engine.set_retention_stats(stats).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_delete_partitions() {
+        let dir = std::env::temp_dir().join(format!("ome-retention-{}", std::process::id()));
+        let day = 1_700_006_400_000; // 2023-11-15
+        let touch = |path: PathBuf| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        };
+        touch(archive::partition_path(&dir, day, "root"));
+        touch(archive::partition_path(&dir, day, "acme"));
+        touch(archive::partition_path(&dir, day + DAY_MS, "root"));
+
+        assert_eq!(delete_partitions(&dir, "root", day + DAY_MS).unwrap(), 1);
+        assert!(!archive::partition_path(&dir, day, "root").exists());
+        // other exchanges' partitions stay, and so does their date directory
+        assert!(archive::partition_path(&dir, day, "acme").exists());
+        assert!(archive::partition_path(&dir, day + DAY_MS, "root").exists());
+        assert_eq!(delete_partitions(&dir, "acme", day + DAY_MS).unwrap(), 1);
+        assert!(!dir.join("date=2023-11-15").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::VecDeque, net::SocketAddr, sync::Arc};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Mutex,
+};
+
+use crate::multicast::{PACKET_HEADER_LEN, packets};
+
+pub const RETRANSMIT_ADDR_ENV: &str = "OME_RETRANSMIT_ADDR"; // unset disables retransmission
+pub const RETRANSMIT_BUFFER_ENV: &str = "OME_RETRANSMIT_BUFFER";
+
+const DEFAULT_CAPACITY: usize = 100_000;
+
+/// Where the multicast feed's retransmission server listens and how many of the latest
+/// messages it can resend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetransmitConfig {
+    pub addr: SocketAddr,
+    pub capacity: usize,
+}
+
+impl RetransmitConfig {
+    pub fn from_env() -> Option<Self> {
+        let addr = std::env::var(RETRANSMIT_ADDR_ENV).ok()?;
+        let Ok(addr) = addr.parse::<SocketAddr>() else {
+            println!("{RETRANSMIT_ADDR_ENV}={addr} is not an address and port, not retransmitting");
+            return None;
+        };
+        Some(RetransmitConfig {
+            addr,
+            capacity: std::env::var(RETRANSMIT_BUFFER_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(DEFAULT_CAPACITY),
+        })
+    }
+}
+
+/// The latest binary frames the publisher sent, by sequence number; the oldest make room
+#[derive(Debug)]
+struct Ring {
+    session: u64,
+    first_seq: u64,
+    frames: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetransmitBuffer {
+    ring: Arc<Mutex<Ring>>,
+}
+
+impl RetransmitBuffer {
+    pub fn new(session: u64, capacity: usize) -> Self {
+        RetransmitBuffer {
+            ring: Arc::new(Mutex::new(Ring {
+                session,
+                first_seq: 1,
+                frames: VecDeque::with_capacity(capacity),
+                capacity,
+            })),
+        }
+    }
+
+    /// Keeps `frames`, numbered from `seq` on right after the ones kept before
+    pub async fn extend(&self, seq: u64, frames: &[Vec<u8>]) {
+        let mut ring = self.ring.lock().await;
+        debug_assert_eq!(seq, ring.first_seq + ring.frames.len() as u64);
+        for frame in frames {
+            if ring.frames.len() == ring.capacity {
+                ring.frames.pop_front();
+                ring.first_seq += 1;
+            }
+            ring.frames.push_back(frame.clone());
+        }
+    }
+
+    /// Packets resending `count` messages from `seq` of `session`, as far as they are still
+    /// kept. When none of them is, a single empty packet names the oldest message kept, and a
+    /// client further behind has to start over from a snapshot.
+    pub async fn resend(&self, session: u64, seq: u64, count: u16) -> Vec<Vec<u8>> {
+        let ring = self.ring.lock().await;
+        let end_seq = ring.first_seq + ring.frames.len() as u64;
+        let from = seq.max(ring.first_seq);
+        let to = seq.saturating_add(count as u64).min(end_seq);
+        if session != ring.session || from >= to {
+            return packets(ring.session, ring.first_seq, &[]);
+        }
+        let frames: Vec<Vec<u8>> = ring
+            .frames
+            .range((from - ring.first_seq) as usize..(to - ring.first_seq) as usize)
+            .cloned()
+            .collect();
+        packets(ring.session, from, &frames)
+    }
+}
+
+/// Answers retransmission requests as long as the server runs. A request is laid out like a
+/// packet header (`session` u64, `seq` u64, `count` u16, little-endian) and is answered with
+/// the packets holding those messages, each prefixed with its length as a `u16`.
+pub async fn run_server(config: RetransmitConfig, buffer: RetransmitBuffer) {
+    let listener = match TcpListener::bind(config.addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("retransmission on {}: {e}", config.addr);
+            return;
+        }
+    };
+    println!("Retransmitting market data on {}", config.addr);
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        tokio::spawn(serve(stream, buffer.clone()));
+    }
+}
+
+async fn serve(mut stream: TcpStream, buffer: RetransmitBuffer) {
+    let mut request = [0u8; PACKET_HEADER_LEN];
+    while stream.read_exact(&mut request).await.is_ok() {
+        let session = u64::from_le_bytes(request[0..8].try_into().expect("8 bytes"));
+        let seq = u64::from_le_bytes(request[8..16].try_into().expect("8 bytes"));
+        let count = u16::from_le_bytes(request[16..18].try_into().expect("2 bytes"));
+        for packet in buffer.resend(session, seq, count).await {
+            let len = (packet.len() as u16).to_le_bytes();
+            if stream.write_all(&len).await.is_err() || stream.write_all(&packet).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn header(packet: &[u8]) -> (u64, u64, u16) {
+        (
+            u64::from_le_bytes(packet[0..8].try_into().unwrap()),
+            u64::from_le_bytes(packet[8..16].try_into().unwrap()),
+            u16::from_le_bytes(packet[16..18].try_into().unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_resend_from_ring() {
+        let buffer = RetransmitBuffer::new(7, 3);
+        let frames: Vec<Vec<u8>> = (1..=5u8).map(|n| vec![n; 4]).collect();
+        buffer.extend(1, &frames[..2]).await;
+        buffer.extend(3, &frames[2..]).await; // 1 and 2 make room
+
+        let resent = buffer.resend(7, 2, 2).await;
+        assert_eq!(resent.len(), 1);
+        assert_eq!(header(&resent[0]), (7, 3, 1)); // 2 is gone, 3 is left of the range
+        assert_eq!(&resent[0][PACKET_HEADER_LEN..], &[3; 4]);
+
+        let resent = buffer.resend(7, 4, 10).await;
+        assert_eq!(header(&resent[0]), (7, 4, 2));
+        // an older session or a range no longer kept point at the oldest message kept
+        assert_eq!(header(&buffer.resend(6, 4, 1).await[0]), (7, 3, 0));
+        assert_eq!(header(&buffer.resend(7, 1, 2).await[0]), (7, 3, 0));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, State, rejection::JsonRejection},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::Principal,
+    consensus::Consensus,
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::{OrderId, ParticipantId, Price, Quantity, RejectReason, Side, Timestamp},
+    validation::{self, FieldError, PriceType},
+};
+
+pub const DEFAULT_RFQ_WINDOW_MS: u64 = 5_000;
+pub const MAX_RFQ_WINDOW_MS: u64 = 60_000;
+pub const MAX_RFQ_MAKERS: usize = 20;
+const RFQ_TICK: Duration = Duration::from_millis(50);
+
+pub type RfqId = String;
+
+#[derive(Debug, Deserialize)]
+struct NewRfqRequest {
+    side: Side,
+    quantity: Quantity,
+    /// Participants asked for a price
+    makers: Vec<ParticipantId>,
+    /// How long makers can respond, `DEFAULT_RFQ_WINDOW_MS` when omitted
+    window_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RfqResponseRequest {
+    /// Price in cents, or dollars with a decimal point
+    price: PriceType,
+}
+
+#[derive(Debug, Serialize)]
+struct RfqsResponse {
+    rfqs: Vec<Rfq>,
+}
+
+/// A taker's request for a price on `quantity`, answered by the selected `makers` until
+/// `expires_at` (unix ms). The best response then trades in full off the book.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rfq {
+    pub id: RfqId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taker: Option<ParticipantId>,
+    /// Side of the taker
+    pub side: Side,
+    pub quantity: Quantity,
+    pub makers: Vec<ParticipantId>,
+    pub expires_at: Timestamp,
+    #[serde(default)]
+    pub responses: Vec<RfqResponse>,
+}
+
+/// A maker's price for the whole quantity of an RFQ; `id` stands in for the maker's order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RfqResponse {
+    pub id: OrderId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maker: Option<ParticipantId>,
+    pub price: Price,
+    /// Unix ms
+    pub timestamp: Timestamp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RfqError {
+    /// Unknown id, or the RFQ's window has closed
+    NotFound,
+    /// The responder isn't one of the makers the taker selected
+    NotInvited,
+    Rejected(RejectReason),
+}
+
+impl From<RejectReason> for RfqError {
+    fn from(reason: RejectReason) -> Self {
+        RfqError::Rejected(reason)
+    }
+}
+
+impl Rfq {
+    pub fn is_invited(&self, maker: &str) -> bool {
+        self.makers.iter().any(|m| m == maker)
+    }
+
+    /// Responses best first: the lowest price for a buying taker, the highest for a selling
+    /// one, earlier responses first at the same price
+    pub fn ranked(&self) -> Vec<&RfqResponse> {
+        let mut responses: Vec<&RfqResponse> = self.responses.iter().collect();
+        responses.sort_by(|a, b| {
+            let by_price = match self.side {
+                Side::Buy => a.price.cmp(&b.price),
+                Side::Sell => b.price.cmp(&a.price),
+            };
+            by_price.then(a.timestamp.cmp(&b.timestamp))
+        });
+        responses
+    }
+
+    /// Cuts the RFQ down to what `viewer` may see: the taker sees everything, a maker neither
+    /// the taker nor the other makers and their responses
+    pub fn redact(&mut self, viewer: &str) {
+        if self.taker.as_deref() == Some(viewer) {
+            return;
+        }
+        self.taker = None;
+        self.makers.retain(|m| m == viewer);
+        self.responses
+            .retain(|r| r.maker.as_deref() == Some(viewer));
+    }
+
+    /// Takes `response` in place of its maker's previous one
+    pub fn respond(&mut self, response: RfqResponse) {
+        self.responses.retain(|r| r.maker != response.maker);
+        self.responses.push(response);
+    }
+}
+
+/// RFQs whose window is still open, by id
+#[derive(Debug, Default)]
+pub struct RfqBook {
+    rfqs: BTreeMap<RfqId, Rfq>,
+}
+
+impl RfqBook {
+    pub fn insert(&mut self, rfq: Rfq) {
+        self.rfqs.insert(rfq.id.clone(), rfq);
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Rfq> {
+        self.rfqs.get_mut(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Rfq> {
+        self.rfqs.remove(id)
+    }
+
+    /// Ids of the RFQs whose window closed by `now` (unix ms), earliest first
+    pub fn due(&self, now: Timestamp) -> Vec<RfqId> {
+        let mut due: Vec<&Rfq> = self.rfqs.values().filter(|r| r.expires_at <= now).collect();
+        due.sort_by_key(|r| r.expires_at);
+        due.into_iter().map(|r| r.id.clone()).collect()
+    }
+
+    /// RFQs `participant` asked for or was asked to answer
+    pub fn visible_to(&self, participant: &str) -> Vec<Rfq> {
+        self.rfqs
+            .values()
+            .filter(|r| r.taker.as_deref() == Some(participant) || r.is_invited(participant))
+            .cloned()
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> Vec<Rfq> {
+        self.rfqs.values().cloned().collect()
+    }
+
+    pub fn restore(&mut self, rfqs: Vec<Rfq>) {
+        self.rfqs = rfqs.into_iter().map(|r| (r.id.clone(), r)).collect();
+    }
+}
+
+/// `/rfqs` for takers and makers; the caller layers authentication and the consensus handle
+pub fn routes() -> Router<MatchingEngine> {
+    Router::new()
+        .route("/rfqs", get(get_rfqs).post(post_rfq))
+        .route("/rfqs/{id}/responses", post(post_rfq_response))
+}
+
+/// Open RFQs of the caller, as the taker or as one of the makers
+async fn get_rfqs(
+    State(engine): State<MatchingEngine>,
+    Extension(principal): Extension<Principal>,
+) -> Json<RfqsResponse> {
+    let mut rfqs = engine.rfqs_of(&principal.subject).await;
+    for rfq in rfqs.iter_mut() {
+        rfq.redact(&principal.subject);
+    }
+    Json(RfqsResponse { rfqs })
+}
+
+async fn post_rfq(
+    State(mut engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
+    payload: Result<Json<NewRfqRequest>, JsonRejection>,
+) -> Result<Json<Rfq>, ApiError> {
+    let Json(req) = payload?;
+    let window_ms = req.window_ms.unwrap_or(DEFAULT_RFQ_WINDOW_MS);
+    let mut makers = req.makers;
+    makers.sort();
+    makers.dedup();
+    let mut errors = Vec::new();
+    if req.quantity == 0 {
+        errors.push(FieldError::new("quantity", "must be > 0"));
+    }
+    if makers.is_empty() || makers.len() > MAX_RFQ_MAKERS {
+        errors.push(FieldError::new(
+            "makers",
+            format!("between 1 and {MAX_RFQ_MAKERS} makers"),
+        ));
+    }
+    if makers.contains(&principal.subject) {
+        errors.push(FieldError::new(
+            "makers",
+            "the taker can't be one of the makers",
+        ));
+    }
+    if window_ms == 0 || window_ms > MAX_RFQ_WINDOW_MS {
+        errors.push(FieldError::new(
+            "window_ms",
+            format!("must be between 1 and {MAX_RFQ_WINDOW_MS}"),
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let rfq = Rfq {
+        id: uuid::Uuid::new_v4().to_string(),
+        taker: Some(principal.subject),
+        side: req.side,
+        quantity: req.quantity,
+        makers,
+        expires_at: engine.clock().now_ms() + window_ms,
+        responses: Vec::new(),
+    };
+    match &consensus {
+        Some(consensus) => consensus.request_quote(rfq.clone()).await?,
+        None => engine.request_quote(rfq.clone()).await?,
+    }
+    Ok(Json(rfq))
+}
+
+/// A maker's price for the RFQ, replacing their previous one
+async fn post_rfq_response(
+    State(mut engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(principal): Extension<Principal>,
+    Path(rfq_id): Path<RfqId>,
+    payload: Result<Json<RfqResponseRequest>, JsonRejection>,
+) -> Result<Json<RfqResponse>, ApiError> {
+    let Json(req) = payload?;
+    let price = match req.price {
+        PriceType::Unsigned(cents) => cents,
+        PriceType::Float(dollars) => {
+            validation::dollars_to_cents(dollars).map_err(|e| ApiError::validation(vec![e]))?
+        }
+    };
+    if price == 0 {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "price",
+            "must be > 0",
+        )]));
+    }
+
+    let response = RfqResponse {
+        id: uuid::Uuid::new_v4().to_string(),
+        maker: Some(principal.subject),
+        price,
+        timestamp: engine.clock().now_ms(),
+    };
+    match &consensus {
+        Some(consensus) => consensus.respond_rfq(rfq_id, response.clone()).await?,
+        None => engine.respond_rfq(&rfq_id, response.clone()).await?,
+    }
+    Ok(Json(response))
+}
+
+/// Closes RFQs once their window is over, trading the best response. In consensus mode the
+/// leader commits each close, so every node executes the same response at the same point.
+pub async fn run_rfq_close(mut engine: MatchingEngine, consensus: Option<Consensus>) {
+    let mut tick = tokio::time::interval(RFQ_TICK);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let due = engine.due_rfqs(engine.clock().now_ms()).await;
+        if due.is_empty() || consensus.as_ref().is_some_and(|c| !c.is_leader()) {
+            continue;
+        }
+        for rfq_id in due {
+            let result = match &consensus {
+                Some(consensus) => consensus.close_rfq(rfq_id.clone()).await.map(|_| ()),
+                None => {
+                    engine.close_rfq(&rfq_id).await;
+                    Ok(())
+                }
+            };
+            if let Err(ApiError { message, .. }) = result {
+                println!("closing RFQ {rfq_id} failed: {message}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response(maker: &str, price: Price, timestamp: Timestamp) -> RfqResponse {
+        RfqResponse {
+            id: format!("{maker}-{timestamp}"),
+            maker: Some(maker.to_string()),
+            price,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_rfq_ranking() {
+        let mut rfq = Rfq {
+            id: "r1".to_string(),
+            taker: Some("taker".to_string()),
+            side: Side::Buy,
+            quantity: 10,
+            makers: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            expires_at: 100,
+            responses: Vec::new(),
+        };
+        rfq.respond(response("a", 101, 1));
+        rfq.respond(response("b", 100, 2));
+        rfq.respond(response("c", 100, 3));
+        rfq.respond(response("a", 99, 4)); // replaces a's first price
+        let best: Vec<Price> = rfq.ranked().iter().map(|r| r.price).collect();
+        assert_eq!(best, [99, 100, 100]);
+        assert_eq!(rfq.ranked()[1].maker.as_deref(), Some("b"));
+
+        rfq.side = Side::Sell;
+        assert_eq!(rfq.ranked()[0].maker.as_deref(), Some("b"));
+        assert!(rfq.is_invited("c") && !rfq.is_invited("taker"));
+
+        let mut book = RfqBook::default();
+        book.insert(rfq);
+        assert!(book.due(99).is_empty());
+        assert_eq!(book.due(100), ["r1"]);
+        assert_eq!(book.visible_to("a").len(), 1);
+        assert!(book.visible_to("d").is_empty());
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::{Order, OrderType, ParticipantId, Price, Quantity, RejectReason, Side};
+
+pub const PRICE_BAND_PCT_ENV: &str = "OME_PRICE_BAND_PCT"; // unset or `0` disables the bands
+pub const PRICE_BAND_MODE_ENV: &str = "OME_PRICE_BAND_MODE"; // `reject` | `collar`
+pub const FAT_FINGER_PCT_ENV: &str = "OME_FAT_FINGER_PCT"; // unset or `0` disables the check
+pub const MAX_ORDER_NOTIONAL_ENV: &str = "OME_MAX_ORDER_NOTIONAL"; // cents
+pub const MAX_ORDER_QTY_ENV: &str = "OME_MAX_ORDER_QTY";
+pub const MAX_LONG_POSITION_ENV: &str = "OME_MAX_LONG_POSITION";
+pub const MAX_SHORT_POSITION_ENV: &str = "OME_MAX_SHORT_POSITION";
+
+/// What happens to a limit order priced outside the bands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BandMode {
+    Reject,
+    /// Clamp the limit price to the nearest band
+    Collar,
+}
+
+/// Static limit up / limit down bands, `pct` percent either side of the reference price
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceBands {
+    pub pct: f64,
+    pub mode: BandMode,
+}
+
+impl Default for PriceBands {
+    fn default() -> Self {
+        PriceBands {
+            pct: 0.0,
+            mode: BandMode::Reject,
+        }
+    }
+}
+
+/// Band limits currently in force
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BandLimits {
+    pub reference: Price,
+    pub lower: Price,
+    pub upper: Price,
+}
+
+impl PriceBands {
+    pub fn from_env() -> Self {
+        let default = PriceBands::default();
+        PriceBands {
+            pct: std::env::var(PRICE_BAND_PCT_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.pct),
+            mode: match std::env::var(PRICE_BAND_MODE_ENV).as_deref() {
+                Ok("collar") => BandMode::Collar,
+                _ => default.mode,
+            },
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.pct > 0.0
+    }
+
+    pub fn limits(&self, reference: Price) -> BandLimits {
+        let width = (reference as f64 * self.pct / 100.0).round() as Price;
+        BandLimits {
+            reference,
+            lower: reference.saturating_sub(width).max(1),
+            upper: reference.saturating_add(width),
+        }
+    }
+
+    /// Rejects or collars a limit order priced outside the bands around `reference`.
+    /// Market orders are left alone, the circuit breaker catches runaway prints.
+    pub fn apply(&self, order: &mut Order, reference: Option<Price>) -> Result<(), RejectReason> {
+        let Some(reference) = reference else {
+            return Ok(()); // nothing to band against before the first reference price
+        };
+        if !self.is_enabled() || order.order_type != OrderType::Limit {
+            return Ok(());
+        }
+
+        let BandLimits { lower, upper, .. } = self.limits(reference);
+        if (lower..=upper).contains(&order.price) {
+            return Ok(());
+        }
+        match self.mode {
+            BandMode::Reject => Err(RejectReason::PriceOutsideBands { lower, upper }),
+            BandMode::Collar => {
+                order.price = order.price.clamp(lower, upper);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Pre-trade check against the current mid (or last) price, configured per book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FatFingerCheck {
+    /// Maximum deviation of a limit price from the reference, in percent
+    pub max_deviation_pct: f64,
+}
+
+impl FatFingerCheck {
+    pub fn from_env() -> Self {
+        FatFingerCheck {
+            max_deviation_pct: std::env::var(FAT_FINGER_PCT_ENV)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn check(&self, order: &Order, reference: Option<Price>) -> Result<(), RejectReason> {
+        let Some(reference) = reference else {
+            return Ok(());
+        };
+        if self.max_deviation_pct <= 0.0 || order.order_type != OrderType::Limit {
+            return Ok(());
+        }
+
+        let deviation_pct =
+            order.price.abs_diff(reference) as f64 * 100.0 / reference.max(1) as f64;
+        if deviation_pct > self.max_deviation_pct {
+            return Err(RejectReason::FatFinger { reference });
+        }
+        Ok(())
+    }
+}
+
+/// Per-order limits for a participant; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskLimits {
+    /// Maximum `price * quantity` of a single order, in cents
+    #[serde(default)]
+    pub max_order_notional: Option<u64>,
+    #[serde(default)]
+    pub max_order_quantity: Option<Quantity>,
+    /// Maximum net long position, counting resting buy orders as filled
+    #[serde(default)]
+    pub max_long_position: Option<u64>,
+    /// Maximum net short position, counting resting sell orders as filled
+    #[serde(default)]
+    pub max_short_position: Option<u64>,
+}
+
+impl RiskLimits {
+    pub fn from_env() -> Self {
+        let limit = |env: &str| std::env::var(env).ok().and_then(|v| v.parse().ok());
+        RiskLimits {
+            max_order_notional: limit(MAX_ORDER_NOTIONAL_ENV),
+            max_order_quantity: limit(MAX_ORDER_QTY_ENV),
+            max_long_position: limit(MAX_LONG_POSITION_ENV),
+            max_short_position: limit(MAX_SHORT_POSITION_ENV),
+        }
+    }
+
+    /// Market orders are valued at `mark`; without a mark their notional can't be checked
+    pub fn check(&self, order: &Order, mark: Option<Price>) -> Result<(), RejectReason> {
+        if let Some(limit) = self.max_order_quantity
+            && order.quantity > limit
+        {
+            return Err(RejectReason::MaxQuantityExceeded { limit });
+        }
+
+        let price = match order.order_type {
+            OrderType::Limit => Some(order.price),
+            OrderType::Market => mark,
+        };
+        if let (Some(limit), Some(price)) = (self.max_order_notional, price)
+            && price.saturating_mul(order.quantity) > limit
+        {
+            return Err(RejectReason::MaxNotionalExceeded { limit });
+        }
+        Ok(())
+    }
+}
+
+/// What a participant holds and has working when a new order comes in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Exposure {
+    pub net_position: i64,
+    pub open_buy: Quantity,
+    pub open_sell: Quantity,
+}
+
+impl RiskLimits {
+    /// Worst case check: every open order on the order's side, plus the order itself, fills
+    pub fn check_position(&self, order: &Order, exposure: Exposure) -> Result<(), RejectReason> {
+        let quantity = order.quantity as i128;
+        let net = exposure.net_position as i128;
+        match order.side {
+            Side::Buy => {
+                if let Some(limit) = self.max_long_position
+                    && net + exposure.open_buy as i128 + quantity > limit as i128
+                {
+                    return Err(RejectReason::PositionLimitExceeded { limit });
+                }
+            }
+            Side::Sell => {
+                if let Some(limit) = self.max_short_position
+                    && exposure.open_sell as i128 + quantity - net > limit as i128
+                {
+                    return Err(RejectReason::PositionLimitExceeded { limit });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a [`RiskCheck`] gets to know about the book when an order comes in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookContext {
+    pub best_bid: Option<Price>,
+    pub best_ask: Option<Price>,
+    /// Mid price, or the last trade price while the book is one-sided
+    pub mid_or_last: Option<Price>,
+    pub mark: Option<Price>,
+    /// Reference price of the price bands
+    pub reference: Option<Price>,
+    /// Of the order's owner; `None` for orders without one
+    pub exposure: Option<Exposure>,
+}
+
+/// A pre-trade check on a new order, after the price bands and before its balance is held
+/// and it reaches the book. Checks run in a chain and the first to fail rejects the order.
+/// They must only depend on the order and the context, so replicas reject the same orders.
+pub trait RiskCheck: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn check(&self, order: &Order, ctx: &BookContext) -> Result<(), RejectReason>;
+}
+
+impl RiskCheck for FatFingerCheck {
+    fn name(&self) -> &'static str {
+        "fat_finger"
+    }
+
+    fn check(&self, order: &Order, ctx: &BookContext) -> Result<(), RejectReason> {
+        FatFingerCheck::check(self, order, ctx.mid_or_last)
+    }
+}
+
+impl RiskCheck for RiskLimits {
+    fn name(&self) -> &'static str {
+        "limits"
+    }
+
+    fn check(&self, order: &Order, ctx: &BookContext) -> Result<(), RejectReason> {
+        RiskLimits::check(self, order, ctx.mark)?;
+        match ctx.exposure {
+            Some(exposure) => self.check_position(order, exposure),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Limits applied to everyone, with per-participant overrides set by admins
+#[derive(Debug, Clone, Default)]
+pub struct ParticipantLimits {
+    pub default: RiskLimits,
+    overrides: HashMap<ParticipantId, RiskLimits>,
+}
+
+impl ParticipantLimits {
+    pub fn get(&self, owner: Option<&str>) -> RiskLimits {
+        owner
+            .and_then(|o| self.overrides.get(o))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    pub fn set(&mut self, owner: ParticipantId, limits: RiskLimits) {
+        self.overrides.insert(owner, limits);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limit(side: Side, price: Price) -> Order {
+        Order::new(String::from("1"), side, OrderType::Limit, 1, price, 1)
+    }
+
+    #[test]
+    fn test_band_limits() {
+        let bands = PriceBands {
+            pct: 10.0,
+            mode: BandMode::Reject,
+        };
+        assert_eq!(
+            bands.limits(1000),
+            BandLimits {
+                reference: 1000,
+                lower: 900,
+                upper: 1100
+            }
+        );
+    }
+
+    #[test]
+    fn test_reject_outside_bands() {
+        let bands = PriceBands {
+            pct: 10.0,
+            mode: BandMode::Reject,
+        };
+        assert!(bands.apply(&mut limit(Side::Buy, 1100), Some(1000)).is_ok());
+        assert_eq!(
+            bands.apply(&mut limit(Side::Buy, 1101), Some(1000)),
+            Err(RejectReason::PriceOutsideBands {
+                lower: 900,
+                upper: 1100
+            })
+        );
+        // no reference yet, nothing to check against
+        assert!(bands.apply(&mut limit(Side::Sell, 1), None).is_ok());
+
+        let mut market = Order::new(String::from("2"), Side::Buy, OrderType::Market, 1, 0, 1);
+        assert!(bands.apply(&mut market, Some(1000)).is_ok());
+    }
+
+    #[test]
+    fn test_collar() {
+        let bands = PriceBands {
+            pct: 10.0,
+            mode: BandMode::Collar,
+        };
+        let mut buy = limit(Side::Buy, 5000);
+        assert!(bands.apply(&mut buy, Some(1000)).is_ok());
+        assert_eq!(buy.price, 1100);
+
+        let mut sell = limit(Side::Sell, 10);
+        assert!(bands.apply(&mut sell, Some(1000)).is_ok());
+        assert_eq!(sell.price, 900);
+    }
+
+    #[test]
+    fn test_fat_finger() {
+        let check = FatFingerCheck {
+            max_deviation_pct: 5.0,
+        };
+        assert!(check.check(&limit(Side::Buy, 1050), Some(1000)).is_ok());
+        assert_eq!(
+            check.check(&limit(Side::Sell, 940), Some(1000)),
+            Err(RejectReason::FatFinger { reference: 1000 })
+        );
+        assert!(check.check(&limit(Side::Sell, 1), None).is_ok());
+        assert!(
+            FatFingerCheck::default()
+                .check(&limit(Side::Buy, 1), Some(1000))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_order_limits() {
+        let limits = RiskLimits {
+            max_order_notional: Some(100_000),
+            max_order_quantity: Some(500),
+            ..Default::default()
+        };
+        assert!(limits.check(&limit(Side::Buy, 200), None).is_ok());
+
+        let mut big = limit(Side::Buy, 100);
+        big.quantity = 501;
+        assert_eq!(
+            limits.check(&big, None),
+            Err(RejectReason::MaxQuantityExceeded { limit: 500 })
+        );
+
+        let mut pricey = limit(Side::Sell, 1000);
+        pricey.quantity = 101;
+        assert_eq!(
+            limits.check(&pricey, None),
+            Err(RejectReason::MaxNotionalExceeded { limit: 100_000 })
+        );
+
+        // market orders are valued at the mark, if there is one
+        let market = Order::new(String::from("2"), Side::Buy, OrderType::Market, 200, 0, 1);
+        assert!(limits.check(&market, None).is_ok());
+        assert!(limits.check(&market, Some(1000)).is_err());
+    }
+
+    #[test]
+    fn test_participant_overrides() {
+        let mut limits = ParticipantLimits {
+            default: RiskLimits {
+                max_order_quantity: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        limits.set(String::from("mm"), RiskLimits::default());
+        assert_eq!(limits.get(Some("alice")).max_order_quantity, Some(10));
+        assert_eq!(limits.get(None).max_order_quantity, Some(10));
+        assert_eq!(limits.get(Some("mm")).max_order_quantity, None);
+    }
+
+    #[test]
+    fn test_position_limits() {
+        let limits = RiskLimits {
+            max_long_position: Some(100),
+            max_short_position: Some(50),
+            ..Default::default()
+        };
+        let order = |side, quantity| {
+            let mut o = limit(side, 10);
+            o.quantity = quantity;
+            o
+        };
+        let exposure = Exposure {
+            net_position: 60,
+            open_buy: 30,
+            open_sell: 0,
+        };
+        assert!(
+            limits
+                .check_position(&order(Side::Buy, 10), exposure)
+                .is_ok()
+        );
+        assert_eq!(
+            limits.check_position(&order(Side::Buy, 11), exposure),
+            Err(RejectReason::PositionLimitExceeded { limit: 100 })
+        );
+        // selling out of a long position: 60 long lets you sell up to 110
+        assert!(
+            limits
+                .check_position(&order(Side::Sell, 110), exposure)
+                .is_ok()
+        );
+        assert!(
+            limits
+                .check_position(&order(Side::Sell, 111), exposure)
+                .is_err()
+        );
+    }
+}
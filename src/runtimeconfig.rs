@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    order::Quantity, ratelimit::RateLimitConfig, risk::PriceBands, validation::FieldError,
+};
+
+/// Largest single order of participants without limits of their own; `None` is unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderSizeLimits {
+    /// Maximum `price * quantity`, in cents
+    #[serde(default)]
+    pub max_order_notional: Option<u64>,
+    #[serde(default)]
+    pub max_order_quantity: Option<Quantity>,
+}
+
+/// Limits operators may change while the server runs, through `/admin/config`. Updates
+/// replace the sections they carry and leave the others alone; reads carry every section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    /// Order entry rate limits of this server, not replicated to standbys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_bands: Option<PriceBands>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_size: Option<OrderSizeLimits>,
+    /// Most recent trades kept in memory, older ones spill or are dropped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade_pool_size: Option<usize>,
+}
+
+impl RuntimeConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == RuntimeConfig::default()
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if let Some(rate_limit) = self.rate_limit {
+            if rate_limit.burst == 0 {
+                errors.push(FieldError::new("rate_limit.burst", "must be > 0"));
+            }
+            if !rate_limit.per_second.is_finite() || rate_limit.per_second < 0.0 {
+                errors.push(FieldError::new(
+                    "rate_limit.per_second",
+                    "must be >= 0, 0 turns rate limiting off",
+                ));
+            }
+        }
+        if let Some(bands) = self.price_bands
+            && (!bands.pct.is_finite() || !(0.0..100.0).contains(&bands.pct))
+        {
+            errors.push(FieldError::new(
+                "price_bands.pct",
+                "must be >= 0 and < 100, 0 turns the bands off",
+            ));
+        }
+        if let Some(order_size) = self.order_size {
+            if order_size.max_order_notional == Some(0) {
+                errors.push(FieldError::new(
+                    "order_size.max_order_notional",
+                    "must be > 0, null for no limit",
+                ));
+            }
+            if order_size.max_order_quantity == Some(0) {
+                errors.push(FieldError::new(
+                    "order_size.max_order_quantity",
+                    "must be > 0, null for no limit",
+                ));
+            }
+        }
+        if self.trade_pool_size == Some(0) {
+            errors.push(FieldError::new("trade_pool_size", "must be > 0"));
+        }
+        if self.is_empty() {
+            errors.push(FieldError::new(
+                "config",
+                "expected at least one of rate_limit, price_bands, order_size, trade_pool_size",
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::risk::BandMode;
+
+    #[test]
+    fn test_validate() {
+        let update: RuntimeConfig =
+            serde_json::from_str(r#"{"price_bands": {"pct": 5.0, "mode": "collar"}}"#).unwrap();
+        assert_eq!(
+            update.price_bands,
+            Some(PriceBands {
+                pct: 5.0,
+                mode: BandMode::Collar
+            })
+        );
+        assert!(update.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(update).unwrap(),
+            serde_json::json!({"price_bands": {"pct": 5.0, "mode": "collar"}})
+        );
+
+        let fields = |update: RuntimeConfig| -> Vec<&'static str> {
+            update
+                .validate()
+                .unwrap_err()
+                .into_iter()
+                .map(|e| e.field)
+                .collect()
+        };
+        assert_eq!(fields(RuntimeConfig::default()), ["config"]);
+        let update = RuntimeConfig {
+            rate_limit: Some(RateLimitConfig {
+                burst: 0,
+                per_second: f64::NAN,
+            }),
+            price_bands: Some(PriceBands {
+                pct: -1.0,
+                mode: BandMode::Reject,
+            }),
+            order_size: Some(OrderSizeLimits {
+                max_order_notional: None,
+                max_order_quantity: Some(0),
+            }),
+            trade_pool_size: Some(0),
+        };
+        assert_eq!(
+            fields(update),
+            [
+                "rate_limit.burst",
+                "rate_limit.per_second",
+                "price_bands.pct",
+                "order_size.max_order_quantity",
+                "trade_pool_size"
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::time::Duration;
+
+use chrono::{NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{consensus::Consensus, matchingengine::MatchingEngine};
+
+pub const MARKET_HOURS_ENV: &str = "OME_MARKET_HOURS"; // `08:00-09:30-16:00[-16:05]` (pre-open, open, closing auction, close; UTC)
+pub const PRE_OPEN_POLICY_ENV: &str = "OME_PRE_OPEN_POLICY"; // `queue` | `reject` | `auction`
+pub const CLOSED_POLICY_ENV: &str = "OME_CLOSED_POLICY"; // `queue` | `reject` | `auction`
+
+const SCHEDULER_TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MarketPhase {
+    PreOpen,
+    Continuous,
+    ClosingAuction,
+    /// Entered by the circuit breaker, never by the schedule
+    VolatilityAuction,
+    Closed,
+}
+
+/// What happens to orders submitted outside continuous trading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OffHoursPolicy {
+    Queue,
+    Reject,
+    /// Limit orders rest in the book without matching; the book is uncrossed when the phase ends
+    Auction,
+}
+
+impl OffHoursPolicy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "queue" => Some(OffHoursPolicy::Queue),
+            "reject" => Some(OffHoursPolicy::Reject),
+            "auction" => Some(OffHoursPolicy::Auction),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhasePolicies {
+    pub pre_open: OffHoursPolicy,
+    pub closed: OffHoursPolicy,
+}
+
+impl Default for PhasePolicies {
+    fn default() -> Self {
+        PhasePolicies {
+            pre_open: OffHoursPolicy::Queue,
+            closed: OffHoursPolicy::Reject,
+        }
+    }
+}
+
+impl PhasePolicies {
+    /// `None` during continuous trading, where orders are always matched
+    pub fn off_hours_policy(&self, phase: MarketPhase) -> Option<OffHoursPolicy> {
+        match phase {
+            MarketPhase::PreOpen => Some(self.pre_open),
+            MarketPhase::Continuous => None,
+            MarketPhase::ClosingAuction | MarketPhase::VolatilityAuction => {
+                Some(OffHoursPolicy::Auction)
+            }
+            MarketPhase::Closed => Some(self.closed),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let default = PhasePolicies::default();
+        let policy = |env: &str| {
+            std::env::var(env)
+                .ok()
+                .and_then(|v| OffHoursPolicy::parse(&v))
+        };
+        PhasePolicies {
+            pre_open: policy(PRE_OPEN_POLICY_ENV).unwrap_or(default.pre_open),
+            closed: policy(CLOSED_POLICY_ENV).unwrap_or(default.closed),
+        }
+    }
+}
+
+/// Daily UTC schedule: pre-open from `pre_open` until `open`, continuous until `close` (or until the
+/// closing auction starts), closed otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketSchedule {
+    pub pre_open: NaiveTime,
+    pub open: NaiveTime,
+    pub closing_auction: Option<NaiveTime>,
+    pub close: NaiveTime,
+}
+
+impl MarketSchedule {
+    pub fn parse(s: &str) -> Option<Self> {
+        let times = s
+            .split('-')
+            .map(|t| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok())
+            .collect::<Option<Vec<NaiveTime>>>()?;
+        let schedule = match times[..] {
+            [pre_open, open, close] => MarketSchedule {
+                pre_open,
+                open,
+                closing_auction: None,
+                close,
+            },
+            [pre_open, open, closing_auction, close] => MarketSchedule {
+                pre_open,
+                open,
+                closing_auction: Some(closing_auction),
+                close,
+            },
+            _ => return None,
+        };
+        let continuous_until = schedule.closing_auction.unwrap_or(schedule.close);
+        if schedule.pre_open > schedule.open
+            || schedule.open >= continuous_until
+            || continuous_until > schedule.close
+        {
+            return None;
+        }
+        Some(schedule)
+    }
+
+    /// `None` when no schedule is configured, i.e. the market trades around the clock
+    pub fn from_env() -> Option<Self> {
+        let raw = std::env::var(MARKET_HOURS_ENV).ok()?;
+        let schedule = MarketSchedule::parse(&raw);
+        if schedule.is_none() {
+            println!("WARNING: ignoring malformed {MARKET_HOURS_ENV}={raw}");
+        }
+        schedule
+    }
+
+    pub fn phase_at(&self, time: NaiveTime) -> MarketPhase {
+        let continuous_until = self.closing_auction.unwrap_or(self.close);
+        if time >= self.open && time < continuous_until {
+            MarketPhase::Continuous
+        } else if time >= continuous_until && time < self.close {
+            MarketPhase::ClosingAuction
+        } else if time >= self.pre_open && time < self.open {
+            MarketPhase::PreOpen
+        } else {
+            MarketPhase::Closed
+        }
+    }
+}
+
+/// Drives the engine through the daily phases, checking the wall clock every second. In
+/// consensus mode the leader commits each phase change to the log, so that every node uncrosses,
+/// releases and expires orders at the same point in the sequence; the other nodes leave it to
+/// the leader.
+pub async fn run_scheduler(
+    engine: MatchingEngine,
+    schedule: MarketSchedule,
+    consensus: Option<Consensus>,
+) {
+    let mut tick = tokio::time::interval(SCHEDULER_TICK);
+    loop {
+        tick.tick().await;
+        if consensus.as_ref().is_some_and(|c| !c.is_leader()) {
+            continue;
+        }
+        let phase = schedule.phase_at(Utc::now().time());
+        let current = engine.market_phase().await;
+        // the circuit breaker hands the book back once its auction is over
+        if current != phase && current != MarketPhase::VolatilityAuction {
+            let trades = match &consensus {
+                Some(consensus) => match consensus.set_market_phase(phase).await {
+                    Ok(trades) => trades,
+                    Err(e) => {
+                        println!("Market phase -> {phase:?} not committed: {}", e.message);
+                        continue;
+                    }
+                },
+                None => engine.set_market_phase(phase).await,
+            };
+            println!(
+                "Market phase -> {:?} ({} trades from the uncross/queued orders)",
+                phase,
+                trades.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn t(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = MarketSchedule::parse("08:00-09:30-16:00").unwrap();
+        assert_eq!(schedule.open, t(9, 30));
+        assert!(MarketSchedule::parse("09:30-08:00-16:00").is_none());
+        assert!(MarketSchedule::parse("08:00-09:30").is_none());
+        assert!(MarketSchedule::parse("08:00-09:30-16:00-15:00").is_none());
+        assert!(MarketSchedule::parse("08:00-09:30-15:55-16:00-17:00").is_none());
+        let schedule = MarketSchedule::parse("08:00-09:30-15:55-16:00").unwrap();
+        assert_eq!(schedule.closing_auction, Some(t(15, 55)));
+    }
+
+    #[test]
+    fn test_phase_at() {
+        let schedule = MarketSchedule::parse("08:00-09:30-16:00").unwrap();
+        assert_eq!(schedule.phase_at(t(7, 59)), MarketPhase::Closed);
+        assert_eq!(schedule.phase_at(t(8, 0)), MarketPhase::PreOpen);
+        assert_eq!(schedule.phase_at(t(9, 30)), MarketPhase::Continuous);
+        assert_eq!(schedule.phase_at(t(16, 0)), MarketPhase::Closed);
+
+        let schedule = MarketSchedule::parse("08:00-09:30-15:55-16:00").unwrap();
+        assert_eq!(schedule.phase_at(t(15, 54)), MarketPhase::Continuous);
+        assert_eq!(schedule.phase_at(t(15, 55)), MarketPhase::ClosingAuction);
+        assert_eq!(schedule.phase_at(t(16, 0)), MarketPhase::Closed);
+    }
+
+    #[test]
+    fn test_off_hours_policy() {
+        let policies = PhasePolicies::default();
+        assert_eq!(
+            policies.off_hours_policy(MarketPhase::PreOpen),
+            Some(OffHoursPolicy::Queue)
+        );
+        assert_eq!(policies.off_hours_policy(MarketPhase::Continuous), None);
+        assert_eq!(
+            OffHoursPolicy::parse(" Reject "),
+            Some(OffHoursPolicy::Reject)
+        );
+    }
+}
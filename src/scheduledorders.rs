@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consensus::Consensus,
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, Timestamp},
+};
+
+/// How often due orders are looked for, the precision of activation times
+const SCHEDULER_TICK: Duration = Duration::from_millis(10);
+
+/// An order held back from matching until `activate_at`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOrder {
+    /// Unix ms
+    pub activate_at: Timestamp,
+    pub order: Order,
+}
+
+/// Orders waiting for their activation time, earliest first; orders due at the same time keep
+/// their arrival order
+#[derive(Debug, Default)]
+pub struct ScheduledOrders {
+    orders: Vec<ScheduledOrder>,
+}
+
+impl ScheduledOrders {
+    pub fn insert(&mut self, activate_at: Timestamp, order: Order) {
+        let pos = self
+            .orders
+            .partition_point(|s| s.activate_at <= activate_at);
+        self.orders
+            .insert(pos, ScheduledOrder { activate_at, order });
+    }
+
+    pub fn get(&self, order_id: &OrderId) -> Option<&Order> {
+        self.iter().find(|o| &o.id == order_id)
+    }
+
+    pub fn remove(&mut self, order_id: &OrderId) -> Option<Order> {
+        let pos = self.orders.iter().position(|s| &s.order.id == order_id)?;
+        Some(self.orders.remove(pos).order)
+    }
+
+    /// Ids of the orders whose time has come at `now`, in activation order
+    pub fn due(&self, now: Timestamp) -> Vec<OrderId> {
+        self.orders
+            .iter()
+            .take_while(|s| s.activate_at <= now)
+            .map(|s| s.order.id.clone())
+            .collect()
+    }
+
+    /// Takes out every order of `owner`
+    pub fn drain_owner(&mut self, owner: &str) -> Vec<Order> {
+        let (removed, kept) = std::mem::take(&mut self.orders)
+            .into_iter()
+            .partition(|s| s.order.owner.as_deref() == Some(owner));
+        self.orders = kept;
+        removed.into_iter().map(|s| s.order).collect()
+    }
+
+    pub fn clear(&mut self) -> Vec<Order> {
+        self.orders.drain(..).map(|s| s.order).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Order> {
+        self.orders.iter().map(|s| &s.order)
+    }
+
+    /// Copy for a state export, earliest first
+    pub fn snapshot(&self) -> Vec<ScheduledOrder> {
+        self.orders.clone()
+    }
+
+    pub fn restore(&mut self, orders: Vec<ScheduledOrder>) {
+        self.orders.clear();
+        for scheduled in orders {
+            self.insert(scheduled.activate_at, scheduled.order);
+        }
+    }
+}
+
+/// Releases scheduled orders into matching once their time has come. In consensus mode the
+/// leader commits each release to the log, so every node activates the order at the same point
+/// in the sequence; the other nodes leave it to the leader.
+pub async fn run_release(mut engine: MatchingEngine, consensus: Option<Consensus>) {
+    let mut tick = tokio::time::interval(SCHEDULER_TICK);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let due = engine.due_orders(engine.clock().now_ms()).await;
+        if due.is_empty() || consensus.as_ref().is_some_and(|c| !c.is_leader()) {
+            continue;
+        }
+        for order_id in due {
+            let result = match &consensus {
+                Some(consensus) => consensus.activate_order(order_id.clone()).await,
+                None => engine
+                    .activate_scheduled(&order_id)
+                    .await
+                    .map_err(ApiError::from),
+            };
+            if let Err(e) = result {
+                println!("scheduled order {order_id} rejected: {}", e.message);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::{OrderType, Side};
+
+    fn order(id: &str) -> Order {
+        Order::new(id.to_string(), Side::Buy, OrderType::Limit, 1, 100, 0)
+            .with_owner(format!("owner-{id}"))
+    }
+
+    #[test]
+    fn test_scheduled_orders() {
+        let mut scheduled = ScheduledOrders::default();
+        scheduled.insert(20, order("b"));
+        scheduled.insert(10, order("a"));
+        scheduled.insert(20, order("c"));
+        assert!(scheduled.due(9).is_empty());
+        assert_eq!(scheduled.due(20), ["a", "b", "c"]);
+
+        assert_eq!(scheduled.remove(&"b".to_string()).unwrap().id, "b");
+        assert!(scheduled.remove(&"b".to_string()).is_none());
+        let drained = scheduled.drain_owner("owner-a");
+        assert_eq!(drained.len(), 1);
+        assert_eq!(scheduled.due(u64::MAX), ["c"]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, OrderType, ParticipantId, Price, Quantity, Side},
+};
+
+pub const SEED_BOOK_ENV: &str = "OME_SEED_BOOK"; // `.json` or `.csv` file of resting orders entered at startup, `{symbol}` is replaced
+
+const CSV_COLUMNS: [&str; 5] = ["side", "price", "quantity", "owner", "id"];
+
+/// A limit order of the seed file; `id` defaults to one from the engine's generator
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SeedOrder {
+    pub side: Side,
+    /// Cents
+    pub price: Price,
+    pub quantity: Quantity,
+    pub owner: Option<ParticipantId>,
+    pub id: Option<OrderId>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SeedFile {
+    Orders(Vec<SeedOrder>),
+    Book { orders: Vec<SeedOrder> },
+}
+
+/// `[{"side": "Buy", "price": 9950, "quantity": 10, "owner": "mm-1"}, ...]`, bare or under
+/// `orders`
+pub fn parse_json(text: &str) -> Result<Vec<SeedOrder>, String> {
+    let orders = match serde_json::from_str(text).map_err(|e| e.to_string())? {
+        SeedFile::Orders(orders) | SeedFile::Book { orders } => orders,
+    };
+    for (i, order) in orders.iter().enumerate() {
+        check(order).map_err(|e| format!("order {}: {e}", i + 1))?;
+    }
+    Ok(orders)
+}
+
+/// A header naming the columns (`side`, `price` and `quantity`, optionally `owner` and `id`)
+/// followed by one order per line; `#` starts a comment line
+pub fn parse_csv(text: &str) -> Result<Vec<SeedOrder>, String> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+    let column = |name| columns.iter().position(|&c| c.eq_ignore_ascii_case(name));
+    let [side, price, quantity, owner, id] = CSV_COLUMNS.map(column);
+    let (Some(side), Some(price), Some(quantity)) = (side, price, quantity) else {
+        return Err(format!(
+            "the header needs side, price and quantity: {header}"
+        ));
+    };
+
+    let mut orders = Vec::new();
+    for (number, line) in lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let field = |i: Option<usize>| {
+            i.and_then(|i| fields.get(i))
+                .filter(|f| !f.is_empty())
+                .map(|f| f.to_string())
+        };
+        let number_of = |i, name| {
+            field(Some(i))
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(|| format!("line {number}: {name} is not a whole number"))
+        };
+        let order = SeedOrder {
+            side: match field(Some(side)).as_deref().map(str::to_ascii_lowercase) {
+                Some(s) if s == "buy" => Side::Buy,
+                Some(s) if s == "sell" => Side::Sell,
+                _ => return Err(format!("line {number}: side is neither buy nor sell")),
+            },
+            price: number_of(price, "price")?,
+            quantity: number_of(quantity, "quantity")?,
+            owner: field(owner),
+            id: field(id),
+        };
+        check(&order).map_err(|e| format!("line {number}: {e}"))?;
+        orders.push(order);
+    }
+    Ok(orders)
+}
+
+fn check(order: &SeedOrder) -> Result<(), &'static str> {
+    if order.price == 0 {
+        return Err("price must be greater than 0");
+    }
+    if order.quantity == 0 {
+        return Err("quantity must be greater than 0");
+    }
+    Ok(())
+}
+
+/// The orders of the seed file at `path`, by its extension
+pub fn load(path: &Path) -> Result<Vec<SeedOrder>, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("json") => parse_json(&text),
+        Some(e) if e.eq_ignore_ascii_case("csv") => parse_csv(&text),
+        _ => Err("not a .json or .csv file".to_string()),
+    }
+}
+
+/// Enters `orders` in file order, time priority included, like orders posted one after the
+/// other; orders that cross trade. Returns how many the engine accepted.
+pub async fn seed_book(engine: &MatchingEngine, orders: Vec<SeedOrder>) -> usize {
+    let start = engine.order_timestamp();
+    let mut engine = engine.clone();
+    let mut accepted = 0;
+    for (i, seed) in orders.into_iter().enumerate() {
+        let id = seed.id.unwrap_or_else(|| engine.next_order_id());
+        let mut order = Order::new(
+            id.clone(),
+            seed.side,
+            OrderType::Limit,
+            seed.quantity,
+            seed.price,
+            start + i as u64,
+        );
+        order.owner = seed.owner;
+        match engine.submit_order(order).await {
+            Ok(_) => accepted += 1,
+            Err(reason) => println!("Seed order {id} rejected: {reason}"),
+        }
+    }
+    accepted
+}
+
+/// Seeds a fresh engine from the file [`SEED_BOOK_ENV`] names, if any. A `replicated` engine
+/// (a standby, or a node in consensus mode) gets its orders from the primary or the log instead.
+pub async fn seed_from_env(engine: &MatchingEngine, symbol: &str, replicated: bool) {
+    let Ok(path) = std::env::var(SEED_BOOK_ENV) else {
+        return;
+    };
+    if path.is_empty() {
+        return;
+    }
+    if replicated {
+        println!("{symbol}: book not seeded, its orders are replicated");
+        return;
+    }
+    let path = path.replace("{symbol}", symbol);
+    match load(Path::new(&path)) {
+        Ok(orders) => {
+            let total = orders.len();
+            let accepted = seed_book(engine, orders).await;
+            println!("{symbol}: seeded {accepted} of {total} orders from {path}");
+        }
+        Err(e) => println!("{symbol}: book not seeded from {path}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seed_book() {
+        let csv = "# demo book\nSide,Price,Quantity,Owner\nbuy, 9950, 10, mm-1\nSELL,10050,5,\n";
+        let orders = parse_csv(csv).unwrap();
+        assert_eq!(
+            orders[1],
+            SeedOrder {
+                side: Side::Sell,
+                price: 10050,
+                quantity: 5,
+                owner: None,
+                id: None,
+            }
+        );
+        assert_eq!(
+            parse_csv("side,price,quantity\nbuy,0,1").unwrap_err(),
+            "line 2: price must be greater than 0"
+        );
+        assert!(parse_csv("side,quantity\nbuy,1").is_err());
+
+        let json = r#"{"orders": [{"side": "Buy", "price": 9900, "quantity": 3, "id": "b2"}]}"#;
+        let mut orders = parse_json(json).unwrap();
+        orders.extend(parse_csv(csv).unwrap());
+        assert_eq!(parse_json(r#"[]"#).unwrap(), vec![]);
+
+        let engine = MatchingEngine::new();
+        assert_eq!(seed_book(&engine, orders).await, 3);
+        let book = engine.settled_book_view().await;
+        assert_eq!((book.bids.len(), book.asks.len()), (2, 1));
+        assert_eq!(book.bids[0].price, 9950);
+        assert_eq!(engine.owner_orders("mm-1").await.len(), 1);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::order::ParticipantId;
+
+pub type SessionId = u64;
+
+/// A connected streaming client and whether its orders are pulled when it goes away
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub participant: ParticipantId,
+    /// Unix millis
+    pub connected_at: i64,
+    pub cancel_on_disconnect: bool,
+    /// When armed with a timeout, a session that stays silent this long is treated as disconnected
+    pub heartbeat_timeout_ms: Option<u64>,
+}
+
+impl SessionInfo {
+    pub fn heartbeat_timeout(&self) -> Option<Duration> {
+        self.heartbeat_timeout_ms
+            .filter(|_| self.cancel_on_disconnect)
+            .map(Duration::from_millis)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SessionRegistry {
+    next_id: Arc<AtomicU64>,
+    sessions: Arc<RwLock<HashMap<SessionId, SessionInfo>>>,
+}
+
+impl SessionRegistry {
+    pub fn new() -> Self {
+        SessionRegistry::default()
+    }
+
+    pub async fn open(&self, participant: ParticipantId) -> SessionInfo {
+        let info = SessionInfo {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed) + 1,
+            participant,
+            connected_at: Utc::now().timestamp_millis(),
+            cancel_on_disconnect: false,
+            heartbeat_timeout_ms: None,
+        };
+        self.sessions.write().await.insert(info.id, info.clone());
+        info
+    }
+
+    /// Arms (or disarms) cancel-on-disconnect, returns the updated session
+    pub async fn set_cancel_on_disconnect(
+        &self,
+        id: SessionId,
+        armed: bool,
+        heartbeat_timeout_ms: Option<u64>,
+    ) -> Option<SessionInfo> {
+        let mut sessions = self.sessions.write().await;
+        let info = sessions.get_mut(&id)?;
+        info.cancel_on_disconnect = armed;
+        info.heartbeat_timeout_ms = if armed { heartbeat_timeout_ms } else { None };
+        Some(info.clone())
+    }
+
+    pub async fn close(&self, id: SessionId) -> Option<SessionInfo> {
+        self.sessions.write().await.remove(&id)
+    }
+
+    pub async fn list(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self.sessions.read().await.values().cloned().collect();
+        sessions.sort_by_key(|s| s.id);
+        sessions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let registry = SessionRegistry::new();
+        let s1 = registry.open(String::from("alice")).await;
+        let s2 = registry.open(String::from("bob")).await;
+        assert_ne!(s1.id, s2.id);
+        assert_eq!(registry.list().await.len(), 2);
+
+        let armed = registry
+            .set_cancel_on_disconnect(s1.id, true, Some(5_000))
+            .await
+            .unwrap();
+        assert!(armed.cancel_on_disconnect);
+        assert_eq!(armed.heartbeat_timeout(), Some(Duration::from_secs(5)));
+
+        let disarmed = registry
+            .set_cancel_on_disconnect(s1.id, false, Some(5_000))
+            .await
+            .unwrap();
+        assert_eq!(disarmed.heartbeat_timeout(), None);
+
+        assert_eq!(registry.close(s1.id).await, Some(disarmed));
+        assert!(registry.close(s1.id).await.is_none());
+        assert_eq!(registry.list().await, vec![s2]);
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consensus::Consensus,
+    matchingengine::MatchingEngine,
+    order::{ParticipantId, Timestamp, Trade, TradeId},
+    wallet::Asset,
+};
+
+pub const SETTLEMENT_PERIOD_SECS_ENV: &str = "OME_SETTLEMENT_PERIOD_SECS";
+
+const DEFAULT_SETTLEMENT_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_SETTLED_BATCHES: usize = 100; // oldest settled batches are dropped past this many
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    /// Still collecting trades
+    Open,
+    /// Cut off, obligations waiting to be settled
+    Pending,
+    Settled,
+}
+
+/// Net amount an account receives (positive) or owes (negative) in one asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettlementInstruction {
+    pub account: ParticipantId,
+    pub asset: Asset,
+    pub amount: i128,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatch {
+    pub id: u64,
+    pub status: BatchStatus,
+    /// Unix timestamps (ms)
+    pub opened_at: Timestamp,
+    pub closed_at: Option<Timestamp>,
+    pub settled_at: Option<Timestamp>,
+    pub trade_ids: Vec<TradeId>,
+    pub instructions: Vec<SettlementInstruction>,
+}
+
+impl SettlementBatch {
+    fn new(id: u64, now: Timestamp) -> Self {
+        SettlementBatch {
+            id,
+            status: BatchStatus::Open,
+            opened_at: now,
+            closed_at: None,
+            settled_at: None,
+            trade_ids: Vec::new(),
+            instructions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementError {
+    NotFound,
+    NotPending,
+}
+
+/// Nets executed trades into per-account debits/credits, one batch per settlement period
+#[derive(Debug)]
+pub struct Settlement {
+    open: SettlementBatch,
+    net: BTreeMap<(ParticipantId, Asset), i128>, // running totals of the open batch
+    closed: VecDeque<SettlementBatch>,
+}
+
+impl Settlement {
+    pub fn new(now: Timestamp) -> Self {
+        Settlement {
+            open: SettlementBatch::new(1, now),
+            net: BTreeMap::new(),
+            closed: VecDeque::new(),
+        }
+    }
+
+    /// Adds `trade` to the open batch; `sign` of `-1` reverses a busted or corrected trade
+    pub fn record(&mut self, trade: &Trade, sign: i128) {
+        let cost = trade.price as i128 * trade.quantity as i128 * sign;
+        let quantity = trade.quantity as i128 * sign;
+        let mut post = |account: &Option<ParticipantId>, asset: Asset, amount: i128| {
+            if let Some(account) = account {
+                *self.net.entry((account.clone(), asset)).or_default() += amount;
+            }
+        };
+        post(&trade.buyer, Asset::Base, quantity);
+        post(&trade.buyer, Asset::Quote, -cost);
+        post(&trade.seller, Asset::Base, -quantity);
+        post(&trade.seller, Asset::Quote, cost);
+
+        if trade.buyer.is_some() || trade.seller.is_some() {
+            self.open.trade_ids.push(trade.id);
+        }
+    }
+
+    /// Cuts off the open batch (if it has any trades) and starts a new one
+    pub fn close_batch(&mut self, now: Timestamp) -> Option<SettlementBatch> {
+        if self.open.trade_ids.is_empty() {
+            return None;
+        }
+        let next = SettlementBatch::new(self.open.id + 1, now);
+        let mut batch = std::mem::replace(&mut self.open, next);
+        batch.status = BatchStatus::Pending;
+        batch.closed_at = Some(now);
+        batch.instructions = std::mem::take(&mut self.net)
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0)
+            .map(|((account, asset), amount)| SettlementInstruction {
+                account,
+                asset,
+                amount,
+            })
+            .collect();
+
+        self.closed.push_back(batch.clone());
+        while self.closed.len() > MAX_SETTLED_BATCHES
+            && self.closed.front().map(|b| b.status) == Some(BatchStatus::Settled)
+        {
+            self.closed.pop_front();
+        }
+        Some(batch)
+    }
+
+    /// Marks a pending batch as settled, i.e. its obligations were paid out
+    pub fn settle(&mut self, id: u64, now: Timestamp) -> Result<SettlementBatch, SettlementError> {
+        let batch = self
+            .closed
+            .iter_mut()
+            .find(|b| b.id == id)
+            .ok_or(SettlementError::NotFound)?;
+        if batch.status != BatchStatus::Pending {
+            return Err(SettlementError::NotPending);
+        }
+        batch.status = BatchStatus::Settled;
+        batch.settled_at = Some(now);
+        Ok(batch.clone())
+    }
+
+    /// Closed batches, oldest first, optionally only those in `status`
+    pub fn batches(&self, status: Option<BatchStatus>) -> Vec<SettlementBatch> {
+        self.closed
+            .iter()
+            .filter(|b| status.is_none_or(|s| b.status == s))
+            .cloned()
+            .collect()
+    }
+
+    /// `account`'s instructions per closed batch
+    pub fn obligations(&self, account: &str) -> Vec<(u64, BatchStatus, SettlementInstruction)> {
+        self.closed
+            .iter()
+            .flat_map(|b| {
+                b.instructions
+                    .iter()
+                    .filter(|i| i.account == account)
+                    .map(|i| (b.id, b.status, i.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Unix timestamp (ms)
+pub fn now_ms() -> Timestamp {
+    chrono::Utc::now().timestamp_millis().max(0) as Timestamp
+}
+
+pub fn period_from_env() -> Duration {
+    std::env::var(SETTLEMENT_PERIOD_SECS_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_SETTLEMENT_PERIOD)
+}
+
+/// Closes a settlement batch every `period`; in consensus mode the leader commits the close to
+/// the log and the other nodes leave it to the leader
+pub async fn run_settlement_cycle(
+    engine: MatchingEngine,
+    period: Duration,
+    consensus: Option<Consensus>,
+) {
+    let mut tick = tokio::time::interval(period);
+    tick.tick().await; // the first tick fires immediately
+    loop {
+        tick.tick().await;
+        let closed = match &consensus {
+            Some(consensus) if !consensus.is_leader() => continue,
+            Some(consensus) => consensus.close_settlement_batch().await,
+            None => Ok(engine.close_settlement_batch().await),
+        };
+        let batch = match closed {
+            Ok(batch) => batch,
+            Err(e) => {
+                println!("Settlement batch not closed: {}", e.message);
+                continue;
+            }
+        };
+        if let Some(batch) = batch {
+            println!(
+                "Settlement batch {} closed ({} trades, {} instructions)",
+                batch.id,
+                batch.trade_ids.len(),
+                batch.instructions.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trade(id: TradeId, buyer: &str, seller: &str, price: u64, quantity: u64) -> Trade {
+        Trade::new(id, String::from("b"), String::from("s"), price, quantity)
+            .with_parties(Some(buyer.to_string()), Some(seller.to_string()))
+    }
+
+    #[test]
+    fn test_netting() {
+        let mut settlement = Settlement::new(0);
+        settlement.record(&trade(1, "alice", "bob", 100, 10), 1);
+        settlement.record(&trade(2, "bob", "alice", 110, 4), 1);
+
+        let batch = settlement.close_batch(1).unwrap();
+        assert_eq!(batch.status, BatchStatus::Pending);
+        assert_eq!(batch.trade_ids, vec![1, 2]);
+        let alice: Vec<_> = batch
+            .instructions
+            .iter()
+            .filter(|i| i.account == "alice")
+            .map(|i| (i.asset, i.amount))
+            .collect();
+        assert_eq!(alice, vec![(Asset::Base, 6), (Asset::Quote, -560)]);
+
+        // nothing traded since, no empty batch
+        assert!(settlement.close_batch(2).is_none());
+    }
+
+    #[test]
+    fn test_settle() {
+        let mut settlement = Settlement::new(0);
+        settlement.record(&trade(1, "alice", "bob", 100, 1), 1);
+        let id = settlement.close_batch(1).unwrap().id;
+
+        assert_eq!(settlement.batches(Some(BatchStatus::Pending)).len(), 1);
+        assert!(settlement.settle(id, 2).is_ok());
+        assert_eq!(
+            settlement.settle(id, 3).unwrap_err(),
+            SettlementError::NotPending
+        );
+        assert_eq!(
+            settlement.settle(42, 3).unwrap_err(),
+            SettlementError::NotFound
+        );
+        assert_eq!(settlement.obligations("bob")[0].1, BatchStatus::Settled);
+    }
+
+    #[test]
+    fn test_reversal_nets_out() {
+        let mut settlement = Settlement::new(0);
+        let t = trade(1, "alice", "bob", 100, 10);
+        settlement.record(&t, 1);
+        settlement.record(&t, -1);
+        let batch = settlement.close_batch(1).unwrap();
+        assert!(batch.instructions.is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::Mutex;
+
+use crate::error::{ApiError, ErrorCode};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const HMAC_KEYS_ENV: &str = "OME_HMAC_KEYS"; // `key1:secret1,key2:secret2`
+
+pub const API_KEY_HEADER: &str = "x-ome-api-key";
+pub const TIMESTAMP_HEADER: &str = "x-ome-timestamp"; // unix millis
+pub const NONCE_HEADER: &str = "x-ome-nonce";
+pub const SIGNATURE_HEADER: &str = "x-ome-signature"; // hex encoded HMAC-SHA256
+
+const DEFAULT_WINDOW: Duration = Duration::from_secs(30);
+const MAX_SIGNED_BODY_BYTES: usize = 64 * 1024;
+
+/// Verifies exchange-style signed requests: `HMAC-SHA256(secret, timestamp + nonce + method + path + body)`.
+/// A request is accepted once; its nonce is remembered for as long as its timestamp is within the window.
+#[derive(Clone)]
+pub struct Signing {
+    secrets: Arc<HashMap<String, Vec<u8>>>, // empty disables signing
+    window: Duration,
+    seen_nonces: Arc<Mutex<HashMap<(String, String), u64>>>, // (api key, nonce) -> timestamp
+}
+
+impl Signing {
+    pub fn new(secrets: HashMap<String, Vec<u8>>) -> Self {
+        Signing {
+            secrets: Arc::new(secrets),
+            window: DEFAULT_WINDOW,
+            seen_nonces: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let secrets = std::env::var(HMAC_KEYS_ENV)
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|pair| pair.split_once(':'))
+            .map(|(key, secret)| (key.trim().to_string(), secret.trim().as_bytes().to_vec()))
+            .collect();
+        Signing::new(secrets)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.secrets.is_empty()
+    }
+
+    pub async fn verify(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+        now_millis: u64,
+    ) -> Result<(), ApiError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| {
+                    ApiError::new(
+                        ErrorCode::InvalidSignature,
+                        format!("missing {name} header"),
+                    )
+                })
+        };
+        let api_key = header(API_KEY_HEADER)?;
+        let nonce = header(NONCE_HEADER)?;
+        let signature = header(SIGNATURE_HEADER)?;
+        let timestamp: u64 = header(TIMESTAMP_HEADER)?.parse().map_err(|_| {
+            ApiError::new(ErrorCode::InvalidSignature, "timestamp must be unix millis")
+        })?;
+
+        let window = self.window.as_millis() as u64;
+        if now_millis.abs_diff(timestamp) > window {
+            return Err(ApiError::new(
+                ErrorCode::InvalidSignature,
+                "timestamp outside of the accepted window",
+            ));
+        }
+
+        let secret = self
+            .secrets
+            .get(api_key)
+            .ok_or_else(|| ApiError::new(ErrorCode::InvalidSignature, "unknown api key"))?;
+        let signature = hex::decode(signature)
+            .map_err(|_| ApiError::new(ErrorCode::InvalidSignature, "signature must be hex"))?;
+
+        payload_mac(secret, timestamp, nonce, method, path, body)
+            .verify_slice(&signature)
+            .map_err(|_| ApiError::new(ErrorCode::InvalidSignature, "signature mismatch"))?;
+
+        // only verified requests may occupy the nonce store
+        let mut seen = self.seen_nonces.lock().await;
+        seen.retain(|_, ts| now_millis.abs_diff(*ts) <= window);
+        if seen
+            .insert((api_key.to_string(), nonce.to_string()), timestamp)
+            .is_some()
+        {
+            return Err(ApiError::new(
+                ErrorCode::InvalidSignature,
+                "nonce already used",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn payload_mac(
+    secret: &[u8],
+    timestamp: u64,
+    nonce: &str,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(nonce.as_bytes());
+    mac.update(method.as_bytes());
+    mac.update(path.as_bytes());
+    mac.update(body);
+    mac
+}
+
+/// The API key a request's signature was verified against, inserted into the request
+/// extensions by [`require_signature`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedApiKey(pub String);
+
+pub async fn require_signature(
+    State(signing): State<Signing>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !signing.is_enabled() {
+        return Ok(next.run(req).await);
+    }
+
+    let (mut parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_SIGNED_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::new(ErrorCode::MalformedJson, "request body too large"))?;
+    let now = Utc::now().timestamp_millis() as u64;
+    signing
+        .verify(
+            parts.method.as_str(),
+            parts.uri.path(),
+            &parts.headers,
+            &bytes,
+            now,
+        )
+        .await?;
+    if let Some(key) = parts
+        .headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+    {
+        let key = VerifiedApiKey(key.to_string());
+        parts.extensions.insert(key);
+    }
+
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECRET: &[u8] = b"s3cret";
+
+    fn signing() -> Signing {
+        Signing::new(HashMap::from([(String::from("k1"), SECRET.to_vec())]))
+    }
+
+    fn signed_headers(timestamp: u64, nonce: &str, body: &[u8]) -> HeaderMap {
+        let sig = hex::encode(
+            payload_mac(SECRET, timestamp, nonce, "POST", "/orders", body)
+                .finalize()
+                .into_bytes(),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, "k1".parse().unwrap());
+        headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers.insert(NONCE_HEADER, nonce.parse().unwrap());
+        headers.insert(SIGNATURE_HEADER, sig.parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_valid_signature() {
+        let s = signing();
+        let body = br#"{"side":"Buy"}"#;
+        let headers = signed_headers(1_000, "n1", body);
+        assert!(
+            s.verify("POST", "/orders", &headers, body, 1_500)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tampered_body_rejected() {
+        let s = signing();
+        let headers = signed_headers(1_000, "n1", br#"{"quantity":1}"#);
+        let err = s
+            .verify("POST", "/orders", &headers, br#"{"quantity":1000}"#, 1_000)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidSignature);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rejected() {
+        let s = signing();
+        let headers = signed_headers(1_000, "n1", b"");
+        assert!(
+            s.verify("POST", "/orders", &headers, b"", 1_000)
+                .await
+                .is_ok()
+        );
+        assert!(
+            s.verify("POST", "/orders", &headers, b"", 1_001)
+                .await
+                .is_err()
+        );
+
+        // same request outside of the window is stale rather than replayed
+        let stale = 1_000 + DEFAULT_WINDOW.as_millis() as u64 + 1;
+        assert!(
+            s.verify("POST", "/orders", &headers, b"", stale)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    matchingengine::TradingState,
+    order::{Order, OrderId, OrderType, ParticipantId, Price, Side, Trade, TradeId},
+    positions::PositionBook,
+    rfq::Rfq,
+    schedule::MarketPhase,
+    scheduledorders::ScheduledOrder,
+    tradespill,
+    validation::FieldError,
+    wallet::Wallets,
+};
+
+/// Bumped whenever the layout of [`EngineState`] changes; imports of other versions are refused
+pub const STATE_VERSION: u32 = 1;
+
+/// Everything needed to bring up an engine where another one stood: the book, open orders,
+/// sequence counters, balances and positions. Fee totals, settlement batches and the audit
+/// entries themselves are not part of it; the restored audit chain continues from `last_seq`
+/// and `audit_head`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineState {
+    pub version: u32,
+    pub trading_state: TradingState,
+    pub market_phase: MarketPhase,
+    /// Resting orders of each side in priority order, best first
+    pub bids: Vec<Order>,
+    pub asks: Vec<Order>,
+    /// Accepted outside continuous trading, waiting for the open
+    pub queued_orders: Vec<Order>,
+    /// Waiting for their activation time, earliest first
+    #[serde(default)]
+    pub scheduled_orders: Vec<ScheduledOrder>,
+    /// Waiting for their trigger, in arrival order
+    #[serde(default)]
+    pub untriggered_orders: Vec<Order>,
+    /// Orders of each market maker's latest quote
+    #[serde(default)]
+    pub quotes: BTreeMap<ParticipantId, Vec<OrderId>>,
+    /// RFQs open for responses
+    #[serde(default)]
+    pub rfqs: Vec<Rfq>,
+    /// The recent trade pool, oldest first
+    pub trades: Vec<Trade>,
+    /// The trades spilled before the pool, left in the spill file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spilled_trades: Option<SpilledTrades>,
+    pub next_trade_id: TradeId,
+    /// Sequence number and hash of the latest audit entry
+    pub last_seq: u64,
+    pub audit_head: String,
+    pub blocked_participants: Vec<ParticipantId>,
+    pub reference_price: Option<Price>,
+    pub positions: PositionBook,
+    pub wallets: Wallets,
+}
+
+/// Trades evicted to the spill file when the state was taken, by reference rather than
+/// inlined, so that the document stays importable however long the history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpilledTrades {
+    /// Spill file of the exporting engine
+    pub path: PathBuf,
+    /// Bytes of it holding the spilled trades; it only grows past them until compacted
+    pub len: u64,
+    pub trades: usize,
+}
+
+impl EngineState {
+    /// Reads the referenced spilled trades back in front of the pool; refused unless they're in
+    /// `own_spill`, the importing engine's spill file, and when it no longer holds them (cleared
+    /// or compacted since the export). Any other path is never opened.
+    pub async fn load_spilled_trades(
+        &mut self,
+        own_spill: Option<&Path>,
+    ) -> Result<(), FieldError> {
+        let Some(spilled) = self.spilled_trades.take() else {
+            return Ok(());
+        };
+        if own_spill != Some(spilled.path.as_path()) {
+            return Err(FieldError::new(
+                "spilled_trades",
+                "only trades spilled by this engine can be referenced, include them in trades",
+            ));
+        }
+        let (path, len) = (spilled.path.clone(), spilled.len);
+        let mut history = tokio::task::spawn_blocking(move || tradespill::read_spilled(&path, len))
+            .await
+            .expect("spill read panicked")
+            .map_err(|e| {
+                FieldError::new(
+                    "spilled_trades",
+                    format!("{} unreadable: {e}", spilled.path.display()),
+                )
+            })?;
+        if history.len() != spilled.trades {
+            return Err(FieldError::new(
+                "spilled_trades",
+                format!(
+                    "{} no longer holds the {} exported trades",
+                    spilled.path.display(),
+                    spilled.trades
+                ),
+            ));
+        }
+        history.append(&mut self.trades);
+        self.trades = history;
+        Ok(())
+    }
+
+    /// Checks that the state describes a book the engine could have built itself
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.version != STATE_VERSION {
+            errors.push(FieldError::new(
+                "version",
+                format!("expected version {STATE_VERSION}, got {}", self.version),
+            ));
+        }
+
+        let mut ids = HashSet::new();
+        let scheduled: Vec<Order> = self
+            .scheduled_orders
+            .iter()
+            .map(|s| s.order.clone())
+            .collect();
+        let sides = [
+            ("bids", &self.bids, Some(Side::Buy)),
+            ("asks", &self.asks, Some(Side::Sell)),
+            ("queued_orders", &self.queued_orders, None),
+            ("scheduled_orders", &scheduled, None),
+            ("untriggered_orders", &self.untriggered_orders, None),
+        ];
+        for (field, orders, side) in sides {
+            for order in orders {
+                if !ids.insert(&order.id) {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("order id {} appears more than once", order.id),
+                    ));
+                }
+                if order.quantity == 0 {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("order {} has nothing left to fill", order.id),
+                    ));
+                }
+                if side.is_some_and(|side| side != order.side) {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("order {} is on the wrong side", order.id),
+                    ));
+                }
+                if side.is_some() && order.order_type != OrderType::Limit {
+                    errors.push(FieldError::new(
+                        field,
+                        format!("order {} is not a limit order", order.id),
+                    ));
+                }
+            }
+        }
+
+        // the book only rests orders that didn't match, and an import doesn't match them again
+        let best_bid = self.bids.iter().map(|o| o.price).max();
+        let best_ask = self.asks.iter().map(|o| o.price).min();
+        if let (Some(bid), Some(ask)) = (best_bid, best_ask)
+            && bid >= ask
+        {
+            errors.push(FieldError::new(
+                "asks",
+                format!("the book is crossed, best bid {bid} is at or above best ask {ask}"),
+            ));
+        }
+
+        if let Some(max_id) = self.trades.iter().map(|t| t.id).max()
+            && self.next_trade_id <= max_id
+        {
+            errors.push(FieldError::new(
+                "next_trade_id",
+                format!("must be greater than the highest trade id {max_id}"),
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::GENESIS_HASH;
+
+    fn state() -> EngineState {
+        EngineState {
+            version: STATE_VERSION,
+            trading_state: TradingState::Open,
+            market_phase: MarketPhase::Continuous,
+            bids: vec![Order::new(
+                "1".to_string(),
+                Side::Buy,
+                OrderType::Limit,
+                10,
+                99,
+                1,
+            )],
+            asks: vec![Order::new(
+                "2".to_string(),
+                Side::Sell,
+                OrderType::Limit,
+                10,
+                101,
+                2,
+            )],
+            queued_orders: Vec::new(),
+            scheduled_orders: Vec::new(),
+            untriggered_orders: Vec::new(),
+            quotes: BTreeMap::new(),
+            rfqs: Vec::new(),
+            trades: vec![Trade::new(1, "3".to_string(), "4".to_string(), 100, 5)],
+            spilled_trades: None,
+            next_trade_id: 2,
+            last_seq: 0,
+            audit_head: GENESIS_HASH.to_string(),
+            blocked_participants: Vec::new(),
+            reference_price: None,
+            positions: PositionBook::default(),
+            wallets: Wallets::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(state().validate(), Ok(()));
+
+        let mut bad = state();
+        bad.version = STATE_VERSION + 1;
+        bad.asks[0].side = Side::Buy;
+        bad.queued_orders.push(bad.bids[0].clone());
+        bad.next_trade_id = 1;
+        let fields: Vec<&str> = bad
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            ["version", "asks", "queued_orders", "next_trade_id"]
+        );
+
+        let mut crossed = state();
+        crossed.asks[0].price = 99;
+        let errors = crossed.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "asks");
+    }
+}
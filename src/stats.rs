@@ -0,0 +1,215 @@
+use std::{mem::size_of, sync::Arc, time::Duration};
+
+use axum::{Extension, Json, Router, extract::State, routing::get};
+use serde::Serialize;
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, Price, Quantity, Side, Trade},
+    orderbook::SideStats,
+};
+
+/// What the engine holds and has done since it started, from counters it keeps as it goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EngineStats {
+    pub bids: SideStats,
+    pub asks: SideStats,
+    pub orders: OrderCounts,
+    /// Trades since the first, block trades included; busts don't take them back
+    pub trades_executed: u64,
+    pub latency: LatencySummary,
+    pub uptime_secs: u64,
+    pub memory: MemoryEstimate,
+}
+
+/// Orders the engine answered since it started, counted from its events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct OrderCounts {
+    /// Passed the checks; an order waiting for its trigger counts once it fires
+    pub accepted: u64,
+    pub rejected: u64,
+    /// Canceled or expired
+    pub canceled: u64,
+}
+
+impl OrderCounts {
+    pub fn count(&mut self, event: &AuditEvent) {
+        match event {
+            AuditEvent::OrderAccepted { order } if order.trigger.is_none() => self.accepted += 1,
+            AuditEvent::OrderRejected { .. } => self.rejected += 1,
+            AuditEvent::OrderCanceled { .. } | AuditEvent::OrderExpired { .. } => {
+                self.canceled += 1
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How long the engine took to answer orders, from taking the order with the book locked to
+/// its result, in power-of-two buckets of nanoseconds
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u64; 64], // bucket `i` counts latencies below 2^i ns
+    samples: u64,
+    total_ns: u128,
+    max_ns: u64,
+}
+
+/// Latencies in nanoseconds; percentiles are the upper bound of their bucket, so within a
+/// factor of two
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LatencySummary {
+    pub samples: u64,
+    pub mean_ns: u64,
+    pub p50_ns: u64,
+    pub p99_ns: u64,
+    pub max_ns: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram {
+            buckets: [0; 64],
+            samples: 0,
+            total_ns: 0,
+            max_ns: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let ns = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - ns.leading_zeros()).min(63) as usize;
+        self.buckets[bucket] += 1;
+        self.samples += 1;
+        self.total_ns += u128::from(ns);
+        self.max_ns = self.max_ns.max(ns);
+    }
+
+    pub fn summary(&self) -> LatencySummary {
+        if self.samples == 0 {
+            return LatencySummary::default();
+        }
+        LatencySummary {
+            samples: self.samples,
+            mean_ns: (self.total_ns / u128::from(self.samples)) as u64,
+            p50_ns: self.percentile(50),
+            p99_ns: self.percentile(99),
+            max_ns: self.max_ns,
+        }
+    }
+
+    fn percentile(&self, p: u64) -> u64 {
+        let rank = (self.samples * p).div_ceil(100);
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return (1u64 << i).saturating_sub(1).min(self.max_ns);
+            }
+        }
+        self.max_ns
+    }
+}
+
+/// The stats of one symbol's engine
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolStats {
+    pub symbol: String,
+    #[serde(flatten)]
+    pub stats: EngineStats,
+}
+
+/// The symbol an exchange's routes serve
+#[derive(Debug, Clone)]
+pub struct Symbol(pub String);
+
+/// `GET /stats` of one exchange
+pub async fn get_stats(
+    State(engine): State<MatchingEngine>,
+    Extension(Symbol(symbol)): Extension<Symbol>,
+) -> Json<SymbolStats> {
+    Json(SymbolStats {
+        symbol,
+        stats: engine.stats().await,
+    })
+}
+
+/// `/symbols/stats` compares the engines of every symbol the server runs
+pub fn routes(engines: Vec<(String, MatchingEngine)>) -> Router {
+    Router::new()
+        .route("/symbols/stats", get(get_symbol_stats))
+        .with_state(Arc::new(engines))
+}
+
+/// The busiest symbols first, by orders accepted
+async fn get_symbol_stats(
+    State(engines): State<Arc<Vec<(String, MatchingEngine)>>>,
+) -> Json<Vec<SymbolStats>> {
+    let mut stats = Vec::with_capacity(engines.len());
+    for (symbol, engine) in engines.iter() {
+        stats.push(SymbolStats {
+            symbol: symbol.clone(),
+            stats: engine.stats().await,
+        });
+    }
+    stats.sort_by(|a, b| {
+        b.stats
+            .orders
+            .accepted
+            .cmp(&a.stats.orders.accepted)
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+    Json(stats)
+}
+
+/// Rough size of the engine's in-memory collections in bytes: their entries times the size of
+/// an entry, leaving out what ids, owners and other heap data of the entries take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryEstimate {
+    pub book_bytes: usize,
+    pub trade_pool_bytes: usize,
+    pub audit_log_bytes: usize,
+    pub total_bytes: usize,
+}
+
+impl MemoryEstimate {
+    pub fn of(resting_orders: usize, trades: usize, audit_entries: usize) -> Self {
+        // every resting order is also in the book's index
+        let index_entry = size_of::<(OrderId, (Quantity, Price, Side))>();
+        let book_bytes = resting_orders * (size_of::<Order>() + index_entry);
+        let trade_pool_bytes = trades * size_of::<Trade>();
+        let audit_log_bytes = audit_entries * size_of::<AuditEntry>();
+        MemoryEstimate {
+            book_bytes,
+            trade_pool_bytes,
+            audit_log_bytes,
+            total_bytes: book_bytes + trade_pool_bytes + audit_log_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!(histogram.summary(), LatencySummary::default());
+        for us in 1..=100 {
+            histogram.record(Duration::from_micros(us));
+        }
+        let summary = histogram.summary();
+        assert_eq!(summary.samples, 100);
+        assert_eq!(summary.mean_ns, 50_500);
+        assert_eq!(summary.max_ns, 100_000);
+        // 50us falls in [32768, 65536) ns
+        assert_eq!(summary.p50_ns, 65_535);
+        assert_eq!(summary.p99_ns, 100_000);
+        histogram.record(Duration::ZERO);
+        assert_eq!(histogram.percentile(0), 0);
+    }
+}
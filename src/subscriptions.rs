@@ -0,0 +1,279 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    audit::AuditEntry,
+    bookview::{BookSnapshot, PriceLevel},
+    marketdata::TradePrint,
+    order::{Price, Quantity, TradeType},
+};
+
+pub const DEFAULT_BOOK_DEPTH: usize = 10;
+pub const MAX_BOOK_DEPTH: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    /// The best `depth` levels of each side
+    Book,
+    /// Executions, without participants
+    Trades,
+    /// Best bid and ask and the last price
+    Ticker,
+    /// The session's own execution reports
+    Orders,
+}
+
+/// What a channel sends when it's due, all of one symbol
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+pub enum ChannelUpdate {
+    Book {
+        symbol: String,
+        bids: Vec<PriceLevel>,
+        asks: Vec<PriceLevel>,
+    },
+    /// Executions since the last update, oldest first
+    Trades {
+        symbol: String,
+        trades: Vec<TradePrint>,
+    },
+    Ticker {
+        symbol: String,
+        best_bid: Option<Price>,
+        bid_size: Quantity,
+        best_ask: Option<Price>,
+        ask_size: Quantity,
+        last_price: Option<Price>,
+    },
+}
+
+#[derive(Debug)]
+struct Subscription {
+    depth: usize,
+    /// Least time between two updates; changes in between are sent together
+    interval: Duration,
+    /// Earliest the next update may go out
+    next_at: Instant,
+    pending: bool,
+    /// Book and ticker updates identical to the last one aren't sent again
+    last: Option<ChannelUpdate>,
+}
+
+impl Subscription {
+    fn due_at(&self) -> Option<Instant> {
+        self.pending.then_some(self.next_at)
+    }
+}
+
+/// The channels a session subscribed to on its exchange's symbol and what each is due to send
+#[derive(Debug)]
+pub struct Subscriptions {
+    symbol: String,
+    channels: HashMap<Channel, Subscription>,
+    /// Executions for the trades channel since its last update
+    trades: Vec<TradePrint>,
+    last_price: Option<Price>,
+}
+
+impl Subscriptions {
+    /// Subscribed to the session's own orders from the start, so orders entered on it get
+    /// their executions back
+    pub fn new(symbol: String, last_price: Option<Price>) -> Self {
+        let mut subscriptions = Subscriptions {
+            symbol,
+            channels: HashMap::new(),
+            trades: Vec::new(),
+            last_price,
+        };
+        subscriptions.subscribe(Channel::Orders, None, Duration::ZERO);
+        subscriptions
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn is_subscribed(&self, channel: Channel) -> bool {
+        self.channels.contains_key(&channel)
+    }
+
+    /// Subscribes to `channel`, or changes the depth and interval of the subscription; the
+    /// first update goes out right away. Returns the depth used.
+    pub fn subscribe(
+        &mut self,
+        channel: Channel,
+        depth: Option<usize>,
+        interval: Duration,
+    ) -> usize {
+        let depth = depth.unwrap_or(DEFAULT_BOOK_DEPTH).clamp(1, MAX_BOOK_DEPTH);
+        if channel == Channel::Trades && !self.is_subscribed(channel) {
+            self.trades.clear();
+        }
+        self.channels.insert(
+            channel,
+            Subscription {
+                depth,
+                interval,
+                next_at: Instant::now(),
+                pending: matches!(channel, Channel::Book | Channel::Ticker),
+                last: None,
+            },
+        );
+        depth
+    }
+
+    /// `false` when the session wasn't subscribed
+    pub fn unsubscribe(&mut self, channel: Channel) -> bool {
+        self.channels.remove(&channel).is_some()
+    }
+
+    /// Notes what `entry` changed for the market data channels
+    pub fn observe(&mut self, entry: &AuditEntry) {
+        if let Some(trade) = TradePrint::from_entry(entry) {
+            if trade.trade_type == TradeType::Regular {
+                self.last_price = Some(trade.price);
+            }
+            if let Some(sub) = self.channels.get_mut(&Channel::Trades) {
+                sub.pending = true;
+                self.trades.push(trade);
+            }
+        }
+        // book and ticker are compared with what they last sent before going out
+        for channel in [Channel::Book, Channel::Ticker] {
+            if let Some(sub) = self.channels.get_mut(&channel) {
+                sub.pending = true;
+            }
+        }
+    }
+
+    /// When the next update is due, `None` when nothing is pending
+    pub fn next_due(&self) -> Option<Instant> {
+        self.channels
+            .values()
+            .filter_map(Subscription::due_at)
+            .min()
+    }
+
+    /// Whether an update due at `now` needs the book
+    pub fn needs_book(&self, now: Instant) -> bool {
+        [Channel::Book, Channel::Ticker].iter().any(|channel| {
+            self.channels
+                .get(channel)
+                .and_then(Subscription::due_at)
+                .is_some_and(|due| due <= now)
+        })
+    }
+
+    /// Updates of the channels due at `now`, with `book` for book and ticker
+    pub fn due(&mut self, now: Instant, book: Option<&BookSnapshot>) -> Vec<ChannelUpdate> {
+        let mut updates = Vec::new();
+        for channel in [Channel::Book, Channel::Ticker, Channel::Trades] {
+            let Some(sub) = self.channels.get_mut(&channel) else {
+                continue;
+            };
+            if sub.due_at().is_none_or(|due| due > now) {
+                continue;
+            }
+            let update = match channel {
+                Channel::Book => {
+                    let Some(book) = book else { continue };
+                    let top =
+                        |levels: &[PriceLevel]| levels.iter().take(sub.depth).copied().collect();
+                    ChannelUpdate::Book {
+                        symbol: self.symbol.clone(),
+                        bids: top(&book.bids),
+                        asks: top(&book.asks),
+                    }
+                }
+                Channel::Ticker => {
+                    let Some(book) = book else { continue };
+                    ChannelUpdate::Ticker {
+                        symbol: self.symbol.clone(),
+                        best_bid: book.bids.first().map(|l| l.price),
+                        bid_size: book.bids.first().map_or(0, |l| l.quantity),
+                        best_ask: book.asks.first().map(|l| l.price),
+                        ask_size: book.asks.first().map_or(0, |l| l.quantity),
+                        last_price: self.last_price,
+                    }
+                }
+                Channel::Trades => ChannelUpdate::Trades {
+                    symbol: self.symbol.clone(),
+                    trades: std::mem::take(&mut self.trades),
+                },
+                Channel::Orders => continue,
+            };
+            sub.pending = false;
+            if channel != Channel::Trades && sub.last.as_ref() == Some(&update) {
+                continue; // nothing the subscriber hasn't seen
+            }
+            sub.next_at = now + sub.interval;
+            sub.last = (channel != Channel::Trades).then(|| update.clone());
+            updates.push(update);
+        }
+        updates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        audit::{AuditEvent, AuditLog},
+        order::Trade,
+    };
+
+    fn level(price: Price, quantity: Quantity) -> PriceLevel {
+        PriceLevel {
+            price,
+            quantity,
+            orders: 1,
+        }
+    }
+
+    #[test]
+    fn test_throttled_channels() {
+        let mut subscriptions = Subscriptions::new("root".to_string(), None);
+        subscriptions.subscribe(Channel::Book, Some(1), Duration::from_millis(100));
+        subscriptions.subscribe(Channel::Trades, None, Duration::from_millis(100));
+        let book = BookSnapshot {
+            bids: vec![level(100, 5), level(99, 1)],
+            asks: vec![level(101, 2)],
+            ..BookSnapshot::default()
+        };
+        let start = Instant::now();
+        assert!(subscriptions.needs_book(start));
+        let updates = subscriptions.due(start, Some(&book));
+        assert_eq!(
+            updates,
+            vec![ChannelUpdate::Book {
+                symbol: "root".to_string(),
+                bids: vec![level(100, 5)],
+                asks: vec![level(101, 2)],
+            }]
+        );
+
+        let mut log = AuditLog::default();
+        for id in 1..=2 {
+            let trade = Trade::new(id, "b".to_string(), "s".to_string(), 101, 1);
+            subscriptions.observe(log.append(AuditEvent::TradeExecuted { trade }, 0));
+        }
+        // the trades go out at once, the unchanged book not at all after its interval
+        let updates = subscriptions.due(start, Some(&book));
+        assert!(
+            matches!(&updates[..], [ChannelUpdate::Trades { trades, .. }] if trades.len() == 2)
+        );
+        assert_eq!(
+            subscriptions.next_due(),
+            Some(start + Duration::from_millis(100))
+        );
+        let later = start + Duration::from_millis(100);
+        assert!(subscriptions.due(later, Some(&book)).is_empty());
+        assert_eq!(subscriptions.next_due(), None);
+
+        assert!(subscriptions.unsubscribe(Channel::Orders));
+        assert!(!subscriptions.is_subscribed(Channel::Orders));
+    }
+}
@@ -0,0 +1,619 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use axum::{
+    Json, Router,
+    extract::{Query, State, rejection::QueryRejection},
+    middleware,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast::error::RecvError};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    auth::{self, Auth},
+    bookview::BookSnapshot,
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::{OrderId, OrderType, ParticipantId, Price, Quantity, Side, Timestamp, TradeId},
+};
+
+pub const SPOOF_MIN_QUANTITY_ENV: &str = "OME_SPOOF_MIN_QUANTITY"; // 0 disables the check
+pub const SPOOF_MIN_DISTANCE_BPS_ENV: &str = "OME_SPOOF_MIN_DISTANCE_BPS";
+pub const SPOOF_MAX_LIFETIME_MS_ENV: &str = "OME_SPOOF_MAX_LIFETIME_MS";
+pub const SPOOF_MIN_REPEATS_ENV: &str = "OME_SPOOF_MIN_REPEATS";
+pub const SPOOF_WINDOW_SECS_ENV: &str = "OME_SPOOF_WINDOW_SECS";
+pub const LINKED_ACCOUNTS_ENV: &str = "OME_LINKED_ACCOUNTS"; // `alice:alice-2,bob:bob-hedge`
+
+const MAX_ALERTS: usize = 10_000; // the oldest make room
+const MAX_ALERT_PAGE: usize = 1_000;
+
+/// What counts as spoofing: `min_repeats` orders of at least `min_quantity`, each placed at
+/// least `min_distance_bps` behind the touch of its side and canceled unfilled within
+/// `max_lifetime_ms`, by the same participant within `window_ms`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpoofingConfig {
+    /// `0` turns the check off
+    pub min_quantity: Quantity,
+    pub min_distance_bps: u64,
+    pub max_lifetime_ms: u64,
+    pub min_repeats: usize,
+    pub window_ms: u64,
+}
+
+impl Default for SpoofingConfig {
+    fn default() -> Self {
+        SpoofingConfig {
+            min_quantity: 1_000,
+            min_distance_bps: 50,
+            max_lifetime_ms: 5_000,
+            min_repeats: 3,
+            window_ms: 60_000,
+        }
+    }
+}
+
+impl SpoofingConfig {
+    pub fn from_env() -> Self {
+        let default = SpoofingConfig::default();
+        let var = |name| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        SpoofingConfig {
+            min_quantity: var(SPOOF_MIN_QUANTITY_ENV).unwrap_or(default.min_quantity),
+            min_distance_bps: var(SPOOF_MIN_DISTANCE_BPS_ENV).unwrap_or(default.min_distance_bps),
+            max_lifetime_ms: var(SPOOF_MAX_LIFETIME_MS_ENV).unwrap_or(default.max_lifetime_ms),
+            min_repeats: var(SPOOF_MIN_REPEATS_ENV)
+                .filter(|n| *n > 0)
+                .map_or(default.min_repeats, |n| n as usize),
+            window_ms: var(SPOOF_WINDOW_SECS_ENV).map_or(default.window_ms, |secs| secs * 1_000),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.min_quantity > 0
+    }
+}
+
+/// Groups of accounts with one beneficial owner, whose trades with each other are wash trades
+/// like those of an account with itself
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkedAccounts {
+    group_of: HashMap<ParticipantId, usize>,
+}
+
+impl LinkedAccounts {
+    /// Groups separated by `,`, the accounts of a group by `:`
+    pub fn parse(s: &str) -> Self {
+        let mut group_of = HashMap::new();
+        for (group, accounts) in s.split(',').enumerate() {
+            for account in accounts.split(':').map(str::trim).filter(|a| !a.is_empty()) {
+                group_of.insert(account.to_string(), group);
+            }
+        }
+        LinkedAccounts { group_of }
+    }
+
+    pub fn from_env() -> Self {
+        std::env::var(LINKED_ACCOUNTS_ENV)
+            .map_or_else(|_| LinkedAccounts::default(), |v| Self::parse(&v))
+    }
+
+    pub fn are_linked(&self, a: &str, b: &str) -> bool {
+        a == b
+            || self
+                .group_of
+                .get(a)
+                .is_some_and(|group| self.group_of.get(b) == Some(group))
+    }
+}
+
+/// What the surveillance task looks for
+#[derive(Debug, Clone, Default)]
+pub struct SurveillanceConfig {
+    pub spoofing: SpoofingConfig,
+    pub linked_accounts: LinkedAccounts,
+}
+
+impl SurveillanceConfig {
+    pub fn from_env() -> Self {
+        SurveillanceConfig {
+            spoofing: SpoofingConfig::from_env(),
+            linked_accounts: LinkedAccounts::from_env(),
+        }
+    }
+}
+
+/// The pattern an alert was raised for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "pattern", rename_all = "snake_case")]
+pub enum Pattern {
+    /// Large orders away from the touch, placed and canceled again and again
+    Spoofing {
+        side: Side,
+        /// The canceled orders, oldest first
+        orders: Vec<OrderId>,
+        /// Their quantity together
+        quantity: Quantity,
+    },
+    /// An execution between an account and itself or an account linked to it, which changes
+    /// no beneficial ownership
+    WashTrade {
+        trade_id: TradeId,
+        price: Price,
+        quantity: Quantity,
+        buyer: ParticipantId,
+        seller: ParticipantId,
+    },
+}
+
+/// The wash trade alert for `entry`, raised against the buyer. The engine matches an
+/// account's orders against each other, so these executions happen and are reported here.
+pub fn wash_trade(entry: &AuditEntry, linked: &LinkedAccounts) -> Option<(ParticipantId, Pattern)> {
+    let AuditEvent::TradeExecuted { trade } = &entry.event else {
+        return None;
+    };
+    let (buyer, seller) = (trade.buyer.clone()?, trade.seller.clone()?);
+    if !linked.are_linked(&buyer, &seller) {
+        return None;
+    }
+    Some((
+        buyer.clone(),
+        Pattern::WashTrade {
+            trade_id: trade.id,
+            price: trade.price,
+            quantity: trade.quantity,
+            buyer,
+            seller,
+        },
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Alert {
+    pub id: u64,
+    pub participant: ParticipantId,
+    /// Event that completed the pattern
+    pub seq: u64,
+    /// Unix ms of that event
+    pub detected_at: Timestamp,
+    #[serde(flatten)]
+    pub pattern: Pattern,
+}
+
+/// A large order away from the touch, until it's filled, canceled or too old to count
+#[derive(Debug, Clone)]
+struct Placement {
+    owner: ParticipantId,
+    side: Side,
+    quantity: Quantity,
+    at: Timestamp,
+}
+
+/// Follows order events for spoofing and layering. Time is the event timestamps, so a replay
+/// of the same events raises the same alerts.
+#[derive(Debug, Default)]
+pub struct SpoofingDetector {
+    config: SpoofingConfig,
+    placed: HashMap<OrderId, Placement>,
+    /// Placements by time, to forget the ones too old to count
+    placed_at: VecDeque<(Timestamp, OrderId)>,
+    /// Each participant's qualifying cancels within the window, oldest first
+    canceled: HashMap<ParticipantId, VecDeque<(OrderId, Placement)>>,
+}
+
+impl SpoofingDetector {
+    pub fn new(config: SpoofingConfig) -> Self {
+        SpoofingDetector {
+            config,
+            ..SpoofingDetector::default()
+        }
+    }
+
+    /// Whether [`Self::observe`] needs the book for `entry`
+    pub fn needs_book(&self, entry: &AuditEntry) -> bool {
+        matches!(&entry.event, AuditEvent::OrderAccepted { order }
+            if self.config.is_enabled()
+                && order.owner.is_some()
+                && order.order_type == OrderType::Limit
+                && order.trigger.is_none()
+                && order.quantity >= self.config.min_quantity)
+    }
+
+    /// Notes `entry`, `book` being the book as it stood after it; returns the participant and
+    /// pattern when the entry completed one
+    pub fn observe(
+        &mut self,
+        entry: &AuditEntry,
+        book: Option<&BookSnapshot>,
+    ) -> Option<(ParticipantId, Pattern)> {
+        if !self.config.is_enabled() {
+            return None;
+        }
+        self.forget(entry.timestamp);
+        match &entry.event {
+            AuditEvent::OrderAccepted { order } if self.needs_book(entry) => {
+                let touch = book.and_then(|book| match order.side {
+                    Side::Buy => book.bids.first(),
+                    Side::Sell => book.asks.first(),
+                })?;
+                if !self.is_away(order.side, order.price, touch.price) {
+                    return None;
+                }
+                self.placed.insert(
+                    order.id.clone(),
+                    Placement {
+                        owner: order.owner.clone()?,
+                        side: order.side,
+                        quantity: order.quantity,
+                        at: entry.timestamp,
+                    },
+                );
+                self.placed_at
+                    .push_back((entry.timestamp, order.id.clone()));
+                None
+            }
+            AuditEvent::TradeExecuted { trade } => {
+                // an order that trades was meant to
+                self.placed.remove(&trade.buy_order_id);
+                self.placed.remove(&trade.sell_order_id);
+                None
+            }
+            AuditEvent::OrderExpired { order } => {
+                self.placed.remove(&order.id);
+                None
+            }
+            AuditEvent::OrderCanceled { order } => {
+                let placement = self.placed.remove(&order.id)?;
+                self.canceled_in_time(order.id.clone(), placement, entry.timestamp)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a `side` order at `price` is far enough behind the `touch` of its side
+    fn is_away(&self, side: Side, price: Price, touch: Price) -> bool {
+        let distance = match side {
+            Side::Buy => touch.saturating_sub(price),
+            Side::Sell => price.saturating_sub(touch),
+        };
+        distance > 0
+            && distance as u128 * 10_000 >= self.config.min_distance_bps as u128 * touch as u128
+    }
+
+    fn canceled_in_time(
+        &mut self,
+        order_id: OrderId,
+        placement: Placement,
+        now: Timestamp,
+    ) -> Option<(ParticipantId, Pattern)> {
+        let owner = placement.owner.clone();
+        let canceled = self.canceled.entry(owner.clone()).or_default();
+        canceled.push_back((order_id, placement));
+        let since = now.saturating_sub(self.config.window_ms);
+        while canceled.front().is_some_and(|(_, p)| p.at < since) {
+            canceled.pop_front();
+        }
+        // repeats on the side being pushed; layering spreads them over several prices
+        let side = canceled.back().expect("just pushed").1.side;
+        let repeats = canceled.iter().filter(|(_, p)| p.side == side).count();
+        if repeats < self.config.min_repeats {
+            return None;
+        }
+        let (orders, rest): (VecDeque<_>, VecDeque<_>) =
+            canceled.drain(..).partition(|(_, p)| p.side == side);
+        *canceled = rest;
+        Some((
+            owner,
+            Pattern::Spoofing {
+                side,
+                quantity: orders.iter().map(|(_, p)| p.quantity).sum(),
+                orders: orders.into_iter().map(|(id, _)| id).collect(),
+            },
+        ))
+    }
+
+    /// Drops placements too old to count as of `now` and the cancels of participants quiet
+    /// for the whole window
+    fn forget(&mut self, now: Timestamp) {
+        let placed_since = now.saturating_sub(self.config.max_lifetime_ms);
+        while let Some((at, _)) = self.placed_at.front()
+            && *at < placed_since
+        {
+            let (_, id) = self.placed_at.pop_front().expect("front exists");
+            if self.placed.get(&id).is_some_and(|p| p.at < placed_since) {
+                self.placed.remove(&id);
+            }
+        }
+        let canceled_since = now.saturating_sub(self.config.window_ms);
+        self.canceled
+            .retain(|_, canceled| canceled.back().is_some_and(|(_, p)| p.at >= canceled_since));
+    }
+}
+
+#[derive(Debug, Default)]
+struct AlertLog {
+    next_id: u64,
+    alerts: VecDeque<Alert>,
+}
+
+/// Alerts raised on one exchange, shared by the detector task and the admin API
+#[derive(Debug, Clone, Default)]
+pub struct Surveillance {
+    log: Arc<RwLock<AlertLog>>,
+}
+
+impl Surveillance {
+    pub fn new() -> Self {
+        Surveillance::default()
+    }
+
+    pub async fn raise(&self, participant: ParticipantId, entry: &AuditEntry, pattern: Pattern) {
+        let mut log = self.log.write().await;
+        log.next_id += 1;
+        let alert = Alert {
+            id: log.next_id,
+            participant,
+            seq: entry.seq,
+            detected_at: entry.timestamp,
+            pattern,
+        };
+        if log.alerts.len() == MAX_ALERTS {
+            log.alerts.pop_front();
+        }
+        log.alerts.push_back(alert);
+    }
+
+    /// Alerts after alert `after`, oldest first, of `participant` when given
+    pub async fn alerts(&self, after: u64, participant: Option<&str>, limit: usize) -> Vec<Alert> {
+        self.log
+            .read()
+            .await
+            .alerts
+            .iter()
+            .filter(|a| a.id > after && participant.is_none_or(|p| a.participant == p))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Watches the events of `engine` as long as the server runs, raising alerts in
+/// `surveillance`; falling behind the bus, it catches up from the audit log
+pub async fn run_surveillance(
+    engine: MatchingEngine,
+    surveillance: Surveillance,
+    config: SurveillanceConfig,
+) {
+    let mut events = engine.subscribe_events();
+    let mut next_seq = engine.last_seq().await + 1;
+    let mut spoofing = SpoofingDetector::new(config.spoofing);
+    loop {
+        let entries = match events.recv().await {
+            Ok(entry) => vec![entry],
+            Err(RecvError::Lagged(_)) => engine.audit_entries(next_seq, usize::MAX).await,
+            Err(RecvError::Closed) => return,
+        };
+        for entry in entries {
+            if entry.seq < next_seq {
+                continue; // seen before catching up
+            }
+            next_seq = entry.seq + 1;
+            let book = match spoofing.needs_book(&entry) {
+                true => Some(engine.settled_book_view().await),
+                false => None,
+            };
+            let found = spoofing
+                .observe(&entry, book.as_deref())
+                .into_iter()
+                .chain(wash_trade(&entry, &config.linked_accounts));
+            for (participant, pattern) in found {
+                surveillance.raise(participant, &entry, pattern).await;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AlertQuery {
+    /// Only alerts with a higher id
+    #[serde(default)]
+    after: u64,
+    participant: Option<ParticipantId>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct AlertsResponse {
+    alerts: Vec<Alert>,
+}
+
+/// `/admin/surveillance/alerts` to read the alerts of `surveillance`
+pub fn routes(auth: Auth, surveillance: Surveillance) -> Router<MatchingEngine> {
+    Router::new()
+        .route("/admin/surveillance/alerts", get(get_alerts))
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_admin))
+        .with_state(surveillance)
+}
+
+async fn get_alerts(
+    State(surveillance): State<Surveillance>,
+    query: Result<Query<AlertQuery>, QueryRejection>,
+) -> Result<Json<AlertsResponse>, ApiError> {
+    let Query(query) = query?;
+    let limit = query.limit.unwrap_or(MAX_ALERT_PAGE).min(MAX_ALERT_PAGE);
+    let alerts = surveillance
+        .alerts(query.after, query.participant.as_deref(), limit)
+        .await;
+    Ok(Json(AlertsResponse { alerts }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        audit::AuditLog,
+        bookview::PriceLevel,
+        order::{Order, Trade},
+    };
+
+    #[test]
+    fn test_repeated_cancels_away_from_touch() {
+        let mut detector = SpoofingDetector::new(SpoofingConfig {
+            min_quantity: 100,
+            min_distance_bps: 100,
+            max_lifetime_ms: 1_000,
+            min_repeats: 3,
+            window_ms: 10_000,
+        });
+        let book = BookSnapshot {
+            bids: vec![PriceLevel {
+                price: 10_000,
+                quantity: 5,
+                orders: 1,
+            }],
+            ..BookSnapshot::default()
+        };
+        let mut log = AuditLog::default();
+        let order = |id: &str, quantity, price| {
+            let mut order = Order::new(
+                id.to_string(),
+                Side::Buy,
+                OrderType::Limit,
+                quantity,
+                price,
+                1,
+            );
+            order.owner = Some("mallory".to_string());
+            order
+        };
+        let mut alerts = Vec::new();
+        let mut observe = |event, at: Timestamp| {
+            let entry = log.append(event, at).clone();
+            alerts.extend(detector.observe(&entry, Some(&book)));
+        };
+
+        // o1 is canceled in time; o2 too late; o3 trades; o4 is small; o5 is close to the touch
+        observe(
+            AuditEvent::OrderAccepted {
+                order: order("o1", 500, 9_900),
+            },
+            0,
+        );
+        observe(
+            AuditEvent::OrderCanceled {
+                order: order("o1", 500, 9_900),
+            },
+            500,
+        );
+        observe(
+            AuditEvent::OrderAccepted {
+                order: order("o2", 500, 9_800),
+            },
+            1_000,
+        );
+        observe(
+            AuditEvent::OrderCanceled {
+                order: order("o2", 500, 9_800),
+            },
+            2_500,
+        );
+        observe(
+            AuditEvent::OrderAccepted {
+                order: order("o3", 500, 9_800),
+            },
+            3_000,
+        );
+        let trade = Trade::new(1, "o3".to_string(), "s".to_string(), 9_800, 1);
+        observe(AuditEvent::TradeExecuted { trade }, 3_100);
+        observe(
+            AuditEvent::OrderCanceled {
+                order: order("o3", 499, 9_800),
+            },
+            3_200,
+        );
+        observe(
+            AuditEvent::OrderAccepted {
+                order: order("o4", 50, 9_800),
+            },
+            4_000,
+        );
+        observe(
+            AuditEvent::OrderCanceled {
+                order: order("o4", 50, 9_800),
+            },
+            4_100,
+        );
+        observe(
+            AuditEvent::OrderAccepted {
+                order: order("o5", 500, 9_950),
+            },
+            5_000,
+        );
+        observe(
+            AuditEvent::OrderCanceled {
+                order: order("o5", 500, 9_950),
+            },
+            5_100,
+        );
+        for (i, id) in ["o6", "o7"].into_iter().enumerate() {
+            let at = 6_000 + i as Timestamp * 1_000;
+            observe(
+                AuditEvent::OrderAccepted {
+                    order: order(id, 300, 9_700),
+                },
+                at,
+            );
+            observe(
+                AuditEvent::OrderCanceled {
+                    order: order(id, 300, 9_700),
+                },
+                at + 200,
+            );
+        }
+
+        assert_eq!(
+            alerts,
+            vec![(
+                "mallory".to_string(),
+                Pattern::Spoofing {
+                    side: Side::Buy,
+                    orders: vec!["o1".to_string(), "o6".to_string(), "o7".to_string()],
+                    quantity: 1_100,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_wash_trades_of_linked_accounts() {
+        let linked = LinkedAccounts::parse("alice:alice-2, bob : bob-hedge");
+        assert!(linked.are_linked("bob", "bob-hedge"));
+        assert!(linked.are_linked("carol", "carol"));
+        assert!(!linked.are_linked("alice", "bob"));
+
+        let mut log = AuditLog::default();
+        let mut trade = |buyer: &str, seller: &str| {
+            let mut trade = Trade::new(1, "b".to_string(), "s".to_string(), 100, 5);
+            trade.buyer = Some(buyer.to_string());
+            trade.seller = Some(seller.to_string());
+            wash_trade(log.append(AuditEvent::TradeExecuted { trade }, 0), &linked)
+        };
+        assert_eq!(trade("alice", "bob"), None);
+        assert_eq!(
+            trade("alice-2", "alice"),
+            Some((
+                "alice-2".to_string(),
+                Pattern::WashTrade {
+                    trade_id: 1,
+                    price: 100,
+                    quantity: 5,
+                    buyer: "alice-2".to_string(),
+                    seller: "alice".to_string(),
+                }
+            ))
+        );
+        assert!(trade("carol", "carol").is_some());
+    }
+}
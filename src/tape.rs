@@ -0,0 +1,107 @@
+use axum::{
+    Extension, Router,
+    extract::{Query, State, WebSocketUpgrade, rejection::QueryRejection},
+    response::Response,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    dropcopy,
+    error::ApiError,
+    heartbeat::HeartbeatConfig,
+    matchingengine::MatchingEngine,
+    order::{Price, Quantity, Side, Timestamp, TradeId, TradeType},
+};
+
+#[derive(Debug, Deserialize)]
+struct TapeQuery {
+    /// Replay executions from this sequence number before going live
+    from: Option<u64>,
+}
+
+/// One execution as printed on the time-and-sales tape
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct TapeEntry {
+    seq: u64,
+    trade_id: TradeId,
+    /// Unix timestamp (ms)
+    time: Timestamp,
+    price: Price,
+    size: Quantity,
+    /// `None` for auction fills and block trades
+    aggressor: Option<Side>,
+    #[serde(skip_serializing_if = "TradeType::is_regular")]
+    trade_type: TradeType,
+}
+
+impl TapeEntry {
+    fn from_entry(entry: &AuditEntry) -> Option<Self> {
+        let AuditEvent::TradeExecuted { trade } = &entry.event else {
+            return None;
+        };
+        Some(TapeEntry {
+            seq: entry.seq,
+            trade_id: trade.id,
+            time: entry.timestamp,
+            price: trade.price,
+            size: trade.quantity,
+            aggressor: trade.aggressor,
+            trade_type: trade.trade_type,
+        })
+    }
+}
+
+/// `/tape`: public time-and-sales stream, one entry per execution
+pub fn routes() -> Router<MatchingEngine> {
+    Router::new().route("/tape", get(tape_upgrade))
+}
+
+async fn tape_upgrade(
+    State(engine): State<MatchingEngine>,
+    Extension(heartbeat): Extension<Option<HeartbeatConfig>>,
+    query: Result<Query<TapeQuery>, QueryRejection>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    Ok(ws.on_upgrade(move |socket| {
+        dropcopy::follow_events(socket, engine, query.from, heartbeat, |entry| {
+            TapeEntry::from_entry(entry)
+                .map(|tape| serde_json::to_string(&tape).expect("tape entries always serialize"))
+        })
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{audit::AuditLog, order::Trade};
+
+    #[test]
+    fn test_tape_entry() {
+        let mut log = AuditLog::default();
+        let trade = Trade::new(7, String::from("b"), String::from("s"), 101, 3)
+            .with_parties(Some(String::from("alice")), Some(String::from("bob")))
+            .with_aggressor(Side::Sell);
+        log.append(AuditEvent::TradeExecuted { trade }, 42);
+        let entry = &log.export(1, 1)[0];
+
+        let tape = TapeEntry::from_entry(entry).unwrap();
+        assert_eq!(
+            tape,
+            TapeEntry {
+                seq: 1,
+                trade_id: 7,
+                time: 42,
+                price: 101,
+                size: 3,
+                aggressor: Some(Side::Sell),
+                trade_type: TradeType::Regular,
+            }
+        );
+        // participants never make it onto the public tape
+        let json = serde_json::to_value(&tape).unwrap();
+        assert!(json.get("buyer").is_none());
+    }
+}
@@ -0,0 +1,40 @@
+pub const TENANTS_ENV: &str = "OME_TENANTS"; // comma separated tenant names, served under `/tenants/{name}`
+
+/// Tenant names from a comma separated list; blank, duplicate and non `[A-Za-z0-9_-]` names are dropped
+pub fn parse(list: &str) -> Vec<String> {
+    let mut tenants: Vec<String> = Vec::new();
+    for name in list.split(',').map(str::trim) {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            if !name.is_empty() {
+                println!("Ignoring invalid tenant name {name:?}");
+            }
+            continue;
+        }
+        if !tenants.iter().any(|t| t == name) {
+            tenants.push(name.to_string());
+        }
+    }
+    tenants
+}
+
+pub fn from_env() -> Vec<String> {
+    std::env::var(TENANTS_ENV)
+        .map(|list| parse(&list))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("acme, sandbox-1,,acme"), vec!["acme", "sandbox-1"]);
+        assert_eq!(parse("bad/name,ok_2"), vec!["ok_2"]);
+        assert!(parse("").is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+use std::{collections::BTreeMap, convert::Infallible};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{Query, State, rejection::QueryRejection},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use futures_util::stream;
+use serde::Deserialize;
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    error::ApiError,
+    matchingengine::MatchingEngine,
+    order::{Side, Timestamp, Trade, TradeId, TradeStatus, TradeType},
+    validation::FieldError,
+};
+
+const CSV_HEADER: &str =
+    "trade_id,seq,time,price,quantity,aggressor,buy_order_id,sell_order_id,status,type\n";
+const ROWS_PER_CHUNK: usize = 1_000; // rows rendered per body chunk
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    /// Only `csv` for now, the default
+    format: Option<String>,
+    /// Unix ms, inclusive
+    from: Option<Timestamp>,
+    /// Unix ms, exclusive
+    to: Option<Timestamp>,
+}
+
+/// A trade with its execution time and sequence number, as it stands after later busts and
+/// corrections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub seq: u64,
+    /// Unix timestamp (ms)
+    pub time: Timestamp,
+    pub trade: Trade,
+}
+
+/// Trades executed in `entries` before `to`, oldest first. Busted trades stay in with their
+/// status; busts and corrections of trades executed before `entries` are not seen.
+pub fn executions(entries: &[AuditEntry], to: Timestamp) -> Vec<Execution> {
+    let mut executions: BTreeMap<TradeId, Execution> = BTreeMap::new();
+    for entry in entries {
+        match &entry.event {
+            AuditEvent::TradeExecuted { trade } if entry.timestamp < to => {
+                executions.insert(
+                    trade.id,
+                    Execution {
+                        seq: entry.seq,
+                        time: entry.timestamp,
+                        trade: trade.clone(),
+                    },
+                );
+            }
+            AuditEvent::TradeBusted { trade } | AuditEvent::TradeCorrected { trade } => {
+                if let Some(execution) = executions.get_mut(&trade.id) {
+                    execution.trade = trade.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    executions.into_values().collect()
+}
+
+/// Quotes a field holding a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+pub fn status_name(status: &TradeStatus) -> &'static str {
+    match status {
+        TradeStatus::Active => "active",
+        TradeStatus::Busted { .. } => "busted",
+        TradeStatus::Corrected { .. } => "corrected",
+    }
+}
+
+pub fn trade_type_name(trade_type: TradeType) -> &'static str {
+    match trade_type {
+        TradeType::Regular => "regular",
+        TradeType::Block => "block",
+    }
+}
+
+fn csv_row(execution: &Execution) -> String {
+    let trade = &execution.trade;
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}\n",
+        trade.id,
+        execution.seq,
+        execution.time,
+        trade.price,
+        trade.quantity,
+        trade.aggressor.map(side_name).unwrap_or_default(),
+        csv_field(&trade.buy_order_id),
+        csv_field(&trade.sell_order_id),
+        status_name(&trade.status),
+        trade_type_name(trade.trade_type)
+    )
+}
+
+/// `/trades/export`: public trade history download
+pub fn routes() -> Router<MatchingEngine> {
+    Router::new().route("/trades/export", get(export_trades))
+}
+
+async fn export_trades(
+    State(engine): State<MatchingEngine>,
+    query: Result<Query<ExportQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    let from = query.from.unwrap_or(0);
+    let to = query.to.unwrap_or(Timestamp::MAX);
+    let mut errors = Vec::new();
+    if let Some(format) = query.format.as_deref()
+        && format != "csv"
+    {
+        errors.push(FieldError::new(
+            "format",
+            format!("unsupported format {format}, only csv is available"),
+        ));
+    }
+    if from >= to {
+        errors.push(FieldError::new("to", "to must be after from"));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let mut executions = engine.executions(from, to).await.into_iter();
+    let rows = std::iter::from_fn(move || {
+        let chunk: String = executions
+            .by_ref()
+            .take(ROWS_PER_CHUNK)
+            .map(|e| csv_row(&e))
+            .collect();
+        (!chunk.is_empty()).then_some(chunk)
+    });
+    let body = std::iter::once(CSV_HEADER.to_string())
+        .chain(rows)
+        .map(Ok::<_, Infallible>);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"trades.csv\"",
+            ),
+        ],
+        Body::from_stream(stream::iter(body)),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::audit::AuditLog;
+
+    #[test]
+    fn test_csv_export() {
+        let mut log = AuditLog::default();
+        let trade = |id, price| {
+            Trade::new(id, "b,1".to_string(), "s".to_string(), price, 5).with_aggressor(Side::Sell)
+        };
+        log.append(
+            AuditEvent::TradeExecuted {
+                trade: trade(1, 100),
+            },
+            10,
+        );
+        log.append(
+            AuditEvent::TradeExecuted {
+                trade: trade(2, 101),
+            },
+            20,
+        );
+        log.append(
+            AuditEvent::TradeExecuted {
+                trade: trade(3, 102),
+            },
+            30,
+        );
+        let mut busted = trade(2, 101);
+        busted.status = TradeStatus::Busted {
+            reason: "error".to_string(),
+        };
+        log.append(AuditEvent::TradeBusted { trade: busted }, 40);
+
+        let executions = executions(log.since(20), 30);
+        assert_eq!(executions.len(), 1);
+        assert_eq!((executions[0].seq, executions[0].time), (2, 20));
+        assert_eq!(
+            csv_row(&executions[0]),
+            "2,2,20,101,5,sell,\"b,1\",s,busted,regular\n"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::order::{ParticipantId, Trade, TradeId};
+
+pub const MAX_TRADES_PER_ACCOUNT: usize = 10_000; // oldest executions of an account are dropped past this
+
+/// Executions indexed by the participants on either side, independent of the public trade pool
+#[derive(Debug, Default)]
+pub struct OwnerTradeIndex {
+    by_owner: HashMap<ParticipantId, VecDeque<Trade>>, // ascending trade ids
+}
+
+impl OwnerTradeIndex {
+    pub fn record(&mut self, trade: &Trade) {
+        for owner in Self::parties(trade) {
+            let trades = self.by_owner.entry(owner.clone()).or_default();
+            if trades.len() >= MAX_TRADES_PER_ACCOUNT {
+                trades.pop_front();
+            }
+            trades.push_back(trade.clone());
+        }
+    }
+
+    /// Replaces the stored copies of a busted or corrected trade
+    pub fn update(&mut self, trade: &Trade) {
+        for owner in Self::parties(trade) {
+            let Some(trades) = self.by_owner.get_mut(owner) else {
+                continue;
+            };
+            if let Ok(pos) = trades.binary_search_by_key(&trade.id, |t| t.id) {
+                trades[pos] = trade.clone();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.by_owner.clear();
+    }
+
+    /// Up to `limit` trades of `owner` older than `before` (all if `None`), newest first
+    pub fn page(&self, owner: &str, before: Option<TradeId>, limit: usize) -> Vec<Trade> {
+        let Some(trades) = self.by_owner.get(owner) else {
+            return Vec::new();
+        };
+        let end = before.map_or(trades.len(), |id| trades.partition_point(|t| t.id < id));
+        trades.range(..end).rev().take(limit).cloned().collect()
+    }
+
+    fn parties(trade: &Trade) -> impl Iterator<Item = &ParticipantId> {
+        let seller = trade
+            .seller
+            .as_ref()
+            .filter(|s| trade.buyer.as_ref() != Some(*s));
+        trade.buyer.iter().chain(seller)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn trade(id: TradeId, buyer: &str, seller: &str) -> Trade {
+        Trade::new(id, String::from("b"), String::from("s"), 100, 1)
+            .with_parties(Some(buyer.to_string()), Some(seller.to_string()))
+    }
+
+    #[test]
+    fn test_pagination() {
+        let mut index = OwnerTradeIndex::default();
+        for id in 1..=5 {
+            index.record(&trade(
+                id,
+                "alice",
+                if id % 2 == 0 { "bob" } else { "carol" },
+            ));
+        }
+        let ids = |trades: Vec<Trade>| trades.iter().map(|t| t.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(index.page("alice", None, 2)), vec![5, 4]);
+        assert_eq!(ids(index.page("alice", Some(4), 2)), vec![3, 2]);
+        assert_eq!(ids(index.page("alice", Some(2), 2)), vec![1]);
+        assert_eq!(ids(index.page("bob", None, 10)), vec![4, 2]);
+        assert!(index.page("dave", None, 10).is_empty());
+    }
+
+    #[test]
+    fn test_update_and_self_trade() {
+        let mut index = OwnerTradeIndex::default();
+        index.record(&trade(1, "alice", "alice"));
+        assert_eq!(index.page("alice", None, 10).len(), 1);
+
+        let mut corrected = trade(1, "alice", "alice");
+        corrected.price = 99;
+        index.update(&corrected);
+        assert_eq!(index.page("alice", None, 10)[0].price, 99);
+    }
+}
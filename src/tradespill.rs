@@ -0,0 +1,203 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{matchingengine::TRADE_POOL_SIZE, order::Trade};
+
+pub const TRADE_POOL_SIZE_ENV: &str = "OME_TRADE_POOL_SIZE"; // trades kept in memory
+pub const TRADE_SPILL_DIR_ENV: &str = "OME_TRADE_SPILL_DIR"; // unset drops evicted trades
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeHistoryConfig {
+    /// Most recent trades kept in memory
+    pub capacity: usize,
+    /// Where trades evicted from memory go, one file per exchange
+    pub spill_dir: Option<PathBuf>,
+}
+
+impl TradeHistoryConfig {
+    pub fn from_env() -> Self {
+        let capacity = std::env::var(TRADE_POOL_SIZE_ENV)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|capacity| *capacity > 0)
+            .unwrap_or(TRADE_POOL_SIZE);
+        let spill_dir = std::env::var(TRADE_SPILL_DIR_ENV)
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        TradeHistoryConfig {
+            capacity,
+            spill_dir,
+        }
+    }
+
+    /// `<dir>/<symbol>.trades.jsonl`
+    pub fn spill_path(&self, symbol: &str) -> Option<PathBuf> {
+        self.spill_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{symbol}.trades.jsonl")))
+    }
+}
+
+/// Append-only file of the trades evicted from the in-memory pool, oldest first, one JSON
+/// object per line. Evicted trades are final: busts and corrections only reach the pool.
+#[derive(Debug)]
+pub struct TradeSpill {
+    path: PathBuf,
+    file: File,
+    /// Bytes of complete lines written so far
+    len: u64,
+    /// Trades in the file
+    trades: usize,
+    /// Whether the latest write failed, leaving the history on disk incomplete
+    failing: bool,
+}
+
+impl TradeSpill {
+    /// Starts an empty spill file at `path`; the history belongs to the running engine, so a
+    /// previous run's file is overwritten
+    pub fn create(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(TradeSpill {
+            path,
+            file,
+            len: 0,
+            trades: 0,
+            failing: false,
+        })
+    }
+
+    pub fn append(&mut self, trade: &Trade) -> io::Result<()> {
+        let result = self.write_trade(trade);
+        self.track(result)
+    }
+
+    fn write_trade(&mut self, trade: &Trade) -> io::Result<()> {
+        let mut line = serde_json::to_vec(trade).expect("trades serialize");
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.len += line.len() as u64;
+        self.trades += 1;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        let result = self.truncate();
+        self.track(result)
+    }
+
+    fn truncate(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.len = 0;
+        self.trades = 0;
+        Ok(())
+    }
+
+    /// Rewrites the file with only the trades `keep` accepts, through a temporary file so a
+    /// crash leaves either version; returns how many were dropped
+    pub fn retain(&mut self, keep: impl Fn(&Trade) -> bool) -> io::Result<usize> {
+        let result = self.rewrite(keep);
+        self.track(result)
+    }
+
+    fn rewrite(&mut self, keep: impl Fn(&Trade) -> bool) -> io::Result<usize> {
+        let trades = read_spilled(&self.path, self.len)?;
+        let compacted = self.path.with_extension("jsonl.compact");
+        let mut file = File::create(&compacted)?;
+        let (mut len, mut kept) = (0, 0);
+        for trade in trades.iter().filter(|t| keep(t)) {
+            let mut line = serde_json::to_vec(trade).expect("trades serialize");
+            line.push(b'\n');
+            file.write_all(&line)?;
+            len += line.len() as u64;
+            kept += 1;
+        }
+        drop(file);
+        fs::rename(&compacted, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.len = len;
+        self.trades = kept;
+        Ok(trades.len() - kept)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Bytes of complete lines written so far
+    pub fn written(&self) -> u64 {
+        self.len
+    }
+
+    pub fn trades(&self) -> usize {
+        self.trades
+    }
+
+    /// Whether the latest append, clear or compaction failed
+    pub fn is_failing(&self) -> bool {
+        self.failing
+    }
+
+    fn track<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        self.failing = result.is_err();
+        result
+    }
+}
+
+/// The first `len` bytes of the spill file at `path`, as trades. Taking the length while the
+/// pool is locked and reading afterwards gives a consistent history without blocking matching
+/// on disk reads.
+pub fn read_spilled(path: &Path, len: u64) -> io::Result<Vec<Trade>> {
+    let reader = BufReader::new(File::open(path)?.take(len));
+    reader
+        .lines()
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spill() {
+        let path = std::env::temp_dir()
+            .join(format!("ome-spill-{}", std::process::id()))
+            .join("root.trades.jsonl");
+        let mut spill = TradeSpill::create(path.clone()).unwrap();
+        spill
+            .append(&Trade::new(1, "b1".to_string(), "s1".to_string(), 100, 5))
+            .unwrap();
+        let len = spill.written();
+        spill
+            .append(&Trade::new(2, "b2".to_string(), "s2".to_string(), 101, 5))
+            .unwrap();
+
+        let ids = |trades: Vec<Trade>| trades.iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(read_spilled(&path, len).unwrap()), [1]);
+        assert_eq!(ids(read_spilled(&path, spill.written()).unwrap()), [1, 2]);
+        assert_eq!(spill.retain(|t| t.id > 1).unwrap(), 1);
+        assert_eq!(spill.trades(), 1);
+        spill
+            .append(&Trade::new(3, "b3".to_string(), "s3".to_string(), 102, 5))
+            .unwrap();
+        assert_eq!(ids(read_spilled(&path, spill.written()).unwrap()), [2, 3]);
+        spill.clear().unwrap();
+        assert!(read_spilled(&path, spill.written()).unwrap().is_empty());
+        assert!(!spill.is_failing());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(spill.retain(|_| true).is_err());
+        assert!(spill.is_failing());
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, State, rejection::JsonRejection},
+    routing::{delete, post},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{
+        Notify, RwLock,
+        broadcast::{self, error::RecvError, error::TryRecvError},
+    },
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    auth::Principal,
+    consensus::Consensus,
+    error::{ApiError, ErrorCode},
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, OrderType, ParticipantId, Price, Quantity, Side, Timestamp},
+    orderentry::{self, OrderAck},
+    settlement,
+    validation::{self, FieldError, PriceType},
+};
+
+pub const MIN_TWAP_DURATION_MS: u64 = 1_000;
+pub const MAX_TWAP_DURATION_MS: u64 = 24 * 60 * 60 * 1_000;
+pub const MAX_TWAP_SLICES: u32 = 1_000;
+const MIN_SLICE_INTERVAL_MS: u64 = 100;
+const DEFAULT_SLICE_INTERVAL_MS: u64 = 10_000;
+const MAX_FINISHED_TWAPS: usize = 1_000; // finished parents kept for their status
+
+pub type TwapId = String;
+
+#[derive(Debug, Deserialize)]
+struct NewTwapRequest {
+    side: Side,
+    quantity: Quantity,
+    duration_ms: u64,
+    /// Child orders to split the quantity into, one every 10 s when omitted
+    slices: Option<u32>,
+    /// Limit price of the children; market orders without one
+    price: Option<PriceType>,
+}
+
+#[derive(Debug, Serialize)]
+struct TwapsResponse {
+    twaps: Vec<TwapOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TwapStatus {
+    Working,
+    /// Filled in full
+    Completed,
+    Canceled,
+    /// The duration ran out before the parent was filled
+    Expired,
+}
+
+/// A parent order executed by the server over `duration_ms` in `slices` child orders sent at
+/// even intervals. Each child catches up on what earlier ones left unfilled; a limit child
+/// still working when the next one is due is canceled first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TwapOrder {
+    pub id: TwapId,
+    pub owner: ParticipantId,
+    pub side: Side,
+    pub quantity: Quantity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<Price>,
+    /// Unix ms of the first child
+    pub start: Timestamp,
+    pub duration_ms: u64,
+    pub slices: u32,
+    pub slices_sent: u32,
+    pub filled_quantity: Quantity,
+    /// Child orders the engine accepted, oldest first
+    pub children: Vec<OrderId>,
+    pub status: TwapStatus,
+    /// Why the latest child was refused
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl TwapOrder {
+    /// Unix ms child `slice` is due at
+    pub fn slice_at(&self, slice: u32) -> Timestamp {
+        self.start + self.duration_ms * slice as u64 / self.slices as u64
+    }
+
+    /// Quantity child `slice` is sent with: what should be filled by its end, less what is
+    pub fn child_quantity(&self, slice: u32) -> Quantity {
+        let target = (self.quantity as u128 * (slice as u128 + 1)).div_ceil(self.slices as u128);
+        (target as Quantity).saturating_sub(self.filled_quantity)
+    }
+}
+
+#[derive(Debug)]
+struct Parent {
+    order: TwapOrder,
+    cancel: Arc<Notify>,
+}
+
+#[derive(Debug, Default)]
+struct TwapBook {
+    parents: HashMap<TwapId, Parent>,
+    finished: VecDeque<TwapId>,
+}
+
+/// The TWAP parents of one exchange. They live in the memory of the server that accepted
+/// them, with their children in the engine like any other orders.
+#[derive(Debug, Clone, Default)]
+pub struct Twaps {
+    book: Arc<RwLock<TwapBook>>,
+}
+
+impl Twaps {
+    pub fn new() -> Self {
+        Twaps::default()
+    }
+
+    async fn insert(&self, order: TwapOrder) -> Arc<Notify> {
+        let cancel = Arc::new(Notify::new());
+        let parent = Parent {
+            order,
+            cancel: cancel.clone(),
+        };
+        let mut book = self.book.write().await;
+        book.parents.insert(parent.order.id.clone(), parent);
+        cancel
+    }
+
+    async fn get(&self, id: &str) -> Option<TwapOrder> {
+        let book = self.book.read().await;
+        book.parents.get(id).map(|parent| parent.order.clone())
+    }
+
+    async fn update(&self, id: &str, f: impl FnOnce(&mut TwapOrder)) {
+        let mut book = self.book.write().await;
+        let Some(parent) = book.parents.get_mut(id) else {
+            return;
+        };
+        f(&mut parent.order);
+        if parent.order.status != TwapStatus::Working {
+            book.finished.push_back(id.to_string());
+            if book.finished.len() > MAX_FINISHED_TWAPS
+                && let Some(oldest) = book.finished.pop_front()
+            {
+                book.parents.remove(&oldest);
+            }
+        }
+    }
+
+    /// Parents of `owner`, working and recently finished, oldest first
+    pub async fn of_owner(&self, owner: &str) -> Vec<TwapOrder> {
+        let book = self.book.read().await;
+        let mut orders: Vec<TwapOrder> = book
+            .parents
+            .values()
+            .filter(|parent| parent.order.owner == owner)
+            .map(|parent| parent.order.clone())
+            .collect();
+        orders.sort_by(|a, b| (a.start, &a.id).cmp(&(b.start, &b.id)));
+        orders
+    }
+
+    /// Stops a working parent of `owner`; its task cancels the working child
+    async fn cancel(&self, id: &str, owner: &str) -> Result<TwapOrder, ApiError> {
+        let book = self.book.read().await;
+        let parent = book
+            .parents
+            .get(id)
+            .filter(|parent| parent.order.owner == owner)
+            .ok_or_else(|| ApiError::new(ErrorCode::OrderNotFound, format!("no TWAP {id}")))?;
+        if parent.order.status != TwapStatus::Working {
+            return Err(ApiError::new(
+                ErrorCode::InvalidTransition,
+                format!("TWAP {id} is no longer working"),
+            ));
+        }
+        parent.cancel.notify_one();
+        Ok(parent.order.clone())
+    }
+}
+
+/// `/algos/twap` for traders; the caller layers authentication, the consensus handle and the
+/// [`Twaps`] of the exchange
+pub fn routes() -> Router<MatchingEngine> {
+    Router::new()
+        .route("/algos/twap", post(post_twap).get(get_twaps))
+        .route("/algos/twap/{id}", delete(cancel_twap))
+}
+
+async fn post_twap(
+    State(engine): State<MatchingEngine>,
+    Extension(consensus): Extension<Option<Consensus>>,
+    Extension(twaps): Extension<Twaps>,
+    Extension(principal): Extension<Principal>,
+    payload: Result<Json<NewTwapRequest>, JsonRejection>,
+) -> Result<Json<TwapOrder>, ApiError> {
+    let Json(req) = payload?;
+    let mut errors = Vec::new();
+    if req.quantity == 0 {
+        errors.push(FieldError::new("quantity", "must be > 0"));
+    }
+    if !(MIN_TWAP_DURATION_MS..=MAX_TWAP_DURATION_MS).contains(&req.duration_ms) {
+        errors.push(FieldError::new(
+            "duration_ms",
+            format!("must be between {MIN_TWAP_DURATION_MS} and {MAX_TWAP_DURATION_MS}"),
+        ));
+    }
+    let slices = req.slices.unwrap_or_else(|| {
+        (req.duration_ms / DEFAULT_SLICE_INTERVAL_MS).clamp(1, MAX_TWAP_SLICES as u64) as u32
+    });
+    if slices == 0 || slices > MAX_TWAP_SLICES {
+        errors.push(FieldError::new(
+            "slices",
+            format!("must be between 1 and {MAX_TWAP_SLICES}"),
+        ));
+    } else if req.duration_ms / (slices as u64) < MIN_SLICE_INTERVAL_MS {
+        errors.push(FieldError::new(
+            "slices",
+            format!("at most one child every {MIN_SLICE_INTERVAL_MS} ms"),
+        ));
+    } else if req.quantity < slices as u64 {
+        errors.push(FieldError::new("slices", "more slices than quantity"));
+    }
+    let price = match &req.price {
+        Some(price) => match validation::validate_new_order(OrderType::Limit, Some(price), 1) {
+            Ok(price) => Some(price),
+            Err(e) => {
+                errors.extend(e);
+                None
+            }
+        },
+        None => None,
+    };
+    if !errors.is_empty() {
+        return Err(ApiError::validation(errors));
+    }
+
+    let order = TwapOrder {
+        id: uuid::Uuid::new_v4().to_string(),
+        owner: principal.subject,
+        side: req.side,
+        quantity: req.quantity,
+        price,
+        start: settlement::now_ms(),
+        duration_ms: req.duration_ms,
+        slices,
+        slices_sent: 0,
+        filled_quantity: 0,
+        children: Vec::new(),
+        status: TwapStatus::Working,
+        last_error: None,
+    };
+    let cancel = twaps.insert(order.clone()).await;
+    tokio::spawn(run_twap(engine, consensus, twaps, order.id.clone(), cancel));
+    Ok(Json(order))
+}
+
+async fn get_twaps(
+    Extension(twaps): Extension<Twaps>,
+    Extension(principal): Extension<Principal>,
+) -> Json<TwapsResponse> {
+    Json(TwapsResponse {
+        twaps: twaps.of_owner(&principal.subject).await,
+    })
+}
+
+async fn cancel_twap(
+    Extension(twaps): Extension<Twaps>,
+    Extension(principal): Extension<Principal>,
+    Path(id): Path<TwapId>,
+) -> Result<Json<TwapOrder>, ApiError> {
+    twaps.cancel(&id, &principal.subject).await.map(Json)
+}
+
+/// Child orders of one parent and what they filled, followed on the engine's events
+struct Children {
+    events: broadcast::Receiver<AuditEntry>,
+    next_seq: u64,
+    ids: HashSet<OrderId>,
+    filled: Quantity,
+}
+
+impl Children {
+    fn observe(&mut self, entry: &AuditEntry) {
+        if entry.seq < self.next_seq {
+            return; // seen before catching up
+        }
+        self.next_seq = entry.seq + 1;
+        if let AuditEvent::TradeExecuted { trade } = &entry.event
+            && (self.ids.contains(&trade.buy_order_id) || self.ids.contains(&trade.sell_order_id))
+        {
+            self.filled += trade.quantity;
+        }
+    }
+
+    /// Takes in the events already published, without waiting
+    async fn catch_up(&mut self, engine: &MatchingEngine) {
+        loop {
+            match self.events.try_recv() {
+                Ok(entry) => self.observe(&entry),
+                Err(TryRecvError::Lagged(_)) => {
+                    for entry in engine.audit_entries(self.next_seq, usize::MAX).await {
+                        self.observe(&entry);
+                    }
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return,
+            }
+        }
+    }
+}
+
+/// Sends the children of parent `id` as they come due until it's filled, its duration is up
+/// or it's canceled
+pub async fn run_twap(
+    engine: MatchingEngine,
+    consensus: Option<Consensus>,
+    twaps: Twaps,
+    id: TwapId,
+    cancel: Arc<Notify>,
+) {
+    let Some(parent) = twaps.get(&id).await else {
+        return;
+    };
+    let mut children = Children {
+        events: engine.subscribe_events(),
+        next_seq: engine.last_seq().await + 1,
+        ids: HashSet::new(),
+        filled: 0,
+    };
+    let mut working: Option<OrderId> = None;
+    let mut slice = 0;
+    let status = loop {
+        // the next child, or the end of the duration after the last one
+        let due_at = match slice < parent.slices {
+            true => parent.slice_at(slice),
+            false => parent.start + parent.duration_ms,
+        };
+        let due =
+            Instant::now() + Duration::from_millis(due_at.saturating_sub(settlement::now_ms()));
+        tokio::select! {
+            _ = sleep_until(due) => {}
+            _ = cancel.notified() => break TwapStatus::Canceled,
+            received = children.events.recv() => {
+                match received {
+                    Ok(entry) => children.observe(&entry),
+                    Err(RecvError::Lagged(_)) => children.catch_up(&engine).await,
+                    Err(RecvError::Closed) => break TwapStatus::Expired,
+                }
+                let filled = children.filled;
+                twaps.update(&id, |order| order.filled_quantity = filled).await;
+                if filled >= parent.quantity {
+                    break TwapStatus::Completed;
+                }
+                continue;
+            }
+        }
+
+        if let Some(child) = working.take() {
+            let _ = orderentry::cancel_order(
+                &engine,
+                consensus.as_ref(),
+                child,
+                Some(parent.owner.clone()),
+            )
+            .await;
+            children.catch_up(&engine).await;
+        }
+        if slice == parent.slices {
+            break match children.filled >= parent.quantity {
+                true => TwapStatus::Completed,
+                false => TwapStatus::Expired,
+            };
+        }
+        let quantity = TwapOrder {
+            filled_quantity: children.filled,
+            ..parent.clone()
+        }
+        .child_quantity(slice);
+        slice += 1;
+        let sent = match quantity {
+            0 => Ok(None),
+            _ => send_child(&engine, consensus.clone(), &parent, quantity)
+                .await
+                .map(Some),
+        };
+        let filled = children.filled;
+        match sent {
+            Ok(child) => {
+                if let Some(child) = &child {
+                    children.ids.insert(child.clone());
+                }
+                twaps
+                    .update(&id, |order| {
+                        order.slices_sent = slice;
+                        order.filled_quantity = filled;
+                        order.children.extend(child.clone());
+                    })
+                    .await;
+                // market children don't rest, whatever they didn't fill is gone
+                working = child.filter(|_| parent.price.is_some());
+            }
+            Err(e) => {
+                twaps
+                    .update(&id, |order| {
+                        order.slices_sent = slice;
+                        order.last_error = Some(e.message);
+                    })
+                    .await;
+            }
+        }
+    };
+
+    if let Some(child) = working {
+        let _ = orderentry::cancel_order(
+            &engine,
+            consensus.as_ref(),
+            child,
+            Some(parent.owner.clone()),
+        )
+        .await;
+    }
+    children.catch_up(&engine).await;
+    let filled = children.filled;
+    twaps
+        .update(&id, |order| {
+            order.filled_quantity = filled;
+            order.status = status;
+        })
+        .await;
+}
+
+/// Enters one child of `parent` for `quantity`; the engine's refusal is an error
+async fn send_child(
+    engine: &MatchingEngine,
+    consensus: Option<Consensus>,
+    parent: &TwapOrder,
+    quantity: Quantity,
+) -> Result<OrderId, ApiError> {
+    let (order_type, price) = match parent.price {
+        Some(price) => (OrderType::Limit, price),
+        None => (OrderType::Market, 0),
+    };
+    let order = Order::new(
+        engine.next_order_id(),
+        parent.side,
+        order_type,
+        quantity,
+        price,
+        engine.order_timestamp(),
+    )
+    .with_owner(parent.owner.clone());
+    let entry = orderentry::enter_order(engine, consensus, order).await?;
+    match entry.ack {
+        OrderAck::Accepted { id, .. } => Ok(id),
+        OrderAck::Rejected { reason, .. } => Err(reason.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parent(quantity: Quantity, slices: u32, price: Option<Price>) -> TwapOrder {
+        TwapOrder {
+            id: "t1".to_string(),
+            owner: "alice".to_string(),
+            side: Side::Buy,
+            quantity,
+            price,
+            start: settlement::now_ms(),
+            duration_ms: 300,
+            slices,
+            slices_sent: 0,
+            filled_quantity: 0,
+            children: Vec::new(),
+            status: TwapStatus::Working,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_slices_catch_up() {
+        let mut order = parent(10, 3, None);
+        assert_eq!(order.slice_at(1), order.start + 100);
+        assert_eq!(order.child_quantity(0), 4);
+        order.filled_quantity = 1; // the first child mostly missed
+        assert_eq!(order.child_quantity(1), 6);
+        order.filled_quantity = 7;
+        assert_eq!(order.child_quantity(2), 3);
+    }
+
+    #[tokio::test]
+    async fn test_twap_slices_into_the_book() {
+        let mut engine = MatchingEngine::new();
+        for id in ["s1", "s2"] {
+            let sell = Order::new(id.to_string(), Side::Sell, OrderType::Limit, 2, 100, 1);
+            engine.submit_order(sell).await.unwrap();
+        }
+        let twaps = Twaps::new();
+        let order = parent(6, 3, Some(100));
+        let cancel = twaps.insert(order.clone()).await;
+        run_twap(
+            engine.clone(),
+            None,
+            twaps.clone(),
+            order.id.clone(),
+            cancel,
+        )
+        .await;
+
+        // 2 + 2 filled, the last child rested unfilled and was canceled at the end
+        let order = twaps.get("t1").await.unwrap();
+        assert_eq!(order.status, TwapStatus::Expired);
+        assert_eq!((order.filled_quantity, order.slices_sent), (4, 3));
+        assert_eq!(order.children.len(), 3);
+        assert!(engine.owner_orders("alice").await.is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+
+use crate::order::{OrderType, Price, Quantity, Side, Trigger, TriggerKind, TriggerSource};
+
+/// Prices are accepted either in cents (`1050`) or in dollars (`10.5`)
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PriceType {
+    Unsigned(u64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        FieldError {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Converts a dollar amount to cents, rounding to the nearest cent (`0.29 * 100.0` is `28.999..`)
+pub fn dollars_to_cents(dollars: f64) -> Result<Price, FieldError> {
+    if !dollars.is_finite() {
+        return Err(FieldError::new("price", "price must be a finite number"));
+    }
+    if dollars < 0.0 {
+        return Err(FieldError::new("price", "price must not be negative"));
+    }
+
+    let cents = (dollars * 100.0).round();
+    if cents >= u64::MAX as f64 {
+        return Err(FieldError::new("price", "price is too large"));
+    }
+    Ok(cents as Price)
+}
+
+/// Validates the fields of an incoming order and returns its price in cents.
+/// Market orders always resolve to a price of `0`, any supplied price is ignored.
+pub fn validate_new_order(
+    order_type: OrderType,
+    price: Option<&PriceType>,
+    quantity: Quantity,
+) -> Result<Price, Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if quantity == 0 {
+        errors.push(FieldError::new(
+            "quantity",
+            "quantity must be greater than 0",
+        ));
+    }
+
+    let price = match order_type {
+        OrderType::Market => Some(0),
+        OrderType::Limit => match price {
+            None => {
+                errors.push(FieldError::new("price", "limit orders require a price"));
+                None
+            }
+            Some(PriceType::Unsigned(cents)) => Some(*cents),
+            Some(PriceType::Float(dollars)) => match dollars_to_cents(*dollars) {
+                Ok(cents) => Some(cents),
+                Err(e) => {
+                    errors.push(e);
+                    None
+                }
+            },
+        },
+    };
+
+    if let Some(price) = price {
+        if order_type == OrderType::Limit && price == 0 {
+            errors.push(FieldError::new(
+                "price",
+                "limit price must be greater than 0",
+            ));
+        } else if price.checked_mul(quantity).is_none() {
+            errors.push(FieldError::new(
+                "quantity",
+                "price * quantity overflows the notional value",
+            ));
+        }
+    }
+
+    match price {
+        Some(price) if errors.is_empty() => Ok(price),
+        _ => Err(errors),
+    }
+}
+
+/// Price of one side of a quote, a limit order; errors name the side's fields, e.g. `bid.price`
+pub fn validate_quote_side(
+    side: Side,
+    price: &PriceType,
+    quantity: Quantity,
+) -> Result<Price, Vec<FieldError>> {
+    let (price_field, quantity_field) = match side {
+        Side::Buy => ("bid.price", "bid.quantity"),
+        Side::Sell => ("ask.price", "ask.quantity"),
+    };
+    validate_new_order(OrderType::Limit, Some(price), quantity).map_err(|errors| {
+        errors
+            .into_iter()
+            .map(|e| FieldError {
+                field: if e.field == "quantity" {
+                    quantity_field
+                } else {
+                    price_field
+                },
+                ..e
+            })
+            .collect()
+    })
+}
+
+/// Parses a price given in cents or dollars for `field`
+fn parse_price(field: &'static str, price: &PriceType) -> Result<Price, FieldError> {
+    match price {
+        PriceType::Unsigned(cents) => Ok(*cents),
+        PriceType::Float(dollars) => {
+            dollars_to_cents(*dollars).map_err(|e| FieldError::new(field, e.message))
+        }
+    }
+}
+
+/// Trigger of a stop or market-if-touched order, `None` when no trigger price is given. Only
+/// stops can trail.
+pub fn validate_trigger(
+    price: Option<&PriceType>,
+    source: Option<TriggerSource>,
+    kind: Option<TriggerKind>,
+    trail: Option<&PriceType>,
+) -> Result<Option<Trigger>, FieldError> {
+    let Some(price) = price else {
+        let field = if source.is_some() {
+            "trigger_source"
+        } else if kind.is_some() {
+            "trigger_kind"
+        } else if trail.is_some() {
+            "trigger_trail"
+        } else {
+            return Ok(None);
+        };
+        return Err(FieldError::new(
+            field,
+            format!("{field} needs a trigger_price"),
+        ));
+    };
+    let price = parse_price("trigger_price", price)?;
+    if price == 0 {
+        return Err(FieldError::new(
+            "trigger_price",
+            "trigger price must be greater than 0",
+        ));
+    }
+    let kind = kind.unwrap_or_default();
+    let trail = match trail
+        .map(|trail| parse_price("trigger_trail", trail))
+        .transpose()?
+    {
+        Some(_) if kind != TriggerKind::Stop => {
+            return Err(FieldError::new(
+                "trigger_trail",
+                "only a Stop trigger can trail",
+            ));
+        }
+        Some(0) => {
+            return Err(FieldError::new(
+                "trigger_trail",
+                "trail must be greater than 0",
+            ));
+        }
+        trail => trail,
+    };
+    Ok(Some(Trigger {
+        price,
+        source: source.unwrap_or_default(),
+        kind,
+        trail,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dollars_to_cents() {
+        assert_eq!(dollars_to_cents(10.5), Ok(1050));
+        assert_eq!(dollars_to_cents(0.29), Ok(29));
+        assert!(dollars_to_cents(-1.0).is_err());
+        assert!(dollars_to_cents(f64::NAN).is_err());
+        assert!(dollars_to_cents(f64::INFINITY).is_err());
+        assert!(dollars_to_cents(1e30).is_err());
+    }
+
+    #[test]
+    fn test_validate_limit_orders() {
+        assert_eq!(
+            validate_new_order(OrderType::Limit, Some(&PriceType::Unsigned(1000)), 10),
+            Ok(1000)
+        );
+
+        let errors = validate_new_order(OrderType::Limit, None, 0).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["quantity", "price"]);
+
+        assert!(validate_new_order(OrderType::Limit, Some(&PriceType::Unsigned(0)), 10).is_err());
+        assert!(
+            validate_new_order(OrderType::Limit, Some(&PriceType::Unsigned(u64::MAX)), 2).is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_market_orders() {
+        assert_eq!(validate_new_order(OrderType::Market, None, 10), Ok(0));
+        assert_eq!(
+            validate_new_order(OrderType::Market, Some(&PriceType::Float(-1.0)), 10),
+            Ok(0)
+        );
+        assert!(validate_new_order(OrderType::Market, None, 0).is_err());
+    }
+
+    #[test]
+    fn test_validate_trigger() {
+        assert_eq!(validate_trigger(None, None, None, None), Ok(None));
+        assert_eq!(
+            validate_trigger(
+                Some(&PriceType::Float(10.5)),
+                Some(TriggerSource::Mark),
+                Some(TriggerKind::Touch),
+                None
+            ),
+            Ok(Some(Trigger {
+                price: 1050,
+                source: TriggerSource::Mark,
+                kind: TriggerKind::Touch,
+                trail: None,
+            }))
+        );
+        assert_eq!(
+            validate_trigger(None, Some(TriggerSource::Mark), None, None)
+                .unwrap_err()
+                .field,
+            "trigger_source"
+        );
+        assert!(validate_trigger(Some(&PriceType::Unsigned(0)), None, None, None).is_err());
+
+        let price = PriceType::Unsigned(1000);
+        let trail = PriceType::Float(0.5);
+        assert_eq!(
+            validate_trigger(Some(&price), None, None, Some(&trail)),
+            Ok(Some(Trigger {
+                price: 1000,
+                source: TriggerSource::LastTrade,
+                kind: TriggerKind::Stop,
+                trail: Some(50),
+            }))
+        );
+        assert_eq!(
+            validate_trigger(Some(&price), None, None, Some(&PriceType::Unsigned(0)))
+                .unwrap_err()
+                .field,
+            "trigger_trail"
+        );
+        assert_eq!(
+            validate_trigger(Some(&price), None, Some(TriggerKind::Touch), Some(&trail))
+                .unwrap_err()
+                .field,
+            "trigger_trail"
+        );
+    }
+
+    #[test]
+    fn test_validate_quote_side() {
+        assert_eq!(
+            validate_quote_side(Side::Buy, &PriceType::Float(9.99), 10),
+            Ok(999)
+        );
+        let errors = validate_quote_side(Side::Sell, &PriceType::Float(-1.0), 0).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["ask.quantity", "ask.price"]);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::order::{Price, Quantity, Timestamp, Trade, TradeId};
+
+/// Windows reported by [`RollingVolume::stats`], label and length (ms)
+pub const WINDOWS: [(&str, Timestamp); 3] = [("1m", 60_000), ("5m", 300_000), ("1h", 3_600_000)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct VolumeStats {
+    pub window: &'static str,
+    pub volume: Quantity,
+    /// Cents
+    pub notional: u64,
+    pub trades: u64,
+    /// Volume weighted average price, `None` without trades in the window
+    pub vwap: Option<Price>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Execution {
+    id: TradeId,
+    at: Timestamp,
+    price: Price,
+    quantity: Quantity,
+}
+
+/// Executions of the longest window, so volume can be summed over any of the windows
+#[derive(Debug, Default)]
+pub struct RollingVolume {
+    executions: VecDeque<Execution>, // ascending trade ids (and times)
+}
+
+impl RollingVolume {
+    pub fn record(&mut self, trade: &Trade, now: Timestamp) {
+        self.evict(now);
+        self.executions.push_back(Execution {
+            id: trade.id,
+            at: now,
+            price: trade.price,
+            quantity: trade.quantity,
+        });
+    }
+
+    /// Drops a busted trade, or takes over a corrected trade's price and quantity
+    pub fn update(&mut self, trade: &Trade) {
+        let Ok(pos) = self.executions.binary_search_by_key(&trade.id, |e| e.id) else {
+            return; // already out of every window
+        };
+        if trade.is_busted() {
+            self.executions.remove(pos);
+        } else {
+            self.executions[pos].price = trade.price;
+            self.executions[pos].quantity = trade.quantity;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.executions.clear();
+    }
+
+    /// Volume per window in [`WINDOWS`], as of `now`
+    pub fn stats(&self, now: Timestamp) -> Vec<VolumeStats> {
+        WINDOWS
+            .iter()
+            .map(|&(window, length)| {
+                let since = now.saturating_sub(length);
+                let start = self.executions.partition_point(|e| e.at < since);
+                let (mut volume, mut notional, mut trades) = (0, 0u64, 0);
+                for e in self.executions.range(start..) {
+                    volume += e.quantity;
+                    notional = notional.saturating_add(e.price.saturating_mul(e.quantity));
+                    trades += 1;
+                }
+                VolumeStats {
+                    window,
+                    volume,
+                    notional,
+                    trades,
+                    vwap: (volume > 0).then(|| notional / volume),
+                }
+            })
+            .collect()
+    }
+
+    fn evict(&mut self, now: Timestamp) {
+        let longest = WINDOWS.iter().map(|(_, length)| *length).max().unwrap_or(0);
+        let since = now.saturating_sub(longest);
+        while self.executions.front().is_some_and(|e| e.at < since) {
+            self.executions.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::order::TradeStatus;
+
+    #[test]
+    fn test_rolling_windows() {
+        let mut volume = RollingVolume::default();
+        volume.record(&Trade::new(1, String::new(), String::new(), 100, 10), 0);
+        volume.record(
+            &Trade::new(2, String::new(), String::new(), 110, 10),
+            250_000,
+        );
+        volume.record(
+            &Trade::new(3, String::new(), String::new(), 120, 5),
+            290_000,
+        );
+
+        let stats = volume.stats(300_000);
+        assert_eq!(
+            (stats[0].window, stats[0].volume, stats[0].trades),
+            ("1m", 15, 2)
+        );
+        assert_eq!((stats[1].volume, stats[1].trades), (25, 3));
+        assert_eq!(stats[1].vwap, Some((1_000 + 1_100 + 600) / 25));
+
+        let mut busted = Trade::new(3, String::new(), String::new(), 120, 5);
+        busted.status = TradeStatus::Busted {
+            reason: String::from("error"),
+        };
+        volume.update(&busted);
+        assert_eq!(volume.stats(300_000)[0].volume, 10);
+
+        // an hour later only the last trade is left
+        volume.record(
+            &Trade::new(4, String::new(), String::new(), 100, 1),
+            4_000_000,
+        );
+        assert_eq!(volume.stats(4_000_000)[2].trades, 1);
+        assert_eq!(volume.stats(4_000_000)[0].vwap, Some(100));
+    }
+}
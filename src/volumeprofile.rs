@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    audit::{AuditEntry, AuditEvent},
+    order::{Price, Quantity, TradeId},
+};
+
+/// Traded volume in the `bucket`-wide price range starting at `price`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PriceVolume {
+    pub price: Price,
+    pub volume: Quantity,
+    pub trades: u64,
+}
+
+/// Volume-at-price of the trades executed in `entries`, lowest price first. Busts and
+/// corrections in `entries` are applied; those of trades executed before it are not seen.
+pub fn profile(entries: &[AuditEntry], bucket: Price) -> Vec<PriceVolume> {
+    let mut trades: BTreeMap<TradeId, (Price, Quantity)> = BTreeMap::new();
+    for entry in entries {
+        match &entry.event {
+            AuditEvent::TradeExecuted { trade } => {
+                trades.insert(trade.id, (trade.price, trade.quantity));
+            }
+            AuditEvent::TradeBusted { trade } => {
+                trades.remove(&trade.id);
+            }
+            AuditEvent::TradeCorrected { trade } => {
+                if let Some(executed) = trades.get_mut(&trade.id) {
+                    *executed = (trade.price, trade.quantity);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let bucket = bucket.max(1);
+    let mut levels: BTreeMap<Price, PriceVolume> = BTreeMap::new();
+    for (price, quantity) in trades.into_values() {
+        let start = price / bucket * bucket;
+        let level = levels.entry(start).or_insert(PriceVolume {
+            price: start,
+            volume: 0,
+            trades: 0,
+        });
+        level.volume += quantity;
+        level.trades += 1;
+    }
+    levels.into_values().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        audit::AuditLog,
+        order::{Trade, TradeStatus},
+    };
+
+    #[test]
+    fn test_profile() {
+        let mut log = AuditLog::default();
+        let mut executed = |id, price, quantity| {
+            let trade = Trade::new(id, String::new(), String::new(), price, quantity);
+            log.append(AuditEvent::TradeExecuted { trade }, 0);
+        };
+        executed(1, 101, 10);
+        executed(2, 109, 5);
+        executed(3, 115, 7);
+        executed(4, 120, 1);
+        let mut busted = Trade::new(4, String::new(), String::new(), 120, 1);
+        busted.status = TradeStatus::Busted {
+            reason: String::from("error"),
+        };
+        log.append(AuditEvent::TradeBusted { trade: busted }, 0);
+        let corrected = Trade::new(3, String::new(), String::new(), 112, 6);
+        log.append(AuditEvent::TradeCorrected { trade: corrected }, 0);
+
+        let levels = profile(log.since(0), 10);
+        assert_eq!(
+            levels,
+            vec![
+                PriceVolume {
+                    price: 100,
+                    volume: 15,
+                    trades: 2
+                },
+                PriceVolume {
+                    price: 110,
+                    volume: 6,
+                    trades: 1
+                },
+            ]
+        );
+    }
+}
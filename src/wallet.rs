@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::order::{
+    Order, OrderId, OrderType, ParticipantId, Price, Quantity, RejectReason, Side, Trade,
+};
+
+pub const WALLETS_ENV: &str = "OME_WALLETS"; // `true` makes orders require funded balances
+
+/// Base is the traded instrument (in quantity units), quote is cash (in cents)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Asset {
+    Base,
+    Quote,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Balance {
+    pub total: u64,
+    /// Reserved for open orders
+    pub held: u64,
+}
+
+impl Balance {
+    pub fn available(&self) -> u64 {
+        self.total.saturating_sub(self.held)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountBalances {
+    pub base: Balance,
+    pub quote: Balance,
+}
+
+impl AccountBalances {
+    fn get_mut(&mut self, asset: Asset) -> &mut Balance {
+        match asset {
+            Asset::Base => &mut self.base,
+            Asset::Quote => &mut self.quote,
+        }
+    }
+}
+
+/// Funds reserved for one open order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Hold {
+    owner: ParticipantId,
+    asset: Asset,
+    remaining: u64,
+    /// Limit price the quote hold was sized at, `None` for base holds and market orders
+    unit_price: Option<Price>,
+}
+
+/// Per-account balances with holds for open orders. When disabled every operation is a no-op.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Wallets {
+    #[serde(skip)] // configuration, not state
+    enabled: bool,
+    accounts: HashMap<ParticipantId, AccountBalances>,
+    holds: HashMap<OrderId, Hold>,
+}
+
+impl Wallets {
+    pub fn new(enabled: bool) -> Self {
+        Wallets {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let enabled = std::env::var(WALLETS_ENV).is_ok_and(|v| v == "true" || v == "1");
+        Wallets::new(enabled)
+    }
+
+    /// Takes over the balances and holds of `other`, keeping this wallet's `enabled` setting
+    pub fn restore(&mut self, other: Wallets) {
+        self.accounts = other.accounts;
+        self.holds = other.holds;
+    }
+
+    /// Balances and holds without the `enabled` setting, for a state export
+    pub fn snapshot(&self) -> Wallets {
+        Wallets {
+            enabled: false,
+            accounts: self.accounts.clone(),
+            holds: self.holds.clone(),
+        }
+    }
+
+    /// Whether orders are checked against balances at all
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn balances(&self, owner: &str) -> AccountBalances {
+        self.accounts.get(owner).copied().unwrap_or_default()
+    }
+
+    pub fn deposit(&mut self, owner: ParticipantId, asset: Asset, amount: u64) -> AccountBalances {
+        let balances = self.accounts.entry(owner).or_default();
+        let balance = balances.get_mut(asset);
+        balance.total = balance.total.saturating_add(amount);
+        *balances
+    }
+
+    /// Takes `amount` out of the available balance, returns `None` if not enough is available
+    pub fn withdraw(&mut self, owner: &str, asset: Asset, amount: u64) -> Option<AccountBalances> {
+        let balances = self.accounts.get_mut(owner)?;
+        let balance = balances.get_mut(asset);
+        if balance.available() < amount {
+            return None;
+        }
+        balance.total -= amount;
+        Some(*balances)
+    }
+
+    /// Reserves what `order` can spend: `price * quantity` quote for limit buys, `cost` (the
+    /// cost of sweeping the book) for market buys, `quantity` base for sells
+    pub fn place_hold(&mut self, order: &Order, market_cost: u64) -> Result<(), RejectReason> {
+        let Some(owner) = &order.owner else {
+            return Ok(());
+        };
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let (asset, amount, unit_price) = match (order.side, order.order_type) {
+            (Side::Sell, _) => (Asset::Base, order.quantity, None),
+            (Side::Buy, OrderType::Limit) => (
+                Asset::Quote,
+                order.price.saturating_mul(order.quantity),
+                Some(order.price),
+            ),
+            (Side::Buy, OrderType::Market) => (Asset::Quote, market_cost, None),
+        };
+        let balance = self
+            .accounts
+            .entry(owner.clone())
+            .or_default()
+            .get_mut(asset);
+        if balance.available() < amount {
+            return Err(RejectReason::InsufficientBalance);
+        }
+        balance.held += amount;
+        self.holds.insert(
+            order.id.clone(),
+            Hold {
+                owner: owner.clone(),
+                asset,
+                remaining: amount,
+                unit_price,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns whatever is still held for `order_id` to the available balance
+    pub fn release(&mut self, order_id: &OrderId) {
+        let Some(hold) = self.holds.remove(order_id) else {
+            return;
+        };
+        if let Some(balances) = self.accounts.get_mut(&hold.owner) {
+            let balance = balances.get_mut(hold.asset);
+            balance.held = balance.held.saturating_sub(hold.remaining);
+        }
+    }
+
+    /// Moves the traded assets between buyer and seller, drawing on the orders' holds
+    pub fn settle(&mut self, trade: &Trade) {
+        if !self.enabled {
+            return;
+        }
+        let cost = trade.price.saturating_mul(trade.quantity);
+        let quote_hold = self.consume_hold(&trade.buy_order_id, |hold| {
+            hold.unit_price.unwrap_or(trade.price) * trade.quantity
+        });
+        let base_hold = self.consume_hold(&trade.sell_order_id, |_| trade.quantity);
+
+        if let Some(buyer) = &trade.buyer {
+            let balances = self.accounts.entry(buyer.clone()).or_default();
+            balances.quote.held = balances.quote.held.saturating_sub(quote_hold);
+            balances.quote.total = balances.quote.total.saturating_sub(cost);
+            balances.base.total += trade.quantity;
+        }
+        if let Some(seller) = &trade.seller {
+            let balances = self.accounts.entry(seller.clone()).or_default();
+            balances.base.held = balances.base.held.saturating_sub(base_hold);
+            balances.base.total = balances.base.total.saturating_sub(trade.quantity);
+            balances.quote.total += cost;
+        }
+    }
+
+    /// Undoes the transfer of a busted (or about to be corrected) trade
+    pub fn reverse(&mut self, trade: &Trade) {
+        self.transfer(trade, false);
+    }
+
+    /// Re-applies a corrected trade; the holds were already consumed by the original fill
+    pub fn apply(&mut self, trade: &Trade) {
+        self.transfer(trade, true);
+    }
+
+    /// Moves `trade`'s assets from seller to buyer (`forward`) or back, without touching holds
+    fn transfer(&mut self, trade: &Trade, forward: bool) {
+        if !self.enabled {
+            return;
+        }
+        let cost = trade.price.saturating_mul(trade.quantity);
+        let (to_buyer, from_buyer) = if forward {
+            ((Asset::Base, trade.quantity), (Asset::Quote, cost))
+        } else {
+            ((Asset::Quote, cost), (Asset::Base, trade.quantity))
+        };
+        if let Some(buyer) = &trade.buyer {
+            let balances = self.accounts.entry(buyer.clone()).or_default();
+            balances.get_mut(to_buyer.0).total += to_buyer.1;
+            let balance = balances.get_mut(from_buyer.0);
+            balance.total = balance.total.saturating_sub(from_buyer.1);
+        }
+        if let Some(seller) = &trade.seller {
+            let balances = self.accounts.entry(seller.clone()).or_default();
+            balances.get_mut(from_buyer.0).total += from_buyer.1;
+            let balance = balances.get_mut(to_buyer.0);
+            balance.total = balance.total.saturating_sub(to_buyer.1);
+        }
+    }
+
+    fn consume_hold(&mut self, order_id: &OrderId, amount: impl Fn(&Hold) -> u64) -> u64 {
+        let Some(hold) = self.holds.get_mut(order_id) else {
+            return 0;
+        };
+        let consumed = amount(hold).min(hold.remaining);
+        hold.remaining -= consumed;
+        if hold.remaining == 0 {
+            self.holds.remove(order_id);
+        }
+        consumed
+    }
+}
+
+/// Quote needed to buy `quantity` by sweeping `asks` best price first; asks past the ones that
+/// cover it aren't looked at
+pub fn sweep_cost<'a>(asks: impl Iterator<Item = &'a Order>, quantity: Quantity) -> u64 {
+    let mut left = quantity;
+    let mut cost = 0u64;
+    for ask in asks {
+        if left == 0 {
+            break;
+        }
+        let fill = ask.quantity.min(left);
+        cost = cost.saturating_add(ask.price.saturating_mul(fill));
+        left -= fill;
+        if left == 0 {
+            break;
+        }
+    }
+    cost
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn order(id: &str, owner: &str, side: Side, quantity: u64, price: u64) -> Order {
+        Order::new(id.to_string(), side, OrderType::Limit, quantity, price, 1)
+            .with_owner(owner.to_string())
+    }
+
+    #[test]
+    fn test_holds_and_release() {
+        let mut wallets = Wallets::new(true);
+        wallets.deposit(String::from("alice"), Asset::Quote, 1_000);
+
+        let buy = order("1", "alice", Side::Buy, 10, 150);
+        assert_eq!(
+            wallets.place_hold(&buy, 0),
+            Err(RejectReason::InsufficientBalance)
+        );
+        let buy = order("1", "alice", Side::Buy, 5, 100);
+        assert!(wallets.place_hold(&buy, 0).is_ok());
+        assert_eq!(wallets.balances("alice").quote.available(), 500);
+
+        wallets.release(&buy.id);
+        assert_eq!(wallets.balances("alice").quote.available(), 1_000);
+    }
+
+    #[test]
+    fn test_settle_with_price_improvement() {
+        let mut wallets = Wallets::new(true);
+        wallets.deposit(String::from("alice"), Asset::Quote, 1_000);
+        wallets.deposit(String::from("bob"), Asset::Base, 10);
+
+        let sell = order("s", "bob", Side::Sell, 10, 90);
+        let buy = order("b", "alice", Side::Buy, 10, 100);
+        wallets.place_hold(&sell, 0).unwrap();
+        wallets.place_hold(&buy, 0).unwrap();
+
+        let trade = Trade::new(1, buy.id.clone(), sell.id.clone(), 90, 10)
+            .with_parties(buy.owner.clone(), sell.owner.clone());
+        wallets.settle(&trade);
+
+        let alice = wallets.balances("alice");
+        assert_eq!(
+            alice.quote,
+            Balance {
+                total: 100,
+                held: 0
+            }
+        );
+        assert_eq!(alice.base.total, 10);
+        let bob = wallets.balances("bob");
+        assert_eq!(bob.base, Balance::default());
+        assert_eq!(bob.quote.total, 900);
+
+        wallets.reverse(&trade);
+        assert_eq!(wallets.balances("alice").quote.total, 1_000);
+        assert_eq!(wallets.balances("bob").base.total, 10);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut wallets = Wallets::new(false);
+        let buy = order("1", "alice", Side::Buy, 5, 100);
+        assert!(wallets.place_hold(&buy, 0).is_ok());
+    }
+
+    #[test]
+    fn test_sweep_cost() {
+        let asks = [
+            order("1", "bob", Side::Sell, 5, 100),
+            order("2", "bob", Side::Sell, 5, 101),
+            order("3", "bob", Side::Sell, 5, 102),
+        ];
+        let mut seen = 0;
+        let cost = sweep_cost(asks.iter().inspect(|_| seen += 1), 8);
+        assert_eq!(cost, 5 * 100 + 3 * 101);
+        // the third ask isn't needed to cover the quantity
+        assert_eq!(seen, 2);
+        assert_eq!(sweep_cost(asks.iter(), 20), 5 * 100 + 5 * 101 + 5 * 102);
+    }
+}
@@ -0,0 +1,52 @@
+//! JavaScript bindings of [`Matcher`] for the browser, e.g.
+//!
+//! ```js
+//! const m = new Matcher("pro_rata", 1);
+//! m.submit({ side: "Sell", order_type: "Limit", price: 100, quantity: 5 });
+//! m.submit({ side: "Buy", order_type: "Market", quantity: 2 }); // { id, trades: [...] }
+//! m.book(); // { bids: [...], asks: [...] }
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    matching::{Matcher, OrderRequest},
+    matchingpolicy::{self, PriceTime},
+};
+
+/// Plain objects rather than `Map`s, since trades serialize with a flattened status
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    Ok(value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}
+
+#[wasm_bindgen(js_name = Matcher)]
+pub struct WasmMatcher(Matcher);
+
+#[wasm_bindgen(js_class = Matcher)]
+impl WasmMatcher {
+    /// `policy` is one of the server's `OME_MATCHING_POLICY` names, price-time when omitted
+    #[wasm_bindgen(constructor)]
+    pub fn new(policy: Option<String>, min_quantity: Option<u32>) -> Result<WasmMatcher, JsError> {
+        let policy = match policy {
+            Some(name) => matchingpolicy::by_name(&name, min_quantity.unwrap_or(1).into())
+                .ok_or_else(|| JsError::new(&format!("unknown matching policy {name}")))?,
+            None => std::sync::Arc::new(PriceTime),
+        };
+        Ok(WasmMatcher(Matcher::new(policy)))
+    }
+
+    pub fn submit(&mut self, order: JsValue) -> Result<JsValue, JsError> {
+        let order: OrderRequest = serde_wasm_bindgen::from_value(order)?;
+        let submitted = self.0.submit_request(order)?;
+        to_js(&submitted)
+    }
+
+    pub fn cancel(&mut self, id: &str) -> bool {
+        self.0.cancel(id)
+    }
+
+    pub fn book(&self) -> Result<JsValue, JsError> {
+        to_js(&self.0.view())
+    }
+}
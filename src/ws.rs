@@ -0,0 +1,756 @@
+use std::{net::SocketAddr, time::Duration};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{
+        ConnectInfo, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    middleware,
+    response::Response,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    audit::AuditEntry,
+    auth::{self, Auth, Principal},
+    consensus::Consensus,
+    error::{ApiError, ErrorCode},
+    events,
+    heartbeat::{self, HeartbeatConfig, Liveness},
+    matchingengine::MatchingEngine,
+    order::{Order, OrderId, OrderStatus, OrderType, ParticipantId, Quantity, Side, TimeInForce},
+    orderentry::{self, OrderAck},
+    ratelimit::{self, RateLimiter},
+    session::{SessionId, SessionInfo, SessionRegistry},
+    subscriptions::{Channel, ChannelUpdate, Subscriptions},
+    validation::{self, PriceType},
+};
+
+#[derive(Clone)]
+struct WsState {
+    engine: MatchingEngine,
+    consensus: Option<Consensus>,
+    /// The REST API's, order entry messages count against the participant's bucket
+    rate_limiter: RateLimiter,
+    sessions: SessionRegistry,
+    /// What the exchange trades, the only symbol sessions can subscribe to
+    symbol: String,
+}
+
+/// Client-chosen id echoed in the answer to an order entry message
+type RequestId = String;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum ClientMessage {
+    Heartbeat,
+    ArmCancelOnDisconnect {
+        heartbeat_timeout_ms: Option<u64>,
+    },
+    DisarmCancelOnDisconnect,
+    NewOrder(NewOrder),
+    CancelOrder {
+        request_id: RequestId,
+        order_id: OrderId,
+    },
+    /// Cancel/replace of a resting order with a new price and/or quantity; the replacement
+    /// is a new order and loses the time priority of the one it replaces
+    AmendOrder {
+        request_id: RequestId,
+        order_id: OrderId,
+        price: Option<PriceType>,
+        quantity: Option<Quantity>,
+    },
+    /// Subscribes to a channel, or changes an existing subscription
+    Subscribe {
+        channel: Channel,
+        /// The exchange's own symbol when omitted
+        symbol: Option<String>,
+        /// Levels per side of the book channel
+        depth: Option<usize>,
+        /// Least time between two updates, every change goes out when omitted
+        #[serde(default)]
+        interval_ms: u64,
+    },
+    Unsubscribe {
+        channel: Channel,
+        symbol: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct NewOrder {
+    request_id: RequestId,
+    side: Side,
+    order_type: OrderType,
+    /// Price in cents, as on `POST /orders`
+    price: Option<PriceType>,
+    quantity: Quantity,
+    #[serde(default)]
+    time_in_force: TimeInForce,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage {
+    Welcome {
+        session_id: SessionId,
+    },
+    HeartbeatAck,
+    CancelOnDisconnect {
+        armed: bool,
+        heartbeat_timeout_ms: Option<u64>,
+    },
+    HeartbeatTimeout {
+        canceled: Vec<OrderId>,
+    },
+    OrderAck {
+        request_id: RequestId,
+        #[serde(flatten)]
+        ack: OrderAck,
+    },
+    CancelAck {
+        request_id: RequestId,
+        order_id: OrderId,
+    },
+    AmendAck {
+        request_id: RequestId,
+        replaced: OrderId,
+        #[serde(flatten)]
+        ack: OrderAck,
+    },
+    /// The order entry message was refused before reaching the engine
+    Reject {
+        request_id: RequestId,
+        error: ApiError,
+    },
+    /// An execution report of the session's participant, as on `/events`
+    Event {
+        event: AuditEntry,
+    },
+    Subscribed {
+        channel: Channel,
+        symbol: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<usize>,
+        interval_ms: u64,
+    },
+    Unsubscribed {
+        channel: Channel,
+        symbol: String,
+    },
+    Update {
+        #[serde(flatten)]
+        update: ChannelUpdate,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct SessionsResponse {
+    sessions: Vec<SessionInfo>,
+}
+
+/// `/ws` for traders plus `/admin/sessions` to inspect connected sessions; in consensus mode
+/// orders entered on a session go through `consensus`, and they're charged to `rate_limiter`
+pub fn routes(
+    engine: MatchingEngine,
+    consensus: Option<Consensus>,
+    rate_limiter: RateLimiter,
+    auth: Auth,
+    sessions: SessionRegistry,
+    symbol: String,
+) -> Router<MatchingEngine> {
+    let state = WsState {
+        engine,
+        consensus,
+        rate_limiter,
+        sessions,
+        symbol,
+    };
+
+    let trader =
+        Router::new()
+            .route("/ws", get(ws_upgrade))
+            .route_layer(middleware::from_fn_with_state(
+                auth.clone(),
+                auth::require_trader,
+            ));
+    let admin = Router::new()
+        .route("/admin/sessions", get(get_sessions))
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_admin));
+
+    trader.merge(admin).with_state(state)
+}
+
+async fn get_sessions(State(state): State<WsState>) -> Json<SessionsResponse> {
+    Json(SessionsResponse {
+        sessions: state.sessions.list().await,
+    })
+}
+
+async fn ws_upgrade(
+    State(state): State<WsState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Extension(principal): Extension<Principal>,
+    Extension(heartbeat): Extension<Option<HeartbeatConfig>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let client = ratelimit::principal_client(Some(&principal), addr);
+    ws.on_upgrade(move |socket| run_session(socket, state, principal, client, heartbeat))
+}
+
+async fn send(socket: &mut WebSocket, msg: &ServerMessage) -> bool {
+    let text = serde_json::to_string(msg).expect("server messages always serialize");
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
+
+/// Enters a new order for `owner` and answers with the engine's ack; its executions follow
+/// as events
+async fn new_order(state: &WsState, owner: &ParticipantId, msg: NewOrder) -> ServerMessage {
+    let NewOrder {
+        request_id,
+        side,
+        order_type,
+        price,
+        quantity,
+        time_in_force,
+    } = msg;
+    let entered = async {
+        let price = validation::validate_new_order(order_type, price.as_ref(), quantity)
+            .map_err(ApiError::validation)?;
+        let id = state.engine.next_order_id();
+        let order = Order::new(
+            id,
+            side,
+            order_type,
+            quantity,
+            price,
+            state.engine.order_timestamp(),
+        )
+        .with_owner(owner.clone())
+        .with_time_in_force(time_in_force);
+        orderentry::enter_order(&state.engine, state.consensus.clone(), order).await
+    };
+    match entered.await {
+        Ok(entry) => ServerMessage::OrderAck {
+            request_id,
+            ack: entry.ack,
+        },
+        Err(error) => ServerMessage::Reject { request_id, error },
+    }
+}
+
+async fn cancel_order(
+    state: &WsState,
+    owner: &ParticipantId,
+    request_id: RequestId,
+    order_id: OrderId,
+) -> ServerMessage {
+    let canceled = orderentry::cancel_order(
+        &state.engine,
+        state.consensus.as_ref(),
+        order_id.clone(),
+        Some(owner.clone()),
+    )
+    .await;
+    match canceled {
+        Ok(true) => ServerMessage::CancelAck {
+            request_id,
+            order_id,
+        },
+        Ok(false) => ServerMessage::Reject {
+            request_id,
+            error: not_found(&order_id),
+        },
+        Err(error) => ServerMessage::Reject { request_id, error },
+    }
+}
+
+/// Cancels `order_id` and enters its replacement with the same side, type and time in force.
+/// Only orders on the book can be amended: a replacement of a queued, scheduled or untriggered
+/// order would skip its wait and trade straight away.
+async fn amend_order(
+    state: &WsState,
+    owner: &ParticipantId,
+    request_id: RequestId,
+    order_id: OrderId,
+    price: Option<PriceType>,
+    quantity: Option<Quantity>,
+) -> ServerMessage {
+    let amended = async {
+        let (order, status) = state
+            .engine
+            .owner_orders(owner)
+            .await
+            .into_iter()
+            .find(|(o, _)| o.id == order_id)
+            .ok_or_else(|| not_found(&order_id))?;
+        if !matches!(status, OrderStatus::New | OrderStatus::PartiallyFilled) {
+            return Err(ApiError::new(
+                ErrorCode::InvalidTransition,
+                format!("order {order_id} is {status:?}, only resting orders can be amended"),
+            ));
+        }
+        let quantity = quantity.unwrap_or(order.quantity);
+        let price = price.unwrap_or(PriceType::Unsigned(order.price));
+        let price = validation::validate_new_order(order.order_type, Some(&price), quantity)
+            .map_err(ApiError::validation)?;
+        let canceled = orderentry::cancel_order(
+            &state.engine,
+            state.consensus.as_ref(),
+            order_id.clone(),
+            Some(owner.clone()),
+        )
+        .await?;
+        if !canceled {
+            return Err(not_found(&order_id)); // filled or canceled in the meantime
+        }
+        let id = state.engine.next_order_id();
+        let replacement = Order::new(
+            id,
+            order.side,
+            order.order_type,
+            quantity,
+            price,
+            state.engine.order_timestamp(),
+        )
+        .with_owner(owner.clone())
+        .with_time_in_force(order.time_in_force);
+        orderentry::enter_order(&state.engine, state.consensus.clone(), replacement).await
+    };
+    match amended.await {
+        Ok(entry) => ServerMessage::AmendAck {
+            request_id,
+            replaced: order_id,
+            ack: entry.ack,
+        },
+        Err(error) => ServerMessage::Reject { request_id, error },
+    }
+}
+
+/// The reject of an order entry message over `client`'s rate limit, `None` when it may go on
+async fn throttle(state: &WsState, client: &str, request_id: &RequestId) -> Option<ServerMessage> {
+    let retry_after = state
+        .rate_limiter
+        .check(client, std::time::Instant::now())
+        .await
+        .err()?;
+    let (error, _) = ratelimit::rate_limited(retry_after);
+    Some(ServerMessage::Reject {
+        request_id: request_id.clone(),
+        error,
+    })
+}
+
+fn not_found(order_id: &str) -> ApiError {
+    ApiError::new(
+        ErrorCode::OrderNotFound,
+        format!("no resting order with id {order_id}"),
+    )
+}
+
+/// Runs a session until its client leaves, stops answering pings or misses its own heartbeat;
+/// either way the session is closed and, when armed, cancel-on-disconnect pulls its orders.
+/// Order entry messages are charged to the rate limit bucket `client`.
+async fn run_session(
+    mut socket: WebSocket,
+    state: WsState,
+    principal: Principal,
+    client: String,
+    heartbeat: Option<HeartbeatConfig>,
+) {
+    let mut session = state.sessions.open(principal.subject).await;
+    let mut last_seen = Instant::now();
+    let mut liveness = Liveness::new(heartbeat);
+    let mut timed_out = false;
+    let mut events = state.engine.subscribe_events();
+    let mut next_seq = state.engine.last_seq().await + 1;
+    let last_price = state.engine.last_trade_price().await;
+    let mut subscriptions = Subscriptions::new(state.symbol.clone(), last_price);
+
+    if send(
+        &mut socket,
+        &ServerMessage::Welcome {
+            session_id: session.id,
+        },
+    )
+    .await
+    {
+        loop {
+            if !flush(&mut socket, &state.engine, &mut subscriptions).await {
+                break;
+            }
+            let deadline = session.heartbeat_timeout().map(|t| last_seen + t);
+            let update_due = subscriptions.next_due();
+            let ping_due = liveness.deadline();
+            let msg = tokio::select! {
+                msg = socket.recv() => msg,
+                received = events.recv() => {
+                    let entries = match received {
+                        Ok(entry) => vec![entry],
+                        // caught up from the log
+                        Err(RecvError::Lagged(_)) => {
+                            state.engine.audit_entries(next_seq, usize::MAX).await
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    let participant = &session.participant;
+                    let forwarded =
+                        forward_events(&mut socket, entries, participant, &mut next_seq, &mut subscriptions);
+                    if !forwarded.await {
+                        break;
+                    }
+                    continue;
+                }
+                _ = sleep_until(update_due.unwrap_or_else(Instant::now)), if update_due.is_some() => {
+                    continue;
+                }
+                _ = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    timed_out = true;
+                    break;
+                }
+                _ = sleep_until(ping_due.unwrap_or_else(Instant::now)), if ping_due.is_some() => {
+                    if !heartbeat::keep_alive(&mut socket, &mut liveness).await {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(Ok(_)) = msg {
+                liveness.seen();
+            }
+            let text = match msg {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => break,
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => {
+                    last_seen = Instant::now(); // pings count as liveness too
+                    continue;
+                }
+            };
+            last_seen = Instant::now();
+
+            let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Heartbeat) => ServerMessage::HeartbeatAck,
+                Ok(ClientMessage::ArmCancelOnDisconnect {
+                    heartbeat_timeout_ms,
+                }) => {
+                    session = state
+                        .sessions
+                        .set_cancel_on_disconnect(session.id, true, heartbeat_timeout_ms)
+                        .await
+                        .unwrap_or(session);
+                    ServerMessage::CancelOnDisconnect {
+                        armed: session.cancel_on_disconnect,
+                        heartbeat_timeout_ms: session.heartbeat_timeout_ms,
+                    }
+                }
+                Ok(ClientMessage::NewOrder(msg)) => {
+                    match throttle(&state, &client, &msg.request_id).await {
+                        Some(reject) => reject,
+                        None => new_order(&state, &session.participant, msg).await,
+                    }
+                }
+                Ok(ClientMessage::CancelOrder {
+                    request_id,
+                    order_id,
+                }) => match throttle(&state, &client, &request_id).await {
+                    Some(reject) => reject,
+                    None => cancel_order(&state, &session.participant, request_id, order_id).await,
+                },
+                Ok(ClientMessage::AmendOrder {
+                    request_id,
+                    order_id,
+                    price,
+                    quantity,
+                }) => match throttle(&state, &client, &request_id).await {
+                    Some(reject) => reject,
+                    None => {
+                        amend_order(
+                            &state,
+                            &session.participant,
+                            request_id,
+                            order_id,
+                            price,
+                            quantity,
+                        )
+                        .await
+                    }
+                },
+                Ok(ClientMessage::Subscribe {
+                    channel,
+                    symbol,
+                    depth,
+                    interval_ms,
+                }) => match other_symbol(&subscriptions, symbol) {
+                    Some(error) => error,
+                    None => {
+                        let interval = Duration::from_millis(interval_ms);
+                        let depth = subscriptions.subscribe(channel, depth, interval);
+                        ServerMessage::Subscribed {
+                            channel,
+                            symbol: subscriptions.symbol().to_string(),
+                            depth: (channel == Channel::Book).then_some(depth),
+                            interval_ms,
+                        }
+                    }
+                },
+                Ok(ClientMessage::Unsubscribe { channel, symbol }) => {
+                    match other_symbol(&subscriptions, symbol) {
+                        Some(error) => error,
+                        None if subscriptions.unsubscribe(channel) => ServerMessage::Unsubscribed {
+                            channel,
+                            symbol: subscriptions.symbol().to_string(),
+                        },
+                        None => ServerMessage::Error {
+                            message: format!("not subscribed to {channel:?}"),
+                        },
+                    }
+                }
+                Ok(ClientMessage::DisarmCancelOnDisconnect) => {
+                    session = state
+                        .sessions
+                        .set_cancel_on_disconnect(session.id, false, None)
+                        .await
+                        .unwrap_or(session);
+                    ServerMessage::CancelOnDisconnect {
+                        armed: false,
+                        heartbeat_timeout_ms: None,
+                    }
+                }
+                Err(e) => ServerMessage::Error {
+                    message: e.to_string(),
+                },
+            };
+            if !send(&mut socket, &reply).await {
+                break;
+            }
+        }
+    }
+
+    let session = state.sessions.close(session.id).await.unwrap_or(session);
+    if session.cancel_on_disconnect {
+        let canceled = cancel_session_orders(&state, &session.participant).await;
+        if timed_out {
+            send(&mut socket, &ServerMessage::HeartbeatTimeout { canceled }).await;
+            let _ = socket.send(Message::Close(None)).await;
+        }
+    }
+}
+
+/// Pulls every order of `owner` when their session goes away. In consensus mode each cancel
+/// goes through the log, so every node cancels the same orders at the same point.
+async fn cancel_session_orders(state: &WsState, owner: &ParticipantId) -> Vec<OrderId> {
+    let Some(consensus) = &state.consensus else {
+        let canceled = state.engine.cancel_participant_orders(owner).await;
+        return canceled.into_iter().map(|o| o.id).collect();
+    };
+    let mut canceled = Vec::new();
+    for (order, _) in state.engine.owner_orders(owner).await {
+        match consensus
+            .cancel_order(order.id.clone(), Some(owner.clone()))
+            .await
+        {
+            Ok(true) => canceled.push(order.id),
+            Ok(false) => {} // filled or canceled in the meantime
+            Err(e) => println!(
+                "{owner}: {} not canceled on disconnect: {}",
+                order.id, e.message
+            ),
+        }
+    }
+    canceled
+}
+
+/// The error for a subscription to a symbol the exchange doesn't trade
+fn other_symbol(subscriptions: &Subscriptions, symbol: Option<String>) -> Option<ServerMessage> {
+    symbol
+        .filter(|s| s != subscriptions.symbol())
+        .map(|s| ServerMessage::Error {
+            message: format!(
+                "unknown symbol {s}, this exchange trades {}",
+                subscriptions.symbol()
+            ),
+        })
+}
+
+/// Sends the channel updates due now
+async fn flush(
+    socket: &mut WebSocket,
+    engine: &MatchingEngine,
+    subscriptions: &mut Subscriptions,
+) -> bool {
+    let now = Instant::now();
+    let book = match subscriptions.needs_book(now) {
+        true => Some(engine.settled_book_view().await),
+        false => None,
+    };
+    for update in subscriptions.due(now, book.as_deref()) {
+        if !send(socket, &ServerMessage::Update { update }).await {
+            return false;
+        }
+    }
+    true
+}
+
+/// Sends the entries of `participant` from `next_seq` on, redacted as on `/events`, when
+/// subscribed to the orders channel, and notes all of them for the market data channels
+async fn forward_events(
+    socket: &mut WebSocket,
+    entries: Vec<AuditEntry>,
+    participant: &str,
+    next_seq: &mut u64,
+    subscriptions: &mut Subscriptions,
+) -> bool {
+    for mut entry in entries {
+        if entry.seq < *next_seq {
+            continue; // already sent before catching up
+        }
+        *next_seq = entry.seq + 1;
+        subscriptions.observe(&entry);
+        if !subscriptions.is_subscribed(Channel::Orders) || !events::involves(&entry, participant) {
+            continue;
+        }
+        events::redact(&mut entry, participant);
+        if !send(socket, &ServerMessage::Event { event: entry }).await {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        order::{Trigger, TriggerKind, TriggerSource},
+        ratelimit::RateLimitConfig,
+    };
+
+    #[tokio::test]
+    async fn test_order_entry_messages() {
+        let state = WsState {
+            engine: MatchingEngine::new(),
+            consensus: None,
+            rate_limiter: RateLimiter::new(RateLimitConfig::default()),
+            sessions: SessionRegistry::new(),
+            symbol: "root".to_string(),
+        };
+        let alice = "alice".to_string();
+        let parse = |json: &str| match serde_json::from_str(json).unwrap() {
+            ClientMessage::NewOrder(msg) => msg,
+            other => panic!("expected a new order, got {other:?}"),
+        };
+        let msg = parse(
+            r#"{"op": "new_order", "request_id": "r1", "side": "Buy", "order_type": "Limit",
+                "price": 100, "quantity": 5}"#,
+        );
+        let ServerMessage::OrderAck {
+            request_id,
+            ack: OrderAck::Accepted { id, .. },
+        } = new_order(&state, &alice, msg).await
+        else {
+            panic!("expected the order to be accepted");
+        };
+        assert_eq!(request_id, "r1");
+
+        let reply = amend_order(&state, &alice, "r2".into(), id.clone(), None, Some(3)).await;
+        let json = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json["type"], "amend_ack");
+        assert_eq!(json["replaced"], id.as_str());
+        assert_eq!(json["status"], "accepted");
+        let orders = state.engine.owner_orders(&alice).await;
+        assert_eq!(orders.len(), 1);
+        assert_eq!((orders[0].0.price, orders[0].0.quantity), (100, 3));
+
+        // the amended order is gone, only its replacement can be canceled
+        let reply = cancel_order(&state, &alice, "r3".into(), id).await;
+        assert!(matches!(reply, ServerMessage::Reject { .. }));
+        let replacement = orders[0].0.id.clone();
+        let reply =
+            cancel_order(&state, &"bob".to_string(), "r4".into(), replacement.clone()).await;
+        assert!(matches!(reply, ServerMessage::Reject { .. }));
+        let reply = cancel_order(&state, &alice, "r5".into(), replacement).await;
+        assert!(matches!(reply, ServerMessage::CancelAck { .. }));
+
+        let msg = parse(
+            r#"{"op": "new_order", "request_id": "r6", "side": "Sell", "order_type": "Limit",
+                "price": 110, "quantity": 2}"#,
+        );
+        let ServerMessage::OrderAck {
+            ack: OrderAck::Accepted { id, .. },
+            ..
+        } = new_order(&state, &alice, msg).await
+        else {
+            panic!("expected the order to be accepted");
+        };
+        assert_eq!(cancel_session_orders(&state, &alice).await, [id]);
+        assert!(state.engine.owner_orders(&alice).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_amend_stop_order() {
+        let mut engine = MatchingEngine::new();
+        let state = WsState {
+            engine: engine.clone(),
+            consensus: None,
+            rate_limiter: RateLimiter::new(RateLimitConfig::default()),
+            sessions: SessionRegistry::new(),
+            symbol: "root".to_string(),
+        };
+        let alice = "alice".to_string();
+        let stop = Order::new("stop".to_string(), Side::Buy, OrderType::Market, 5, 0, 1)
+            .with_owner(alice.clone())
+            .with_trigger(Trigger {
+                price: 120,
+                source: TriggerSource::default(),
+                kind: TriggerKind::Stop,
+                trail: None,
+            });
+        engine.submit_order(stop).await.unwrap();
+        let resting = Order::new("ask".to_string(), Side::Sell, OrderType::Limit, 5, 100, 2)
+            .with_owner("bob".to_string());
+        engine.submit_order(resting).await.unwrap();
+
+        // the stop keeps waiting for its trigger instead of buying the ask
+        let reply = amend_order(&state, &alice, "r1".into(), "stop".into(), None, Some(3)).await;
+        let json = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json["type"], "reject");
+        assert_eq!(json["error"]["code"], "invalid_transition");
+        let orders = engine.owner_orders(&alice).await;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].1, OrderStatus::Untriggered);
+        assert_eq!(engine.get_sell_orders().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_order_entry() {
+        let state = WsState {
+            engine: MatchingEngine::new(),
+            consensus: None,
+            rate_limiter: RateLimiter::new(RateLimitConfig {
+                burst: 1,
+                per_second: 0.001,
+            }),
+            sessions: SessionRegistry::new(),
+            symbol: "root".to_string(),
+        };
+        assert!(throttle(&state, "sub:alice", &"r1".into()).await.is_none());
+        let reply = throttle(&state, "sub:alice", &"r2".into()).await.unwrap();
+        let json = serde_json::to_value(&reply).unwrap();
+        assert_eq!(json["type"], "reject");
+        assert_eq!(json["request_id"], "r2");
+        assert_eq!(json["error"]["code"], "rate_limited");
+        // the bucket is the participant's, not the exchange's
+        assert!(throttle(&state, "sub:bob", &"r3".into()).await.is_none());
+    }
+}